@@ -46,24 +46,274 @@ impl From<MultiSamplingMode> for ash::vk::SampleCountFlags {
     }
 }
 
+/// The post-processing effects available to the CLI.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum PostProcessEffect {
+    Fxaa,
+    Smaa,
+    Taa,
+    Bloom,
+}
+impl From<PostProcessEffect> for crate::engine::utils::post_process::PostProcessEffect {
+    /// Convert the CLI post-processing effect to the engine equivalent.
+    fn from(effect: PostProcessEffect) -> Self {
+        match effect {
+            PostProcessEffect::Fxaa => Self::Fxaa,
+            PostProcessEffect::Smaa => Self::Smaa,
+            PostProcessEffect::Taa => Self::Taa,
+            PostProcessEffect::Bloom => Self::Bloom,
+        }
+    }
+}
+
+/// The anti-aliasing methods available to the CLI, covering both the screen-space post-processing effects and MSAA.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum AntiAliasing {
+    None,
+    Fxaa,
+    Smaa,
+    Taa,
+    MsaaX2,
+    MsaaX4,
+    MsaaX8,
+    MsaaX16,
+    MsaaX32,
+    MsaaX64,
+}
+impl AntiAliasing {
+    /// The multisampling mode used by this anti-aliasing method.
+    pub fn multisampling(self) -> MultiSamplingMode {
+        match self {
+            AntiAliasing::None | AntiAliasing::Fxaa | AntiAliasing::Smaa | AntiAliasing::Taa => {
+                MultiSamplingMode::Disabled
+            }
+            AntiAliasing::MsaaX2 => MultiSamplingMode::X2,
+            AntiAliasing::MsaaX4 => MultiSamplingMode::X4,
+            AntiAliasing::MsaaX8 => MultiSamplingMode::X8,
+            AntiAliasing::MsaaX16 => MultiSamplingMode::X16,
+            AntiAliasing::MsaaX32 => MultiSamplingMode::X32,
+            AntiAliasing::MsaaX64 => MultiSamplingMode::X64,
+        }
+    }
+
+    /// The post-processing effect used by this anti-aliasing method, if it is a screen-space method.
+    pub fn post_process_effect(self) -> Option<PostProcessEffect> {
+        match self {
+            AntiAliasing::Fxaa => Some(PostProcessEffect::Fxaa),
+            AntiAliasing::Smaa => Some(PostProcessEffect::Smaa),
+            AntiAliasing::Taa => Some(PostProcessEffect::Taa),
+            _ => None,
+        }
+    }
+}
+
+/// The FXAA quality presets available to the CLI.
+#[derive(Clone, Copy, Debug, Default, clap::ValueEnum)]
+pub enum FxaaQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Extreme,
+}
+impl From<FxaaQuality> for crate::engine::utils::fxaa_pass::FxaaQuality {
+    /// Convert the CLI FXAA quality to the engine equivalent.
+    fn from(quality: FxaaQuality) -> Self {
+        match quality {
+            FxaaQuality::Low => Self::Low,
+            FxaaQuality::Medium => Self::Medium,
+            FxaaQuality::High => Self::High,
+            FxaaQuality::Extreme => Self::Extreme,
+        }
+    }
+}
+
+/// The advection schemes available to the fluid simulation through the CLI.
+#[derive(Clone, Copy, Debug, Default, clap::ValueEnum)]
+pub enum AdvectionScheme {
+    #[default]
+    SemiLagrangian,
+    MacCormack,
+}
+impl From<AdvectionScheme> for crate::engine::example_fluid::AdvectionScheme {
+    /// Convert the CLI advection scheme to the fluid simulation equivalent.
+    fn from(scheme: AdvectionScheme) -> Self {
+        match scheme {
+            AdvectionScheme::SemiLagrangian => Self::SemiLagrangian,
+            AdvectionScheme::MacCormack => Self::MacCormack,
+        }
+    }
+}
+
+/// The filters available to the CLI for upscaling the scene to the swapchain resolution.
+#[derive(Clone, Copy, Debug, Default, clap::ValueEnum)]
+pub enum Upscaler {
+    Bilinear,
+    Lanczos,
+    #[default]
+    Fsr,
+}
+impl From<Upscaler> for crate::engine::utils::upscale_pass::UpscaleFilter {
+    /// Convert the CLI upscaler to the engine equivalent.
+    fn from(upscaler: Upscaler) -> Self {
+        match upscaler {
+            Upscaler::Bilinear => Self::Bilinear,
+            Upscaler::Lanczos => Self::Lanczos,
+            Upscaler::Fsr => Self::Fsr,
+        }
+    }
+}
+
+/// Parse a render scale, which must be between the minimum render scale and one.
+fn parse_render_scale(s: &str) -> Result<f32, String> {
+    use crate::engine::utils::upscale_pass::MIN_RENDER_SCALE;
+    match s.parse::<f32>() {
+        Ok(scale) if (MIN_RENDER_SCALE..=1.).contains(&scale) => Ok(scale),
+        Ok(_) => Err(format!(
+            "the render scale must be between {MIN_RENDER_SCALE} and 1"
+        )),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Parse a frame time in milliseconds, which must be a positive and finite number.
+fn parse_frame_time(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(time) if time.is_finite() && time > 0. => Ok(time),
+        Ok(_) => Err("the frame time must be positive".to_owned()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Parse a frame rate limit in frames per second, which must be a positive and finite number.
+fn parse_fps_cap(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(fps) if fps.is_finite() && fps > 0. => Ok(fps),
+        Ok(_) => Err("the frame rate limit must be positive".to_owned()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Parse a simulation rate in Hz, which must be a positive and finite number.
+fn parse_simulation_rate(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(rate) if rate.is_finite() && rate > 0. => Ok(rate),
+        Ok(_) => Err("the simulation rate must be positive".to_owned()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
 /// The command-line interface for Pompeii.
 #[derive(clap::Parser)]
 pub struct Args {
     /// The preferred present mode to use.
     /// Immediate and FIFO-relaxed may show screen tearing.
-    /// FIFO is the most efficient, and mailbox is the most responsive.
+    /// FIFO is the most efficient, and mailbox is the most responsive. Cycle through the supported modes at runtime with `P`.
     #[arg(short, long, default_value_t, value_enum)]
     pub present_mode: PresentMode,
 
+    /// Limit the number of frames rendered per second.
+    #[arg(long, value_name = "FPS", value_parser = parse_fps_cap)]
+    pub fps_cap: Option<f32>,
+
+    /// The number of frames the CPU may record while the GPU renders previous ones, independent of the number of swapchain images.
+    /// Fewer frames reduce latency, and more frames improve throughput when the CPU and GPU times vary.
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u32).range(1..=crate::engine::utils::EXPECTED_MAX_FRAMES_IN_FLIGHT as i64))]
+    pub frames_in_flight: u32,
+
+    /// Reduce the latency from input to display by queueing at most one frame for presentation, and sampling input just before each frame is recorded.
+    /// Reports the measured input-to-present latency when the device supports `VK_KHR_present_wait`.
+    #[arg(long, default_value_t)]
+    pub low_latency: bool,
+
     /// The type of multisampling to prefer using, if any. If the desired multi-sample count is not supported, single sampling will be used instead.
     #[arg(long, default_value_t, value_enum)]
     pub msaa: MultiSamplingMode,
 
+    /// The anti-aliasing method to use, in place of `--msaa` and `--fxaa`.
+    /// FXAA, SMAA, and TAA are appended to the post-processing effects, if not already present.
+    #[arg(long, value_enum, conflicts_with_all = ["msaa", "fxaa"])]
+    pub aa: Option<AntiAliasing>,
+
     /// Start with FXAA (a fast screen-space anti-aliasing algorithm) enabled.
+    /// Shorthand for appending `fxaa` to the post-processing effects, if not already present.
     #[arg(long, default_value_t)]
     pub fxaa: bool,
 
+    /// The quality preset of FXAA. Higher qualities detect fainter edges and follow them further.
+    #[arg(long, default_value_t, value_enum)]
+    pub fxaa_quality: FxaaQuality,
+
+    /// Show which pixels FXAA modified, in red, instead of the anti-aliased image.
+    #[arg(long, default_value_t)]
+    pub fxaa_debug: bool,
+
+    /// A comma-separated list of post-processing effects, applied in the given order.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub post_process: Vec<PostProcessEffect>,
+
+    /// The strength of bloom, when the `bloom` post-processing effect is enabled. Adjust at runtime with `[` and `]`.
+    #[arg(long, default_value_t = 0.5)]
+    pub bloom_intensity: f32,
+
+    /// The brightness above which light blooms, where one is SDR white. Adjust at runtime with `,` and `.`.
+    #[arg(long, default_value_t = 1.)]
+    pub bloom_threshold: f32,
+
+    /// The fraction of the window width and height the scene is rendered at, before being upscaled to the window.
+    #[arg(long, default_value_t = 1., value_parser = parse_render_scale)]
+    pub render_scale: f32,
+
+    /// The filter used to upscale the scene when the render scale is below one.
+    /// FSR is an edge-adaptive upscale followed by sharpening, in the style of AMD FidelityFX Super Resolution 1.
+    #[arg(long, default_value_t, value_enum)]
+    pub upscaler: Upscaler,
+
+    /// Automatically adjust the render scale to keep the GPU time of each frame near this many milliseconds, starting from `--render-scale`.
+    #[arg(long, value_name = "MILLISECONDS", value_parser = parse_frame_time)]
+    pub target_frame_time: Option<f32>,
+
     /// Prefer presenting to an HDR colorspace if available.
     #[arg(long, default_value_t)]
     pub hdr: bool,
+
+    /// The advection scheme used by the fluid simulation.
+    /// MacCormack preserves much finer detail than semi-Lagrangian at a higher cost per step.
+    #[arg(long, default_value_t, value_enum)]
+    pub advection: AdvectionScheme,
+
+    /// The number of fluid simulation steps per second. The simulation advances at a fixed timestep, independent of the frame rate.
+    #[arg(long, default_value_t = 60., value_parser = parse_simulation_rate)]
+    pub simulation_rate: f32,
+
+    /// The maximum number of fluid simulation steps to compute in a single frame.
+    /// When the frame rate falls too low to keep up, the simulation slows down instead of taking ever longer frames.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_substeps: u32,
+
+    /// Step the fluid simulation on a separate, low-priority compute queue when the device has one, so that rendering takes precedence.
+    /// Otherwise, the simulation is stepped on the graphics queue.
+    #[arg(long, default_value_t)]
+    pub async_compute: bool,
+
+    /// Simulate buoyant smoke in the fluid demo. The cursor injects hot, dense smoke which rises as it cools.
+    #[arg(long, default_value_t)]
+    pub smoke: bool,
+
+    /// The fluid simulation snapshot file. Press F5 to save the current state to it and F9 to load from it.
+    #[arg(long, default_value = "fluid.snapshot")]
+    pub snapshot_file: std::path::PathBuf,
+
+    /// Load the fluid simulation snapshot file at startup.
+    #[arg(long, default_value_t)]
+    pub load_snapshot: bool,
+
+    /// Record every input event and the frame timings to the given file, so that the session can be replayed.
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<std::path::PathBuf>,
+
+    /// Replay the inputs and frame timings recorded to the given file, ignoring live input, then exit.
+    /// The main window is resized to match the recording, and the replay stops with an error if it can't be.
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<std::path::PathBuf>,
 }
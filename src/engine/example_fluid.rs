@@ -1,5 +1,6 @@
 use crate::engine::utils::{
     self,
+    deletion_queue::{DeferredDestroy, DeletionQueue},
     shaders::{ENTRY_POINT_MAIN, FULLSCREEN_VERTEX},
     FIVE_SECONDS_IN_NANOSECONDS,
 };
@@ -8,6 +9,8 @@ pub mod shaders {
     /// Standard triangle-example fragment shader.
     pub const FLUID_ADVECTION: &[u32] =
         inline_spirv::include_spirv!("src/shaders/example_fluid_advection.comp", comp, glsl);
+    pub const FLUID_BUOYANCY: &[u32] =
+        inline_spirv::include_spirv!("src/shaders/example_fluid_buoyancy.comp", comp, glsl);
     pub const FLUID_CURL: &[u32] =
         inline_spirv::include_spirv!("src/shaders/example_fluid_curl.comp", comp, glsl);
     pub const FLUID_DIVERGENCE: &[u32] =
@@ -29,6 +32,15 @@ pub mod shaders {
 /// This value is half the number of iterations used elsewhere because each iteration has two stages which are interleaved.
 const MAX_PRESSURE_SMOOTHING_ITERATIONS: u32 = 16;
 
+/// The bytes at the start of every fluid simulation snapshot file.
+const SNAPSHOT_MAGIC: [u8; 8] = *b"PMPFLUID";
+
+/// The version of the snapshot file layout. Increment this whenever the header or field layout changes.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// The only element format currently written to snapshots: little-endian 32-bit floats.
+const SNAPSHOT_FORMAT_F32_LE: u32 = 0;
+
 /// Define the shared push constants for each compute stage of this minimal fluid simulation.
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -42,6 +54,8 @@ pub struct PushConstants {
     pub output_velocity_buffer: ash::vk::DeviceAddress,
     pub input_dye_buffer: ash::vk::DeviceAddress,
     pub output_dye_buffer: ash::vk::DeviceAddress,
+    pub input_smoke_buffer: ash::vk::DeviceAddress,
+    pub output_smoke_buffer: ash::vk::DeviceAddress,
 
     // Fluid simulation parameters.
     pub cursor_dye: [f32; 4],
@@ -51,11 +65,74 @@ pub struct PushConstants {
 
     pub delta_time: f32,
 
+    /// Sane values are 0 to 50. Default is 30.
+    pub vorticity_strength: f32,
+}
+
+/// Define the numerical scheme used to advect the velocity and dye fields.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, strum::EnumCount, strum::FromRepr)]
+pub enum AdvectionScheme {
+    /// Single backward step with bilinear sampling. Cheap and stable, but numerically diffusive.
+    #[default]
+    SemiLagrangian,
+
+    /// Corrects the semi-Lagrangian step with an estimate of its own error, limited to the range of the sampled texels.
+    /// Preserves much finer detail in the dye and velocity fields at roughly six times the sampling cost, as the error estimate repeats the backward step at four texels.
+    MacCormack,
+}
+impl AdvectionScheme {
+    /// Get the next advection scheme in a cycle of types.
+    pub fn next(self) -> Self {
+        AdvectionScheme::from_repr((self as u32).wrapping_add(1))
+            .unwrap_or(AdvectionScheme::SemiLagrangian)
+    }
+}
+
+/// Define the specialization constants that can be used with the compute shaders of the fluid simulation.
+/// Only the advection and buoyancy shaders accept application data this way, each using the subset of constants it declares.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SpecializationConstants {
+    /// The numerical scheme used by the advection stage.
+    pub advection_scheme: AdvectionScheme,
+
+    /// Exponential decay rates of the velocity and dye fields, per second.
     pub velocity_diffusion_rate: f32,
     pub dye_diffusion_rate: f32,
 
-    /// Sane values are 0 to 50. Default is 30.
-    pub vorticity_strength: f32,
+    /// Transport temperature and smoke density fields and apply their buoyant forces to the velocity field.
+    pub smoke: ash::vk::Bool32,
+
+    /// Exponential rate at which the temperature returns to ambient, per second.
+    pub temperature_cooling_rate: f32,
+
+    /// Exponential decay rate of the smoke density, per second.
+    pub smoke_dissipation_rate: f32,
+
+    /// The upward acceleration per unit of temperature above ambient, in pixels per second squared.
+    pub buoyancy: f32,
+
+    /// The downward acceleration per unit of smoke density, in pixels per second squared.
+    pub smoke_weight: f32,
+
+    /// The temperature which the smoke cools towards and which produces no buoyant force.
+    pub ambient_temperature: f32,
+}
+impl Default for SpecializationConstants {
+    fn default() -> Self {
+        Self {
+            advection_scheme: AdvectionScheme::default(),
+            velocity_diffusion_rate: 0.12,
+            dye_diffusion_rate: 1.2,
+            smoke: ash::vk::FALSE,
+            temperature_cooling_rate: 0.6,
+            smoke_dissipation_rate: 0.25,
+            buoyancy: 180.,
+            smoke_weight: 40.,
+            ambient_temperature: 0.,
+        }
+    }
 }
 
 /// Define the texture to display from the fluid simulation.
@@ -70,6 +147,7 @@ pub enum FluidDisplayTexture {
     ColorFieldLine,
     ColorFieldCircle,
     ColorFieldGradient,
+    Temperature,
 }
 impl FluidDisplayTexture {
     /// Get the next fluid display variant in a cycle of types.
@@ -87,10 +165,20 @@ struct FragmentPushConstants {
     pub velocity_buffer: ash::vk::DeviceAddress,
     pub dye_buffer: ash::vk::DeviceAddress,
     pub pressure_buffer: ash::vk::DeviceAddress,
+    pub smoke_buffer: ash::vk::DeviceAddress,
 
     // Fluid simulation parameters.
     pub screen_size: [u32; 2],
     pub display_texture: FluidDisplayTexture,
+
+    /// The sub-pixel offset at which each pixel samples the simulation, for temporal post-processing.
+    pub jitter: [f32; 2],
+
+    /// The size of the image being drawn to, which the simulation grid is scaled to fill.
+    pub render_size: [u32; 2],
+
+    /// The pixels left of this position sample with the jitter, and the rest without it.
+    pub jittered_width: u32,
 }
 
 /// Create the render pass capable of orchestrating the rendering of framebuffers for this application.
@@ -254,20 +342,19 @@ impl AllocatedBuffer {
     }
 }
 
-/// Create a framebuffer that can be used with this render pass.
-pub fn create_framebuffers(
+/// Create the buffers storing the state and partial results of the fluid simulation, with one element per cell of the simulation grid.
+fn create_simulation_buffers(
     device: &ash::Device,
     memory_allocator: &mut gpu_allocator::vulkan::Allocator,
     extent: ash::vk::Extent2D,
-    destination_views: &[ash::vk::ImageView],
-    render_pass: ash::vk::RenderPass,
     pageable_device_local_memory: Option<&ash::ext::pageable_device_local_memory::Device>,
-) -> (Vec<ash::vk::Framebuffer>, [AllocatedBuffer; 8]) {
+) -> [AllocatedBuffer; 10] {
     // Create several images for storing the partial results of the fluid simulation each frame.
-    // These images are not strictly part of the framebuffers, but are used in the fluid simulation and dependent on the size of the surface.
     let mut buffer_info = ash::vk::BufferCreateInfo::default().usage(
         ash::vk::BufferUsageFlags::STORAGE_BUFFER
-            | ash::vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            | ash::vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS
+            | ash::vk::BufferUsageFlags::TRANSFER_SRC
+            | ash::vk::BufferUsageFlags::TRANSFER_DST,
     );
     let pixel_count = u64::from(extent.width) * u64::from(extent.height);
 
@@ -335,8 +422,72 @@ pub fn create_framebuffers(
         pageable_device_local_memory,
     );
 
-    // The actual render pass framebuffers simply draw to the destination views as color attachments.
-    let framebuffers = destination_views
+    // The smoke buffers store temperature and density pairs.
+    buffer_info.size = pixel_count * std::mem::size_of::<[f32; 2]>() as u64;
+    let input_smoke_image = AllocatedBuffer::new(
+        device,
+        memory_allocator,
+        &buffer_info,
+        "Fluid Sim input smoke buffer",
+        pageable_device_local_memory,
+    );
+    let output_smoke_image = AllocatedBuffer::new(
+        device,
+        memory_allocator,
+        &buffer_info,
+        "Fluid Sim output smoke buffer",
+        pageable_device_local_memory,
+    );
+
+    [
+        input_velocity_image,
+        curl_image,
+        divergence_image,
+        alpha_pressure_image,
+        beta_pressure_image,
+        output_velocity_image,
+        input_dye_image,
+        output_dye_image,
+        input_smoke_image,
+        output_smoke_image,
+    ]
+}
+
+/// Declare the device extensions and features the simulation needs.
+/// The fields are buffers accessed by their device address, and are given a high priority when device memory is pageable.
+pub fn device_requirements(
+    builder: utils::device_builder::DeviceBuilder,
+) -> utils::device_builder::DeviceBuilder {
+    builder
+        .require_features(&utils::EnginePhysicalDeviceFeatures {
+            buffer_device_address: ash::vk::PhysicalDeviceBufferDeviceAddressFeatures::default()
+                .buffer_device_address(true),
+            ..Default::default()
+        })
+        .optional_extensions(
+            &[
+                ash::ext::memory_priority::NAME,
+                ash::ext::pageable_device_local_memory::NAME,
+            ],
+            utils::EnginePhysicalDeviceFeatures {
+                pageable_device_local_memory:
+                    ash::vk::PhysicalDevicePageableDeviceLocalMemoryFeaturesEXT::default()
+                        .pageable_device_local_memory(true),
+                ..Default::default()
+            },
+            &[],
+        )
+}
+
+/// Create the render pass framebuffers, which simply draw to the destination views as color attachments.
+/// The destination may be a different size than the simulation, which is scaled to fill the area of it rendered to each frame.
+pub fn create_destination_framebuffers(
+    device: &ash::Device,
+    extent: ash::vk::Extent2D,
+    destination_views: &[ash::vk::ImageView],
+    render_pass: ash::vk::RenderPass,
+) -> Vec<ash::vk::Framebuffer> {
+    destination_views
         .iter()
         .map(|destination_view| {
             let framebuffer_info = ash::vk::FramebufferCreateInfo {
@@ -349,23 +500,9 @@ pub fn create_framebuffers(
                 ..Default::default()
             };
             unsafe { device.create_framebuffer(&framebuffer_info, None) }
-                .expect("Failed to create framebuffer for FXAA post-processing")
+                .expect("Failed to create framebuffer for the fluid simulation")
         })
-        .collect();
-
-    (
-        framebuffers,
-        [
-            input_velocity_image,
-            curl_image,
-            divergence_image,
-            alpha_pressure_image,
-            beta_pressure_image,
-            output_velocity_image,
-            input_dye_image,
-            output_dye_image,
-        ],
-    )
+        .collect()
 }
 
 /// Create the compute and graphics pipeline layouts.
@@ -407,6 +544,7 @@ fn create_pipeline_layout(device: &ash::Device) -> [ash::vk::PipelineLayout; 2]
 /// Helper for creating and maintaining the shaders used in the fluid simulation.
 struct FluidShaders {
     pub advection: ash::vk::ShaderModule,
+    pub buoyancy: ash::vk::ShaderModule,
     pub curl: ash::vk::ShaderModule,
     pub divergence: ash::vk::ShaderModule,
     pub gradient_subtract: ash::vk::ShaderModule,
@@ -419,6 +557,7 @@ impl FluidShaders {
     /// Create the shader modules for the fluid simulation.
     pub fn new(device: &ash::Device) -> Self {
         let advection = utils::create_shader_module(device, shaders::FLUID_ADVECTION);
+        let buoyancy = utils::create_shader_module(device, shaders::FLUID_BUOYANCY);
         let curl = utils::create_shader_module(device, shaders::FLUID_CURL);
         let divergence = utils::create_shader_module(device, shaders::FLUID_DIVERGENCE);
         let gradient_subtract =
@@ -430,6 +569,7 @@ impl FluidShaders {
 
         Self {
             advection,
+            buoyancy,
             curl,
             divergence,
             gradient_subtract,
@@ -444,6 +584,7 @@ impl FluidShaders {
     pub fn destroy(&self, device: &ash::Device) {
         unsafe {
             device.destroy_shader_module(self.advection, None);
+            device.destroy_shader_module(self.buoyancy, None);
             device.destroy_shader_module(self.curl, None);
             device.destroy_shader_module(self.divergence, None);
             device.destroy_shader_module(self.gradient_subtract, None);
@@ -483,9 +624,82 @@ fn create_compute_pipeline(
         .into_iter().next().expect("vkCreateComputePipelines returned an empty list of pipelines but provided a successful result")
 }
 
+/// Create a compute pipeline for a stage which depends on the application's specialization constants.
+/// The same map entries are used for every such stage; entries for constants a shader does not declare are ignored.
+fn create_specialized_pipeline(
+    device: &ash::Device,
+    pipeline_layout: ash::vk::PipelineLayout,
+    shader_module: ash::vk::ShaderModule,
+    specialization_constants: &SpecializationConstants,
+) -> ash::vk::Pipeline {
+    let map_entry = |constant_id, offset, size| ash::vk::SpecializationMapEntry {
+        constant_id,
+        offset: offset as u32,
+        size,
+    };
+    let specialization_map = [
+        map_entry(
+            0,
+            std::mem::offset_of!(SpecializationConstants, advection_scheme),
+            std::mem::size_of::<AdvectionScheme>(),
+        ),
+        map_entry(
+            1,
+            std::mem::offset_of!(SpecializationConstants, velocity_diffusion_rate),
+            std::mem::size_of::<f32>(),
+        ),
+        map_entry(
+            2,
+            std::mem::offset_of!(SpecializationConstants, dye_diffusion_rate),
+            std::mem::size_of::<f32>(),
+        ),
+        map_entry(
+            3,
+            std::mem::offset_of!(SpecializationConstants, smoke),
+            std::mem::size_of::<ash::vk::Bool32>(),
+        ),
+        map_entry(
+            4,
+            std::mem::offset_of!(SpecializationConstants, temperature_cooling_rate),
+            std::mem::size_of::<f32>(),
+        ),
+        map_entry(
+            5,
+            std::mem::offset_of!(SpecializationConstants, smoke_dissipation_rate),
+            std::mem::size_of::<f32>(),
+        ),
+        map_entry(
+            6,
+            std::mem::offset_of!(SpecializationConstants, buoyancy),
+            std::mem::size_of::<f32>(),
+        ),
+        map_entry(
+            7,
+            std::mem::offset_of!(SpecializationConstants, smoke_weight),
+            std::mem::size_of::<f32>(),
+        ),
+        map_entry(
+            8,
+            std::mem::offset_of!(SpecializationConstants, ambient_temperature),
+            std::mem::size_of::<f32>(),
+        ),
+    ];
+    create_compute_pipeline(
+        device,
+        pipeline_layout,
+        shader_module,
+        Some(
+            ash::vk::SpecializationInfo::default()
+                .map_entries(&specialization_map)
+                .data(utils::data_byte_slice(specialization_constants)),
+        ),
+    )
+}
+
 /// Helper to create and manage all of the compute pipelines for the fluid simulation.
 struct FluidComputeStages {
     advection: ash::vk::Pipeline,
+    buoyancy: ash::vk::Pipeline,
     curl: ash::vk::Pipeline,
     divergence: ash::vk::Pipeline,
     gradient_subtract: ash::vk::Pipeline,
@@ -499,8 +713,20 @@ impl FluidComputeStages {
         device: &ash::Device,
         pipeline_layout: ash::vk::PipelineLayout,
         shaders: &FluidShaders,
+        specialization_constants: &SpecializationConstants,
     ) -> Self {
-        let advection = create_compute_pipeline(device, pipeline_layout, shaders.advection, None);
+        let advection = create_specialized_pipeline(
+            device,
+            pipeline_layout,
+            shaders.advection,
+            specialization_constants,
+        );
+        let buoyancy = create_specialized_pipeline(
+            device,
+            pipeline_layout,
+            shaders.buoyancy,
+            specialization_constants,
+        );
         let curl = create_compute_pipeline(device, pipeline_layout, shaders.curl, None);
         let divergence = create_compute_pipeline(device, pipeline_layout, shaders.divergence, None);
         let gradient_subtract =
@@ -534,6 +760,7 @@ impl FluidComputeStages {
 
         Self {
             advection,
+            buoyancy,
             curl,
             divergence,
             gradient_subtract,
@@ -547,6 +774,7 @@ impl FluidComputeStages {
     pub fn destroy(&self, device: &ash::Device) {
         unsafe {
             device.destroy_pipeline(self.advection, None);
+            device.destroy_pipeline(self.buoyancy, None);
             device.destroy_pipeline(self.curl, None);
             device.destroy_pipeline(self.divergence, None);
             device.destroy_pipeline(self.gradient_subtract, None);
@@ -618,6 +846,250 @@ fn create_graphics_pipeline(
         .into_iter().next().expect("vkCreateGraphicsPipelines returned an empty list of pipelines but provided a successful result")
 }
 
+/// The simulation fields which are stored in a snapshot.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::FromRepr)]
+enum SnapshotField {
+    Velocity,
+    Pressure,
+    Dye,
+    Smoke,
+}
+impl SnapshotField {
+    /// All of the fields, in the order they are written to a snapshot.
+    const ALL: [Self; 4] = [Self::Velocity, Self::Pressure, Self::Dye, Self::Smoke];
+
+    /// The index of the `FluidSimulation::allocated_images` buffer holding the current state of this field.
+    fn buffer_index(self) -> usize {
+        match self {
+            Self::Velocity => 0,
+            Self::Pressure => 3,
+            Self::Dye => 6,
+            Self::Smoke => 8,
+        }
+    }
+
+    /// The number of `f32` components stored per pixel.
+    fn components(self) -> usize {
+        match self {
+            Self::Velocity | Self::Smoke => 2,
+            Self::Pressure => 1,
+            Self::Dye => 4,
+        }
+    }
+}
+
+/// Read a little-endian `u32` from the snapshot stream.
+fn read_u32(reader: &mut impl std::io::Read) -> std::io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Read a little-endian `f32` from the snapshot stream.
+fn read_f32(reader: &mut impl std::io::Read) -> std::io::Result<f32> {
+    read_u32(reader).map(f32::from_bits)
+}
+
+/// Create an error describing a malformed snapshot.
+fn invalid_snapshot(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// The header at the start of a snapshot, describing the grid, the simulation parameters, and the fields which follow it.
+struct SnapshotHeader {
+    extent: ash::vk::Extent2D,
+    specialization_constants: SpecializationConstants,
+    fields: Vec<SnapshotField>,
+}
+impl SnapshotHeader {
+    /// Write the header in the snapshot layout, with every value as little-endian.
+    fn write(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        writer.write_all(&SNAPSHOT_MAGIC)?;
+        let constants = &self.specialization_constants;
+        for value in [
+            SNAPSHOT_VERSION,
+            self.extent.width,
+            self.extent.height,
+            SNAPSHOT_FORMAT_F32_LE,
+            constants.advection_scheme as u32,
+            constants.velocity_diffusion_rate.to_bits(),
+            constants.dye_diffusion_rate.to_bits(),
+            constants.smoke,
+            constants.temperature_cooling_rate.to_bits(),
+            constants.smoke_dissipation_rate.to_bits(),
+            constants.buoyancy.to_bits(),
+            constants.smoke_weight.to_bits(),
+            constants.ambient_temperature.to_bits(),
+            self.fields.len() as u32,
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        for field in &self.fields {
+            writer.write_all(&(*field as u32).to_le_bytes())?;
+            writer.write_all(&(field.components() as u32).to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Read and validate a header, including that its fields fit in the rest of the `stream_length` bytes.
+    fn read(
+        reader: &mut (impl std::io::Read + std::io::Seek),
+        stream_length: u64,
+    ) -> std::io::Result<Self> {
+        let mut magic = [0; SNAPSHOT_MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != SNAPSHOT_MAGIC {
+            return Err(invalid_snapshot(
+                "The file is not a fluid simulation snapshot".to_owned(),
+            ));
+        }
+        let version = read_u32(reader)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(invalid_snapshot(format!(
+                "Unsupported snapshot version {version}, expected {SNAPSHOT_VERSION}"
+            )));
+        }
+        let extent = ash::vk::Extent2D {
+            width: read_u32(reader)?,
+            height: read_u32(reader)?,
+        };
+        if extent.width == 0 || extent.height == 0 {
+            return Err(invalid_snapshot(format!(
+                "Snapshot has an empty grid size {extent:?}"
+            )));
+        }
+        let format = read_u32(reader)?;
+        if format != SNAPSHOT_FORMAT_F32_LE {
+            return Err(invalid_snapshot(format!(
+                "Unsupported snapshot element format {format}"
+            )));
+        }
+
+        let advection_scheme = read_u32(reader)?;
+        let specialization_constants = SpecializationConstants {
+            advection_scheme: AdvectionScheme::from_repr(advection_scheme).ok_or_else(|| {
+                invalid_snapshot(format!("Unknown advection scheme {advection_scheme}"))
+            })?,
+            velocity_diffusion_rate: read_f32(reader)?,
+            dye_diffusion_rate: read_f32(reader)?,
+            smoke: read_u32(reader)?,
+            temperature_cooling_rate: read_f32(reader)?,
+            smoke_dissipation_rate: read_f32(reader)?,
+            buoyancy: read_f32(reader)?,
+            smoke_weight: read_f32(reader)?,
+            ambient_temperature: read_f32(reader)?,
+        };
+
+        let field_count = read_u32(reader)?;
+        let mut fields = Vec::new();
+        for _ in 0..field_count {
+            let id = read_u32(reader)?;
+            let components = read_u32(reader)?;
+            let field = SnapshotField::from_repr(id)
+                .ok_or_else(|| invalid_snapshot(format!("Unknown snapshot field {id}")))?;
+            if components as usize != field.components() {
+                return Err(invalid_snapshot(format!(
+                    "Snapshot field {field:?} has {components} components, expected {}",
+                    field.components()
+                )));
+            }
+            fields.push(field);
+        }
+
+        // Check that the fields fit in the rest of the stream before they are allocated, since a corrupt grid size could be huge.
+        let field_size = |field: SnapshotField| {
+            (extent.width as usize)
+                .checked_mul(extent.height as usize)?
+                .checked_mul(field.components() * std::mem::size_of::<f32>())
+        };
+        let fields_size = fields.iter().try_fold(0usize, |total, &field| {
+            total.checked_add(field_size(field)?)
+        });
+        let remaining_length = stream_length.saturating_sub(reader.stream_position()?);
+        if fields_size.is_none_or(|size| size as u64 > remaining_length) {
+            return Err(invalid_snapshot(format!(
+                "Snapshot fields for grid size {extent:?} exceed the remaining {remaining_length} bytes of the file"
+            )));
+        }
+
+        Ok(Self {
+            extent,
+            specialization_constants,
+            fields,
+        })
+    }
+}
+
+/// Bilinearly resample a field with interleaved components from one grid size to another.
+/// Samples are taken at pixel centers and clamped to the edges of the source grid.
+fn resample_field(
+    data: &[f32],
+    components: usize,
+    source: ash::vk::Extent2D,
+    destination: ash::vk::Extent2D,
+) -> Vec<f32> {
+    let scale_x = source.width as f32 / destination.width as f32;
+    let scale_y = source.height as f32 / destination.height as f32;
+    let max_x = source.width as usize - 1;
+    let max_y = source.height as usize - 1;
+    let texel =
+        |x: usize, y: usize, c: usize| data[(y * source.width as usize + x) * components + c];
+
+    let mut resampled =
+        Vec::with_capacity(destination.width as usize * destination.height as usize * components);
+    for y in 0..destination.height {
+        let source_y = ((y as f32 + 0.5) * scale_y - 0.5).max(0.);
+        let y0 = (source_y as usize).min(max_y);
+        let y1 = (y0 + 1).min(max_y);
+        let fy = source_y - y0 as f32;
+        for x in 0..destination.width {
+            let source_x = ((x as f32 + 0.5) * scale_x - 0.5).max(0.);
+            let x0 = (source_x as usize).min(max_x);
+            let x1 = (x0 + 1).min(max_x);
+            let fx = source_x - x0 as f32;
+            for c in 0..components {
+                let top = texel(x0, y0, c) * (1. - fx) + texel(x1, y0, c) * fx;
+                let bottom = texel(x0, y1, c) * (1. - fx) + texel(x1, y1, c) * fx;
+                resampled.push(top * (1. - fy) + bottom * fy);
+            }
+        }
+    }
+    resampled
+}
+
+/// Create a host-visible buffer for transferring simulation data between the host and the device.
+fn create_staging_buffer(
+    device: &ash::Device,
+    memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    size: u64,
+    usage: ash::vk::BufferUsageFlags,
+    location: gpu_allocator::MemoryLocation,
+) -> (ash::vk::Buffer, gpu_allocator::vulkan::Allocation) {
+    let buffer = unsafe {
+        device.create_buffer(
+            &ash::vk::BufferCreateInfo::default().size(size).usage(usage),
+            None,
+        )
+    }
+    .expect("Unable to create the staging buffer for the fluid simulation");
+    let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+
+    let allocation = memory_allocator
+        .allocate(&gpu_allocator::vulkan::AllocationCreateDesc {
+            name: "Fluid Sim staging buffer",
+            requirements,
+            location,
+            linear: true,
+            allocation_scheme: gpu_allocator::vulkan::AllocationScheme::DedicatedBuffer(buffer),
+        })
+        .expect("Unable to allocate the staging buffer for the fluid simulation");
+    unsafe { device.bind_buffer_memory(buffer, allocation.memory(), allocation.offset()) }
+        .expect("Unable to bind the staging buffer memory for the fluid simulation");
+
+    (buffer, allocation)
+}
+
 /// The fluid simulation renderer and resources.
 pub struct FluidSimulation {
     shaders: FluidShaders,
@@ -626,40 +1098,46 @@ pub struct FluidSimulation {
     render_pass: ash::vk::RenderPass,
     compute_pipelines: FluidComputeStages,
     graphics_pipeline: ash::vk::Pipeline,
-    framebuffers: Vec<ash::vk::Framebuffer>,
+    extent: ash::vk::Extent2D,
     allocated_images: Vec<AllocatedBuffer>,
     compute_fence: ash::vk::Fence,
-    graphics_fence: Option<ash::vk::Fence>,
+    graphics_fences: Vec<ash::vk::Fence>, // The fences of the frames which have drawn the simulation since it last stepped.
     compute_command_buffer: ash::vk::CommandBuffer,
-    current_display_texture: FluidDisplayTexture,
+    specialization_constants: SpecializationConstants,
+    clear_smoke_buffers: bool,
+    pending_steps: u32,
+    jitter: utils::post_process::Jitter,
 }
 impl FluidSimulation {
-    /// Create a new fluid simulation renderer from the swapchain image properties.
+    /// Create a new fluid simulation renderer, simulating a grid of the given size and rendering to images of the given format.
     pub fn new(
         device: &ash::Device,
         memory_allocator: &mut gpu_allocator::vulkan::Allocator,
         extent: ash::vk::Extent2D,
         image_format: ash::vk::Format,
         destination_layout: ash::vk::ImageLayout,
-        destination_views: &[ash::vk::ImageView],
         compute_command_pool: ash::vk::CommandPool,
         pageable_device_local_memory: Option<&ash::ext::pageable_device_local_memory::Device>,
+        specialization_constants: SpecializationConstants,
     ) -> Self {
         let shaders = FluidShaders::new(device);
         let [compute_pipeline_layout, graphics_pipeline_layout] = create_pipeline_layout(device);
 
         let render_pass = create_render_pass(device, image_format, destination_layout);
 
-        let compute_pipelines = FluidComputeStages::new(device, compute_pipeline_layout, &shaders);
+        let compute_pipelines = FluidComputeStages::new(
+            device,
+            compute_pipeline_layout,
+            &shaders,
+            &specialization_constants,
+        );
         let graphics_pipeline =
             create_graphics_pipeline(device, &shaders, graphics_pipeline_layout, render_pass);
 
-        let (framebuffers, allocated_images) = create_framebuffers(
+        let allocated_images = create_simulation_buffers(
             device,
             memory_allocator,
             extent,
-            destination_views,
-            render_pass,
             pageable_device_local_memory,
         );
 
@@ -691,12 +1169,15 @@ impl FluidSimulation {
             render_pass,
             compute_pipelines,
             graphics_pipeline,
-            framebuffers,
+            extent,
             allocated_images: allocated_images.into(),
             compute_fence,
-            graphics_fence: None,
+            graphics_fences: Vec::new(),
             compute_command_buffer,
-            current_display_texture: FluidDisplayTexture::default(),
+            specialization_constants,
+            clear_smoke_buffers: true,
+            pending_steps: 1,
+            jitter: utils::post_process::Jitter::default(),
         }
     }
 
@@ -715,9 +1196,6 @@ impl FluidSimulation {
             for image in self.allocated_images.drain(..) {
                 image.destroy(device, memory_allocator);
             }
-            for framebuffer in &self.framebuffers {
-                device.destroy_framebuffer(*framebuffer, None);
-            }
 
             device.destroy_pipeline(self.graphics_pipeline, None);
             self.compute_pipelines.destroy(device);
@@ -730,76 +1208,64 @@ impl FluidSimulation {
         self.shaders.destroy(device);
     }
 
-    /// Create the framebuffers, likely after a swapchain recreation.
-    /// # Safety
-    /// * The existing render pass must be valid.
-    pub fn recreate_framebuffers(
+    /// Recreate the simulation buffers for a grid of a new size, likely after the render resolution has changed.
+    /// The old simulation buffers are destroyed once the frames in flight have completed.
+    pub fn recreate_simulation_buffers(
         &mut self,
         device: &ash::Device,
         memory_allocator: &mut gpu_allocator::vulkan::Allocator,
         extent: ash::vk::Extent2D,
-        destination_views: &[ash::vk::ImageView],
         pageable_device_local_memory: Option<&ash::ext::pageable_device_local_memory::Device>,
+        deletion_queue: &mut DeletionQueue,
     ) {
-        // Wait for the compute fence to ensure all resources are available.
-        unsafe {
-            let fences = [
-                self.compute_fence,
-                self.graphics_fence.take().unwrap_or_default(),
-            ];
-            device
-                .wait_for_fences(
-                    if self.graphics_fence.is_some() {
-                        &fences
-                    } else {
-                        &fences[..1]
-                    },
-                    true,
-                    FIVE_SECONDS_IN_NANOSECONDS,
-                )
-                .expect(
-                    "Failed to wait for the compute or graphics fence for the fluid simulation",
-                );
-        }
-
-        unsafe {
-            device.destroy_fence(self.compute_fence, None);
-            self.compute_fence = device
-                .create_fence(
-                    &ash::vk::FenceCreateInfo::default().flags(ash::vk::FenceCreateFlags::SIGNALED),
-                    None,
-                )
-                .expect("Unable to create the compute fence for the fluid simulation");
-        }
-
-        for framebuffer in &self.framebuffers {
-            unsafe {
-                device.destroy_framebuffer(*framebuffer, None);
-            }
-        }
         for allocated_image in self.allocated_images.drain(..) {
-            allocated_image.destroy(device, memory_allocator);
+            deletion_queue.push(DeferredDestroy::Buffer(
+                allocated_image.buffer,
+                allocated_image.allocation,
+            ));
         }
-        let (framebuffers, allocated_images) = create_framebuffers(
+        let allocated_images = create_simulation_buffers(
             device,
             memory_allocator,
             extent,
-            destination_views,
-            self.render_pass,
             pageable_device_local_memory,
         );
-        self.framebuffers = framebuffers;
+        self.extent = extent;
         self.allocated_images = allocated_images.into();
+        self.clear_smoke_buffers = true;
+    }
+
+    /// Recreate the render pass and graphics pipeline to render to destination images with a new format or layout.
+    /// The simulation buffers are untouched, so the state of the simulation is preserved.
+    /// The old render pass and pipeline are destroyed once the frames in flight have completed, and framebuffers must be recreated by the caller.
+    pub fn recreate_render_pass(
+        &mut self,
+        device: &ash::Device,
+        image_format: ash::vk::Format,
+        destination_layout: ash::vk::ImageLayout,
+        deletion_queue: &mut DeletionQueue,
+    ) {
+        deletion_queue.push(DeferredDestroy::Pipeline(self.graphics_pipeline));
+        deletion_queue.push(DeferredDestroy::RenderPass(self.render_pass));
+
+        self.render_pass = create_render_pass(device, image_format, destination_layout);
+        self.graphics_pipeline = create_graphics_pipeline(
+            device,
+            &self.shaders,
+            self.graphics_pipeline_layout,
+            self.render_pass,
+        );
     }
 
     /// Helper to record the compute commands for the fluid simulation to the desired command buffer.
+    /// Records `steps` consecutive simulation steps, swapping the input and output buffers after each one.
     /// # Safety
     /// The command buffer must not be in the recording state.
     fn create_compute_command_buffer(
         &mut self,
         device: &ash::Device,
-        extent: ash::vk::Extent2D,
         push_constants: &PushConstants,
+        steps: u32,
     ) {
         // Ensure that the command buffer is in the recording state.
         unsafe {
@@ -830,64 +1296,101 @@ impl FluidSimulation {
             }
         };
 
-        // Record the compute commands for each stage of the fluid simulation.
-        unsafe {
-            device.cmd_push_constants(
-                self.compute_command_buffer,
-                self.compute_pipeline_layout,
-                ash::vk::ShaderStageFlags::COMPUTE,
-                0,
-                utils::data_byte_slice(push_constants),
+        // The smoke buffers are only written while smoke is enabled, but may still be displayed, so clear them before their first use.
+        let smoke_enabled = self.specialization_constants.smoke != ash::vk::FALSE;
+        if self.clear_smoke_buffers {
+            unsafe {
+                for smoke_buffer in &self.allocated_images[8..10] {
+                    device.cmd_fill_buffer(
+                        self.compute_command_buffer,
+                        smoke_buffer.buffer,
+                        0,
+                        ash::vk::WHOLE_SIZE,
+                        0,
+                    );
+                }
+            }
+            add_barrier(
+                ash::vk::PipelineStageFlags2::CLEAR,
+                ash::vk::AccessFlags2::TRANSFER_WRITE,
+                ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                ash::vk::AccessFlags2::SHADER_READ | ash::vk::AccessFlags2::SHADER_WRITE,
             );
+            self.clear_smoke_buffers = false;
+        }
 
+        // Record the compute commands for each stage of the fluid simulation.
+        unsafe {
             // NOTE: The use of `8` here is directly related to the local group size in the compute shaders.
             // 8*8=64 is a multiple of 64 to accommodate NVIDIA and AMD physical hardware.
-            let workgroups_x = extent.width / 8 + u32::from(extent.width % 8 != 0);
-            let workgroups_y = extent.height / 8 + u32::from(extent.height % 8 != 0);
-
-            // Apply the curl compute shader.
-            device.cmd_bind_pipeline(
-                self.compute_command_buffer,
-                ash::vk::PipelineBindPoint::COMPUTE,
-                self.compute_pipelines.curl,
-            );
-            device.cmd_dispatch(self.compute_command_buffer, workgroups_x, workgroups_y, 1);
-
-            // The vorticity compute shader requires sampling the curl texture, so add a barrier.
-            add_barrier(
-                ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
-                ash::vk::AccessFlags2::SHADER_WRITE,
-                ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
-                ash::vk::AccessFlags2::SHADER_READ,
-            );
+            let ash::vk::Extent2D { width, height } = self.extent;
+            let workgroups_x = width / 8 + u32::from(width % 8 != 0);
+            let workgroups_y = height / 8 + u32::from(height % 8 != 0);
+
+            for step in 0..steps {
+                // Each step reads the output of the previous step, so add a barrier.
+                if step > 0 {
+                    add_barrier(
+                        ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                        ash::vk::AccessFlags2::SHADER_WRITE,
+                        ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                        ash::vk::AccessFlags2::SHADER_READ | ash::vk::AccessFlags2::SHADER_WRITE,
+                    );
+                }
+
+                // Point the push constants at the current input and output buffers.
+                let mut step_push_constants = *push_constants;
+                self.set_buffer_addresses(&mut step_push_constants);
+                device.cmd_push_constants(
+                    self.compute_command_buffer,
+                    self.compute_pipeline_layout,
+                    ash::vk::ShaderStageFlags::COMPUTE,
+                    0,
+                    utils::data_byte_slice(&step_push_constants),
+                );
 
-            // Apply the vorticity compute shader.
-            device.cmd_bind_pipeline(
-                self.compute_command_buffer,
-                ash::vk::PipelineBindPoint::COMPUTE,
-                self.compute_pipelines.vorticity,
-            );
-            device.cmd_dispatch(self.compute_command_buffer, workgroups_x, workgroups_y, 1);
+                // Apply the curl compute shader.
+                device.cmd_bind_pipeline(
+                    self.compute_command_buffer,
+                    ash::vk::PipelineBindPoint::COMPUTE,
+                    self.compute_pipelines.curl,
+                );
+                device.cmd_dispatch(self.compute_command_buffer, workgroups_x, workgroups_y, 1);
 
-            // The divergence compute shader requires sampling the velocity texture, so add a barrier.
-            add_barrier(
-                ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
-                ash::vk::AccessFlags2::SHADER_WRITE,
-                ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
-                ash::vk::AccessFlags2::SHADER_READ,
-            );
+                // The vorticity compute shader requires sampling the curl texture, so add a barrier.
+                add_barrier(
+                    ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                    ash::vk::AccessFlags2::SHADER_WRITE,
+                    ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                    ash::vk::AccessFlags2::SHADER_READ,
+                );
 
-            // Apply the divergence compute shader.
-            device.cmd_bind_pipeline(
-                self.compute_command_buffer,
-                ash::vk::PipelineBindPoint::COMPUTE,
-                self.compute_pipelines.divergence,
-            );
-            device.cmd_dispatch(self.compute_command_buffer, workgroups_x, workgroups_y, 1);
+                // Apply the vorticity compute shader.
+                device.cmd_bind_pipeline(
+                    self.compute_command_buffer,
+                    ash::vk::PipelineBindPoint::COMPUTE,
+                    self.compute_pipelines.vorticity,
+                );
+                device.cmd_dispatch(self.compute_command_buffer, workgroups_x, workgroups_y, 1);
 
-            // Apply the pressure compute shaders in an iterative loop.
-            for _ in 0..MAX_PRESSURE_SMOOTHING_ITERATIONS {
-                // Both pressure stages are dependent on the previous pressure stage, so add a barrier.
+                // Apply the buoyancy compute shader when simulating smoke. It updates the same velocity texture as the vorticity stage.
+                if smoke_enabled {
+                    add_barrier(
+                        ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                        ash::vk::AccessFlags2::SHADER_WRITE,
+                        ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                        ash::vk::AccessFlags2::SHADER_READ | ash::vk::AccessFlags2::SHADER_WRITE,
+                    );
+
+                    device.cmd_bind_pipeline(
+                        self.compute_command_buffer,
+                        ash::vk::PipelineBindPoint::COMPUTE,
+                        self.compute_pipelines.buoyancy,
+                    );
+                    device.cmd_dispatch(self.compute_command_buffer, workgroups_x, workgroups_y, 1);
+                }
+
+                // The divergence compute shader requires sampling the velocity texture, so add a barrier.
                 add_barrier(
                     ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
                     ash::vk::AccessFlags2::SHADER_WRITE,
@@ -895,14 +1398,48 @@ impl FluidSimulation {
                     ash::vk::AccessFlags2::SHADER_READ,
                 );
 
+                // Apply the divergence compute shader.
                 device.cmd_bind_pipeline(
                     self.compute_command_buffer,
                     ash::vk::PipelineBindPoint::COMPUTE,
-                    self.compute_pipelines.alpha_pressure,
+                    self.compute_pipelines.divergence,
                 );
                 device.cmd_dispatch(self.compute_command_buffer, workgroups_x, workgroups_y, 1);
 
-                // A second-stage barrier.
+                // Apply the pressure compute shaders in an iterative loop.
+                for _ in 0..MAX_PRESSURE_SMOOTHING_ITERATIONS {
+                    // Both pressure stages are dependent on the previous pressure stage, so add a barrier.
+                    add_barrier(
+                        ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                        ash::vk::AccessFlags2::SHADER_WRITE,
+                        ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                        ash::vk::AccessFlags2::SHADER_READ,
+                    );
+
+                    device.cmd_bind_pipeline(
+                        self.compute_command_buffer,
+                        ash::vk::PipelineBindPoint::COMPUTE,
+                        self.compute_pipelines.alpha_pressure,
+                    );
+                    device.cmd_dispatch(self.compute_command_buffer, workgroups_x, workgroups_y, 1);
+
+                    // A second-stage barrier.
+                    add_barrier(
+                        ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                        ash::vk::AccessFlags2::SHADER_WRITE,
+                        ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                        ash::vk::AccessFlags2::SHADER_READ,
+                    );
+
+                    device.cmd_bind_pipeline(
+                        self.compute_command_buffer,
+                        ash::vk::PipelineBindPoint::COMPUTE,
+                        self.compute_pipelines.beta_pressure,
+                    );
+                    device.cmd_dispatch(self.compute_command_buffer, workgroups_x, workgroups_y, 1);
+                }
+
+                // The gradient subtract compute shader requires sampling the pressure textures, so add a barrier.
                 add_barrier(
                     ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
                     ash::vk::AccessFlags2::SHADER_WRITE,
@@ -910,45 +1447,36 @@ impl FluidSimulation {
                     ash::vk::AccessFlags2::SHADER_READ,
                 );
 
+                // Apply the gradient subtract compute shader.
                 device.cmd_bind_pipeline(
                     self.compute_command_buffer,
                     ash::vk::PipelineBindPoint::COMPUTE,
-                    self.compute_pipelines.beta_pressure,
+                    self.compute_pipelines.gradient_subtract,
                 );
                 device.cmd_dispatch(self.compute_command_buffer, workgroups_x, workgroups_y, 1);
-            }
 
-            // The gradient subtract compute shader requires sampling the pressure textures, so add a barrier.
-            add_barrier(
-                ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
-                ash::vk::AccessFlags2::SHADER_WRITE,
-                ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
-                ash::vk::AccessFlags2::SHADER_READ,
-            );
-
-            // Apply the gradient subtract compute shader.
-            device.cmd_bind_pipeline(
-                self.compute_command_buffer,
-                ash::vk::PipelineBindPoint::COMPUTE,
-                self.compute_pipelines.gradient_subtract,
-            );
-            device.cmd_dispatch(self.compute_command_buffer, workgroups_x, workgroups_y, 1);
+                // The advection compute shader requires sampling the output velocity texture, so add a barrier.
+                add_barrier(
+                    ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                    ash::vk::AccessFlags2::SHADER_WRITE,
+                    ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                    ash::vk::AccessFlags2::SHADER_READ,
+                );
 
-            // The advection compute shader requires sampling the output velocity texture, so add a barrier.
-            add_barrier(
-                ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
-                ash::vk::AccessFlags2::SHADER_WRITE,
-                ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
-                ash::vk::AccessFlags2::SHADER_READ,
-            );
+                // Apply the advection compute shader.
+                device.cmd_bind_pipeline(
+                    self.compute_command_buffer,
+                    ash::vk::PipelineBindPoint::COMPUTE,
+                    self.compute_pipelines.advection,
+                );
+                device.cmd_dispatch(self.compute_command_buffer, workgroups_x, workgroups_y, 1);
 
-            // Apply the advection compute shader.
-            device.cmd_bind_pipeline(
-                self.compute_command_buffer,
-                ash::vk::PipelineBindPoint::COMPUTE,
-                self.compute_pipelines.advection,
-            );
-            device.cmd_dispatch(self.compute_command_buffer, workgroups_x, workgroups_y, 1);
+                // Update device addresses by switching input and output (alpha/beta) buffers.
+                self.allocated_images.swap(0, 5);
+                self.allocated_images.swap(3, 4);
+                self.allocated_images.swap(6, 7);
+                self.allocated_images.swap(8, 9);
+            }
 
             // End the command buffer recording.
             device
@@ -959,51 +1487,93 @@ impl FluidSimulation {
         }
     }
 
-    /// Update the internal active display texture to the next in the cycle.
-    pub fn next_display_texture(&mut self) {
-        self.current_display_texture = self.current_display_texture.next();
+    /// Recreate the specialized compute pipelines with new specialization constants.
+    /// The simulation state is preserved, except for the smoke fields when smoke is newly enabled.
+    /// The old pipelines are destroyed once the frames in flight, and the compute work they depend on, have completed.
+    pub fn update_specialization_constants(
+        &mut self,
+        device: &ash::Device,
+        specialization_constants: SpecializationConstants,
+        deletion_queue: &mut DeletionQueue,
+    ) {
+        deletion_queue.push(DeferredDestroy::Pipeline(self.compute_pipelines.advection));
+        deletion_queue.push(DeferredDestroy::Pipeline(self.compute_pipelines.buoyancy));
 
-        println!(
-            "Switched to the next display texture: {:?}",
-            self.current_display_texture
+        self.compute_pipelines.advection = create_specialized_pipeline(
+            device,
+            self.compute_pipeline_layout,
+            self.shaders.advection,
+            &specialization_constants,
+        );
+        self.compute_pipelines.buoyancy = create_specialized_pipeline(
+            device,
+            self.compute_pipeline_layout,
+            self.shaders.buoyancy,
+            &specialization_constants,
         );
+
+        if self.specialization_constants.smoke == ash::vk::FALSE {
+            self.clear_smoke_buffers = true;
+        }
+        self.specialization_constants = specialization_constants;
     }
 
-    /// Render the fluid simulation.
+    /// Stop waiting on fences which are about to be destroyed, such as those of a removed render target.
     /// # Safety
-    /// The `graphics_command_buffer` must be in the recording state to be submitted by the caller.
-    pub fn render_frame(
+    /// The draws which signal the fences must have completed.
+    pub fn forget_graphics_fences(&mut self, fences: &[ash::vk::Fence]) {
+        self.graphics_fences.retain(|fence| !fences.contains(fence));
+    }
+
+    /// Get the size of the simulation grid.
+    pub fn extent(&self) -> ash::vk::Extent2D {
+        self.extent
+    }
+
+    /// Get the render pass the simulation is drawn with.
+    pub fn render_pass(&self) -> ash::vk::RenderPass {
+        self.render_pass
+    }
+
+    /// Get the specialization constants used to create the compute pipelines.
+    pub fn specialization_constants(&self) -> SpecializationConstants {
+        self.specialization_constants
+    }
+
+    /// Set the number of simulation steps to compute when rendering the next frame.
+    /// Zero steps will render the current state again without advancing the simulation.
+    pub fn set_simulation_steps(&mut self, steps: u32) {
+        self.pending_steps = steps;
+    }
+
+    /// Set the sub-pixel offset at which the next frame samples the simulation.
+    pub fn set_jitter(&mut self, jitter: utils::post_process::Jitter) {
+        self.jitter = jitter;
+    }
+
+    /// Submit the pending simulation steps to the compute queue.
+    /// The optional `compute_semaphore` is signaled when the steps complete, for the next draw to wait on.
+    pub fn step(
         &mut self,
         device: &ash::Device,
         compute_semaphore: Option<ash::vk::Semaphore>,
         compute_queue: ash::vk::Queue,
-        graphics_command_buffer: ash::vk::CommandBuffer,
-        extent: ash::vk::Extent2D,
-        image_index: usize,
         push_constants: &PushConstants,
-        current_graphics_fence: ash::vk::Fence,
     ) {
-        // Wait for the compute fence to ensure all resources are available.
+        // Wait for the previous steps and every draw since to complete, as the steps write to the buffers they read.
         unsafe {
-            let fences = [self.compute_fence, self.graphics_fence.unwrap_or_default()];
+            self.graphics_fences.push(self.compute_fence);
             device
-                .wait_for_fences(
-                    if self.graphics_fence.is_some() {
-                        &fences
-                    } else {
-                        &fences[..1]
-                    },
-                    true,
-                    FIVE_SECONDS_IN_NANOSECONDS,
-                )
+                .wait_for_fences(&self.graphics_fences, true, FIVE_SECONDS_IN_NANOSECONDS)
                 .expect(
-                    "Failed to wait for the compute or graphics fence for the fluid simulation",
+                    "Failed to wait for the compute or graphics fences for the fluid simulation",
                 );
-            self.graphics_fence = Some(current_graphics_fence);
+            self.graphics_fences.clear();
         }
 
         // Record the compute commands for the fluid simulation to the desired command buffer.
-        self.create_compute_command_buffer(device, extent, push_constants);
+        // Even when no steps are needed, an empty submission still signals the compute semaphore the graphics queue waits on.
+        self.create_compute_command_buffer(device, push_constants, self.pending_steps);
 
         unsafe {
             // Requires some hoops to satisfy the borrow checker, but sets the command buffer to the submit info.
@@ -1025,15 +1595,48 @@ impl FluidSimulation {
                 .queue_submit(compute_queue, &[submit_info], self.compute_fence)
                 .expect("Failed to submit the compute command buffer for the fluid simulation");
         }
+    }
+
+    /// Block until the last submitted simulation steps have completed.
+    /// Draws which do not wait on the compute semaphore signaled by `step` must call this first when the compute queue is separate from the graphics queue.
+    pub fn wait_for_step(&self, device: &ash::Device) {
+        unsafe {
+            device
+                .wait_for_fences(&[self.compute_fence], true, FIVE_SECONDS_IN_NANOSECONDS)
+                .expect("Unable to wait for the compute fence to signal");
+        }
+    }
+
+    /// Draw the current state of the fluid simulation to the given framebuffer, created with `create_destination_framebuffers` for this render pass.
+    /// The `graphics_fence` must be signaled when the submitted draw completes, so that the next step waits for it.
+    /// # Safety
+    /// The `graphics_command_buffer` must be in the recording state to be submitted by the caller.
+    pub fn draw(
+        &mut self,
+        device: &ash::Device,
+        graphics_command_buffer: ash::vk::CommandBuffer,
+        framebuffer: ash::vk::Framebuffer,
+        extent: ash::vk::Extent2D,
+        display_texture: FluidDisplayTexture,
+        graphics_fence: ash::vk::Fence,
+    ) {
+        if !self.graphics_fences.contains(&graphics_fence) {
+            self.graphics_fences.push(graphics_fence);
+        }
 
         // Ensure that the graphics command buffer has the proper push constants bound.
+        // The buffers were swapped after the last step, so its outputs are now the input buffers.
         unsafe {
             let push_constants = FragmentPushConstants {
-                velocity_buffer: self.allocated_images[5].device_address,
-                dye_buffer: self.allocated_images[7].device_address,
-                pressure_buffer: self.allocated_images[3].device_address,
-                screen_size: [extent.width, extent.height],
-                display_texture: self.current_display_texture,
+                velocity_buffer: self.allocated_images[0].device_address,
+                dye_buffer: self.allocated_images[6].device_address,
+                pressure_buffer: self.allocated_images[4].device_address,
+                smoke_buffer: self.allocated_images[8].device_address,
+                screen_size: [self.extent.width, self.extent.height],
+                display_texture,
+                jitter: self.jitter.offset,
+                render_size: [extent.width, extent.height],
+                jittered_width: (self.jitter.split_position * extent.width as f32) as u32,
             };
             device.cmd_push_constants(
                 graphics_command_buffer,
@@ -1050,7 +1653,7 @@ impl FluidSimulation {
                 graphics_command_buffer,
                 &ash::vk::RenderPassBeginInfo::default()
                     .render_pass(self.render_pass)
-                    .framebuffer(self.framebuffers[image_index])
+                    .framebuffer(framebuffer)
                     .render_area(ash::vk::Rect2D {
                         offset: ash::vk::Offset2D { x: 0, y: 0 },
                         extent,
@@ -1097,14 +1700,241 @@ impl FluidSimulation {
 
         // End the render pass.
         unsafe { device.cmd_end_render_pass(graphics_command_buffer) };
+    }
+
+    /// Write the current velocity, pressure, dye, and smoke fields to a versioned binary snapshot.
+    /// # Safety
+    /// The caller must ensure that no graphics commands reading the simulation buffers are still pending.
+    pub fn save_snapshot(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        compute_queue: &utils::IndexedQueue,
+        compute_command_pool: ash::vk::CommandPool,
+        transfer_queue: &utils::transfer::TransferQueue,
+        path: &std::path::Path,
+    ) -> std::io::Result<()> {
+        let extent = self.extent;
+        let pixel_count = extent.width as usize * extent.height as usize;
+        let field_sizes = SnapshotField::ALL
+            .map(|field| (pixel_count * field.components() * std::mem::size_of::<f32>()) as u64);
+        let (staging_buffer, staging_allocation) = create_staging_buffer(
+            device,
+            memory_allocator,
+            field_sizes.iter().sum(),
+            ash::vk::BufferUsageFlags::TRANSFER_DST,
+            gpu_allocator::MemoryLocation::GpuToCpu,
+        );
 
-        // Update device addresses by switching input and output (alpha/beta) buffers.
-        self.allocated_images.swap(0, 5);
-        self.allocated_images.swap(3, 4);
-        self.allocated_images.swap(6, 7);
+        // Copy each field into consecutive regions of the staging buffer.
+        // The simulation buffers are owned by the compute queue family, which may differ from the transfer queue family.
+        let buffers =
+            SnapshotField::ALL.map(|field| self.allocated_images[field.buffer_index()].buffer);
+        transfer_queue.submit(
+            device,
+            compute_queue,
+            compute_command_pool,
+            &buffers,
+            |command_buffer| unsafe {
+                let mut offset = 0;
+                for (&buffer, &size) in buffers.iter().zip(&field_sizes) {
+                    device.cmd_copy_buffer(
+                        command_buffer,
+                        buffer,
+                        staging_buffer,
+                        &[ash::vk::BufferCopy {
+                            src_offset: 0,
+                            dst_offset: offset,
+                            size,
+                        }],
+                    );
+                    offset += size;
+                }
+            },
+        );
+
+        // Write the header followed by the raw field data.
+        let result = (|| {
+            use std::io::Write as _;
+            let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+            SnapshotHeader {
+                extent,
+                specialization_constants: self.specialization_constants,
+                fields: SnapshotField::ALL.to_vec(),
+            }
+            .write(&mut writer)?;
+
+            let mapped = staging_allocation
+                .mapped_slice()
+                .expect("Staging buffer did not allocate a mapping");
+            for value in mapped.chunks_exact(std::mem::size_of::<f32>()) {
+                let value = f32::from_ne_bytes(value.try_into().unwrap());
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            writer.flush()
+        })();
+
+        unsafe { device.destroy_buffer(staging_buffer, None) };
+        memory_allocator
+            .free(staging_allocation)
+            .expect("Unable to free the staging buffer allocation");
+        result
     }
 
-    /// Helper for creating new push constants with the given information and buffer addresses.
+    /// Replace the simulation state and parameters with those from a snapshot, resampling the fields if the grid size differs.
+    /// # Safety
+    /// The caller must ensure that no graphics commands reading the simulation buffers are still pending.
+    pub fn load_snapshot(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        compute_queue: &utils::IndexedQueue,
+        compute_command_pool: ash::vk::CommandPool,
+        transfer_queue: &utils::transfer::TransferQueue,
+        path: &std::path::Path,
+    ) -> std::io::Result<()> {
+        let extent = self.extent;
+        use std::io::Read as _;
+        let file = std::fs::File::open(path)?;
+        let file_length = file.metadata()?.len();
+        let mut reader = std::io::BufReader::new(file);
+
+        // Validate the header before reading any of the field data.
+        let SnapshotHeader {
+            extent: source_extent,
+            specialization_constants,
+            fields,
+        } = SnapshotHeader::read(&mut reader, file_length)?;
+
+        // Read each field, resampling it to the current grid size if necessary.
+        let source_pixel_count = source_extent.width as usize * source_extent.height as usize;
+        let mut field_data = Vec::with_capacity(fields.len());
+        for &field in &fields {
+            let mut bytes =
+                vec![0; source_pixel_count * field.components() * std::mem::size_of::<f32>()];
+            reader.read_exact(&mut bytes)?;
+            let mut data: Vec<f32> = bytes
+                .chunks_exact(std::mem::size_of::<f32>())
+                .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                .collect();
+
+            if source_extent != extent {
+                data = match field {
+                    // Velocities are measured in grid cells per second, so each axis scales with the grid along it.
+                    SnapshotField::Velocity => {
+                        let mut data = resample_field(&data, 2, source_extent, extent);
+                        let scale_x = extent.width as f32 / source_extent.width as f32;
+                        let scale_y = extent.height as f32 / source_extent.height as f32;
+                        for velocity in data.chunks_exact_mut(2) {
+                            velocity[0] *= scale_x;
+                            velocity[1] *= scale_y;
+                        }
+                        data
+                    }
+
+                    // Pressure has no single scale when the aspect ratio changes, and it is only the initial guess
+                    // of the pressure solve, so it is reset rather than resampled.
+                    SnapshotField::Pressure => {
+                        vec![0.; extent.width as usize * extent.height as usize]
+                    }
+
+                    SnapshotField::Dye | SnapshotField::Smoke => {
+                        resample_field(&data, field.components(), source_extent, extent)
+                    }
+                };
+            }
+            field_data.push(data);
+        }
+
+        // Apply the snapshot parameters before uploading, so that enabling smoke does not clear the loaded smoke field.
+        // No submitted work is using the simulation, so the replaced pipelines are destroyed immediately.
+        let mut replaced_pipelines = DeletionQueue::default();
+        self.update_specialization_constants(
+            device,
+            specialization_constants,
+            &mut replaced_pipelines,
+        );
+        replaced_pipelines.destroy(device, memory_allocator);
+
+        let total_size = field_data
+            .iter()
+            .map(|data| std::mem::size_of_val(data.as_slice()) as u64)
+            .sum();
+        let (staging_buffer, mut staging_allocation) = create_staging_buffer(
+            device,
+            memory_allocator,
+            total_size,
+            ash::vk::BufferUsageFlags::TRANSFER_SRC,
+            gpu_allocator::MemoryLocation::CpuToGpu,
+        );
+        {
+            let mapped = staging_allocation
+                .mapped_slice_mut()
+                .expect("Staging buffer did not allocate a mapping");
+            for (destination, value) in mapped
+                .chunks_exact_mut(std::mem::size_of::<f32>())
+                .zip(field_data.iter().flatten())
+            {
+                destination.copy_from_slice(&value.to_ne_bytes());
+            }
+        }
+
+        // Copy each field from the staging buffer into the current simulation buffers.
+        let copies: Vec<_> = fields
+            .iter()
+            .zip(&field_data)
+            .scan(0, |offset, (field, data)| {
+                let size = std::mem::size_of_val(data.as_slice()) as u64;
+                let copy = (
+                    self.allocated_images[field.buffer_index()].buffer,
+                    ash::vk::BufferCopy {
+                        src_offset: *offset,
+                        dst_offset: 0,
+                        size,
+                    },
+                );
+                *offset += size;
+                Some(copy)
+            })
+            .collect();
+        // The simulation buffers are owned by the compute queue family, which may differ from the transfer queue family.
+        let buffers: Vec<_> = copies.iter().map(|(buffer, _)| *buffer).collect();
+        transfer_queue.submit(
+            device,
+            compute_queue,
+            compute_command_pool,
+            &buffers,
+            |command_buffer| unsafe {
+                for (buffer, region) in &copies {
+                    device.cmd_copy_buffer(command_buffer, staging_buffer, *buffer, &[*region]);
+                }
+            },
+        );
+        self.clear_smoke_buffers = false;
+
+        unsafe { device.destroy_buffer(staging_buffer, None) };
+        memory_allocator
+            .free(staging_allocation)
+            .expect("Unable to free the staging buffer allocation");
+        Ok(())
+    }
+
+    /// Point the push constants at the current input and output buffers of the simulation.
+    fn set_buffer_addresses(&self, push_constants: &mut PushConstants) {
+        push_constants.input_velocity_buffer = self.allocated_images[0].device_address;
+        push_constants.curl_buffer = self.allocated_images[1].device_address;
+        push_constants.divergence_buffer = self.allocated_images[2].device_address;
+        push_constants.alpha_pressure_buffer = self.allocated_images[3].device_address;
+        push_constants.beta_pressure_buffer = self.allocated_images[4].device_address;
+        push_constants.output_velocity_buffer = self.allocated_images[5].device_address;
+        push_constants.input_dye_buffer = self.allocated_images[6].device_address;
+        push_constants.output_dye_buffer = self.allocated_images[7].device_address;
+        push_constants.input_smoke_buffer = self.allocated_images[8].device_address;
+        push_constants.output_smoke_buffer = self.allocated_images[9].device_address;
+    }
+
+    /// Helper for creating new push constants with the given information.
+    /// `delta_time` is the duration of a single simulation step. The buffer addresses are filled in for each step when recording.
     pub fn new_push_constants(
         &mut self,
         extent: ash::vk::Extent2D,
@@ -1115,14 +1945,16 @@ impl FluidSimulation {
     ) -> PushConstants {
         // Create a new set of push constants for the fluid simulation.
         PushConstants {
-            input_velocity_buffer: self.allocated_images[0].device_address,
-            curl_buffer: self.allocated_images[1].device_address,
-            divergence_buffer: self.allocated_images[2].device_address,
-            alpha_pressure_buffer: self.allocated_images[3].device_address,
-            beta_pressure_buffer: self.allocated_images[4].device_address,
-            output_velocity_buffer: self.allocated_images[5].device_address,
-            input_dye_buffer: self.allocated_images[6].device_address,
-            output_dye_buffer: self.allocated_images[7].device_address,
+            input_velocity_buffer: 0,
+            curl_buffer: 0,
+            divergence_buffer: 0,
+            alpha_pressure_buffer: 0,
+            beta_pressure_buffer: 0,
+            output_velocity_buffer: 0,
+            input_dye_buffer: 0,
+            output_dye_buffer: 0,
+            input_smoke_buffer: 0,
+            output_smoke_buffer: 0,
 
             cursor_dye,
             cursor_position,
@@ -1130,9 +1962,126 @@ impl FluidSimulation {
             screen_size: [extent.width, extent.height],
 
             delta_time,
-            velocity_diffusion_rate: 0.12,
-            dye_diffusion_rate: 1.2,
             vorticity_strength: 22.,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent(width: u32, height: u32) -> ash::vk::Extent2D {
+        ash::vk::Extent2D { width, height }
+    }
+
+    #[test]
+    fn resample_field_identity() {
+        let data: Vec<f32> = (0..12).map(|i| i as f32 * 0.25).collect();
+        assert_eq!(resample_field(&data, 2, extent(3, 2), extent(3, 2)), data);
+    }
+
+    #[test]
+    fn resample_field_double() {
+        // Each destination pixel center maps a quarter of a source pixel from the nearest source center, clamped at the edges.
+        let data = [0., 1., 2., 3.];
+        let resampled = resample_field(&data, 1, extent(2, 2), extent(4, 4));
+        let rows = [0., 0.5, 1.5, 2.];
+        let columns = [0., 0.25, 0.75, 1.];
+        let expected: Vec<f32> = rows
+            .iter()
+            .flat_map(|row| columns.iter().map(move |column| row + column))
+            .collect();
+        assert_eq!(resampled, expected);
+    }
+
+    fn header_bytes(header: &SnapshotHeader) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        header.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn test_header() -> SnapshotHeader {
+        SnapshotHeader {
+            extent: extent(4, 3),
+            specialization_constants: SpecializationConstants {
+                advection_scheme: AdvectionScheme::MacCormack,
+                smoke: ash::vk::TRUE,
+                buoyancy: 0.1 + 0.2,
+                ..Default::default()
+            },
+            fields: SnapshotField::ALL.to_vec(),
+        }
+    }
+
+    /// The number of bytes of field data following the test header.
+    const TEST_FIELDS_SIZE: usize = 4 * 3 * (2 + 1 + 4 + 2) * std::mem::size_of::<f32>();
+
+    #[test]
+    fn snapshot_header_round_trip() {
+        let header = test_header();
+        let mut bytes = header_bytes(&header);
+        bytes.resize(bytes.len() + TEST_FIELDS_SIZE, 0);
+
+        let length = bytes.len() as u64;
+        let read = SnapshotHeader::read(&mut std::io::Cursor::new(bytes), length).unwrap();
+        assert_eq!(read.extent, header.extent);
+        assert_eq!(read.fields, header.fields);
+        let (expected, actual) = (
+            header.specialization_constants,
+            read.specialization_constants,
+        );
+        assert_eq!(
+            actual.advection_scheme as u32,
+            expected.advection_scheme as u32
+        );
+        assert_eq!(actual.smoke, expected.smoke);
+        assert_eq!(actual.buoyancy.to_bits(), expected.buoyancy.to_bits());
+    }
+
+    #[test]
+    fn snapshot_header_rejects_bad_magic() {
+        let mut bytes = header_bytes(&test_header());
+        bytes[0] = b'X';
+        let length = bytes.len() as u64;
+        let error = SnapshotHeader::read(&mut std::io::Cursor::new(bytes), length)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn snapshot_header_rejects_unknown_version() {
+        let mut bytes = header_bytes(&test_header());
+        let version = SNAPSHOT_MAGIC.len();
+        bytes[version..version + 4].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+        let length = bytes.len() as u64;
+        let error = SnapshotHeader::read(&mut std::io::Cursor::new(bytes), length)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn snapshot_header_rejects_truncated_fields() {
+        let mut bytes = header_bytes(&test_header());
+        bytes.resize(bytes.len() + TEST_FIELDS_SIZE - 1, 0);
+        let length = bytes.len() as u64;
+        let error = SnapshotHeader::read(&mut std::io::Cursor::new(bytes), length)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn snapshot_header_rejects_huge_grid() {
+        let mut header = test_header();
+        header.extent = extent(u32::MAX, u32::MAX);
+        let bytes = header_bytes(&header);
+        let length = bytes.len() as u64;
+        let error = SnapshotHeader::read(&mut std::io::Cursor::new(bytes), length)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -1,4 +1,8 @@
-use crate::engine::utils::{self, shaders::ENTRY_POINT_MAIN};
+use crate::engine::utils::{
+    self,
+    deletion_queue::{DeferredDestroy, DeletionQueue},
+    shaders::ENTRY_POINT_MAIN,
+};
 
 /// Store the SPIR-V representation of the shaders in the binary.
 /// This basic triangle example uses a vertex shader which stores it own vertices for simplicity.
@@ -23,12 +27,16 @@ pub struct SpecializationConstants {
 }
 
 /// Define the push constants that can be used with the shaders of this application (i.e., `BB_TRIANGLE_FRAGMENT`).
-/// Specifically, this shader only accepts a single float representing a time in seconds since the application start.
+/// Specifically, a time in seconds since the application start and a sub-pixel offset for the vertices.
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct PushConstants {
     /// The time in seconds since the application started.
     pub time: f32,
+
+    /// The offset applied to every vertex position, in normalized device coordinates.
+    /// Used by temporal post-processing to cover different sub-pixel positions each frame.
+    pub jitter: [f32; 2],
 }
 
 /// Create the render pass capable of orchestrating the rendering of framebuffers for this application.
@@ -166,20 +174,13 @@ pub fn create_framebuffers(
     device: &ash::Device,
     swapchain: &utils::Swapchain,
     render_pass: ash::vk::RenderPass,
-    fxaa_pass: Option<&utils::fxaa_pass::FxaaPass>,
+    post_process: &utils::post_process::PostProcessChain,
 ) -> Vec<ash::vk::Framebuffer> {
-    let extent = swapchain.extent();
+    // Cover the whole of the input images, as the render area of the scene within them changes with the render scale.
+    let extent = post_process.extent();
 
-    // Render to a temporary image if FXAA is enabled, otherwise render to the swapchain's images.
-    let destination_image_views = if let Some(fxaa_pass) = &fxaa_pass {
-        fxaa_pass
-            .framebuffer_data()
-            .iter()
-            .map(|(_, img, _, _)| *img)
-            .collect()
-    } else {
-        swapchain.image_views().to_vec()
-    };
+    // Render to a temporary image if there are post-processing passes, otherwise render to the swapchain's images.
+    let destination_image_views = post_process.input_views();
 
     // Render to a multisampled image if MSAA is enabled, otherwise render directly to the destination image.
     if let Some(multisample_views) = swapchain.multisample_views() {
@@ -218,8 +219,8 @@ pub struct Pipeline {
     layout: ash::vk::PipelineLayout,
     specialization_constants: SpecializationConstants,
     render_pass: ash::vk::RenderPass,
+    multisample_count: Option<ash::vk::SampleCountFlags>,
     shaders: Shaders,
-    framebuffers: Vec<ash::vk::Framebuffer>,
 }
 
 /// Allow the caller to create a new pipeline from either an existing render pass or the information to create a new one.
@@ -234,19 +235,16 @@ pub enum CreateReuseRenderPass {
 
 impl Pipeline {
     /// Create the graphics pipeline capable of rendering this application's scene.
+    /// The multisample count must match the multisampled images of the swapchains it renders to, or be `None` without them.
     //  TODO: Create a type to help configure the pipeline creation.
     pub fn new(
         device: &ash::Device,
         vertex_module: Option<ash::vk::ShaderModule>,
         fragment_module: Option<ash::vk::ShaderModule>,
         create_or_reuse_render_pass: CreateReuseRenderPass,
-        swapchain: &utils::Swapchain,
+        multisample_count: Option<ash::vk::SampleCountFlags>,
         specialization_constants: SpecializationConstants,
-        fxaa_pass: Option<&utils::fxaa_pass::FxaaPass>,
     ) -> Self {
-        // Determine whether the swapchain contains additional multisampled images.
-        let multisample_count = swapchain.multisample_count();
-
         // Create or reuse the render pass for this pipeline.
         let render_pass = match create_or_reuse_render_pass {
             CreateReuseRenderPass::Create {
@@ -323,9 +321,9 @@ impl Pipeline {
 
         // TODO: Store the push constant ranges with the pipeline so that each can be explicitly reused during the render.
         let push_constant_ranges = [ash::vk::PushConstantRange {
-            stage_flags: ash::vk::ShaderStageFlags::FRAGMENT,
+            stage_flags: ash::vk::ShaderStageFlags::VERTEX | ash::vk::ShaderStageFlags::FRAGMENT,
             offset: 0,
-            size: std::mem::size_of::<f32>() as u32,
+            size: std::mem::size_of::<PushConstants>() as u32,
         }];
         let pipeline_layout = unsafe {
             device
@@ -379,19 +377,16 @@ impl Pipeline {
         .first()
         .expect("vkCreateGraphicsPipelines returned an empty list of pipelines");
 
-        // Create the framebuffers for this application.
-        let framebuffers = create_framebuffers(device, swapchain, render_pass, fxaa_pass);
-
         Self {
             handle: pipeline,
             layout: pipeline_layout,
             specialization_constants,
             render_pass,
+            multisample_count,
             shaders: Shaders {
                 vertex_module,
                 fragment_module,
             },
-            framebuffers,
         }
     }
 
@@ -412,39 +407,18 @@ impl Pipeline {
                 device.destroy_shader_module(self.shaders.vertex_module, None);
                 device.destroy_shader_module(self.shaders.fragment_module, None);
             }
-            for framebuffer in self.framebuffers {
-                device.destroy_framebuffer(framebuffer, None);
-            }
-        }
-    }
-
-    /// Create the framebuffers, likely after a swapchain recreation.
-    /// # Safety
-    /// The existing render pass must be valid.
-    pub fn recreate_framebuffers(
-        &mut self,
-        device: &ash::Device,
-        swapchain: &utils::Swapchain,
-        fxaa_pass: Option<&utils::fxaa_pass::FxaaPass>,
-    ) {
-        for framebuffer in &self.framebuffers {
-            unsafe {
-                device.destroy_framebuffer(*framebuffer, None);
-            }
         }
-        self.framebuffers = create_framebuffers(device, swapchain, self.render_pass, fxaa_pass);
     }
 
     /// Recreate the graphics pipeline with updated values.
-    /// # Safety
-    /// Ensure that the pipeline resources can be safely destroyed at the time of calling this function.
+    /// The old pipeline resources are destroyed once the frames in flight have completed.
+    /// Framebuffers created for a replaced render pass must be recreated by the caller.
     pub fn recreate(
         &mut self,
         device: &ash::Device,
         create_or_reuse_render_pass: CreateReuseRenderPass,
-        swapchain: &utils::Swapchain,
         specialization_constants: SpecializationConstants,
-        fxaa_pass: Option<&utils::fxaa_pass::FxaaPass>,
+        deletion_queue: &mut DeletionQueue,
     ) {
         let should_free_old_render_pass = !matches!(&create_or_reuse_render_pass, CreateReuseRenderPass::Reuse(r) if *r == self.render_pass);
 
@@ -454,27 +428,32 @@ impl Pipeline {
             Some(self.shaders.vertex_module),
             Some(self.shaders.fragment_module),
             create_or_reuse_render_pass,
-            swapchain,
+            self.multisample_count,
             specialization_constants,
-            fxaa_pass,
         );
 
         // Swap the new graphics pipeline with the old one.
         std::mem::swap(self, &mut new_pipeline);
         let old_pipeline = new_pipeline; // Rename the variable for clarity.
 
-        // Destroy the old graphics pipeline and render pass.
-        old_pipeline.destroy(device, should_free_old_render_pass, false);
+        // Queue the old graphics pipeline and render pass for destruction. The shader modules are reused.
+        deletion_queue.push(DeferredDestroy::Pipeline(old_pipeline.handle));
+        deletion_queue.push(DeferredDestroy::PipelineLayout(old_pipeline.layout));
+        if should_free_old_render_pass {
+            deletion_queue.push(DeferredDestroy::RenderPass(old_pipeline.render_pass));
+        }
     }
 
-    /// Render the example triangle to the current frame.
+    /// Render the example triangle to the given framebuffer, created with `create_framebuffers` for this render pass.
+    /// Only the pixels left of `jittered_width` are rendered with the jitter in the push constants, and the rest without it.
     pub fn render_frame(
         &mut self,
         device: &ash::Device,
         command_buffer: ash::vk::CommandBuffer,
         extent: ash::vk::Extent2D,
-        image_index: usize,
+        framebuffer: ash::vk::Framebuffer,
         push_constants: &PushConstants,
+        jittered_width: u32,
     ) {
         // Begin the render pass for the current frame.
         unsafe {
@@ -485,7 +464,7 @@ impl Pipeline {
                 command_buffer,
                 &ash::vk::RenderPassBeginInfo {
                     render_pass: self.render_pass,
-                    framebuffer: self.framebuffers[image_index],
+                    framebuffer,
                     render_area: ash::vk::Rect2D {
                         offset: ash::vk::Offset2D::default(),
                         extent,
@@ -498,17 +477,7 @@ impl Pipeline {
             );
         }
 
-        unsafe {
-            device.cmd_push_constants(
-                command_buffer,
-                self.layout,
-                ash::vk::ShaderStageFlags::FRAGMENT,
-                0,
-                utils::data_byte_slice(push_constants),
-            );
-        }
-
-        // Set the viewport and scissor in the command buffer because we specified they would be set dynamically in the pipeline.
+        // Set the viewport in the command buffer because we specified it would be set dynamically in the pipeline.
         unsafe {
             device.cmd_set_viewport(
                 command_buffer,
@@ -522,14 +491,6 @@ impl Pipeline {
                     max_depth: 1.,
                 }],
             );
-            device.cmd_set_scissor(
-                command_buffer,
-                0,
-                &[ash::vk::Rect2D {
-                    offset: ash::vk::Offset2D::default(),
-                    extent,
-                }],
-            );
         }
 
         // Bind the graphics pipeline to the command buffer.
@@ -541,9 +502,45 @@ impl Pipeline {
             );
         }
 
-        // Draw the example triangle.
-        unsafe {
-            device.cmd_draw(command_buffer, 3, 1, 0, 0);
+        // Draw the example triangle, once with the jitter and once without, each limited to its part of the image by the scissor.
+        // The jitter moves the vertices, so it cannot be applied to only some of the pixels of a single draw.
+        let jittered_width = jittered_width.min(extent.width);
+        let regions = [
+            (0, jittered_width, *push_constants),
+            (
+                jittered_width,
+                extent.width - jittered_width,
+                PushConstants {
+                    jitter: [0.; 2],
+                    ..*push_constants
+                },
+            ),
+        ];
+        for (x, width, push_constants) in regions {
+            if width == 0 {
+                continue;
+            }
+            unsafe {
+                device.cmd_push_constants(
+                    command_buffer,
+                    self.layout,
+                    ash::vk::ShaderStageFlags::VERTEX | ash::vk::ShaderStageFlags::FRAGMENT,
+                    0,
+                    utils::data_byte_slice(&push_constants),
+                );
+                device.cmd_set_scissor(
+                    command_buffer,
+                    0,
+                    &[ash::vk::Rect2D {
+                        offset: ash::vk::Offset2D { x: x as i32, y: 0 },
+                        extent: ash::vk::Extent2D {
+                            width,
+                            height: extent.height,
+                        },
+                    }],
+                );
+                device.cmd_draw(command_buffer, 3, 1, 0, 0);
+            }
         }
 
         // End the render pass for the current frame.
@@ -1,23 +1,27 @@
 use std::{collections::HashSet, ffi::CStr};
 
 use smallvec::{smallvec, SmallVec};
-use utils::{fxaa_pass::FxaaPass, EXPECTED_MAX_FRAMES_IN_FLIGHT, FIVE_SECONDS_IN_NANOSECONDS};
+use utils::{
+    deletion_queue::{DeferredDestroy, DeletionQueue},
+    post_process::{PostProcessChain, PostProcessEffect, PostProcessSettings, SCENE_FORMAT},
+    tonemap_pass::OutputEncoding,
+    upscale_pass::{DynamicResolution, UpscaleSettings},
+    EXPECTED_MAX_FRAMES_IN_FLIGHT, FIVE_SECONDS_IN_NANOSECONDS,
+};
 
 pub mod example_fluid;
 pub mod example_triangle;
 pub mod utils;
 
-/// A sane constant for the expected maximum number of enabled device extensions. This is not for restrictions but to allow optimizations to avoid heap allocation.
-const EXPECTED_MAX_ENABLED_DEVICE_EXTENSIONS: usize = 4;
-
 /// The demos the application is capable of rendering.
 pub enum DemoPipeline {
     Triangle(example_triangle::Pipeline),
-    Fluid(example_fluid::FluidSimulation),
+    Fluid(Box<example_fluid::FluidSimulation>),
 }
 
 /// The push constants necessary to render the active demo.
 /// Each demo needs a unique set of information to render each frame.
+#[derive(Clone, Copy)]
 pub enum DemoPushConstants {
     Triangle(example_triangle::PushConstants),
     Fluid(example_fluid::PushConstants),
@@ -27,84 +31,230 @@ pub enum DemoPushConstants {
 enum ResizeSwapchainState {
     None,
     Resized,
+    SurfaceLost,
+}
+
+/// Identify a surface the renderer draws to, such as one of the windows of the application.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TargetId(usize);
+
+impl TargetId {
+    /// The render target created with the renderer, which advances the active demo each frame and cannot be removed.
+    pub const PRIMARY: Self = Self(0);
+}
+
+/// A surface the renderer draws to, with the swapchain and per-frame resources to render and present to it.
+/// Every target shows the active demo, through its own post-processing chain.
+struct RenderTarget {
+    id: TargetId,
+    surface: ash::vk::SurfaceKHR,
+    swapchain: Option<utils::Swapchain>, // The surface is null and the swapchain is `None` while the target is suspended.
+    resize_swapchain: ResizeSwapchainState,
+    swapchain_preferences: utils::SwapchainPreferences,
+
+    command_buffers: Vec<ash::vk::CommandBuffer>,
+    frame_fences: Vec<ash::vk::Fence>,
+    submitted_frame_numbers: Vec<u64>, // The deletion queue frame number last submitted with each frame in flight.
+    frame_timer: Option<utils::GpuFrameTimer>, // Only the primary target is timed, as it alone adjusts the render scale.
+    frame_pacer: utils::frame_pacing::FramePacer,
+
+    post_process_settings: PostProcessSettings, // The chain is rebuilt from these settings whenever the swapchain is replaced.
+    post_process: PostProcessChain,
+    demo_framebuffers: Vec<ash::vk::Framebuffer>, // The framebuffers of the active demo, one for each input view of the post-processing chain.
+    fluid_display_texture: example_fluid::FluidDisplayTexture,
+}
+
+impl RenderTarget {
+    /// Allocate a command buffer and create a fence for each frame in flight.
+    fn create_frame_resources(
+        device: &ash::Device,
+        command_pool: ash::vk::CommandPool,
+        frames_in_flight: usize,
+    ) -> (Vec<ash::vk::CommandBuffer>, Vec<ash::vk::Fence>) {
+        // Allocate a command buffer for each frame in flight.
+        let command_buffer_info = ash::vk::CommandBufferAllocateInfo {
+            command_pool,
+            level: ash::vk::CommandBufferLevel::PRIMARY,
+            command_buffer_count: 1,
+            ..Default::default()
+        };
+        let command_buffers = unsafe {
+            let mut c = Vec::new();
+            c.resize_with(frames_in_flight, || {
+                *device
+                    .allocate_command_buffers(&command_buffer_info)
+                    .expect("Unable to allocate command buffer")
+                    .first()
+                    .expect("No command buffers were allocated")
+            });
+            c
+        };
+
+        // Create a fence for each frame in flight so the CPU can wait for the GPU to finish a given frame.
+        let fence_create_info = ash::vk::FenceCreateInfo {
+            flags: ash::vk::FenceCreateFlags::SIGNALED,
+            ..Default::default()
+        };
+        let frame_fences = unsafe {
+            let mut f = Vec::new();
+            f.resize_with(frames_in_flight, || {
+                device
+                    .create_fence(&fence_create_info, None)
+                    .expect("Unable to create fence")
+            });
+            f
+        };
+
+        (command_buffers, frame_fences)
+    }
+
+    /// Destroy the render target and its dependent resources, including its surface.
+    /// # Safety
+    /// This function **must** only be called when the owned resources are not currently being processed by the GPU.
+    fn destroy(
+        mut self,
+        vulkan: &utils::VulkanCore,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        command_pool: ash::vk::CommandPool,
+    ) {
+        unsafe {
+            // Destroy all fences.
+            for fence in self.frame_fences {
+                device.destroy_fence(fence, None);
+            }
+            if let Some(frame_timer) = &self.frame_timer {
+                frame_timer.destroy(device);
+            }
+
+            // Free the command buffers of each frame in flight.
+            device.free_command_buffers(command_pool, &self.command_buffers);
+
+            for framebuffer in self.demo_framebuffers {
+                device.destroy_framebuffer(framebuffer, None);
+            }
+        }
+        self.post_process.destroy(device, memory_allocator);
+
+        // Destroy the swapchain and its dependent resources, then the surface itself.
+        if let Some(swapchain) = self.swapchain {
+            swapchain.destroy(device, memory_allocator);
+        }
+        Renderer::destroy_surface(vulkan, self.surface);
+    }
+
+    /// The swapchain of a render target which is not suspended.
+    fn swapchain(&self) -> &utils::Swapchain {
+        self.swapchain
+            .as_ref()
+            .expect("The render target is suspended without a swapchain")
+    }
+    fn swapchain_mut(&mut self) -> &mut utils::Swapchain {
+        self.swapchain
+            .as_mut()
+            .expect("The render target is suspended without a swapchain")
+    }
 }
 
 /// Define which rendering objects are necessary for this application.
+/// The device, the memory allocator, and the active demo are shared by every render target.
 pub struct Renderer {
     physical_device: ash::vk::PhysicalDevice,
     device_extensions: HashSet<&'static CStr>,
     pub logical_device: ash::Device,
     pageable_device_local_memory: Option<ash::ext::pageable_device_local_memory::Device>,
+    hdr_metadata: Option<ash::ext::hdr_metadata::Device>,
+    present_wait: Option<ash::khr::present_wait::Device>,
     memory_allocator: gpu_allocator::vulkan::Allocator,
 
-    surface: ash::vk::SurfaceKHR,
-    pub swapchain: utils::Swapchain,
-    resize_swapchain: ResizeSwapchainState,
+    // The surfaces to render to. The primary target is always first.
+    targets: Vec<RenderTarget>,
+    next_target_id: usize,
 
     // The specific object we are interested in rendering.
     pub active_demo: DemoPipeline,
+    multisample_count: Option<ash::vk::SampleCountFlags>, // The swapchains of every target are created with the same multisample preferences.
 
     graphics_queue: utils::IndexedQueue,
     compute_queue: utils::IndexedQueue,
     presentation_queue: utils::IndexedQueue,
+    transfer_queue: utils::transfer::TransferQueue,
     command_pool: ash::vk::CommandPool,
-    compute_command_pool: Option<(ash::vk::CommandPool, ash::vk::Semaphore)>, // Optional compute command pool and compute semaphore if the graphics and compute queue families are separate.
+    compute_command_pool: Option<(ash::vk::CommandPool, ash::vk::Semaphore)>, // Optional compute command pool and compute semaphore if the graphics and compute queues are separate.
 
-    command_buffers: Vec<ash::vk::CommandBuffer>,
-    frame_fences: Vec<ash::vk::Fence>,
-
-    fxaa_pass: Option<FxaaPass>,
-    pub swapchain_preferences: utils::SwapchainPreferences,
+    deletion_queue: DeletionQueue,
+    dynamic_resolution: Option<DynamicResolution>,
+    fps_cap: Option<f32>,
+    low_latency: bool,
 }
 
 #[derive(Clone, Copy, Debug)]
 pub enum DemoSpecializationConstants {
     Triangle(example_triangle::SpecializationConstants),
-
-    #[allow(dead_code)]
-    Fluid,
+    Fluid(example_fluid::SpecializationConstants),
 }
 
 /// The new demo to switch to, and any unique parameters necessary to initialize it.
 #[derive(Clone, Copy, Debug)]
 pub enum NewDemo {
     Triangle(example_triangle::SpecializationConstants),
-    Fluid,
+    Fluid(example_fluid::SpecializationConstants),
 }
 
 impl Renderer {
     /// Create a new renderer for the application.
+    /// With `async_compute`, the fluid simulation is stepped on a separate low-priority compute queue when the device has one, instead of the graphics queue.
     pub fn new(
         vulkan: &utils::VulkanCore,
         surface: ash::vk::SurfaceKHR,
         swapchain_preferences: utils::SwapchainPreferences,
         specialization_constants: DemoSpecializationConstants,
-        enable_fxaa: bool,
+        post_process_settings: PostProcessSettings,
+        async_compute: bool,
     ) -> Self {
-        // Required device extensions for the swapchain.
-        const DEVICE_EXTENSIONS: [*const i8; 1] = [ash::khr::swapchain::NAME.as_ptr()];
-
-        // Define the device features that are required from this application.
-        let required_device_features = utils::EnginePhysicalDeviceFeatures {
-            buffer_device_address: ash::vk::PhysicalDeviceBufferDeviceAddressFeatures::default()
-                .buffer_device_address(true),
-            dynamic_rendering: ash::vk::PhysicalDeviceDynamicRenderingFeatures::default()
-                .dynamic_rendering(true),
-            synchronization2: ash::vk::PhysicalDeviceSynchronization2Features::default()
-                .synchronization2(true),
-            ..Default::default()
-        };
+        // Declare the device extensions and features needed by this application and its demos.
+        // Optional extensions and features are only enabled when the chosen physical device supports them.
+        let device_builder = example_fluid::device_requirements(
+            utils::device_builder::DeviceBuilder::default()
+                // Required device extensions for the swapchain.
+                .require_extension(ash::khr::swapchain::NAME)
+                .require_features(&utils::EnginePhysicalDeviceFeatures {
+                    buffer_device_address:
+                        ash::vk::PhysicalDeviceBufferDeviceAddressFeatures::default()
+                            .buffer_device_address(true),
+                    dynamic_rendering: ash::vk::PhysicalDeviceDynamicRenderingFeatures::default()
+                        .dynamic_rendering(true),
+                    synchronization2: ash::vk::PhysicalDeviceSynchronization2Features::default()
+                        .synchronization2(true),
+                    ..Default::default()
+                })
+                .optional_extensions(
+                    &[ash::ext::swapchain_maintenance1::NAME],
+                    utils::EnginePhysicalDeviceFeatures::default(),
+                    &[ash::ext::surface_maintenance1::NAME],
+                )
+                .optional_extensions(
+                    &[ash::ext::hdr_metadata::NAME],
+                    utils::EnginePhysicalDeviceFeatures::default(),
+                    &[],
+                )
+                // Track presents so that frames can be paced to the display.
+                .optional_extensions(
+                    &[ash::khr::present_id::NAME, ash::khr::present_wait::NAME],
+                    utils::EnginePhysicalDeviceFeatures {
+                        present_id: ash::vk::PhysicalDevicePresentIdFeaturesKHR::default()
+                            .present_id(true),
+                        present_wait: ash::vk::PhysicalDevicePresentWaitFeaturesKHR::default()
+                            .present_wait(true),
+                        ..Default::default()
+                    },
+                    &[],
+                ),
+        );
 
         // Use simple heuristics to find the best suitable physical device.
-        let (physical_device, device_properties, mut device_features) =
-            *utils::get_sorted_physical_devices(
-                &vulkan.instance,
-                vulkan.version,
-                &DEVICE_EXTENSIONS,
-                &required_device_features,
-            )
-            .first()
-            .expect("Unable to find a suitable physical device");
+        let (physical_device, device_properties, device_features) =
+            device_builder.select_physical_device(vulkan);
 
         #[cfg(debug_assertions)]
         println!(
@@ -114,86 +264,80 @@ impl Renderer {
                 .expect("Unable to get device name")
         );
 
-        // Check the physical device for optional features we can enable for this application.
-        let mut custom_extensions =
-            SmallVec::<[*const i8; EXPECTED_MAX_ENABLED_DEVICE_EXTENSIONS]>::new();
-        let available_extensions = unsafe {
-            vulkan
-                .instance
-                .enumerate_device_extension_properties(physical_device)
-                .expect("Unable to enumerate device extensions")
-        };
-        let enabled_swapchain_maintenance =
-            if vulkan.enabled_instance_extension(ash::ext::surface_maintenance1::NAME) {
-                if utils::extensions_list_contains(
-                    &available_extensions,
-                    ash::ext::swapchain_maintenance1::NAME,
-                ) {
-                    #[cfg(debug_assertions)]
-                    println!("INFO: Enabling VK_EXT_swapchain_maintenance1 device extension");
-                    custom_extensions.push(ash::ext::swapchain_maintenance1::NAME.as_ptr());
-
-                    true
-                } else {
-                    false
-                }
-            } else {
-                false
-            };
-        let enabled_pageable_device_local_memory = if utils::extensions_list_contains(
-            &available_extensions,
-            ash::ext::memory_priority::NAME,
-        ) && utils::extensions_list_contains(
-            &available_extensions,
-            ash::ext::pageable_device_local_memory::NAME,
-        ) {
-            #[cfg(debug_assertions)]
-            println!("INFO: Enabling VK_EXT_memory_priority device extension");
-            custom_extensions.push(ash::ext::memory_priority::NAME.as_ptr());
-            #[cfg(debug_assertions)]
-            println!("INFO: Enabling VK_EXT_pageable_device_local_memory device extension");
-            custom_extensions.push(ash::ext::pageable_device_local_memory::NAME.as_ptr());
-            true
+        // Request queues for rendering, presenting to the surface, simulating, and one-off copies.
+        // Requests are given distinct queues in order, while their queue families have enough.
+        let queue_families = utils::get_queue_families(vulkan, physical_device, surface);
+        let mut queue_requests = utils::QueueRequests::default();
+        // NOTE: Prefer that the graphics and compute queues share a family because the `example_fluid` module will benefit from shared resources.
+        let graphics_request = queue_requests.push(utils::QueueRequest {
+            capabilities: ash::vk::QueueFlags::GRAPHICS,
+            prefer_sharing: ash::vk::QueueFlags::COMPUTE,
+            present: false,
+            priorities: vec![1.],
+        });
+
+        // By default, compute shares the graphics queue, so the simulation is ordered with the draws without extra synchronization.
+        // A separate compute queue runs the simulation in the background, with a lower priority than rendering.
+        let compute_request = if async_compute {
+            queue_requests.push(utils::QueueRequest {
+                capabilities: ash::vk::QueueFlags::COMPUTE,
+                prefer_sharing: ash::vk::QueueFlags::GRAPHICS,
+                present: false,
+                priorities: vec![0.25],
+            })
         } else {
-            false
+            graphics_request
         };
 
-        // We required these features in physical device selection.
-        // Now we can request the features be enabled over the logical device we create.
-        // Ignore the features we are not interested in, and enable the ones we are.
-        let enabled_pageable_device_local_memory =
-            enabled_pageable_device_local_memory && device_features.pageable_device_local_memory();
-        let mut features = ash::vk::PhysicalDeviceFeatures2::default()
-            .push_next(&mut device_features.dynamic_rendering)
-            .push_next(&mut device_features.synchronization2)
-            .push_next(&mut device_features.buffer_device_address);
-
-        // Add optional device features when they are available.
-        if enabled_pageable_device_local_memory {
+        // Prefer a dedicated transfer queue family, so that uploads and readbacks do not occupy the graphics or compute queues.
+        // One-off copies are not latency sensitive, so they are given a lower priority.
+        let transfer_request = queue_requests.push(utils::QueueRequest {
+            capabilities: ash::vk::QueueFlags::TRANSFER,
+            prefer_sharing: ash::vk::QueueFlags::empty(),
+            present: false,
+            priorities: vec![0.5],
+        });
+
+        // Prefer using the graphics queue for presentation if possible.
+        let graphics_family = queue_requests
+            .request(graphics_request)
+            .family_index(&queue_families)
+            .expect("Unable to find a graphics queue family");
+        let present_request = if queue_families.present.contains(&graphics_family) {
+            graphics_request
+        } else {
             #[cfg(debug_assertions)]
-            println!("INFO: Enabling VK_EXT_pageable_device_local_memory device feature");
+            eprintln!("WARN: Using different queue families for presentation and primary graphics");
 
-            features = features.push_next(&mut device_features.pageable_device_local_memory);
-        }
-
-        // Collect all the device extensions we need to enable.
-        let all_extension_pointers = DEVICE_EXTENSIONS
-            .iter()
-            .chain(custom_extensions.iter())
-            .copied()
-            .collect::<Vec<_>>();
+            queue_requests.push(utils::QueueRequest {
+                capabilities: ash::vk::QueueFlags::empty(),
+                prefer_sharing: ash::vk::QueueFlags::GRAPHICS,
+                present: true,
+                priorities: vec![1.],
+            })
+        };
 
         // Create a logical device capable of rendering to the surface and performing compute operations.
-        let (logical_device, queue_families) = utils::new_device(
+        let (logical_device, queues, enabled_support) = device_builder.build(
             vulkan,
             physical_device,
-            surface,
-            &all_extension_pointers,
-            Some(&mut features),
+            &device_features,
+            &queue_families,
+            &queue_requests,
         );
 
-        let pageable_device_local_memory = if enabled_pageable_device_local_memory {
-            Some(ash::ext::pageable_device_local_memory::Device::new(
+        let pageable_device_local_memory =
+            if enabled_support.features.pageable_device_local_memory() {
+                Some(ash::ext::pageable_device_local_memory::Device::new(
+                    &vulkan.instance,
+                    &logical_device,
+                ))
+            } else {
+                None
+            };
+
+        let hdr_metadata = if enabled_support.extension(ash::ext::hdr_metadata::NAME) {
+            Some(ash::ext::hdr_metadata::Device::new(
                 &vulkan.instance,
                 &logical_device,
             ))
@@ -201,51 +345,37 @@ impl Renderer {
             None
         };
 
-        let (graphics_index, compute_index, present_index) = {
-            // NOTE: Prefer that the graphics and compute queues are equivalent because the `example_fluid` module will benefit from shared resources.
-            let graphics = utils::get_queue_family_index(
-                ash::vk::QueueFlags::GRAPHICS,
-                ash::vk::QueueFlags::COMPUTE,
-                &queue_families,
-            );
-
-            // Prefer using the graphics queue for presentation if possible.
-            let present = if queue_families.present.contains(&graphics) {
-                graphics
-            } else {
-                #[cfg(debug_assertions)]
-                eprintln!(
-                    "WARN: Using different queue families for presentation and primary graphics"
-                );
-
-                // Choose the first queue family that supports presentation.
-                *queue_families
-                    .present
-                    .first()
-                    .expect("Unable to find a present queue family")
-            };
-
-            // NOTE: Prefer that the graphics and compute queues are equivalent because the `example_fluid` module will benefit from shared resources.
-            let compute = if queue_families.compute.contains(&graphics) {
-                graphics
-            } else {
-                *queue_families
-                    .compute
-                    .first()
-                    .expect("Unable to find a compute queue family")
-            };
-
-            (graphics, compute, present)
+        // Track presents so that frames can be paced to the display.
+        let present_wait = if enabled_support.features.present_wait() {
+            Some(ash::khr::present_wait::Device::new(
+                &vulkan.instance,
+                &logical_device,
+            ))
+        } else {
+            None
         };
+        let frame_pacer = utils::frame_pacing::FramePacer::new(present_wait.clone());
 
         // Get a handle to a queue capable of performing graphics commands.
-        let graphics_queue = utils::IndexedQueue::get(&logical_device, graphics_index, 0);
+        let graphics_queue = queues.queue(graphics_request, 0);
+        let graphics_index = graphics_queue.family_index;
 
         // Get a handle to a queue capable of performing compute commands.
-        let compute_queue = utils::IndexedQueue::get(&logical_device, compute_index, 0);
+        let compute_queue = queues.queue(compute_request, 0);
 
         // Get a handle to a presentation queue for use with swapchain presentation.
-        let presentation_queue = utils::IndexedQueue::get(&logical_device, present_index, 0);
+        let presentation_queue = queues.queue(present_request, 0);
+
+        // Get a handle to a queue for one-off copies.
+        let transfer_queue = queues.queue(transfer_request, 0);
+        #[cfg(debug_assertions)]
+        if transfer_queue.family_index != graphics_index {
+            println!(
+                "INFO: Using a separate transfer queue family {}",
+                transfer_queue.family_index
+            );
+        }
+        let transfer_queue = utils::transfer::TransferQueue::new(&logical_device, transfer_queue);
 
         let mut memory_allocator =
             gpu_allocator::vulkan::Allocator::new(&gpu_allocator::vulkan::AllocatorCreateDesc {
@@ -259,6 +389,11 @@ impl Renderer {
             .expect("Unable to create memory allocator (GPU Allocator)");
 
         // Create an object to manage the swapchain, its images, and synchronization primitives.
+        // Demos render the scene before it is tonemapped, so any multisampled images must match the scene format.
+        let swapchain_preferences = utils::SwapchainPreferences {
+            multisample_format: Some(SCENE_FORMAT),
+            ..swapchain_preferences
+        };
         let swapchain = utils::Swapchain::new(
             vulkan,
             physical_device,
@@ -266,12 +401,18 @@ impl Renderer {
             surface,
             &mut memory_allocator,
             swapchain_preferences,
-            enabled_swapchain_maintenance,
+            enabled_support.extension(ash::ext::swapchain_maintenance1::NAME),
             None,
         );
         let frames_in_flight = swapchain.frames_in_flight();
-        let extent = swapchain.extent();
-        let image_format = swapchain.image_format();
+        #[cfg(debug_assertions)]
+        println!(
+            "INFO: Swapchain encodes sRGB in hardware: {}",
+            swapchain.encodes_srgb()
+        );
+        if let Some(hdr_metadata) = &hdr_metadata {
+            Self::update_hdr_metadata(hdr_metadata, &swapchain);
+        }
 
         // Create a pool for allocating new commands.
         // NOTE: https://developer.nvidia.com/blog/vulkan-dos-donts/ Recommends `image_count * recording_thread_count` many command pools for optimal command buffer allocation.
@@ -291,7 +432,8 @@ impl Renderer {
                 .expect("Unable to create command pool")
         };
 
-        let compute_queue_extra = if graphics_index == compute_index {
+        // A compute queue separate from the graphics queue must be synchronized with a semaphore, even within the same family.
+        let compute_queue_extra = if graphics_queue.queue == compute_queue.queue {
             None
         } else {
             #[cfg(debug_assertions)]
@@ -302,7 +444,7 @@ impl Renderer {
                     &ash::vk::CommandPoolCreateInfo {
                         flags: ash::vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER
                             | ash::vk::CommandPoolCreateFlags::TRANSIENT,
-                        queue_family_index: compute_index,
+                        queue_family_index: compute_queue.family_index,
                         ..Default::default()
                     },
                     None,
@@ -319,20 +461,17 @@ impl Renderer {
             Some((pool, semaphore))
         };
 
-        // Create the FXAA post-processing pass if it is desired.
+        // Create the chain of post-processing passes, which always ends by tonemapping the scene for the swapchain.
         // Creating post processing passes first is helpful for chaining passes together.
-        let fxaa_pass = if enable_fxaa {
-            Some(FxaaPass::new(
-                &logical_device,
-                &mut memory_allocator,
-                extent,
-                image_format,
-                swapchain.image_views(),
-                ash::vk::ImageLayout::PRESENT_SRC_KHR, // The FXAA pass will render directly to the swapchain image for presentation.
-            ))
-        } else {
-            None
-        };
+        let post_process = PostProcessChain::new(
+            &logical_device,
+            &mut memory_allocator,
+            &transfer_queue,
+            &graphics_queue,
+            command_pool,
+            &swapchain,
+            &post_process_settings,
+        );
 
         let active_demo = match specialization_constants {
             DemoSpecializationConstants::Triangle(constants) => {
@@ -342,112 +481,107 @@ impl Renderer {
                     None,
                     None,
                     example_triangle::CreateReuseRenderPass::Create {
-                        image_format,
-                        destination_layout: if enable_fxaa {
-                            // NOTE: If we have at least one post-processing pass, we should render to a color attachment.
-                            ash::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
-                        } else {
-                            ash::vk::ImageLayout::PRESENT_SRC_KHR
-                        },
+                        image_format: SCENE_FORMAT,
+                        destination_layout: post_process.input_layout(),
                     },
-                    &swapchain,
+                    swapchain.multisample_count(),
                     constants,
-                    fxaa_pass.as_ref(),
                 );
                 DemoPipeline::Triangle(demo)
             }
-            DemoSpecializationConstants::Fluid => {
+            DemoSpecializationConstants::Fluid(constants) => {
                 let demo = example_fluid::FluidSimulation::new(
                     &logical_device,
                     &mut memory_allocator,
-                    extent,
-                    image_format,
-                    ash::vk::ImageLayout::PRESENT_SRC_KHR,
-                    swapchain.image_views(),
+                    post_process.extent(),
+                    SCENE_FORMAT,
+                    post_process.input_layout(),
                     compute_queue_extra.map_or(command_pool, |(pool, _)| pool),
                     pageable_device_local_memory.as_ref(),
+                    constants,
                 );
-                DemoPipeline::Fluid(demo)
+                DemoPipeline::Fluid(Box::new(demo))
             }
         };
 
-        // Allocate a command buffer for each frame in flight.
-        // One may want to use a different number if there are background tasks not related to an image presentation.
-        let command_buffer_info = ash::vk::CommandBufferAllocateInfo {
-            command_pool,
-            level: ash::vk::CommandBufferLevel::PRIMARY,
-            command_buffer_count: 1,
-            ..Default::default()
-        };
-        let command_buffers = unsafe {
-            let mut c = Vec::new();
-            c.resize_with(frames_in_flight, || {
-                *logical_device
-                    .allocate_command_buffers(&command_buffer_info)
-                    .expect("Unable to allocate command buffer")
-                    .first()
-                    .expect("No command buffers were allocated")
-            });
-            c
-        };
+        // Allocate the command buffers and fences for each frame in flight of the primary target.
+        let (command_buffers, frame_fences) =
+            RenderTarget::create_frame_resources(&logical_device, command_pool, frames_in_flight);
 
-        // Create a fence for each image in the swapchain so the CPU can wait for the GPU to finish a given frame.
-        let fence_create_info = ash::vk::FenceCreateInfo {
-            flags: ash::vk::FenceCreateFlags::SIGNALED,
-            ..Default::default()
-        };
-        let frame_fences = unsafe {
-            let mut f = Vec::new();
-            f.resize_with(frames_in_flight, || {
-                logical_device
-                    .create_fence(&fence_create_info, None)
-                    .expect("Unable to create fence")
-            });
-            f
+        // Measure the GPU time of each frame, for adjusting the render scale automatically.
+        let frame_timer = utils::GpuFrameTimer::new(
+            &logical_device,
+            frames_in_flight,
+            device_properties.limits.timestamp_period,
+            queue_families.queue_families[graphics_index as usize].timestamp_valid_bits,
+        );
+
+        let multisample_count = swapchain.multisample_count();
+        let primary_target = RenderTarget {
+            id: TargetId::PRIMARY,
+            surface,
+            swapchain: Some(swapchain),
+            resize_swapchain: ResizeSwapchainState::None,
+            swapchain_preferences,
+
+            command_buffers,
+            frame_fences,
+            submitted_frame_numbers: vec![0; frames_in_flight],
+            frame_timer,
+            frame_pacer,
+
+            post_process_settings,
+            post_process,
+            demo_framebuffers: Vec::new(),
+            fluid_display_texture: example_fluid::FluidDisplayTexture::default(),
         };
 
-        Self {
+        let mut renderer = Self {
             physical_device,
-            device_extensions: custom_extensions
-                .into_iter()
-                .map(|p| unsafe { CStr::from_ptr(p) })
-                .collect::<HashSet<_>>(),
+            device_extensions: enabled_support.extensions,
             logical_device,
             pageable_device_local_memory,
+            hdr_metadata,
+            present_wait,
             memory_allocator,
 
-            surface,
-            swapchain,
-            resize_swapchain: ResizeSwapchainState::None,
+            targets: vec![primary_target],
+            next_target_id: 1,
 
             active_demo,
+            multisample_count,
             graphics_queue,
             compute_queue,
             presentation_queue,
+            transfer_queue,
             command_pool,
             compute_command_pool: compute_queue_extra,
 
-            command_buffers,
-            frame_fences,
-
-            fxaa_pass,
-            swapchain_preferences,
-        }
+            deletion_queue: DeletionQueue::default(),
+            dynamic_resolution: None,
+            fps_cap: None,
+            low_latency: false,
+        };
+        renderer.recreate_demo_framebuffers(0);
+        renderer
     }
 
-    /// Destroy the Pompeii renderer and its dependent resources.
+    /// Destroy the Pompeii renderer and its dependent resources, including the surface of every render target.
     /// # Safety
     /// This function **must** only be called when the owned resources are not currently being processed by the GPU.
     pub fn destroy(mut self, vulkan: &utils::VulkanCore) {
-        unsafe {
-            // Destroy all fences.
-            for fence in self.frame_fences {
-                self.logical_device.destroy_fence(fence, None);
-            }
+        // Destroy the swapchain, surface, and per-frame resources of every render target.
+        for target in self.targets {
+            target.destroy(
+                vulkan,
+                &self.logical_device,
+                &mut self.memory_allocator,
+                self.command_pool,
+            );
+        }
 
-            // Destroy all command buffers and the command pool.
-            self.logical_device
-                .free_command_buffers(self.command_pool, &self.command_buffers);
+        unsafe {
+            // Destroy the command pool, now that its command buffers have been freed.
             self.logical_device
                 .destroy_command_pool(self.command_pool, None);
 
@@ -461,172 +595,496 @@ impl Renderer {
                 }
             }
 
-            if let Some(mut fxaa_pass) = self.fxaa_pass.take() {
-                fxaa_pass.destroy(&self.logical_device, &mut self.memory_allocator);
-            }
+            self.deletion_queue
+                .destroy(&self.logical_device, &mut self.memory_allocator);
+
+            // Destroy the resources for one-off copies.
+            self.transfer_queue.destroy(&self.logical_device);
+
+            // Destroy additional compute resources if the exist.
+            if let Some((command_pool, semaphore)) = self.compute_command_pool {
+                self.logical_device.destroy_command_pool(command_pool, None);
+                self.logical_device.destroy_semaphore(semaphore, None);
+            }
+
+            // Destroy the logical device itself.
+            self.logical_device.destroy_device(None);
+        }
+    }
+
+    /// Destroy a Vulkan surface, if it has not already been destroyed.
+    /// # Safety
+    /// Every swapchain created from the surface must have been destroyed.
+    fn destroy_surface(vulkan: &utils::VulkanCore, surface: ash::vk::SurfaceKHR) {
+        if surface == ash::vk::SurfaceKHR::null() {
+            return;
+        }
+        if let Some(khr) = vulkan.khr.as_ref() {
+            unsafe { khr.destroy_surface(surface, None) };
+        } else {
+            eprintln!(
+                "ERROR: Unable to destroy surface because the `khr` extension is not available"
+            );
+        }
+    }
+
+    /// Get the index of a render target in `self.targets`.
+    fn target_index(&self, target: TargetId) -> usize {
+        self.targets
+            .iter()
+            .position(|t| t.id == target)
+            .expect("The render target does not exist")
+    }
+
+    /// Ensure that the presentation queue can present to a new surface.
+    /// # Panics
+    /// * The presentation queue family must support presenting to the surface.
+    fn assert_present_support(&self, vulkan: &utils::VulkanCore, surface: ash::vk::SurfaceKHR) {
+        let supports_present = unsafe {
+            vulkan
+                .khr
+                .as_ref()
+                .expect("Vulkan instance does not support the KHR surface extension")
+                .get_physical_device_surface_support(
+                    self.physical_device,
+                    self.presentation_queue.family_index,
+                    surface,
+                )
+                .expect("Unable to query surface support")
+        };
+        assert!(
+            supports_present,
+            "The presentation queue family does not support presenting to the new surface"
+        );
+    }
+
+    /// Add a surface to render to, such as another window, with its own swapchain.
+    /// The new target shows the active demo with the post-processing settings of the primary target.
+    /// # Panics
+    /// * The presentation queue family must support presenting to the new surface.
+    pub fn add_target(
+        &mut self,
+        vulkan: &utils::VulkanCore,
+        surface: ash::vk::SurfaceKHR,
+        swapchain_preferences: utils::SwapchainPreferences,
+    ) -> TargetId {
+        self.assert_present_support(vulkan, surface);
+
+        // Demos render the scene before it is tonemapped, so any multisampled images must match the scene format.
+        let swapchain_preferences = utils::SwapchainPreferences {
+            multisample_format: Some(SCENE_FORMAT),
+            ..swapchain_preferences
+        };
+        let swapchain = utils::Swapchain::new(
+            vulkan,
+            self.physical_device,
+            &self.logical_device,
+            surface,
+            &mut self.memory_allocator,
+            swapchain_preferences,
+            self.device_extensions
+                .contains(ash::ext::swapchain_maintenance1::NAME),
+            None,
+        );
+        if let Some(hdr_metadata) = &self.hdr_metadata {
+            Self::update_hdr_metadata(hdr_metadata, &swapchain);
+        }
+
+        // Apply the same post-processing as the primary target.
+        let post_process_settings = self.targets[0].post_process_settings.clone();
+        let post_process = PostProcessChain::new(
+            &self.logical_device,
+            &mut self.memory_allocator,
+            &self.transfer_queue,
+            &self.graphics_queue,
+            self.command_pool,
+            &swapchain,
+            &post_process_settings,
+        );
+
+        let frames_in_flight = swapchain.frames_in_flight();
+        let (command_buffers, frame_fences) = RenderTarget::create_frame_resources(
+            &self.logical_device,
+            self.command_pool,
+            frames_in_flight,
+        );
+        let mut frame_pacer = utils::frame_pacing::FramePacer::new(self.present_wait.clone());
+        frame_pacer.set_fps_cap(self.fps_cap);
+        frame_pacer.set_low_latency(self.low_latency);
+
+        let id = TargetId(self.next_target_id);
+        self.next_target_id += 1;
+        self.targets.push(RenderTarget {
+            id,
+            surface,
+            swapchain: Some(swapchain),
+            resize_swapchain: ResizeSwapchainState::None,
+            swapchain_preferences,
+
+            command_buffers,
+            frame_fences,
+            submitted_frame_numbers: vec![0; frames_in_flight],
+            frame_timer: None,
+            frame_pacer,
+
+            post_process_settings,
+            post_process,
+            demo_framebuffers: Vec::new(),
+            fluid_display_texture: example_fluid::FluidDisplayTexture::default(),
+        });
+        self.recreate_demo_framebuffers(self.targets.len() - 1);
+        id
+    }
+
+    /// Stop rendering to a target added with `add_target`, and destroy it along with its surface.
+    /// # Panics
+    /// * The primary target cannot be removed.
+    pub fn remove_target(&mut self, vulkan: &utils::VulkanCore, target: TargetId) {
+        assert_ne!(
+            target,
+            TargetId::PRIMARY,
+            "The primary render target cannot be removed"
+        );
+
+        // Suspending the target waits for the work submitted to it to complete.
+        self.suspend(vulkan, target);
+        let render_target = self.targets.remove(self.target_index(target));
+        if let DemoPipeline::Fluid(simulation) = &mut self.active_demo {
+            simulation.forget_graphics_fences(&render_target.frame_fences);
+        }
+        render_target.destroy(
+            vulkan,
+            &self.logical_device,
+            &mut self.memory_allocator,
+            self.command_pool,
+        );
+    }
+
+    /// Destroy the surface and swapchain of a target, such as when the application is suspended and its window may no longer be drawn to.
+    /// The device and the state of the demo are kept, so that rendering continues where it left off after `resume`.
+    pub fn suspend(&mut self, vulkan: &utils::VulkanCore, target: TargetId) {
+        let index = self.target_index(target);
+        let render_target = &mut self.targets[index];
+        let Some(swapchain) = render_target.swapchain.take() else {
+            return;
+        };
+
+        // The swapchain images may still be in use by queued presentations, which only the device becoming idle guarantees are complete.
+        unsafe {
+            self.logical_device
+                .device_wait_idle()
+                .expect("Unable to wait for device to become idle");
+        }
+        swapchain.destroy(&self.logical_device, &mut self.memory_allocator);
+        Self::destroy_surface(vulkan, render_target.surface);
+        render_target.surface = ash::vk::SurfaceKHR::null();
+    }
+
+    /// Create a swapchain for a new surface of a target, after it was suspended or the previous surface was lost.
+    /// The surface format and color space are chosen again, as the new surface may be on a display with different capabilities.
+    /// # Panics
+    /// * The presentation queue family must support presenting to the new surface.
+    pub fn resume(
+        &mut self,
+        vulkan: &utils::VulkanCore,
+        target: TargetId,
+        surface: ash::vk::SurfaceKHR,
+    ) {
+        self.suspend(vulkan, target);
+        self.assert_present_support(vulkan, surface);
 
-            // Destroy additional compute resources if the exist.
-            if let Some((command_pool, semaphore)) = self.compute_command_pool {
-                self.logical_device.destroy_command_pool(command_pool, None);
-                self.logical_device.destroy_semaphore(semaphore, None);
-            }
+        let index = self.target_index(target);
+        let render_target = &mut self.targets[index];
+        render_target.surface = surface;
+        render_target.swapchain = Some(utils::Swapchain::new(
+            vulkan,
+            self.physical_device,
+            &self.logical_device,
+            surface,
+            &mut self.memory_allocator,
+            render_target.swapchain_preferences,
+            self.device_extensions
+                .contains(ash::ext::swapchain_maintenance1::NAME),
+            None,
+        ));
+        self.swapchain_replaced(index);
+    }
 
-            // Destroy the swapchain and its dependent resources.
-            self.swapchain
-                .destroy(&self.logical_device, &mut self.memory_allocator);
+    /// The size of the swapchain images of a target, or `None` while it is suspended.
+    pub fn swapchain_extent(&self, target: TargetId) -> Option<ash::vk::Extent2D> {
+        self.targets[self.target_index(target)]
+            .swapchain
+            .as_ref()
+            .map(utils::Swapchain::extent)
+    }
 
-            // Destroy the logical device itself.
-            self.logical_device.destroy_device(None);
+    /// Whether the target has no surface to draw to, until `resume` is called with a new one.
+    pub fn is_suspended(&self, target: TargetId) -> bool {
+        self.targets[self.target_index(target)].swapchain.is_none()
+    }
 
-            // Destroy the Vulkan surface.
-            if let Some(khr) = vulkan.khr.as_ref() {
-                khr.destroy_surface(self.surface, None);
-            } else {
-                eprintln!(
-                    "ERROR: Unable to destroy surface because the `khr` extension is not available"
-                );
-            }
-        }
+    /// Whether the surface of the target was lost while rendering, and must be replaced with `resume` before rendering again.
+    pub fn surface_lost(&self, target: TargetId) -> bool {
+        matches!(
+            self.targets[self.target_index(target)].resize_swapchain,
+            ResizeSwapchainState::SurfaceLost
+        )
     }
 
-    /// Indicate that the swapchain needs to be recreated before next use.
-    pub fn swapchain_resize_required(&mut self, new_extent: Option<ash::vk::Extent2D>) {
+    /// Indicate that the swapchain of the target needs to be recreated before next use.
+    pub fn swapchain_resize_required(
+        &mut self,
+        target: TargetId,
+        new_extent: Option<ash::vk::Extent2D>,
+    ) {
+        let index = self.target_index(target);
+        let render_target = &mut self.targets[index];
         if let Some(extent) = new_extent {
-            self.swapchain_preferences.preferred_extent = Some(extent);
+            render_target.swapchain_preferences.preferred_extent = Some(extent);
+        }
+        if !matches!(
+            render_target.resize_swapchain,
+            ResizeSwapchainState::SurfaceLost
+        ) {
+            render_target.resize_swapchain = ResizeSwapchainState::Resized;
         }
-        self.resize_swapchain = ResizeSwapchainState::Resized;
     }
 
-    /// Handle any impending swapchain recreations.
-    pub fn handle_swapchain_resize(&mut self, vulkan: &utils::VulkanCore) {
-        if matches!(self.resize_swapchain, ResizeSwapchainState::Resized) {
-            self.recreate_swapchain(vulkan);
+    /// Handle any impending swapchain recreation of the target.
+    pub fn handle_swapchain_resize(&mut self, vulkan: &utils::VulkanCore, target: TargetId) {
+        if matches!(
+            self.targets[self.target_index(target)].resize_swapchain,
+            ResizeSwapchainState::Resized
+        ) {
+            self.recreate_swapchain(vulkan, target);
         }
     }
 
-    /// Recreate the swapchain, including the framebuffers and image views for the frames owned by the swapchain.
-    /// The `self.swapchain_preferences` are used to recreate the swapchain and do not need to match those used with the initial swapchain creation.
-    pub fn recreate_swapchain(&mut self, vulkan: &utils::VulkanCore) {
-        let old_format = self.swapchain.image_format();
+    /// Recreate the swapchain of the target, including the framebuffers and image views for the frames owned by the swapchain.
+    /// The swapchain preferences of the target are used to recreate the swapchain and do not need to match those used with the initial swapchain creation.
+    pub fn recreate_swapchain(&mut self, vulkan: &utils::VulkanCore, target: TargetId) {
+        let index = self.target_index(target);
+        let render_target = &mut self.targets[index];
+        let Some(swapchain) = &mut render_target.swapchain else {
+            // A new swapchain is created when the target is resumed.
+            return;
+        };
 
         // Recreate the swapchain using the new preferences.
-        self.swapchain.recreate_swapchain(
+        if let Some(old_swapchain) = swapchain.recreate_swapchain(
             vulkan,
             self.physical_device,
             &self.logical_device,
-            self.surface,
+            render_target.surface,
             &mut self.memory_allocator,
-            self.swapchain_preferences,
-        );
+            render_target.swapchain_preferences,
+        ) {
+            // The old swapchain is destroyed once the frames which presented to it have completed.
+            old_swapchain.retire(&mut self.deletion_queue);
+        }
+        self.swapchain_replaced(index);
+    }
 
-        // Check if the image format has changed and recreate the render pass and graphics pipeline if necessary.
-        let new_swapchain_format = self.swapchain.image_format();
-        let extent = self.swapchain.extent();
+    /// Update the resources which depend on the swapchain of the target at the given index after it has been replaced.
+    fn swapchain_replaced(&mut self, index: usize) {
+        let render_target = &mut self.targets[index];
+        let Some(swapchain) = &render_target.swapchain else {
+            return;
+        };
+        if let Some(hdr_metadata) = &self.hdr_metadata {
+            Self::update_hdr_metadata(hdr_metadata, swapchain);
+        }
+        render_target.frame_pacer.swapchain_recreated();
 
-        // Recreate the FXAA pass if it was enabled.
-        if let Some(fxaa_pass) = &mut self.fxaa_pass {
-            if new_swapchain_format == old_format {
-                fxaa_pass.recreate_framebuffers(
-                    &self.logical_device,
-                    &mut self.memory_allocator,
-                    extent,
-                    new_swapchain_format,
-                    self.swapchain.image_views(),
-                );
-            } else {
-                let new_fxaa_pass = FxaaPass::new(
-                    &self.logical_device,
-                    &mut self.memory_allocator,
-                    extent,
-                    new_swapchain_format,
-                    self.swapchain.image_views(),
-                    ash::vk::ImageLayout::PRESENT_SRC_KHR,
-                );
+        // Build a new post-processing chain for the new swapchain, as the frames in flight may still use the old one.
+        self.rebuild_post_process(index);
+
+        // Reset the flag indicating the swapchain needs to be recreated.
+        self.targets[index].resize_swapchain = ResizeSwapchainState::None;
+    }
 
-                // Destroy the old FXAA pass and replace it with the new one.
-                fxaa_pass.destroy(&self.logical_device, &mut self.memory_allocator);
+    /// Replace the post-processing chain of the target at the given index with a new one built from its settings, and recreate the framebuffers of the active demo to render to it.
+    /// The old chain is destroyed once the frames in flight have completed, so the frames which use it are not waited on.
+    /// A suspended target keeps its old chain until it is resumed, when the chain is built for its new swapchain.
+    fn rebuild_post_process(&mut self, index: usize) {
+        let render_target = &mut self.targets[index];
+        let Some(swapchain) = &render_target.swapchain else {
+            return;
+        };
 
-                *fxaa_pass = new_fxaa_pass;
-            }
+        let mut post_process = PostProcessChain::new(
+            &self.logical_device,
+            &mut self.memory_allocator,
+            &self.transfer_queue,
+            &self.graphics_queue,
+            self.command_pool,
+            swapchain,
+            &render_target.post_process_settings,
+        );
+        std::mem::swap(&mut render_target.post_process, &mut post_process);
+        let mut old_post_process = post_process; // Rename for clarity.
+        self.deletion_queue.push(DeferredDestroy::Other(Box::new(
+            move |device, memory_allocator| {
+                old_post_process.destroy(device, memory_allocator);
+            },
+        )));
+        self.recreate_demo_framebuffers(index);
+    }
+
+    /// Switch the target to the given present mode, without recreating the swapchain if `VK_EXT_swapchain_maintenance1` allows it.
+    /// The preference is kept so that later recreations of the swapchain use the same present mode.
+    pub fn set_present_mode(
+        &mut self,
+        vulkan: &utils::VulkanCore,
+        target: TargetId,
+        present_mode: ash::vk::PresentModeKHR,
+    ) {
+        let index = self.target_index(target);
+        let render_target = &mut self.targets[index];
+        render_target.swapchain_preferences.present_mode = Some(present_mode);
+        if !render_target
+            .swapchain
+            .as_mut()
+            .is_some_and(|swapchain| swapchain.set_present_mode(present_mode))
+        {
+            self.recreate_swapchain(vulkan, target);
         }
+    }
 
-        if new_swapchain_format == old_format {
-            // Recreate the framebuffers to account for the new size. Other details are unchanged.
-            match &mut self.active_demo {
-                DemoPipeline::Triangle(triangle_pipeline) => {
-                    triangle_pipeline.recreate_framebuffers(
-                        &self.logical_device,
-                        &self.swapchain,
-                        self.fxaa_pass.as_ref(),
-                    );
-                }
-                DemoPipeline::Fluid(simulation) => {
-                    simulation.recreate_framebuffers(
-                        &self.logical_device,
-                        &mut self.memory_allocator,
-                        extent,
-                        self.swapchain.image_views(),
-                        self.pageable_device_local_memory.as_ref(),
-                    );
-                }
-            }
-        } else {
-            // Wait for the resources to be available for destruction.
-            self.wait_for_tasks();
+    /// Switch the target to the next present mode supported by its surface, in the order immediate, mailbox, FIFO, and FIFO-relaxed.
+    /// Returns the present mode now in use, or the preferred present mode if the target is suspended, as the supported modes of its next surface are unknown.
+    /// # Panics
+    /// * The `utils::VulkanCore` struct must have a `khr` field that is not `None`.
+    pub fn cycle_present_mode(
+        &mut self,
+        vulkan: &utils::VulkanCore,
+        target: TargetId,
+    ) -> ash::vk::PresentModeKHR {
+        const PRESENT_MODE_CYCLE: [ash::vk::PresentModeKHR; 4] = [
+            ash::vk::PresentModeKHR::IMMEDIATE,
+            ash::vk::PresentModeKHR::MAILBOX,
+            ash::vk::PresentModeKHR::FIFO,
+            ash::vk::PresentModeKHR::FIFO_RELAXED,
+        ];
+        let index = self.target_index(target);
+        let Some(swapchain) = &self.targets[index].swapchain else {
+            return self.targets[index]
+                .swapchain_preferences
+                .present_mode
+                .unwrap_or(ash::vk::PresentModeKHR::FIFO);
+        };
+        let current_present_mode = swapchain.present_mode();
+        let supported_present_modes = unsafe {
+            vulkan
+                .khr
+                .as_ref()
+                .expect("Vulkan instance does not support the KHR surface extension")
+                .get_physical_device_surface_present_modes(
+                    self.physical_device,
+                    self.targets[index].surface,
+                )
+                .expect("Unable to get supported present modes")
+        };
 
-            // Destroy the old pipeline and recreate the necessary resources.
-            match &mut self.active_demo {
-                DemoPipeline::Triangle(triangle_pipeline) => {
-                    triangle_pipeline.recreate(
-                        &self.logical_device,
-                        example_triangle::CreateReuseRenderPass::Create {
-                            image_format: new_swapchain_format,
-                            destination_layout: if self.fxaa_pass.is_some() {
-                                ash::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
-                            } else {
-                                ash::vk::ImageLayout::PRESENT_SRC_KHR
-                            },
-                        },
-                        &self.swapchain,
-                        triangle_pipeline.specialization_constants(),
-                        self.fxaa_pass.as_ref(),
-                    );
-                }
-                DemoPipeline::Fluid(simulation) => {
-                    let new_sim = example_fluid::FluidSimulation::new(
+        // Start after the current present mode and take the first supported mode, wrapping around the cycle.
+        let current = PRESENT_MODE_CYCLE
+            .iter()
+            .position(|&mode| mode == current_present_mode)
+            .unwrap_or_default();
+        let next_present_mode = (1..=PRESENT_MODE_CYCLE.len())
+            .map(|offset| PRESENT_MODE_CYCLE[(current + offset) % PRESENT_MODE_CYCLE.len()])
+            .find(|mode| supported_present_modes.contains(mode))
+            .unwrap_or(ash::vk::PresentModeKHR::FIFO);
+
+        self.set_present_mode(vulkan, target, next_present_mode);
+        self.targets[index].swapchain().present_mode()
+    }
+
+    /// Recreate the framebuffers of the active demo for the target at the given index, covering the whole of the current input images of its post-processing chain.
+    /// Demos draw to only the render area of the framebuffers, so they are unaffected by changes to the render scale.
+    /// A suspended target has no framebuffers until it is resumed.
+    /// The fluid simulation runs at the swapchain resolution of the primary target, so its buffers are also recreated when that changes.
+    fn recreate_demo_framebuffers(&mut self, index: usize) {
+        let render_target = &mut self.targets[index];
+        for framebuffer in render_target.demo_framebuffers.drain(..) {
+            self.deletion_queue
+                .push(DeferredDestroy::Framebuffer(framebuffer));
+        }
+        let Some(swapchain) = &render_target.swapchain else {
+            return;
+        };
+
+        let extent = render_target.post_process.extent();
+        render_target.demo_framebuffers = match &mut self.active_demo {
+            DemoPipeline::Triangle(triangle_pipeline) => example_triangle::create_framebuffers(
+                &self.logical_device,
+                swapchain,
+                triangle_pipeline.render_pass(),
+                &render_target.post_process,
+            ),
+            DemoPipeline::Fluid(simulation) => {
+                if render_target.id == TargetId::PRIMARY && simulation.extent() != extent {
+                    simulation.recreate_simulation_buffers(
                         &self.logical_device,
                         &mut self.memory_allocator,
                         extent,
-                        new_swapchain_format,
-                        ash::vk::ImageLayout::PRESENT_SRC_KHR,
-                        self.swapchain.image_views(),
-                        self.compute_command_pool
-                            .map_or(self.command_pool, |(pool, _)| pool),
                         self.pageable_device_local_memory.as_ref(),
+                        &mut self.deletion_queue,
                     );
-                    simulation.destroy(&self.logical_device, &mut self.memory_allocator);
-                    *simulation = new_sim;
                 }
+                example_fluid::create_destination_framebuffers(
+                    &self.logical_device,
+                    extent,
+                    &render_target.post_process.input_views(),
+                    simulation.render_pass(),
+                )
             }
-        }
-
-        // Reset the flag indicating the swapchain needs to be recreated.
-        self.resize_swapchain = ResizeSwapchainState::None;
+        };
     }
 
-    /// Attempt to render the next frame of the application. If there is a recoverable error, then the swapchain is recreated and the function bails early without rendering.
+    /// Attempt to render the next frame of the application to the target. If there is a recoverable error, then the swapchain is recreated and the function bails early without rendering.
+    /// The primary target advances the active demo, while the other targets show its current state.
     /// # Panics
     /// * The `utils::VulkanCore` struct must have a `khr` field that is not `None`.
-    pub fn render_frame(&mut self, vulkan: &utils::VulkanCore, push_constants: &DemoPushConstants) {
-        // Synchronize the CPU with the GPU for the resources previously used for this frame in flight.
-        // Specifically, the command buffer cannot be reused until the fence is signaled.
-        let current_frame = self.swapchain.current_frame();
-        let frame_graphics_fence = self.frame_fences[current_frame];
-        let presentation_fence = self.swapchain.present_complete();
-        unsafe {
-            self.logical_device
-                .wait_for_fences(&[frame_graphics_fence], true, FIVE_SECONDS_IN_NANOSECONDS)
-                .expect("Unable to wait for fence to begin frame");
+    /// * The target must not be suspended.
+    pub fn render_frame(
+        &mut self,
+        vulkan: &utils::VulkanCore,
+        target: TargetId,
+        push_constants: &DemoPushConstants,
+    ) {
+        // Synchronize the CPU with the GPU and the display, unless already done before the caller sampled input.
+        self.wait_for_next_frame(target);
+        let index = self.target_index(target);
+        let current_frame = self.targets[index].swapchain().current_frame();
+        let frame_graphics_fence = self.targets[index].frame_fences[current_frame];
+        let presentation_fence = self.targets[index].swapchain().present_complete();
+
+        // Adjust the render scale to the GPU time of the last frame which used these resources.
+        let new_render_scale = match (
+            &self.targets[index].frame_timer,
+            &mut self.dynamic_resolution,
+        ) {
+            (Some(frame_timer), Some(dynamic_resolution)) => frame_timer
+                .frame_time(&self.logical_device, current_frame)
+                .and_then(|frame_time| {
+                    dynamic_resolution.update(
+                        frame_time,
+                        self.targets[index]
+                            .post_process_settings
+                            .upscale_settings
+                            .render_scale,
+                    )
+                }),
+            _ => None,
+        };
+        if let Some(render_scale) = new_render_scale {
+            #[cfg(debug_assertions)]
+            println!("INFO: Changing the render scale to {render_scale:.2}");
+            self.set_upscale_settings(UpscaleSettings {
+                render_scale,
+                ..self.targets[index].post_process_settings.upscale_settings
+            });
         }
 
         // Get the next image to render to. Has internal synchronization to ensure the previous acquire completed on the GPU.
@@ -634,7 +1092,7 @@ impl Renderer {
             image_index,
             suboptimal,
             ..
-        } = match self.swapchain.acquire_next_image() {
+        } = match self.targets[index].swapchain_mut().acquire_next_image() {
             Ok(f) => f,
 
             Err(ash::vk::Result::ERROR_OUT_OF_DATE_KHR) => {
@@ -645,7 +1103,7 @@ impl Renderer {
                         .unwrap()
                         .get_physical_device_surface_capabilities(
                             self.physical_device,
-                            self.surface,
+                            self.targets[index].surface,
                         )
                         .expect("Unable to get surface capabilities")
                 };
@@ -661,6 +1119,7 @@ impl Renderer {
 
                 println!("WARN: Swapchain is out of date at image acquire, needs to be recreated.");
                 self.swapchain_resize_required(
+                    target,
                     if surface_capabilities.current_extent == utils::SPECIAL_SURFACE_EXTENT {
                         None
                     } else {
@@ -670,6 +1129,12 @@ impl Renderer {
                 return;
             }
 
+            Err(ash::vk::Result::ERROR_SURFACE_LOST_KHR) => {
+                println!("WARN: Surface was lost at image acquire, needs to be recreated.");
+                self.targets[index].resize_swapchain = ResizeSwapchainState::SurfaceLost;
+                return;
+            }
+
             Err(e) => panic!("Unable to acquire next image from swapchain: {e}"),
         };
 
@@ -678,10 +1143,11 @@ impl Renderer {
             println!(
                 "WARN: Swapchain image is suboptimal, recreating the swapchain after this frame"
             );
-            self.resize_swapchain = ResizeSwapchainState::None;
+            self.targets[index].resize_swapchain = ResizeSwapchainState::None;
         }
 
-        let command_buffer = self.command_buffers[current_frame];
+        let render_target = &mut self.targets[index];
+        let command_buffer = render_target.command_buffers[current_frame];
         unsafe {
             // NOTE: We do not need to reset the command buffer here because `ONE_TIME_SUBMIT` command buffers are implicitly reset.
             self.logical_device
@@ -694,8 +1160,19 @@ impl Renderer {
                 )
                 .expect("Unable to begin command buffer");
         }
+        if let Some(frame_timer) = &mut render_target.frame_timer {
+            frame_timer.begin(&self.logical_device, command_buffer, current_frame);
+        }
+
+        // Demos render the scene to the render area of the input images, which the post-processing chain upscales if necessary.
+        let extent = render_target.post_process.render_extent();
+        let framebuffer = render_target.demo_framebuffers[image_index as usize];
+
+        // Get the sub-pixel offset requested by temporal post-processing passes, if any.
+        let jitter = render_target.post_process.jitter();
 
-        let extent = self.swapchain.extent();
+        // Only the primary target steps the fluid simulation, and waits on the compute semaphore it signals.
+        let step_simulation = target == TargetId::PRIMARY;
 
         // Draw the active demo pipeline.
         match &mut self.active_demo {
@@ -703,43 +1180,61 @@ impl Renderer {
                 let DemoPushConstants::Triangle(push_constants) = push_constants else {
                     panic!("Push constants do not match the active demo");
                 };
+                let push_constants = example_triangle::PushConstants {
+                    jitter: [
+                        2. * jitter.offset[0] / extent.width as f32,
+                        2. * jitter.offset[1] / extent.height as f32,
+                    ],
+                    ..*push_constants
+                };
                 pipeline.render_frame(
                     &self.logical_device,
                     command_buffer,
                     extent,
-                    image_index as usize,
-                    push_constants,
+                    framebuffer,
+                    &push_constants,
+                    (jitter.split_position * extent.width as f32) as u32,
                 );
-
-                // Add the optional FXAA render pass to the command buffer, if enabled.
-                if let Some(fxaa_pass) = &self.fxaa_pass {
-                    fxaa_pass.render_frame(
+            }
+            DemoPipeline::Fluid(simulation) => {
+                if step_simulation {
+                    let DemoPushConstants::Fluid(push_constants) = push_constants else {
+                        panic!("Push constants do not match the active demo");
+                    };
+                    simulation.step(
                         &self.logical_device,
-                        command_buffer,
-                        extent,
-                        image_index as usize,
+                        self.compute_command_pool
+                            .map(|(_, compute_semaphore)| compute_semaphore),
+                        self.compute_queue.queue,
+                        push_constants,
                     );
+                } else if self.compute_command_pool.is_some() {
+                    // Without the compute semaphore, wait on the CPU for the steps on the separate compute queue.
+                    simulation.wait_for_step(&self.logical_device);
                 }
-            }
-            DemoPipeline::Fluid(simulation) => {
-                let DemoPushConstants::Fluid(push_constants) = push_constants else {
-                    panic!("Push constants do not match the active demo");
-                };
-                simulation.render_frame(
+                simulation.set_jitter(jitter);
+                simulation.draw(
                     &self.logical_device,
-                    self.compute_command_pool
-                        .map(|(_, compute_semaphore)| compute_semaphore),
-                    self.compute_queue.queue,
                     command_buffer,
+                    framebuffer,
                     extent,
-                    image_index as usize,
-                    push_constants,
+                    render_target.fluid_display_texture,
                     frame_graphics_fence,
                 );
             }
         }
 
+        // Apply the post-processing passes to the rendered image, if any.
+        render_target.post_process.render_frame(
+            &self.logical_device,
+            command_buffer,
+            image_index as usize,
+        );
+
         // Complete the graphics command buffer.
+        if let Some(frame_timer) = &render_target.frame_timer {
+            frame_timer.end(&self.logical_device, command_buffer, current_frame);
+        }
         unsafe {
             self.logical_device
                 .end_command_buffer(command_buffer)
@@ -747,10 +1242,11 @@ impl Renderer {
         }
 
         // Submit the draw command buffer to the GPU.
-        let mut semaphores: SmallVec<[_; 2]> = smallvec![self.swapchain.image_available()];
+        let mut semaphores: SmallVec<[_; 2]> =
+            smallvec![render_target.swapchain().image_available()];
         let mut semaphore_access: SmallVec<[_; 2]> =
             smallvec![ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-        if matches!(self.active_demo, DemoPipeline::Fluid(_)) {
+        if step_simulation && matches!(self.active_demo, DemoPipeline::Fluid(_)) {
             if let Some((_, compute_semaphore)) = &self.compute_command_pool {
                 semaphores.push(*compute_semaphore);
                 semaphore_access.push(ash::vk::PipelineStageFlags::FRAGMENT_SHADER);
@@ -763,7 +1259,7 @@ impl Renderer {
             command_buffer_count: 1,
             p_command_buffers: &command_buffer,
             signal_semaphore_count: 1,
-            p_signal_semaphores: &self.swapchain.image_rendered(),
+            p_signal_semaphores: &render_target.swapchain().image_rendered(),
             ..Default::default()
         };
         unsafe {
@@ -787,13 +1283,19 @@ impl Renderer {
                 )
                 .expect("Unable to submit command buffer");
         }
+        render_target.submitted_frame_numbers[current_frame] =
+            self.deletion_queue.frame_submitted();
 
         // Queue the presentation of the swapchain image.
-        match self.swapchain.present(
+        let present_id = render_target.frame_pacer.next_present_id();
+        let present_result = render_target.swapchain_mut().present(
             self.presentation_queue.queue,
             self.device_extensions
                 .contains(ash::ext::swapchain_maintenance1::NAME),
-        ) {
+            present_id,
+        );
+        render_target.frame_pacer.presented(present_id);
+        match present_result {
             Ok(_) => (),
             Err(ash::vk::Result::ERROR_OUT_OF_DATE_KHR) => {
                 let surface_capabilities = unsafe {
@@ -803,7 +1305,7 @@ impl Renderer {
                         .unwrap()
                         .get_physical_device_surface_capabilities(
                             self.physical_device,
-                            self.surface,
+                            self.targets[index].surface,
                         )
                         .expect("Unable to get surface capabilities")
                 };
@@ -822,6 +1324,7 @@ impl Renderer {
                     "WARN: Swapchain is out of date at image presentation, needs to be recreated."
                 );
                 self.swapchain_resize_required(
+                    target,
                     if surface_capabilities.current_extent == utils::SPECIAL_SURFACE_EXTENT {
                         None
                     } else {
@@ -829,39 +1332,171 @@ impl Renderer {
                     },
                 );
             }
+            Err(ash::vk::Result::ERROR_SURFACE_LOST_KHR) => {
+                println!("WARN: Surface was lost at image presentation, needs to be recreated.");
+                self.targets[index].resize_swapchain = ResizeSwapchainState::SurfaceLost;
+            }
             Err(e) => panic!("Unable to present swapchain image: {e:?}"),
         }
     }
 
+    /// Block until the next frame of the target may begin: the resources of its frame in flight are free, few enough frames are queued for presentation, and the frame rate limit allows it.
+    /// Input sampled after this returns reaches the screen sooner. Has no effect if the frame has already begun or the target is suspended.
+    pub fn wait_for_next_frame(&mut self, target: TargetId) {
+        let index = self.target_index(target);
+        let render_target = &mut self.targets[index];
+        let Some(swapchain) = &render_target.swapchain else {
+            return;
+        };
+        if render_target.frame_pacer.frame_began() {
+            return;
+        }
+
+        // Synchronize the CPU with the GPU for the resources previously used for this frame in flight.
+        // Specifically, the command buffer cannot be reused until the fence is signaled.
+        let current_frame = swapchain.current_frame();
+        unsafe {
+            self.logical_device
+                .wait_for_fences(
+                    &[render_target.frame_fences[current_frame]],
+                    true,
+                    FIVE_SECONDS_IN_NANOSECONDS,
+                )
+                .expect("Unable to wait for fence to begin frame");
+        }
+
+        // Destroy the resources which were replaced before this frame in flight was last submitted, including retired swapchains.
+        // Frames of every target are submitted to the same graphics queue, so resources used by the other targets are also released.
+        self.deletion_queue.frame_completed(
+            &self.logical_device,
+            &mut self.memory_allocator,
+            render_target.submitted_frame_numbers[current_frame],
+        );
+        render_target
+            .frame_pacer
+            .wait_for_next_frame(swapchain.handle(), swapchain.frames_in_flight() as u64);
+    }
+
+    /// Whether `wait_for_next_frame` has completed for a frame of the target which has not yet been rendered.
+    pub fn frame_began(&self, target: TargetId) -> bool {
+        self.targets[self.target_index(target)]
+            .frame_pacer
+            .frame_began()
+    }
+
+    /// Record that input was sampled for the next frame of the target, to measure the latency until it is presented.
+    pub fn input_sampled(&mut self, target: TargetId) {
+        let index = self.target_index(target);
+        self.targets[index].frame_pacer.input_sampled();
+    }
+
+    /// Limit the number of frames rendered per second to each target. `None` removes the limit.
+    pub fn set_fps_cap(&mut self, fps_cap: Option<f32>) {
+        self.fps_cap = fps_cap;
+        for target in &mut self.targets {
+            target.frame_pacer.set_fps_cap(fps_cap);
+        }
+    }
+
+    /// Queue at most one frame for presentation and report the input-to-present latency, trading throughput for responsiveness.
+    pub fn set_low_latency(&mut self, low_latency: bool) {
+        self.low_latency = low_latency;
+        for target in &mut self.targets {
+            target.frame_pacer.set_low_latency(low_latency);
+        }
+    }
+
     /// Recreate the graphics pipeline with the new specialization constants.
+    /// The old pipelines are destroyed once the frames in flight have completed.
     /// # Safety
     /// The specialization constants enum type must match the active demo.
     pub fn update_specialization_constants(
         &mut self,
         specialization_constants: DemoSpecializationConstants,
     ) {
-        // Wait for the GPU to finish processing all tasks submitted by this renderer.
-        self.wait_for_tasks();
-
         match specialization_constants {
             DemoSpecializationConstants::Triangle(specialization_constants) => {
                 let DemoPipeline::Triangle(triangle_pipeline) = &mut self.active_demo else {
                     panic!("Specialization constants do not match the active demo");
                 };
 
+                // The render pass is reused, so the framebuffers of each target remain valid.
                 triangle_pipeline.recreate(
                     &self.logical_device,
                     example_triangle::CreateReuseRenderPass::Reuse(triangle_pipeline.render_pass()),
-                    &self.swapchain,
                     specialization_constants,
-                    self.fxaa_pass.as_ref(),
+                    &mut self.deletion_queue,
+                );
+            }
+            DemoSpecializationConstants::Fluid(specialization_constants) => {
+                let DemoPipeline::Fluid(simulation) = &mut self.active_demo else {
+                    panic!("Specialization constants do not match the active demo");
+                };
+
+                simulation.update_specialization_constants(
+                    &self.logical_device,
+                    specialization_constants,
+                    &mut self.deletion_queue,
                 );
             }
-            DemoSpecializationConstants::Fluid => {}
         }
     }
 
-    /// Wait for the GPU to finish processing all tasks submitted by this renderer.
+    /// Display the next texture of the fluid simulation in the target. Each target may display a different texture of the same simulation.
+    pub fn next_fluid_display_texture(&mut self, target: TargetId) {
+        let index = self.target_index(target);
+        let render_target = &mut self.targets[index];
+        render_target.fluid_display_texture = render_target.fluid_display_texture.next();
+
+        println!(
+            "Switched to the next display texture: {:?}",
+            render_target.fluid_display_texture
+        );
+    }
+
+    /// Save the state of the active fluid simulation to a snapshot file.
+    pub fn save_fluid_snapshot(&mut self, path: &std::path::Path) -> std::io::Result<()> {
+        // Ensure that no submitted work is still using the simulation buffers.
+        self.wait_for_tasks();
+
+        let DemoPipeline::Fluid(simulation) = &mut self.active_demo else {
+            return Err(std::io::Error::other(
+                "Snapshots can only be saved from the fluid demo",
+            ));
+        };
+        simulation.save_snapshot(
+            &self.logical_device,
+            &mut self.memory_allocator,
+            &self.compute_queue,
+            self.compute_command_pool
+                .map_or(self.command_pool, |(pool, _)| pool),
+            &self.transfer_queue,
+            path,
+        )
+    }
+
+    /// Replace the state of the active fluid simulation with the contents of a snapshot file.
+    pub fn load_fluid_snapshot(&mut self, path: &std::path::Path) -> std::io::Result<()> {
+        // Ensure that no submitted work is still using the simulation buffers.
+        self.wait_for_tasks();
+
+        let DemoPipeline::Fluid(simulation) = &mut self.active_demo else {
+            return Err(std::io::Error::other(
+                "Snapshots can only be loaded into the fluid demo",
+            ));
+        };
+        simulation.load_snapshot(
+            &self.logical_device,
+            &mut self.memory_allocator,
+            &self.compute_queue,
+            self.compute_command_pool
+                .map_or(self.command_pool, |(pool, _)| pool),
+            &self.transfer_queue,
+            path,
+        )
+    }
+
+    /// Wait for the GPU to finish processing all tasks submitted by this renderer, to every render target.
     fn wait_for_tasks(&self) {
         unsafe {
             if self
@@ -869,15 +1504,20 @@ impl Renderer {
                 .contains(ash::ext::swapchain_maintenance1::NAME)
             {
                 let present_fences: SmallVec<[_; EXPECTED_MAX_FRAMES_IN_FLIGHT]> = self
-                    .swapchain
-                    .frame_syncs()
+                    .targets
                     .iter()
-                    .map(|f| f.present_complete)
+                    .filter_map(|target| target.swapchain.as_ref())
+                    .flat_map(|swapchain| {
+                        swapchain.frame_syncs().iter().map(|f| f.present_complete)
+                    })
                     .collect();
 
-                self.logical_device
-                    .wait_for_fences(&present_fences, true, FIVE_SECONDS_IN_NANOSECONDS)
-                    .expect("Unable to wait for present fences to become signaled");
+                // Suspended targets have no presentations to wait for.
+                if !present_fences.is_empty() {
+                    self.logical_device
+                        .wait_for_fences(&present_fences, true, FIVE_SECONDS_IN_NANOSECONDS)
+                        .expect("Unable to wait for present fences to become signaled");
+                }
             } else {
                 self.logical_device
                     .device_wait_idle()
@@ -886,8 +1526,142 @@ impl Renderer {
         }
     }
 
-    /// Toggle which demo is currently active.
+    /// Describe the content presented to an HDR swapchain. SDR swapchains do not use HDR metadata.
+    fn update_hdr_metadata(
+        hdr_metadata: &ash::ext::hdr_metadata::Device,
+        swapchain: &utils::Swapchain,
+    ) {
+        let encoding = OutputEncoding::from_surface_format(swapchain.surface_format());
+        if encoding.is_hdr() {
+            swapchain.set_hdr_metadata(hdr_metadata, &encoding.hdr_metadata());
+        }
+    }
+
+    /// Enable or disable FXAA, keeping the order of the other post-processing effects.
+    pub fn toggle_fxaa(&mut self) {
+        let mut effects = self.targets[0].post_process_settings.effects.clone();
+        if let Some(index) = effects
+            .iter()
+            .position(|effect| *effect == PostProcessEffect::Fxaa)
+        {
+            effects.remove(index);
+        } else {
+            effects.push(PostProcessEffect::Fxaa);
+        }
+        self.set_post_process_effects(&effects);
+    }
+
+    /// Enable or disable bloom, keeping the order of the other post-processing effects.
+    /// Bloom is added at the start of the chain, so that anti-aliasing also smooths the edges of bright regions.
+    pub fn toggle_bloom(&mut self) {
+        let mut effects = self.targets[0].post_process_settings.effects.clone();
+        if let Some(index) = effects
+            .iter()
+            .position(|effect| *effect == PostProcessEffect::Bloom)
+        {
+            effects.remove(index);
+        } else {
+            effects.insert(0, PostProcessEffect::Bloom);
+        }
+        self.set_post_process_effects(&effects);
+    }
+
+    /// The intensity and threshold of bloom.
+    pub fn bloom_settings(&self) -> utils::bloom_pass::BloomSettings {
+        self.targets[0].post_process_settings.bloom_settings
+    }
+
+    /// Change the intensity and threshold of bloom for every target, taking effect on the next frame.
+    pub fn set_bloom_settings(&mut self, settings: utils::bloom_pass::BloomSettings) {
+        for target in &mut self.targets {
+            target.post_process_settings.bloom_settings = settings;
+            target.post_process.set_bloom_settings(settings);
+        }
+    }
+
+    /// Toggle a split-screen comparison, where post-processing is only applied to the left half of the screen.
+    pub fn toggle_post_process_split_screen(&mut self) {
+        let split_screen = !self.targets[0].post_process_settings.split_screen;
+        for target in &mut self.targets {
+            target.post_process_settings.split_screen = split_screen;
+            target.post_process.set_split_screen(split_screen);
+        }
+    }
+
+    /// Change the resolution the scene is rendered at, or the filter used to upscale it, for every target.
+    /// The post-processing chains allocate their images at the swapchain resolution, so a new render scale only changes the area of them which is rendered to,
+    /// and frequent changes by dynamic resolution keep the history of temporal passes without allocating anything.
+    /// A chain is only rebuilt when it needs different passes, such as for a new filter, and the old one is destroyed once the frames in flight have completed.
+    pub fn set_upscale_settings(&mut self, settings: UpscaleSettings) {
+        for index in 0..self.targets.len() {
+            let render_target = &mut self.targets[index];
+            if settings == render_target.post_process_settings.upscale_settings {
+                continue;
+            }
+            render_target.post_process_settings.upscale_settings = settings;
+            if !render_target.post_process.set_upscale_settings(settings) {
+                self.rebuild_post_process(index);
+            }
+        }
+    }
+
+    /// Automatically adjust the render scale to keep the GPU time of each frame of the primary target near the target, in milliseconds.
+    /// `None` keeps the current render scale fixed.
+    /// The post-processing chains keep their upscale pass while the scale is adjusted, even at full scale, so that adjusting it never rebuilds them.
+    pub fn set_dynamic_resolution(&mut self, target_frame_time: Option<f32>) {
+        if target_frame_time.is_some() && self.targets[0].frame_timer.is_none() {
+            eprintln!("WARN: The graphics queue does not support timestamps, so the render scale cannot be adjusted automatically");
+        }
+        self.dynamic_resolution = target_frame_time.map(DynamicResolution::new);
+        self.set_upscale_settings(UpscaleSettings {
+            dynamic: self.dynamic_resolution.is_some(),
+            ..self.targets[0].post_process_settings.upscale_settings
+        });
+    }
+
+    /// Replace the post-processing chain of every target with one applying the given effects, in order.
+    /// Only the render pass of the active demo is rebuilt to target the new chains, so the state of the demo is preserved.
+    /// The old chains and render pass are destroyed once the frames in flight have completed.
+    pub fn set_post_process_effects(&mut self, effects: &[PostProcessEffect]) {
+        for render_target in &mut self.targets {
+            render_target.post_process_settings.effects = effects.to_vec();
+        }
+
+        // Every chain applies the same effects, so they share the layout of their input images.
+        let destination_layout = self.targets[0].post_process.input_layout();
+        match &mut self.active_demo {
+            DemoPipeline::Triangle(triangle_pipeline) => {
+                triangle_pipeline.recreate(
+                    &self.logical_device,
+                    example_triangle::CreateReuseRenderPass::Create {
+                        image_format: SCENE_FORMAT,
+                        destination_layout,
+                    },
+                    triangle_pipeline.specialization_constants(),
+                    &mut self.deletion_queue,
+                );
+            }
+            DemoPipeline::Fluid(simulation) => {
+                simulation.recreate_render_pass(
+                    &self.logical_device,
+                    SCENE_FORMAT,
+                    destination_layout,
+                    &mut self.deletion_queue,
+                );
+            }
+        }
+
+        // Build the new chains, which also recreates the framebuffers of the active demo for its new render pass.
+        for index in 0..self.targets.len() {
+            self.rebuild_post_process(index);
+        }
+    }
+
+    /// Toggle which demo is currently active, for every target.
+    /// The old demo is destroyed once the frames in flight have completed.
     pub fn switch_demo(&mut self, new_demo: NewDemo) {
+        // Demos are shared by every target, so they are created for the resolution and input layout of the primary target.
+        let primary_post_process = &self.targets[0].post_process;
         match new_demo {
             NewDemo::Triangle(constants) => {
                 if let DemoPipeline::Triangle(_) = &self.active_demo {
@@ -899,55 +1673,57 @@ impl Renderer {
                     None,
                     None,
                     example_triangle::CreateReuseRenderPass::Create {
-                        image_format: self.swapchain.image_format(),
-                        destination_layout: if self.fxaa_pass.is_some() {
-                            ash::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
-                        } else {
-                            ash::vk::ImageLayout::PRESENT_SRC_KHR
-                        },
+                        image_format: SCENE_FORMAT,
+                        destination_layout: primary_post_process.input_layout(),
                     },
-                    &self.swapchain,
+                    self.multisample_count,
                     constants,
-                    self.fxaa_pass.as_ref(),
                 ));
 
-                self.wait_for_tasks();
                 std::mem::swap(&mut self.active_demo, &mut new_triangle);
                 let old_demo = new_triangle; // Rename for clarity.
 
                 match old_demo {
                     DemoPipeline::Fluid(mut simulation) => {
-                        simulation.destroy(&self.logical_device, &mut self.memory_allocator);
+                        self.deletion_queue.push(DeferredDestroy::Other(Box::new(
+                            move |device, memory_allocator| {
+                                simulation.destroy(device, memory_allocator);
+                            },
+                        )));
                     }
                     DemoPipeline::Triangle(_) => {
                         panic!("switch_demo: Wait, I thought we were not using the `Triangle` pipeline at the top of this function...");
                     }
                 }
             }
-            NewDemo::Fluid => {
+            NewDemo::Fluid(constants) => {
                 if let DemoPipeline::Fluid(_) = &self.active_demo {
                     return;
                 }
 
-                let mut new_fluid = DemoPipeline::Fluid(example_fluid::FluidSimulation::new(
-                    &self.logical_device,
-                    &mut self.memory_allocator,
-                    self.swapchain.extent(),
-                    self.swapchain.image_format(),
-                    ash::vk::ImageLayout::PRESENT_SRC_KHR,
-                    self.swapchain.image_views(),
-                    self.compute_command_pool
-                        .map_or(self.command_pool, |(pool, _)| pool),
-                    self.pageable_device_local_memory.as_ref(),
-                ));
+                let mut new_fluid =
+                    DemoPipeline::Fluid(Box::new(example_fluid::FluidSimulation::new(
+                        &self.logical_device,
+                        &mut self.memory_allocator,
+                        primary_post_process.extent(),
+                        SCENE_FORMAT,
+                        primary_post_process.input_layout(),
+                        self.compute_command_pool
+                            .map_or(self.command_pool, |(pool, _)| pool),
+                        self.pageable_device_local_memory.as_ref(),
+                        constants,
+                    )));
 
-                self.wait_for_tasks();
                 std::mem::swap(&mut self.active_demo, &mut new_fluid);
                 let old_demo = new_fluid; // Rename for clarity.
 
                 match old_demo {
                     DemoPipeline::Triangle(triangle) => {
-                        triangle.destroy(&self.logical_device, true, true);
+                        self.deletion_queue.push(DeferredDestroy::Other(Box::new(
+                            move |device, _| {
+                                triangle.destroy(device, true, true);
+                            },
+                        )));
                     }
                     DemoPipeline::Fluid(_) => {
                         panic!("switch_demo: Wait, I thought we were not using the `Fluid` pipeline at the top of this function...");
@@ -955,5 +1731,11 @@ impl Renderer {
                 }
             }
         }
+
+        // The history of each target belongs to the previous demo, and so do the framebuffers.
+        for index in 0..self.targets.len() {
+            self.targets[index].post_process.invalidate_history();
+            self.recreate_demo_framebuffers(index);
+        }
     }
 }
@@ -0,0 +1,926 @@
+use super::{
+    post_process::{FrameExtent, PostProcessPass},
+    shaders::ENTRY_POINT_MAIN,
+};
+
+/// Shader for downsampling the scene into the bloom mip chain, keeping only the bright light.
+const DOWNSAMPLE_COMPUTE: &[u32] =
+    inline_spirv::include_spirv!("src/shaders/bloom_downsample_comp.glsl", comp, glsl);
+
+/// Shader for upsampling and accumulating the bloom mip chain.
+const UPSAMPLE_COMPUTE: &[u32] =
+    inline_spirv::include_spirv!("src/shaders/bloom_upsample_comp.glsl", comp, glsl);
+
+/// Shader for adding the accumulated bloom back to the scene.
+const COMPOSITE_FRAGMENT: &[u32] =
+    inline_spirv::include_spirv!("src/shaders/bloom_composite_frag.glsl", frag, glsl);
+
+/// The format of the bloom mip chain. Bloom is accumulated in linear values which may exceed one.
+const BLOOM_FORMAT: ash::vk::Format = ash::vk::Format::R16G16B16A16_SFLOAT;
+
+/// The maximum number of levels in the bloom mip chain. The first level is half the size of the screen.
+const MAX_BLOOM_LEVELS: u32 = 6;
+
+/// The fraction of the threshold over which the bright-pass fades in, rather than cutting off sharply.
+const SOFT_KNEE: f32 = 0.5;
+
+/// The spacing of the upsample filter taps, in texels of the smaller level.
+const FILTER_RADIUS: f32 = 1.;
+
+/// The width and height of the workgroups in the bloom compute shaders.
+const WORKGROUP_SIZE: u32 = 8;
+
+/// The settings of the bloom effect which can be changed while it is running.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BloomSettings {
+    /// The strength of the bloom added to the scene.
+    pub intensity: f32,
+
+    /// The brightness above which light blooms, where one is SDR white.
+    pub threshold: f32,
+}
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 0.5,
+            threshold: 1.,
+        }
+    }
+}
+
+/// Define the push constants that are used in both compute shaders of the bloom effect.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct BlurPushConstants {
+    /// The brightness above which light contributes to bloom.
+    threshold: f32,
+
+    /// The fraction of the threshold over which the bright-pass smoothly fades in.
+    soft_knee: f32,
+
+    /// Non-zero when downsampling the scene itself, applying the bright-pass filter.
+    prefilter: u32,
+
+    /// The spacing of the upsample filter taps, in texels of the smaller level.
+    filter_radius: f32,
+
+    /// The largest texture coordinates the downsample samples, which keep the bright-pass inside the render area of the scene.
+    source_max_coord: [f32; 2],
+}
+
+/// Define the push constants that are used in the fragment shader which composites the bloom.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CompositePushConstants {
+    /// The width and height of the render surface, as `f32`s.
+    pub inverse_screen_size: [f32; 2],
+
+    /// The fraction of the screen width, from the left, where bloom is applied. The rest shows the unprocessed input.
+    pub split_position: f32,
+
+    /// The strength of the bloom, already divided by the number of accumulated levels.
+    pub intensity: f32,
+}
+
+/// Define the compute pipelines used to blur the bright light of the scene.
+struct BlurPipelines {
+    layout: ash::vk::PipelineLayout,
+    downsample: ash::vk::Pipeline,
+    upsample: ash::vk::Pipeline,
+    descriptor_set_layout: ash::vk::DescriptorSetLayout,
+    downsample_shader: ash::vk::ShaderModule,
+    upsample_shader: ash::vk::ShaderModule,
+}
+
+impl BlurPipelines {
+    /// Create the compute pipelines for downsampling and upsampling the bloom mip chain.
+    /// Both read one level through a sampler and write another as a storage image.
+    fn new(device: &ash::Device, sampler: ash::vk::Sampler) -> Self {
+        let downsample_shader = super::create_shader_module(device, DOWNSAMPLE_COMPUTE);
+        let upsample_shader = super::create_shader_module(device, UPSAMPLE_COMPUTE);
+
+        // Create the descriptor set layout.
+        let descriptor_set_layout = {
+            let sampler = [sampler];
+            let bindings = [
+                ash::vk::DescriptorSetLayoutBinding::default()
+                    .binding(0)
+                    .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .stage_flags(ash::vk::ShaderStageFlags::COMPUTE)
+                    .immutable_samplers(&sampler),
+                ash::vk::DescriptorSetLayoutBinding::default()
+                    .binding(1)
+                    .descriptor_type(ash::vk::DescriptorType::STORAGE_IMAGE)
+                    .descriptor_count(1)
+                    .stage_flags(ash::vk::ShaderStageFlags::COMPUTE),
+            ];
+            let descriptor_set_info =
+                ash::vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+            unsafe { device.create_descriptor_set_layout(&descriptor_set_info, None) }
+                .expect("Failed to create descriptor set layout for the bloom blur")
+        };
+
+        // Create the pipeline layout.
+        let layout = {
+            let descriptor_set_layout = [descriptor_set_layout];
+            let push_constants_range = [ash::vk::PushConstantRange {
+                stage_flags: ash::vk::ShaderStageFlags::COMPUTE,
+                offset: 0,
+                size: std::mem::size_of::<BlurPushConstants>() as u32,
+            }];
+            let layout_info = ash::vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&descriptor_set_layout)
+                .push_constant_ranges(&push_constants_range);
+
+            unsafe { device.create_pipeline_layout(&layout_info, None) }
+                .expect("Failed to create pipeline layout for the bloom blur")
+        };
+
+        // Create both compute pipelines at once.
+        let [downsample, upsample] = {
+            let pipeline_infos = [downsample_shader, upsample_shader].map(|module| {
+                ash::vk::ComputePipelineCreateInfo::default()
+                    .stage(
+                        ash::vk::PipelineShaderStageCreateInfo::default()
+                            .stage(ash::vk::ShaderStageFlags::COMPUTE)
+                            .module(module)
+                            .name(ENTRY_POINT_MAIN),
+                    )
+                    .layout(layout)
+            });
+            unsafe {
+                device.create_compute_pipelines(
+                    ash::vk::PipelineCache::null(),
+                    &pipeline_infos,
+                    None,
+                )
+            }
+            .expect("Failed to create compute pipelines for the bloom blur")
+            .try_into()
+            .expect("Bloom compute pipeline creation returned the wrong number of pipelines")
+        };
+
+        Self {
+            layout,
+            downsample,
+            upsample,
+            descriptor_set_layout,
+            downsample_shader,
+            upsample_shader,
+        }
+    }
+
+    /// Destroy the pipelines and their resources.
+    fn destroy(&self, device: &ash::Device) {
+        unsafe { device.destroy_pipeline(self.downsample, None) };
+        unsafe { device.destroy_pipeline(self.upsample, None) };
+        unsafe { device.destroy_pipeline_layout(self.layout, None) };
+        unsafe { device.destroy_descriptor_set_layout(self.descriptor_set_layout, None) };
+        unsafe { device.destroy_shader_module(self.downsample_shader, None) };
+        unsafe { device.destroy_shader_module(self.upsample_shader, None) };
+    }
+}
+
+/// Define the graphics pipeline used to add the bloom to the scene.
+struct CompositePipeline {
+    render_pass: ash::vk::RenderPass,
+    layout: ash::vk::PipelineLayout,
+    pipeline: ash::vk::Pipeline,
+    descriptor_set_layout: ash::vk::DescriptorSetLayout,
+    fullscreen_vert_shader: ash::vk::ShaderModule,
+    composite_frag_shader: ash::vk::ShaderModule,
+}
+
+impl CompositePipeline {
+    /// Create a new graphics pipeline for compositing the bloom onto the scene.
+    fn new(
+        device: &ash::Device,
+        render_pass: ash::vk::RenderPass,
+        sampler: ash::vk::Sampler,
+    ) -> Self {
+        // Create the shader modules for the vertex and fragment shaders.
+        let fullscreen_vert_shader =
+            super::create_shader_module(device, super::shaders::FULLSCREEN_VERTEX);
+        let composite_frag_shader = super::create_shader_module(device, COMPOSITE_FRAGMENT);
+
+        let shader_stages = [
+            ash::vk::PipelineShaderStageCreateInfo::default()
+                .stage(ash::vk::ShaderStageFlags::VERTEX)
+                .module(fullscreen_vert_shader)
+                .name(ENTRY_POINT_MAIN),
+            ash::vk::PipelineShaderStageCreateInfo::default()
+                .stage(ash::vk::ShaderStageFlags::FRAGMENT)
+                .module(composite_frag_shader)
+                .name(ENTRY_POINT_MAIN),
+        ];
+
+        let viewport_state = ash::vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        let input_state = ash::vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(ash::vk::PrimitiveTopology::TRIANGLE_STRIP);
+        let rasterizer = ash::vk::PipelineRasterizationStateCreateInfo::default().line_width(1.);
+        let sampling = ash::vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(ash::vk::SampleCountFlags::TYPE_1);
+
+        // Overwrite the destination without blending.
+        let color_blend_attachment = [ash::vk::PipelineColorBlendAttachmentState {
+            color_write_mask: ash::vk::ColorComponentFlags::RGBA,
+            ..Default::default()
+        }];
+        let color_blend_state = ash::vk::PipelineColorBlendStateCreateInfo::default()
+            .attachments(&color_blend_attachment);
+
+        // Create the descriptor set layout for the scene and the first level of the bloom mip chain.
+        let descriptor_set_layout = {
+            let sampler = [sampler];
+            let bindings = [0, 1].map(|binding| {
+                ash::vk::DescriptorSetLayoutBinding::default()
+                    .binding(binding)
+                    .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .stage_flags(ash::vk::ShaderStageFlags::FRAGMENT)
+                    .immutable_samplers(&sampler)
+            });
+            let descriptor_set_info =
+                ash::vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+            unsafe { device.create_descriptor_set_layout(&descriptor_set_info, None) }
+                .expect("Failed to create descriptor set layout for the bloom composite")
+        };
+
+        // Create the pipeline layout.
+        let pipeline_layout = {
+            let descriptor_set_layout = [descriptor_set_layout];
+            let push_constants_range = [ash::vk::PushConstantRange {
+                stage_flags: ash::vk::ShaderStageFlags::FRAGMENT,
+                offset: 0,
+                size: std::mem::size_of::<CompositePushConstants>() as u32,
+            }];
+            let layout_info = ash::vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&descriptor_set_layout)
+                .push_constant_ranges(&push_constants_range);
+
+            unsafe { device.create_pipeline_layout(&layout_info, None) }
+                .expect("Failed to create pipeline layout for the bloom composite")
+        };
+
+        // Use dynamic states for the viewport and scissor rectangles.
+        let dynamic_states = ash::vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&[
+            ash::vk::DynamicState::VIEWPORT,
+            ash::vk::DynamicState::SCISSOR,
+        ]);
+
+        // Create the composite pipeline.
+        let pipeline = {
+            let pipeline_info = ash::vk::GraphicsPipelineCreateInfo {
+                stage_count: shader_stages.len() as u32,
+                p_stages: shader_stages.as_ptr(),
+                p_vertex_input_state: &ash::vk::PipelineVertexInputStateCreateInfo::default(),
+                p_input_assembly_state: &input_state,
+                p_viewport_state: &viewport_state,
+                p_rasterization_state: &rasterizer,
+                p_multisample_state: &sampling,
+                p_color_blend_state: &color_blend_state,
+                p_dynamic_state: &dynamic_states,
+                layout: pipeline_layout,
+                render_pass,
+                subpass: 0,
+                base_pipeline_index: -1,
+                ..Default::default()
+            };
+
+            unsafe {
+                device.create_graphics_pipelines(
+                    ash::vk::PipelineCache::null(),
+                    &[pipeline_info],
+                    None,
+                )
+            }
+            .expect("Failed to create graphics pipeline for the bloom composite")[0]
+        };
+
+        Self {
+            render_pass,
+            layout: pipeline_layout,
+            pipeline,
+            descriptor_set_layout,
+            fullscreen_vert_shader,
+            composite_frag_shader,
+        }
+    }
+
+    /// Destroy the pipeline and its resources.
+    fn destroy(&self, device: &ash::Device) {
+        unsafe { device.destroy_pipeline(self.pipeline, None) };
+        unsafe { device.destroy_pipeline_layout(self.layout, None) };
+        unsafe { device.destroy_descriptor_set_layout(self.descriptor_set_layout, None) };
+        unsafe { device.destroy_render_pass(self.render_pass, None) };
+        unsafe { device.destroy_shader_module(self.fullscreen_vert_shader, None) };
+        unsafe { device.destroy_shader_module(self.composite_frag_shader, None) };
+    }
+}
+
+/// A mip chain the bright light of one frame is blurred in, with a view of each level.
+struct BloomImage {
+    image: ash::vk::Image,
+    level_views: Vec<ash::vk::ImageView>,
+    allocation: gpu_allocator::vulkan::Allocation,
+}
+
+/// The descriptor sets used to render one frame.
+struct FrameDescriptorSets {
+    /// Each set samples a level (or the scene, for the first) and writes the next, smaller level.
+    downsample: Vec<ash::vk::DescriptorSet>,
+
+    /// Each set samples the level after its index and adds to the level at its index.
+    upsample: Vec<ash::vk::DescriptorSet>,
+
+    /// Samples the scene and the first level of the bloom mip chain.
+    composite: ash::vk::DescriptorSet,
+}
+
+/// A physically-based bloom effect, spreading the light brighter than a threshold into its surroundings.
+/// The bright light is downsampled through a mip chain and upsampled back in compute, which blurs it with a wide and
+/// smooth falloff, and then added to the scene before it is tonemapped.
+pub struct BloomPass {
+    blur: BlurPipelines,
+    composite: CompositePipeline,
+    sampler: ash::vk::Sampler,
+    bloom_images: Vec<BloomImage>,
+    bloom_levels: u32,
+    bloom_extent: ash::vk::Extent2D,
+    framebuffers: Vec<ash::vk::Framebuffer>,
+    descriptor_pool: ash::vk::DescriptorPool,
+    descriptor_sets: Vec<FrameDescriptorSets>,
+    settings: BloomSettings,
+    split_screen: bool,
+}
+
+impl BloomPass {
+    /// The layout of the bloom mip chain, which is both sampled and written as a storage image.
+    const BLOOM_IMAGE_LAYOUT: ash::vk::ImageLayout = ash::vk::ImageLayout::GENERAL;
+
+    /// Create a new bloom pass and associated resources.
+    /// The input images are sampled and the result is written to the destination images with the same index.
+    pub fn new(
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        extent: ash::vk::Extent2D,
+        swapchain_format: ash::vk::Format,
+        input_views: &[ash::vk::ImageView],
+        destination_views: &[ash::vk::ImageView],
+        destination_layout: ash::vk::ImageLayout,
+    ) -> Self {
+        // Create the sampler. Linear filtering lets each tap of the blur average several texels at once.
+        let sampler = {
+            let sampler_info = ash::vk::SamplerCreateInfo {
+                mag_filter: ash::vk::Filter::LINEAR,
+                min_filter: ash::vk::Filter::LINEAR,
+                mipmap_mode: ash::vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: ash::vk::LOD_CLAMP_NONE,
+                ..Default::default()
+            };
+            unsafe { device.create_sampler(&sampler_info, None) }
+                .expect("Failed to create sampler for bloom post-processing")
+        };
+
+        // Create the compute and graphics pipelines.
+        let blur = BlurPipelines::new(device, sampler);
+        let render_pass = Self::create_render_pass(device, swapchain_format, destination_layout);
+        let composite = CompositePipeline::new(device, render_pass, sampler);
+
+        // Create a descriptor pool with enough sets for a full mip chain for each input image.
+        let descriptor_pool = {
+            let image_count = input_views.len() as u32;
+            let pool_sizes = [
+                ash::vk::DescriptorPoolSize {
+                    ty: ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: image_count * (2 * MAX_BLOOM_LEVELS + 1),
+                },
+                ash::vk::DescriptorPoolSize {
+                    ty: ash::vk::DescriptorType::STORAGE_IMAGE,
+                    descriptor_count: image_count * 2 * MAX_BLOOM_LEVELS,
+                },
+            ];
+            let pool_info = ash::vk::DescriptorPoolCreateInfo::default()
+                .max_sets(image_count * 2 * MAX_BLOOM_LEVELS)
+                .pool_sizes(&pool_sizes);
+            unsafe { device.create_descriptor_pool(&pool_info, None) }
+                .expect("Failed to create descriptor pool for bloom post-processing")
+        };
+
+        let mut bloom = Self {
+            blur,
+            composite,
+            sampler,
+            bloom_images: Vec::new(),
+            bloom_levels: 0,
+            bloom_extent: ash::vk::Extent2D::default(),
+            framebuffers: Vec::new(),
+            descriptor_pool,
+            descriptor_sets: Vec::new(),
+            settings: BloomSettings::default(),
+            split_screen: false,
+        };
+        bloom.create_framebuffers(
+            device,
+            memory_allocator,
+            extent,
+            input_views,
+            destination_views,
+        );
+        bloom
+    }
+
+    /// Helper to create a new render pass for compositing the bloom onto the scene.
+    fn create_render_pass(
+        device: &ash::Device,
+        swapchain_format: ash::vk::Format,
+        destination_layout: ash::vk::ImageLayout,
+    ) -> ash::vk::RenderPass {
+        let attachment = [ash::vk::AttachmentDescription {
+            format: swapchain_format,
+            samples: ash::vk::SampleCountFlags::TYPE_1,
+            load_op: ash::vk::AttachmentLoadOp::DONT_CARE, // Each bit of the surface will be re-drawn so a clear is not necessary.
+            store_op: ash::vk::AttachmentStoreOp::STORE,
+            initial_layout: ash::vk::ImageLayout::UNDEFINED,
+            final_layout: destination_layout,
+            ..Default::default()
+        }];
+        let color_attachment_reference = [ash::vk::AttachmentReference {
+            attachment: 0,
+            layout: ash::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }];
+
+        // Define the single subpass that will be used in the render pass.
+        let subpass_description = [ash::vk::SubpassDescription::default()
+            .pipeline_bind_point(ash::vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_attachment_reference)];
+
+        // Define the subpass dependencies. The blur is made visible by a pipeline barrier before the render pass begins.
+        let subpass_dependencies = [ash::vk::SubpassDependency {
+            src_subpass: ash::vk::SUBPASS_EXTERNAL,
+            dst_subpass: 0,
+            src_stage_mask: ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            dst_stage_mask: ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            src_access_mask: ash::vk::AccessFlags::NONE,
+            dst_access_mask: ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            ..Default::default()
+        }];
+
+        // Create the render pass.
+        let render_pass_info = ash::vk::RenderPassCreateInfo::default()
+            .attachments(&attachment)
+            .subpasses(&subpass_description)
+            .dependencies(&subpass_dependencies);
+        unsafe { device.create_render_pass(&render_pass_info, None) }
+            .expect("Failed to create render pass for bloom post-processing")
+    }
+
+    /// Helper to create the bloom mip chains, framebuffers, and descriptor sets, which all depend on the size of the images.
+    fn create_framebuffers(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        extent: ash::vk::Extent2D,
+        input_views: &[ash::vk::ImageView],
+        destination_views: &[ash::vk::ImageView],
+    ) {
+        // Start the mip chain at half the size of the screen, with as many levels as fit.
+        self.bloom_extent = ash::vk::Extent2D {
+            width: (extent.width / 2).max(1),
+            height: (extent.height / 2).max(1),
+        };
+        self.bloom_levels = (self
+            .bloom_extent
+            .width
+            .min(self.bloom_extent.height)
+            .ilog2()
+            + 1)
+        .min(MAX_BLOOM_LEVELS);
+
+        let image_info = ash::vk::ImageCreateInfo {
+            image_type: ash::vk::ImageType::TYPE_2D,
+            format: BLOOM_FORMAT,
+            extent: ash::vk::Extent3D {
+                width: self.bloom_extent.width,
+                height: self.bloom_extent.height,
+                depth: 1,
+            },
+            mip_levels: self.bloom_levels,
+            array_layers: 1,
+            samples: ash::vk::SampleCountFlags::TYPE_1,
+            usage: ash::vk::ImageUsageFlags::STORAGE | ash::vk::ImageUsageFlags::SAMPLED,
+            ..Default::default()
+        };
+        self.bloom_images = input_views
+            .iter()
+            .map(|_| {
+                let (image, allocation) =
+                    super::create_image(device, memory_allocator, &image_info, "Bloom Image");
+
+                // Create a view of each level, so that each can be sampled and written separately.
+                let level_views = (0..self.bloom_levels)
+                    .map(|level| {
+                        let view_info = ash::vk::ImageViewCreateInfo {
+                            image,
+                            view_type: ash::vk::ImageViewType::TYPE_2D,
+                            format: BLOOM_FORMAT,
+                            subresource_range: ash::vk::ImageSubresourceRange {
+                                aspect_mask: ash::vk::ImageAspectFlags::COLOR,
+                                base_mip_level: level,
+                                level_count: 1,
+                                base_array_layer: 0,
+                                layer_count: 1,
+                            },
+                            ..Default::default()
+                        };
+                        unsafe { device.create_image_view(&view_info, None) }
+                            .expect("Failed to create bloom image view")
+                    })
+                    .collect();
+                BloomImage {
+                    image,
+                    level_views,
+                    allocation,
+                }
+            })
+            .collect();
+
+        // Create a framebuffer for each destination image.
+        self.framebuffers = destination_views
+            .iter()
+            .map(|destination_view| {
+                let framebuffer_info = ash::vk::FramebufferCreateInfo::default()
+                    .render_pass(self.composite.render_pass)
+                    .attachments(std::slice::from_ref(destination_view))
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1);
+                unsafe { device.create_framebuffer(&framebuffer_info, None) }
+                    .expect("Failed to create framebuffer for bloom post-processing")
+            })
+            .collect();
+
+        // Create the descriptor sets for each frame.
+        let levels = self.bloom_levels as usize;
+        self.descriptor_sets = input_views
+            .iter()
+            .zip(&self.bloom_images)
+            .map(|(&input_view, bloom_image)| {
+                let mut blur_sets = unsafe {
+                    device.allocate_descriptor_sets(
+                        &ash::vk::DescriptorSetAllocateInfo::default()
+                            .descriptor_pool(self.descriptor_pool)
+                            .set_layouts(&vec![self.blur.descriptor_set_layout; 2 * levels - 1]),
+                    )
+                }
+                .expect("Failed to allocate descriptor sets for the bloom blur");
+                let &composite = unsafe {
+                    device.allocate_descriptor_sets(
+                        &ash::vk::DescriptorSetAllocateInfo::default()
+                            .descriptor_pool(self.descriptor_pool)
+                            .set_layouts(&[self.composite.descriptor_set_layout]),
+                    )
+                }
+                .expect("Failed to allocate descriptor set for the bloom composite")
+                .first()
+                .expect("Bloom composite descriptor set allocation returned an empty list");
+                let upsample = blur_sets.split_off(levels);
+                let downsample = blur_sets;
+
+                // Each blur set samples one view and writes another.
+                let write_blur_set = |set, (source_view, source_layout), destination_view| {
+                    let source_info = [ash::vk::DescriptorImageInfo {
+                        sampler: self.sampler,
+                        image_view: source_view,
+                        image_layout: source_layout,
+                    }];
+                    let destination_info = [ash::vk::DescriptorImageInfo {
+                        sampler: ash::vk::Sampler::null(),
+                        image_view: destination_view,
+                        image_layout: Self::BLOOM_IMAGE_LAYOUT,
+                    }];
+                    let writes = [
+                        ash::vk::WriteDescriptorSet::default()
+                            .dst_set(set)
+                            .dst_binding(0)
+                            .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                            .image_info(&source_info),
+                        ash::vk::WriteDescriptorSet::default()
+                            .dst_set(set)
+                            .dst_binding(1)
+                            .descriptor_type(ash::vk::DescriptorType::STORAGE_IMAGE)
+                            .image_info(&destination_info),
+                    ];
+                    unsafe { device.update_descriptor_sets(&writes, &[]) };
+                };
+                let views = &bloom_image.level_views;
+                for (level, &set) in downsample.iter().enumerate() {
+                    let source = if level == 0 {
+                        (input_view, ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    } else {
+                        (views[level - 1], Self::BLOOM_IMAGE_LAYOUT)
+                    };
+                    write_blur_set(set, source, views[level]);
+                }
+                for (level, &set) in upsample.iter().enumerate() {
+                    write_blur_set(
+                        set,
+                        (views[level + 1], Self::BLOOM_IMAGE_LAYOUT),
+                        views[level],
+                    );
+                }
+
+                // The composite samples the scene and the accumulated bloom.
+                let image_infos = [
+                    (input_view, ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+                    (views[0], Self::BLOOM_IMAGE_LAYOUT),
+                ]
+                .map(|(image_view, image_layout)| {
+                    [ash::vk::DescriptorImageInfo {
+                        sampler: self.sampler,
+                        image_view,
+                        image_layout,
+                    }]
+                });
+                let writes = [0, 1].map(|binding| {
+                    ash::vk::WriteDescriptorSet::default()
+                        .dst_set(composite)
+                        .dst_binding(binding)
+                        .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                        .image_info(&image_infos[binding as usize])
+                });
+                unsafe { device.update_descriptor_sets(&writes, &[]) };
+
+                FrameDescriptorSets {
+                    downsample,
+                    upsample,
+                    composite,
+                }
+            })
+            .collect();
+    }
+
+    /// Helper to destroy the bloom mip chains, framebuffers, and descriptor sets.
+    fn destroy_framebuffers(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    ) {
+        unsafe {
+            device.reset_descriptor_pool(
+                self.descriptor_pool,
+                ash::vk::DescriptorPoolResetFlags::empty(),
+            )
+        }
+        .expect("Failed to free bloom descriptor sets");
+        self.descriptor_sets.clear();
+
+        for framebuffer in self.framebuffers.drain(..) {
+            unsafe { device.destroy_framebuffer(framebuffer, None) };
+        }
+        for bloom_image in self.bloom_images.drain(..) {
+            for image_view in bloom_image.level_views {
+                unsafe { device.destroy_image_view(image_view, None) };
+            }
+            unsafe { device.destroy_image(bloom_image.image, None) };
+            memory_allocator
+                .free(bloom_image.allocation)
+                .expect("Failed to free bloom image allocation");
+        }
+    }
+
+    /// Helper to record a dispatch of a blur pipeline, covering the given level of the mip chain.
+    fn dispatch_blur(
+        &self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        set: ash::vk::DescriptorSet,
+        push_constants: &BlurPushConstants,
+        level: usize,
+    ) {
+        let width = (self.bloom_extent.width >> level).max(1);
+        let height = (self.bloom_extent.height >> level).max(1);
+        unsafe {
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                ash::vk::PipelineBindPoint::COMPUTE,
+                self.blur.layout,
+                0,
+                &[set],
+                &[],
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.blur.layout,
+                ash::vk::ShaderStageFlags::COMPUTE,
+                0,
+                super::data_byte_slice(push_constants),
+            );
+            device.cmd_dispatch(
+                command_buffer,
+                width.div_ceil(WORKGROUP_SIZE),
+                height.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+
+        // Make the written level visible to the next dispatch and to the composite.
+        let memory_barrier = ash::vk::MemoryBarrier2::default()
+            .src_stage_mask(ash::vk::PipelineStageFlags2::COMPUTE_SHADER)
+            .src_access_mask(ash::vk::AccessFlags2::SHADER_STORAGE_WRITE)
+            .dst_stage_mask(
+                ash::vk::PipelineStageFlags2::COMPUTE_SHADER
+                    | ash::vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            )
+            .dst_access_mask(
+                ash::vk::AccessFlags2::SHADER_SAMPLED_READ
+                    | ash::vk::AccessFlags2::SHADER_STORAGE_READ,
+            );
+        unsafe {
+            device.cmd_pipeline_barrier2(
+                command_buffer,
+                &ash::vk::DependencyInfo::default().memory_barriers(&[memory_barrier]),
+            );
+        }
+    }
+}
+
+impl PostProcessPass for BloomPass {
+    /// Record the commands necessary to blur the bright light of the input image and add it back, writing the result to the output image.
+    fn render_frame(
+        &self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        frame_extent: FrameExtent,
+        image_index: usize,
+    ) {
+        // Only the render area of the images holds the scene.
+        let extent = frame_extent.render;
+        let sets = &self.descriptor_sets[image_index];
+
+        // Discard the previous contents of the mip chain, once the previous frame has finished reading it.
+        let image_barrier = ash::vk::ImageMemoryBarrier2::default()
+            .src_stage_mask(
+                ash::vk::PipelineStageFlags2::COMPUTE_SHADER
+                    | ash::vk::PipelineStageFlags2::FRAGMENT_SHADER,
+            )
+            .src_access_mask(ash::vk::AccessFlags2::NONE)
+            .dst_stage_mask(ash::vk::PipelineStageFlags2::COMPUTE_SHADER)
+            .dst_access_mask(ash::vk::AccessFlags2::SHADER_STORAGE_WRITE)
+            .old_layout(ash::vk::ImageLayout::UNDEFINED)
+            .new_layout(Self::BLOOM_IMAGE_LAYOUT)
+            .image(self.bloom_images[image_index].image)
+            .subresource_range(ash::vk::ImageSubresourceRange {
+                aspect_mask: ash::vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: self.bloom_levels,
+                base_array_layer: 0,
+                layer_count: 1,
+            });
+        unsafe {
+            device.cmd_pipeline_barrier2(
+                command_buffer,
+                &ash::vk::DependencyInfo::default().image_memory_barriers(&[image_barrier]),
+            );
+        }
+
+        // Downsample the scene through the mip chain, keeping only the bright light in the first level.
+        // Samples of the scene are clamped to the texel centers at the edge of its render area, so the stale texels beyond it do not bloom.
+        let [inverse_width, inverse_height] = frame_extent.inverse_image_size();
+        let mut push_constants = BlurPushConstants {
+            threshold: self.settings.threshold,
+            soft_knee: SOFT_KNEE,
+            prefilter: 1,
+            filter_radius: FILTER_RADIUS,
+            source_max_coord: [
+                (extent.width as f32 - 0.5) * inverse_width,
+                (extent.height as f32 - 0.5) * inverse_height,
+            ],
+        };
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                ash::vk::PipelineBindPoint::COMPUTE,
+                self.blur.downsample,
+            );
+        }
+        for (level, &set) in sets.downsample.iter().enumerate() {
+            self.dispatch_blur(device, command_buffer, set, &push_constants, level);
+            push_constants.prefilter = 0;
+            push_constants.source_max_coord = [1., 1.];
+        }
+
+        // Upsample back to the first level, accumulating each level into the next larger one.
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                ash::vk::PipelineBindPoint::COMPUTE,
+                self.blur.upsample,
+            );
+        }
+        for (level, &set) in sets.upsample.iter().enumerate().rev() {
+            self.dispatch_blur(device, command_buffer, set, &push_constants, level);
+        }
+
+        // Begin the render pass which adds the bloom to the scene.
+        let render_area = ash::vk::Rect2D {
+            offset: ash::vk::Offset2D::default(),
+            extent,
+        };
+        unsafe {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &ash::vk::RenderPassBeginInfo::default()
+                    .render_pass(self.composite.render_pass)
+                    .framebuffer(self.framebuffers[image_index])
+                    .render_area(render_area),
+                ash::vk::SubpassContents::INLINE,
+            );
+        }
+
+        // Set the shader push constants. Every level adds its own light, so divide by the number of levels to preserve energy.
+        let composite_push_constants = CompositePushConstants {
+            inverse_screen_size: frame_extent.inverse_image_size(),
+            split_position: frame_extent.split_position(self.split_screen),
+            intensity: self.settings.intensity / self.bloom_levels as f32,
+        };
+        unsafe {
+            device.cmd_push_constants(
+                command_buffer,
+                self.composite.layout,
+                ash::vk::ShaderStageFlags::FRAGMENT,
+                0,
+                super::data_byte_slice(&composite_push_constants),
+            );
+        }
+
+        // Set the viewport and scissor in the command buffer because we specified they would be set dynamically in the pipeline.
+        unsafe {
+            device.cmd_set_viewport(
+                command_buffer,
+                0,
+                &[ash::vk::Viewport {
+                    x: 0.,
+                    y: 0.,
+                    width: extent.width as f32,
+                    height: extent.height as f32,
+                    min_depth: 0.,
+                    max_depth: 1.,
+                }],
+            );
+            device.cmd_set_scissor(command_buffer, 0, &[render_area]);
+        }
+
+        // Bind the graphics pipeline and the descriptor set for the scene and bloom.
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                ash::vk::PipelineBindPoint::GRAPHICS,
+                self.composite.pipeline,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                ash::vk::PipelineBindPoint::GRAPHICS,
+                self.composite.layout,
+                0,
+                &[sets.composite],
+                &[],
+            );
+        }
+
+        // Draw the full screen quad.
+        unsafe { device.cmd_draw(command_buffer, 4, 1, 0, 0) };
+
+        // End the render pass.
+        unsafe { device.cmd_end_render_pass(command_buffer) };
+    }
+
+    /// Apply bloom to only the left half of the screen, for comparison with the unprocessed right half.
+    fn set_split_screen(&mut self, split_screen: bool) {
+        self.split_screen = split_screen;
+    }
+
+    /// Change the intensity and threshold of the bloom.
+    fn set_bloom_settings(&mut self, settings: BloomSettings) {
+        self.settings = settings;
+    }
+
+    /// Clean up the resources used by this bloom pass.
+    fn destroy(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    ) {
+        self.destroy_framebuffers(device, memory_allocator);
+        unsafe { device.destroy_descriptor_pool(self.descriptor_pool, None) };
+        self.blur.destroy(device);
+        self.composite.destroy(device);
+        unsafe { device.destroy_sampler(self.sampler, None) };
+    }
+}
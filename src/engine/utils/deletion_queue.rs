@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+
+/// A function which destroys an object composed of many resources.
+type DestroyFn = Box<dyn FnOnce(&ash::Device, &mut gpu_allocator::vulkan::Allocator)>;
+
+/// A resource whose destruction is deferred until the frames which may use it have completed.
+pub enum DeferredDestroy {
+    Pipeline(ash::vk::Pipeline),
+    PipelineLayout(ash::vk::PipelineLayout),
+    RenderPass(ash::vk::RenderPass),
+    Framebuffer(ash::vk::Framebuffer),
+    ImageView(ash::vk::ImageView),
+    Image(ash::vk::Image, gpu_allocator::vulkan::Allocation),
+    Buffer(ash::vk::Buffer, gpu_allocator::vulkan::Allocation),
+
+    /// An object composed of many resources which knows how to destroy itself, such as a demo, a post-processing chain, or a swapchain.
+    Other(DestroyFn),
+}
+
+impl DeferredDestroy {
+    /// Destroy the resource immediately.
+    /// # Safety
+    /// This function **must** only be called when the resource is not currently being processed by the GPU.
+    fn destroy(
+        self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    ) {
+        unsafe {
+            match self {
+                Self::Pipeline(pipeline) => device.destroy_pipeline(pipeline, None),
+                Self::PipelineLayout(layout) => device.destroy_pipeline_layout(layout, None),
+                Self::RenderPass(render_pass) => device.destroy_render_pass(render_pass, None),
+                Self::Framebuffer(framebuffer) => device.destroy_framebuffer(framebuffer, None),
+                Self::ImageView(image_view) => device.destroy_image_view(image_view, None),
+                Self::Image(image, allocation) => {
+                    device.destroy_image(image, None);
+                    memory_allocator
+                        .free(allocation)
+                        .expect("Unable to free a deferred image allocation");
+                }
+                Self::Buffer(buffer, allocation) => {
+                    device.destroy_buffer(buffer, None);
+                    memory_allocator
+                        .free(allocation)
+                        .expect("Unable to free a deferred buffer allocation");
+                }
+                Self::Other(destroy) => destroy(device, memory_allocator),
+            }
+        }
+    }
+}
+
+/// A queue of resources to destroy once every frame submitted before they were queued has completed.
+/// This allows resources to be replaced while frames in flight still use them, without waiting for the GPU.
+#[derive(Default)]
+pub struct DeletionQueue {
+    pending: VecDeque<(u64, DeferredDestroy)>,
+    submitted_frames: u64,
+}
+
+impl DeletionQueue {
+    /// Destroy the resource after every frame submitted so far has completed.
+    pub fn push(&mut self, resource: DeferredDestroy) {
+        self.pending.push_back((self.submitted_frames, resource));
+    }
+
+    /// Record that a frame has been submitted, and return its frame number to pass to `frame_completed` after its fence is signaled.
+    pub fn frame_submitted(&mut self) -> u64 {
+        self.submitted_frames += 1;
+        self.submitted_frames
+    }
+
+    /// Destroy the resources which are no longer used now that the given frame, and every frame submitted before it, has completed.
+    pub fn frame_completed(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        frame_number: u64,
+    ) {
+        for resource in self.take_completed(frame_number) {
+            resource.destroy(device, memory_allocator);
+        }
+    }
+
+    /// Remove the resources which are no longer used now that the given frame has completed, in the order they were queued.
+    fn take_completed(&mut self, frame_number: u64) -> impl Iterator<Item = DeferredDestroy> + '_ {
+        // Resources are queued in order of the frames they wait for, so the completed ones are all at the front.
+        let completed = self
+            .pending
+            .partition_point(|(last_frame, _)| *last_frame <= frame_number);
+        self.pending
+            .drain(..completed)
+            .map(|(_, resource)| resource)
+    }
+
+    /// Destroy all of the queued resources.
+    /// # Safety
+    /// This function **must** only be called when the queued resources are not currently being processed by the GPU.
+    pub fn destroy(
+        self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    ) {
+        for (_, resource) in self.pending {
+            resource.destroy(device, memory_allocator);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle as _;
+
+    fn pipeline(id: u64) -> DeferredDestroy {
+        DeferredDestroy::Pipeline(ash::vk::Pipeline::from_raw(id))
+    }
+
+    fn completed_ids(queue: &mut DeletionQueue, frame_number: u64) -> Vec<u64> {
+        queue
+            .take_completed(frame_number)
+            .map(|resource| match resource {
+                DeferredDestroy::Pipeline(pipeline) => pipeline.as_raw(),
+                _ => unreachable!("Only pipelines are queued in these tests"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn resources_wait_for_frames_submitted_before_them() {
+        let mut queue = DeletionQueue::default();
+        let first_frame = queue.frame_submitted();
+        let second_frame = queue.frame_submitted();
+        queue.push(pipeline(1));
+        let third_frame = queue.frame_submitted();
+        queue.push(pipeline(2));
+
+        assert!(completed_ids(&mut queue, first_frame).is_empty());
+        assert_eq!(completed_ids(&mut queue, second_frame), [1]);
+        assert_eq!(completed_ids(&mut queue, third_frame), [2]);
+        assert!(queue.pending.is_empty());
+    }
+
+    #[test]
+    fn resources_queued_before_any_frame_wait_for_the_next_completion() {
+        let mut queue = DeletionQueue::default();
+        queue.push(pipeline(1));
+        let frame = queue.frame_submitted();
+        assert_eq!(completed_ids(&mut queue, frame), [1]);
+    }
+
+    #[test]
+    fn completing_a_frame_releases_every_earlier_resource_in_order() {
+        let mut queue = DeletionQueue::default();
+        queue.frame_submitted();
+        queue.push(pipeline(1));
+        queue.push(pipeline(2));
+        let second_frame = queue.frame_submitted();
+        queue.push(pipeline(3));
+        queue.frame_submitted();
+        queue.push(pipeline(4));
+
+        assert_eq!(completed_ids(&mut queue, second_frame), [1, 2, 3]);
+        assert_eq!(queue.pending.len(), 1);
+    }
+}
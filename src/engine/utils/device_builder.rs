@@ -0,0 +1,173 @@
+use std::{collections::HashSet, ffi::CStr};
+
+use smallvec::SmallVec;
+
+use super::{
+    extensions_list_contains, get_sorted_physical_devices, new_device,
+    EnginePhysicalDeviceFeatures, QueueFamilies, QueueRequests, QueueSet, VulkanCore,
+    EXPECTED_MAX_ENABLED_DEVICE_EXTENSIONS,
+};
+
+/// A set of device extensions and features which are only enabled together, when the physical device supports all of them.
+struct OptionalExtensions {
+    extensions: SmallVec<[&'static CStr; 2]>,
+    features: EnginePhysicalDeviceFeatures,
+    instance_extensions: SmallVec<[&'static CStr; 1]>,
+}
+
+/// The extensions and features that were enabled on a logical device created by a `DeviceBuilder`.
+/// The features are the required and optional features merged into the chain used to create the device.
+pub struct EnabledDeviceSupport {
+    pub extensions: HashSet<&'static CStr>,
+    pub features: EnginePhysicalDeviceFeatures,
+}
+impl EnabledDeviceSupport {
+    /// Check if the logical device was created with a specific extension enabled.
+    pub fn extension(&self, extension: &CStr) -> bool {
+        self.extensions.contains(extension)
+    }
+}
+
+/// Declare the extensions and features a logical device needs, then choose a physical device and create the device with them.
+/// Required extensions and features filter the physical devices, while optional ones are enabled only when the chosen device supports them.
+#[derive(Default)]
+pub struct DeviceBuilder {
+    required_extensions: SmallVec<[&'static CStr; EXPECTED_MAX_ENABLED_DEVICE_EXTENSIONS]>,
+    required_features: EnginePhysicalDeviceFeatures,
+    optional_extensions: Vec<OptionalExtensions>,
+}
+
+impl DeviceBuilder {
+    /// Require a device extension, so only physical devices supporting it are chosen.
+    pub fn require_extension(mut self, extension: &'static CStr) -> Self {
+        self.required_extensions.push(extension);
+        self
+    }
+
+    /// Require every enabled (i.e., `true`) feature in the mask, in addition to those already required.
+    pub fn require_features(mut self, features: &EnginePhysicalDeviceFeatures) -> Self {
+        self.required_features.enable_mask(features);
+        self
+    }
+
+    /// Enable the device extensions and features together if the chosen physical device supports all of them, and the instance was created with each of the `instance_extensions`.
+    pub fn optional_extensions(
+        mut self,
+        extensions: &[&'static CStr],
+        features: EnginePhysicalDeviceFeatures,
+        instance_extensions: &[&'static CStr],
+    ) -> Self {
+        self.optional_extensions.push(OptionalExtensions {
+            extensions: SmallVec::from_slice(extensions),
+            features,
+            instance_extensions: SmallVec::from_slice(instance_extensions),
+        });
+        self
+    }
+
+    /// Use simple heuristics to find the best physical device which supports the required extensions and features.
+    /// Returns the physical device, its properties, and all the features it supports.
+    pub fn select_physical_device(
+        &self,
+        vulkan: &VulkanCore,
+    ) -> (
+        ash::vk::PhysicalDevice,
+        ash::vk::PhysicalDeviceProperties,
+        EnginePhysicalDeviceFeatures,
+    ) {
+        let required_extensions = self
+            .required_extensions
+            .iter()
+            .map(|extension| extension.as_ptr())
+            .collect::<SmallVec<[_; EXPECTED_MAX_ENABLED_DEVICE_EXTENSIONS]>>();
+
+        *get_sorted_physical_devices(
+            &vulkan.instance,
+            vulkan.version,
+            &required_extensions,
+            &self.required_features,
+        )
+        .first()
+        .expect("Unable to find a suitable physical device")
+    }
+
+    /// Create a logical device with the requested queues, the required extensions and features, and the optional ones the physical device supports.
+    /// The `supported_features` are those returned with the physical device by `select_physical_device`.
+    /// Returns the device, its queues, and a record of the extensions and features that were enabled.
+    pub fn build(
+        &self,
+        vulkan: &VulkanCore,
+        physical_device: ash::vk::PhysicalDevice,
+        supported_features: &EnginePhysicalDeviceFeatures,
+        queue_families: &QueueFamilies,
+        queue_requests: &QueueRequests,
+    ) -> (ash::Device, QueueSet, EnabledDeviceSupport) {
+        let available_extensions = unsafe {
+            vulkan
+                .instance
+                .enumerate_device_extension_properties(physical_device)
+                .expect("Unable to enumerate device extensions")
+        };
+
+        // Start with the required extensions and features, which the physical device was chosen to support.
+        let mut extensions = self
+            .required_extensions
+            .iter()
+            .copied()
+            .collect::<SmallVec<[_; EXPECTED_MAX_ENABLED_DEVICE_EXTENSIONS]>>();
+        let mut features = self.required_features;
+
+        // Add each set of optional extensions and features that is fully supported.
+        for optional in &self.optional_extensions {
+            if !optional
+                .instance_extensions
+                .iter()
+                .all(|extension| vulkan.enabled_instance_extension(extension))
+                || !optional
+                    .extensions
+                    .iter()
+                    .all(|extension| extensions_list_contains(&available_extensions, extension))
+                || !supported_features.contains_mask(&optional.features)
+            {
+                continue;
+            }
+
+            for &extension in &optional.extensions {
+                #[cfg(debug_assertions)]
+                println!("INFO: Enabling {extension:?} device extension");
+                extensions.push(extension);
+            }
+            features.enable_mask(&optional.features);
+        }
+
+        #[cfg(debug_assertions)]
+        println!("INFO: Enabling device features {features:?}");
+
+        // Chain the structures of the enabled features for the device creation info.
+        let extension_pointers = extensions
+            .iter()
+            .map(|extension| extension.as_ptr())
+            .collect::<SmallVec<[_; EXPECTED_MAX_ENABLED_DEVICE_EXTENSIONS]>>();
+        let mut feature_chain = features.enabled_feature_chain();
+        let (device, queues) = new_device(
+            vulkan,
+            physical_device,
+            queue_families,
+            queue_requests,
+            &extension_pointers,
+            Some(&mut feature_chain),
+        );
+
+        // The chained structures point to each other, which is meaningless after the device is created.
+        features.clear_pointers();
+
+        (
+            device,
+            queues,
+            EnabledDeviceSupport {
+                extensions: extensions.into_iter().collect(),
+                features,
+            },
+        )
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use super::FIVE_SECONDS_IN_NANOSECONDS;
+
+/// The number of measured frames to average over before reporting the input-to-present latency.
+const LATENCY_REPORT_FRAMES: u32 = 120;
+
+/// Limit the rate and depth of queued frames, and measure the latency from sampling input to the frame being presented.
+/// Presents are tracked with `VK_KHR_present_id` and waited on with `VK_KHR_present_wait` when the device supports them.
+pub struct FramePacer {
+    present_wait: Option<ash::khr::present_wait::Device>,
+    last_present_id: u64,
+    first_present_id: u64,
+    min_frame_interval: Option<Duration>,
+    next_frame_start: Option<Instant>,
+    low_latency: bool,
+    frame_began: bool,
+    input_sampled: Option<Instant>,
+    pending_presents: VecDeque<(u64, Instant)>,
+    latency_sum: Duration,
+    latency_max: Duration,
+    latency_count: u32,
+}
+
+impl FramePacer {
+    /// Create a frame pacer without a frame rate limit, optionally waiting on presents with the device extension `VK_KHR_present_wait`.
+    pub fn new(present_wait: Option<ash::khr::present_wait::Device>) -> Self {
+        Self {
+            present_wait,
+            last_present_id: 0,
+            first_present_id: 1,
+            min_frame_interval: None,
+            next_frame_start: None,
+            low_latency: false,
+            frame_began: false,
+            input_sampled: None,
+            pending_presents: VecDeque::new(),
+            latency_sum: Duration::ZERO,
+            latency_max: Duration::ZERO,
+            latency_count: 0,
+        }
+    }
+
+    /// Limit the number of frames started per second. `None` removes the limit.
+    pub fn set_fps_cap(&mut self, fps_cap: Option<f32>) {
+        self.min_frame_interval = fps_cap.map(|fps| Duration::from_secs_f32(fps.recip()));
+        self.next_frame_start = None;
+    }
+
+    /// Queue at most one frame for presentation, and report the input-to-present latency.
+    pub fn set_low_latency(&mut self, low_latency: bool) {
+        if low_latency && self.present_wait.is_none() {
+            eprintln!("WARN: The device does not support waiting for presents, so low-latency mode cannot limit queued frames or measure latency");
+        }
+        self.low_latency = low_latency;
+    }
+
+    /// Whether the waits for the next frame have completed, and it has not yet been presented.
+    pub fn frame_began(&self) -> bool {
+        self.frame_began
+    }
+
+    /// Block until the next frame should begin, limited by the number of queued presents and the frame rate limit.
+    /// Has no effect if the frame has already begun.
+    pub fn wait_for_next_frame(&mut self, swapchain: ash::vk::SwapchainKHR, frames_in_flight: u64) {
+        if self.frame_began {
+            return;
+        }
+        self.frame_began = true;
+
+        // Wait until few enough presents are queued, which bounds how far the CPU may run ahead of the display.
+        let queue_depth = if self.low_latency {
+            1
+        } else {
+            frames_in_flight
+        };
+        if let Some(wait_id) = (self.last_present_id + 1).checked_sub(queue_depth) {
+            self.wait_for_present(swapchain, wait_id, FIVE_SECONDS_IN_NANOSECONDS);
+        }
+
+        // Record the latency of any other presents which have completed, without blocking.
+        while let Some(&(present_id, _)) = self.pending_presents.front() {
+            if !self.wait_for_present(swapchain, present_id, 0) {
+                break;
+            }
+        }
+
+        // Sleep until the start of the next frame allowed by the frame rate limit.
+        if let Some(interval) = self.min_frame_interval {
+            let now = Instant::now();
+            let start = match self.next_frame_start {
+                Some(start) if start > now => {
+                    std::thread::sleep(start - now);
+                    start
+                }
+                // Don't try to catch up on frames which started late.
+                _ => now,
+            };
+            self.next_frame_start = Some(start + interval);
+        }
+    }
+
+    /// Record that input was sampled for the next frame, which begins the measurement of its latency.
+    pub fn input_sampled(&mut self) {
+        self.input_sampled = Some(Instant::now());
+    }
+
+    /// Get the identifier to present the next frame with, if presents can be waited on.
+    pub fn next_present_id(&mut self) -> Option<u64> {
+        self.present_wait.as_ref()?;
+        self.last_present_id += 1;
+        Some(self.last_present_id)
+    }
+
+    /// Record that the frame has been queued for presentation, with the identifier from `next_present_id`.
+    pub fn presented(&mut self, present_id: Option<u64>) {
+        self.frame_began = false;
+        if let (Some(present_id), Some(input_sampled)) = (present_id, self.input_sampled.take()) {
+            self.pending_presents.push_back((present_id, input_sampled));
+        }
+    }
+
+    /// Forget the presents queued to a swapchain which has been replaced, since they can no longer be waited on.
+    /// The next frame must wait again, as the frames in flight restart with the new swapchain.
+    pub fn swapchain_recreated(&mut self) {
+        self.frame_began = false;
+        self.first_present_id = self.last_present_id + 1;
+        self.pending_presents.clear();
+    }
+
+    /// Wait for the present with the given identifier to complete, and measure the latency of every present completed by then.
+    /// Returns whether the present completed within the timeout, in nanoseconds.
+    fn wait_for_present(
+        &mut self,
+        swapchain: ash::vk::SwapchainKHR,
+        present_id: u64,
+        timeout: u64,
+    ) -> bool {
+        let Some(present_wait) = &self.present_wait else {
+            return false;
+        };
+        if present_id < self.first_present_id {
+            return false;
+        }
+        if unsafe { present_wait.wait_for_present(swapchain, present_id, timeout) }.is_err() {
+            // Timeouts are expected when polling, and other errors are reported when the swapchain is next used.
+            return false;
+        }
+
+        let now = Instant::now();
+        while let Some(&(id, input_sampled)) = self.pending_presents.front() {
+            if id > present_id {
+                break;
+            }
+            self.pending_presents.pop_front();
+            self.record_latency(now - input_sampled);
+        }
+        true
+    }
+
+    /// Accumulate a measured input-to-present latency, and periodically report the average when in low-latency mode.
+    fn record_latency(&mut self, latency: Duration) {
+        self.latency_sum += latency;
+        self.latency_max = self.latency_max.max(latency);
+        self.latency_count += 1;
+        if self.latency_count < LATENCY_REPORT_FRAMES {
+            return;
+        }
+
+        if self.low_latency {
+            println!(
+                "INFO: Input-to-present latency: average {:.2} ms, maximum {:.2} ms",
+                1_000. * self.latency_sum.as_secs_f64() / f64::from(self.latency_count),
+                1_000. * self.latency_max.as_secs_f64()
+            );
+        }
+        self.latency_sum = Duration::ZERO;
+        self.latency_max = Duration::ZERO;
+        self.latency_count = 0;
+    }
+}
@@ -1,4 +1,7 @@
-use super::shaders::ENTRY_POINT_MAIN;
+use super::{
+    post_process::{FrameExtent, PostProcessPass},
+    shaders::ENTRY_POINT_MAIN,
+};
 
 /// Shader for texture-mapping the entire screen. Useful for post-processing and fullscreen effects.
 const FXAA_FRAGMENT: &[u32] =
@@ -10,6 +13,72 @@ const FXAA_FRAGMENT: &[u32] =
 pub struct PushConstants {
     /// The width and height of the render surface, as `f32`s.
     pub inverse_screen_size: [f32; 2],
+
+    /// The fraction of the screen width, from the left, where FXAA is applied. The rest shows the unprocessed input.
+    pub split_position: f32,
+}
+
+/// Preset quality levels for FXAA, trading performance for the amount of aliasing removed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FxaaQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Extreme,
+}
+
+/// Define the specialization constants used in the fragment shader of the FXAA algorithm.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SpecializationConstants {
+    /// The minimum contrast in luminance for a pixel to be considered part of an edge.
+    pub edge_threshold_min: f32,
+
+    /// The contrast in luminance, relative to the brightest neighbor, for a pixel to be considered part of an edge.
+    pub edge_threshold_max: f32,
+
+    /// The maximum amount a pixel is blended with its neighbors to remove sub-pixel aliasing.
+    pub pixel_blend_limit: f32,
+
+    /// The amount of sub-pixel aliasing which is ignored before blending.
+    pub min_pixel_aliasing: f32,
+
+    /// The number of steps taken in each direction while searching for the ends of an edge.
+    pub edge_search_steps: u32,
+
+    /// Output a visualization of the pixels FXAA modified instead of the anti-aliased image.
+    pub debug_edges: ash::vk::Bool32,
+}
+impl Default for SpecializationConstants {
+    fn default() -> Self {
+        FxaaQuality::default().into()
+    }
+}
+impl From<FxaaQuality> for SpecializationConstants {
+    /// Get the FXAA parameters for a preset quality level.
+    fn from(quality: FxaaQuality) -> Self {
+        let (
+            edge_threshold_min,
+            edge_threshold_max,
+            pixel_blend_limit,
+            min_pixel_aliasing,
+            edge_search_steps,
+        ) = match quality {
+            FxaaQuality::Low => (1. / 12., 1. / 4., 0.6, 1. / 4., 1),
+            FxaaQuality::Medium => (1. / 20., 1. / 12., 0.8, 1. / 8., 2),
+            FxaaQuality::High => (1. / 24., 1. / 16., 0.9, 1. / 16., 4),
+            FxaaQuality::Extreme => (1. / 32., 1. / 24., 1., 1. / 32., 8),
+        };
+        Self {
+            edge_threshold_min,
+            edge_threshold_max,
+            pixel_blend_limit,
+            min_pixel_aliasing,
+            edge_search_steps,
+            debug_edges: ash::vk::FALSE,
+        }
+    }
 }
 
 /// Define the pipeline used for the FXAA post-processing effect.
@@ -28,12 +97,56 @@ impl Pipeline {
         device: &ash::Device,
         render_pass: ash::vk::RenderPass,
         sampler: ash::vk::Sampler,
+        specialization_constants: &SpecializationConstants,
     ) -> Self {
         // Create the shader modules for the vertex and fragment shaders.
         // TODO: Allow caching of these shader modules.
         let fullscreen_vert_shader =
             super::create_shader_module(device, super::shaders::FULLSCREEN_VERTEX);
         let fxaa_frag_shader = super::create_shader_module(device, FXAA_FRAGMENT);
+
+        // Define the specialization constants used to configure the FXAA algorithm.
+        let map_entry = |constant_id, offset, size| ash::vk::SpecializationMapEntry {
+            constant_id,
+            offset: offset as u32,
+            size,
+        };
+        let specialization_map = [
+            map_entry(
+                0,
+                std::mem::offset_of!(SpecializationConstants, edge_threshold_min),
+                std::mem::size_of::<f32>(),
+            ),
+            map_entry(
+                1,
+                std::mem::offset_of!(SpecializationConstants, edge_threshold_max),
+                std::mem::size_of::<f32>(),
+            ),
+            map_entry(
+                2,
+                std::mem::offset_of!(SpecializationConstants, pixel_blend_limit),
+                std::mem::size_of::<f32>(),
+            ),
+            map_entry(
+                3,
+                std::mem::offset_of!(SpecializationConstants, min_pixel_aliasing),
+                std::mem::size_of::<f32>(),
+            ),
+            map_entry(
+                4,
+                std::mem::offset_of!(SpecializationConstants, edge_search_steps),
+                std::mem::size_of::<u32>(),
+            ),
+            map_entry(
+                5,
+                std::mem::offset_of!(SpecializationConstants, debug_edges),
+                std::mem::size_of::<ash::vk::Bool32>(),
+            ),
+        ];
+        let specialization_info = ash::vk::SpecializationInfo::default()
+            .map_entries(&specialization_map)
+            .data(super::data_byte_slice(specialization_constants));
+
         let shader_stages = [
             ash::vk::PipelineShaderStageCreateInfo::default()
                 .stage(ash::vk::ShaderStageFlags::VERTEX)
@@ -42,7 +155,8 @@ impl Pipeline {
             ash::vk::PipelineShaderStageCreateInfo::default()
                 .stage(ash::vk::ShaderStageFlags::FRAGMENT)
                 .module(fxaa_frag_shader)
-                .name(ENTRY_POINT_MAIN),
+                .name(ENTRY_POINT_MAIN)
+                .specialization_info(&specialization_info),
         ];
 
         // Define the push constants that will be used by the fragment shader.
@@ -162,14 +276,10 @@ impl Pipeline {
 pub struct FxaaPass {
     pipeline: Pipeline,
     pub sampler: ash::vk::Sampler,
-    pub framebuffers: Vec<(
-        ash::vk::Framebuffer,
-        ash::vk::ImageView,
-        ash::vk::Image,
-        gpu_allocator::vulkan::Allocation,
-    )>,
+    pub framebuffers: Vec<ash::vk::Framebuffer>,
     descriptor_pool: ash::vk::DescriptorPool,
     descriptor_sets: Vec<ash::vk::DescriptorSet>,
+    split_screen: bool,
 }
 
 impl FxaaPass {
@@ -177,13 +287,15 @@ impl FxaaPass {
         ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
 
     /// Create a new FXAA render pass and associated resources.
+    /// The input images are sampled and the result is written to the destination images with the same index.
     pub fn new(
         device: &ash::Device,
-        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
         extent: ash::vk::Extent2D,
         swapchain_format: ash::vk::Format,
+        input_views: &[ash::vk::ImageView],
         destination_views: &[ash::vk::ImageView],
         destination_layout: ash::vk::ImageLayout,
+        specialization_constants: &SpecializationConstants,
     ) -> Self {
         // Create the sampler which will allow the FXAA shader to sample the input image.
         let sampler = {
@@ -204,26 +316,20 @@ impl FxaaPass {
 
         // Create the FXAA render pass and graphics pipeline.
         let render_pass = Self::create_render_pass(device, swapchain_format, destination_layout);
-        let pipeline = Pipeline::new(device, render_pass, sampler);
+        let pipeline = Pipeline::new(device, render_pass, sampler, specialization_constants);
 
         // Create the framebuffers that will be used during this render.
-        let framebuffers = Self::create_framebuffers(
-            device,
-            memory_allocator,
-            extent,
-            swapchain_format,
-            destination_views,
-            pipeline.render_pass,
-        );
+        let framebuffers =
+            Self::create_framebuffers(device, extent, destination_views, pipeline.render_pass);
 
         // Create a descriptor pool and descriptor sets.
         let descriptor_pool = {
             let pool_sizes = [ash::vk::DescriptorPoolSize {
                 ty: ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                descriptor_count: destination_views.len() as u32, // One descriptor per framebuffer.
+                descriptor_count: input_views.len() as u32, // One descriptor per input image.
             }];
             let pool_info = ash::vk::DescriptorPoolCreateInfo::default()
-                .max_sets(framebuffers.len() as u32)
+                .max_sets(input_views.len() as u32)
                 .pool_sizes(&pool_sizes);
             unsafe { device.create_descriptor_pool(&pool_info, None) }
                 .expect("Failed to create descriptor pool for FXAA post-processing")
@@ -233,7 +339,7 @@ impl FxaaPass {
             device,
             descriptor_pool,
             pipeline.descriptor_set_layout,
-            framebuffers.iter().map(|(_, view, _, _)| *view),
+            input_views.iter().copied(),
             sampler,
         );
 
@@ -243,49 +349,10 @@ impl FxaaPass {
             framebuffers,
             descriptor_pool,
             descriptor_sets,
+            split_screen: false,
         }
     }
 
-    /// Clean up the resources used by this FXAA render pass instance.
-    pub fn destroy(
-        &mut self,
-        device: &ash::Device,
-        allocator: &mut gpu_allocator::vulkan::Allocator,
-    ) {
-        // Destroy the descriptor pool and descriptor sets.
-        unsafe {
-            device.reset_descriptor_pool(
-                self.descriptor_pool,
-                ash::vk::DescriptorPoolResetFlags::empty(),
-            )
-        }
-        .expect("Failed to free FXAA descriptor sets");
-        unsafe { device.destroy_descriptor_pool(self.descriptor_pool, None) };
-
-        // Destroy the framebuffers.
-        for (framebuffer, image_view, image, allocation) in self.framebuffers.drain(..) {
-            unsafe { device.destroy_framebuffer(framebuffer, None) };
-            unsafe { device.destroy_image_view(image_view, None) };
-            unsafe { device.destroy_image(image, None) };
-            allocator
-                .free(allocation)
-                .expect("Failed to free FXAA image allocation");
-        }
-
-        // Destroy the pipeline.
-        unsafe { device.destroy_pipeline(self.pipeline.pipeline, None) };
-        unsafe { device.destroy_pipeline_layout(self.pipeline.layout, None) };
-        unsafe { device.destroy_descriptor_set_layout(self.pipeline.descriptor_set_layout, None) };
-        unsafe { device.destroy_render_pass(self.pipeline.render_pass, None) };
-
-        // Destroy the shader modules.
-        unsafe { device.destroy_shader_module(self.pipeline.fullscreen_vert_shader, None) };
-        unsafe { device.destroy_shader_module(self.pipeline.fxaa_frag_shader, None) };
-
-        // Destroy the sampler.
-        unsafe { device.destroy_sampler(self.sampler, None) };
-    }
-
     /// Helper to create a new render pass for the FXAA post-processing effect.
     fn create_render_pass(
         device: &ash::Device,
@@ -335,40 +402,13 @@ impl FxaaPass {
     /// Create new framebuffers that will be used during the FXAA render.
     fn create_framebuffers(
         device: &ash::Device,
-        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
         extent: ash::vk::Extent2D,
-        swapchain_format: ash::vk::Format,
         destination_views: &[ash::vk::ImageView],
         render_pass: ash::vk::RenderPass,
-    ) -> Vec<(
-        ash::vk::Framebuffer,
-        ash::vk::ImageView,
-        ash::vk::Image,
-        gpu_allocator::vulkan::Allocation,
-    )> {
+    ) -> Vec<ash::vk::Framebuffer> {
         destination_views
             .iter()
             .map(|destination_view| {
-                // Create an intermediate image to render to before FXAA processing. The final image will be the swapchain image.
-                let image_info = ash::vk::ImageCreateInfo {
-                    image_type: ash::vk::ImageType::TYPE_2D,
-                    format: swapchain_format,
-                    extent: ash::vk::Extent3D {
-                        width: extent.width,
-                        height: extent.height,
-                        depth: 1,
-                    },
-                    mip_levels: 1,
-                    array_layers: 1,
-                    samples: ash::vk::SampleCountFlags::TYPE_1,
-                    usage: ash::vk::ImageUsageFlags::COLOR_ATTACHMENT
-                        | ash::vk::ImageUsageFlags::SAMPLED,
-                    ..Default::default()
-                };
-                let (image, allocation) =
-                    super::create_image(device, memory_allocator, &image_info, "FXAA Image");
-                let image_view = super::create_image_view(device, image, swapchain_format, 1);
-
                 let framebuffer_info = ash::vk::FramebufferCreateInfo {
                     render_pass,
                     attachment_count: 1,
@@ -378,9 +418,8 @@ impl FxaaPass {
                     layers: 1,
                     ..Default::default()
                 };
-                let framebuffer = unsafe { device.create_framebuffer(&framebuffer_info, None) }
-                    .expect("Failed to create framebuffer for FXAA post-processing");
-                (framebuffer, image_view, image, allocation)
+                unsafe { device.create_framebuffer(&framebuffer_info, None) }
+                    .expect("Failed to create framebuffer for FXAA post-processing")
             })
             .collect()
     }
@@ -431,47 +470,27 @@ impl FxaaPass {
             })
             .collect()
     }
+}
 
+impl PostProcessPass for FxaaPass {
     /// Record the commands necessary to perform FXAA post-processing on the input image and write the result to the output image.
-    /// # Safety
-    /// * The command buffer must be in the recording state.
-    /// * The image index must be a valid index into the framebuffers array.
-    /// * The image index must specify an internal image that currently has layout `COLOR_ATTACHMENT_OPTIMAL`.
-    pub fn render_frame(
+    fn render_frame(
         &self,
         device: &ash::Device,
         command_buffer: ash::vk::CommandBuffer,
-        extent: ash::vk::Extent2D,
+        frame_extent: FrameExtent,
         image_index: usize,
     ) {
-        unsafe {
-            //Use a pipeline barrier to ensure that we are able to read the input sampler in the correct layout.
-            let image_barrier = ash::vk::ImageMemoryBarrier2::default()
-                .src_stage_mask(ash::vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
-                .src_access_mask(ash::vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
-                .dst_stage_mask(ash::vk::PipelineStageFlags2::FRAGMENT_SHADER)
-                .dst_access_mask(ash::vk::AccessFlags2::SHADER_READ)
-                .old_layout(ash::vk::ImageLayout::ATTACHMENT_OPTIMAL)
-                .new_layout(ash::vk::ImageLayout::READ_ONLY_OPTIMAL)
-                .image(self.framebuffers[image_index].2)
-                .subresource_range(ash::vk::ImageSubresourceRange {
-                    aspect_mask: ash::vk::ImageAspectFlags::COLOR,
-                    base_mip_level: 0,
-                    level_count: 1,
-                    base_array_layer: 0,
-                    layer_count: 1,
-                });
-            device.cmd_pipeline_barrier2(
-                command_buffer,
-                &ash::vk::DependencyInfo::default().image_memory_barriers(&[image_barrier]),
-            );
+        // Only the render area of the images holds the scene.
+        let extent = frame_extent.render;
 
-            // Begin the render pass for the current frame.
+        // Begin the render pass for the current frame.
+        unsafe {
             device.cmd_begin_render_pass(
                 command_buffer,
                 &ash::vk::RenderPassBeginInfo::default()
                     .render_pass(self.pipeline.render_pass)
-                    .framebuffer(self.framebuffers[image_index].0)
+                    .framebuffer(self.framebuffers[image_index])
                     .render_area(ash::vk::Rect2D {
                         offset: ash::vk::Offset2D::default(),
                         extent,
@@ -482,7 +501,8 @@ impl FxaaPass {
 
         // Set the shader push constants.
         let push_constants = PushConstants {
-            inverse_screen_size: [1. / extent.width as f32, 1. / extent.height as f32],
+            inverse_screen_size: frame_extent.inverse_image_size(),
+            split_position: frame_extent.split_position(self.split_screen),
         };
         unsafe {
             device.cmd_push_constants(
@@ -546,14 +566,16 @@ impl FxaaPass {
         unsafe { device.cmd_end_render_pass(command_buffer) };
     }
 
-    /// Recreate the framebuffers used by this FXAA render pass instance, as well as the resources that use them.
-    pub fn recreate_framebuffers(
+    /// Apply FXAA to only the left half of the screen, for comparison with the unprocessed right half.
+    fn set_split_screen(&mut self, split_screen: bool) {
+        self.split_screen = split_screen;
+    }
+
+    /// Clean up the resources used by this FXAA render pass instance.
+    fn destroy(
         &mut self,
         device: &ash::Device,
-        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
-        extent: ash::vk::Extent2D,
-        image_format: ash::vk::Format,
-        destination_views: &[ash::vk::ImageView],
+        _memory_allocator: &mut gpu_allocator::vulkan::Allocator,
     ) {
         // Destroy the descriptor pool and descriptor sets.
         unsafe {
@@ -563,46 +585,24 @@ impl FxaaPass {
             )
         }
         .expect("Failed to free FXAA descriptor sets");
+        unsafe { device.destroy_descriptor_pool(self.descriptor_pool, None) };
 
         // Destroy the framebuffers.
-        for (framebuffer, image_view, image, allocation) in self.framebuffers.drain(..) {
+        for framebuffer in self.framebuffers.drain(..) {
             unsafe { device.destroy_framebuffer(framebuffer, None) };
-            unsafe { device.destroy_image_view(image_view, None) };
-            unsafe { device.destroy_image(image, None) };
-            memory_allocator
-                .free(allocation)
-                .expect("Failed to free FXAA image allocation");
         }
 
-        // Recreate the framebuffers.
-        self.framebuffers = Self::create_framebuffers(
-            device,
-            memory_allocator,
-            extent,
-            image_format,
-            destination_views,
-            self.pipeline.render_pass,
-        );
+        // Destroy the pipeline.
+        unsafe { device.destroy_pipeline(self.pipeline.pipeline, None) };
+        unsafe { device.destroy_pipeline_layout(self.pipeline.layout, None) };
+        unsafe { device.destroy_descriptor_set_layout(self.pipeline.descriptor_set_layout, None) };
+        unsafe { device.destroy_render_pass(self.pipeline.render_pass, None) };
 
-        // Recreate the descriptor sets.
-        self.descriptor_sets = Self::create_descriptor_sets(
-            device,
-            self.descriptor_pool,
-            self.pipeline.descriptor_set_layout,
-            self.framebuffers.iter().map(|(_, view, _, _)| *view),
-            self.sampler,
-        );
-    }
+        // Destroy the shader modules.
+        unsafe { device.destroy_shader_module(self.pipeline.fullscreen_vert_shader, None) };
+        unsafe { device.destroy_shader_module(self.pipeline.fxaa_frag_shader, None) };
 
-    /// Get the framebuffers and related data.
-    pub fn framebuffer_data(
-        &self,
-    ) -> &[(
-        ash::vk::Framebuffer,
-        ash::vk::ImageView,
-        ash::vk::Image,
-        gpu_allocator::vulkan::Allocation,
-    )] {
-        &self.framebuffers
+        // Destroy the sampler.
+        unsafe { device.destroy_sampler(self.sampler, None) };
     }
 }
@@ -5,9 +5,18 @@ use std::{
 };
 
 use smallvec::SmallVec;
-use strum::EnumCount as _;
 
+pub mod bloom_pass;
+pub mod deletion_queue;
+pub mod device_builder;
+pub mod frame_pacing;
 pub mod fxaa_pass;
+pub mod post_process;
+pub mod smaa_pass;
+pub mod taa_pass;
+pub mod tonemap_pass;
+pub mod transfer;
+pub mod upscale_pass;
 
 /// Store the SPIR-V representation of the shaders in the binary.
 pub mod shaders {
@@ -26,6 +35,14 @@ const VULKAN_API_VERSION: u32 = ash::vk::API_VERSION_1_3;
 /// A heap allocation is required if the number of queue families exceeds this value.
 pub const EXPECTED_MAX_QUEUE_FAMILIES: usize = 8;
 
+/// Set a sane value for the maximum expected number of queues in a single queue request.
+/// A heap allocation is required if the number of requested queues exceeds this value.
+pub const EXPECTED_MAX_QUEUES_PER_REQUEST: usize = 2;
+
+/// Set a sane value for the maximum expected number of enabled device extensions.
+/// A heap allocation is required if the number of device extensions exceeds this value.
+pub const EXPECTED_MAX_ENABLED_DEVICE_EXTENSIONS: usize = 8;
+
 /// Set a sane value for the maximum expected number of instance extensions.
 /// A heap allocation is required if the number of instance extensions exceeds this value.
 pub const EXPECTED_MAX_ENABLED_INSTANCE_EXTENSIONS: usize = 8;
@@ -37,6 +54,12 @@ pub const EXPECTED_MAX_VULKAN_PHYSICAL_DEVICES: usize = 4;
 /// Sane maximum number of frames-in-flight before certain heap allocations are required.
 pub const EXPECTED_MAX_FRAMES_IN_FLIGHT: usize = 4;
 
+/// The number of frames-in-flight when not specified, so that a new frame can be recorded while another is rendered.
+const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+
+/// Sane maximum number of present modes a swapchain may switch between before a heap allocation is required.
+const EXPECTED_MAX_PRESENT_MODES: usize = 4;
+
 /// The number of nanoseconds in five seconds. Used for sane timeouts on synchronization objects.
 pub const FIVE_SECONDS_IN_NANOSECONDS: u64 = 5_000_000_000;
 
@@ -283,6 +306,8 @@ pub fn query_physical_feature_support(
     feature_chain = feature_chain.push_next(&mut requested_features.dynamic_rendering);
     feature_chain = feature_chain.push_next(&mut requested_features.synchronization2);
     feature_chain = feature_chain.push_next(&mut requested_features.pageable_device_local_memory);
+    feature_chain = feature_chain.push_next(&mut requested_features.present_id);
+    feature_chain = feature_chain.push_next(&mut requested_features.present_wait);
     feature_chain = feature_chain.push_next(&mut requested_features.ray_query);
     feature_chain = feature_chain.push_next(&mut requested_features.ray_tracing);
 
@@ -348,6 +373,12 @@ pub struct EnginePhysicalDeviceFeatures {
     pub pageable_device_local_memory:
         ash::vk::PhysicalDevicePageableDeviceLocalMemoryFeaturesEXT<'static>,
 
+    /// Corresponds to `VkPhysicalDevicePresentIdFeaturesKHR`.
+    pub present_id: ash::vk::PhysicalDevicePresentIdFeaturesKHR<'static>,
+
+    /// Corresponds to `VkPhysicalDevicePresentWaitFeaturesKHR`.
+    pub present_wait: ash::vk::PhysicalDevicePresentWaitFeaturesKHR<'static>,
+
     /// Corresponds to `VkPhysicalDeviceRayQueryFeaturesKHR`.
     pub ray_query: ash::vk::PhysicalDeviceRayQueryFeaturesKHR<'static>,
 
@@ -366,6 +397,8 @@ impl EnginePhysicalDeviceFeatures {
             && (!mask.buffer_device_address() || self.buffer_device_address())
             && (!mask.dynamic_rendering() || self.dynamic_rendering())
             && (!mask.pageable_device_local_memory() || self.pageable_device_local_memory())
+            && (!mask.present_id() || self.present_id())
+            && (!mask.present_wait() || self.present_wait())
             && (!mask.ray_query() || self.ray_query())
             && (!mask.ray_tracing() || self.ray_tracing())
             && (!mask.synchronization2() || self.synchronization2())
@@ -386,6 +419,12 @@ impl EnginePhysicalDeviceFeatures {
             .pageable_device_local_memory
             == ash::vk::TRUE
     }
+    pub fn present_id(&self) -> bool {
+        self.present_id.present_id == ash::vk::TRUE
+    }
+    pub fn present_wait(&self) -> bool {
+        self.present_wait.present_wait == ash::vk::TRUE
+    }
     pub fn ray_query(&self) -> bool {
         self.ray_query.ray_query == ash::vk::TRUE
     }
@@ -396,6 +435,79 @@ impl EnginePhysicalDeviceFeatures {
         self.synchronization2.synchronization2 == ash::vk::TRUE
     }
 
+    /// Enable every enabled (i.e., `true`) feature in the provided mask, keeping the features already enabled.
+    /// # Note
+    /// Like `contains_mask`, this doesn't merge sub-properties of a feature, such as those of `descriptor_indexing`.
+    pub fn enable_mask(&mut self, mask: &EnginePhysicalDeviceFeatures) {
+        if mask.acceleration_structure() {
+            self.acceleration_structure.acceleration_structure = ash::vk::TRUE;
+        }
+        if mask.buffer_device_address() {
+            self.buffer_device_address.buffer_device_address = ash::vk::TRUE;
+        }
+        if mask.dynamic_rendering() {
+            self.dynamic_rendering.dynamic_rendering = ash::vk::TRUE;
+        }
+        if mask.pageable_device_local_memory() {
+            self.pageable_device_local_memory
+                .pageable_device_local_memory = ash::vk::TRUE;
+        }
+        if mask.present_id() {
+            self.present_id.present_id = ash::vk::TRUE;
+        }
+        if mask.present_wait() {
+            self.present_wait.present_wait = ash::vk::TRUE;
+        }
+        if mask.ray_query() {
+            self.ray_query.ray_query = ash::vk::TRUE;
+        }
+        if mask.ray_tracing() {
+            self.ray_tracing.ray_tracing_pipeline = ash::vk::TRUE;
+        }
+        if mask.synchronization2() {
+            self.synchronization2.synchronization2 = ash::vk::TRUE;
+        }
+    }
+
+    /// Build a feature chain from the structures of the enabled features, for enabling them on a logical device.
+    /// # Note
+    /// Features which are only sub-properties, such as `descriptor_indexing`, are not chained.
+    pub fn enabled_feature_chain(&mut self) -> ash::vk::PhysicalDeviceFeatures2<'_> {
+        // Ensure there are no circular references in the feature chain.
+        self.clear_pointers();
+
+        let enabled = *self;
+        let mut feature_chain = ash::vk::PhysicalDeviceFeatures2::default();
+        if enabled.acceleration_structure() {
+            feature_chain = feature_chain.push_next(&mut self.acceleration_structure);
+        }
+        if enabled.buffer_device_address() {
+            feature_chain = feature_chain.push_next(&mut self.buffer_device_address);
+        }
+        if enabled.dynamic_rendering() {
+            feature_chain = feature_chain.push_next(&mut self.dynamic_rendering);
+        }
+        if enabled.pageable_device_local_memory() {
+            feature_chain = feature_chain.push_next(&mut self.pageable_device_local_memory);
+        }
+        if enabled.present_id() {
+            feature_chain = feature_chain.push_next(&mut self.present_id);
+        }
+        if enabled.present_wait() {
+            feature_chain = feature_chain.push_next(&mut self.present_wait);
+        }
+        if enabled.ray_query() {
+            feature_chain = feature_chain.push_next(&mut self.ray_query);
+        }
+        if enabled.ray_tracing() {
+            feature_chain = feature_chain.push_next(&mut self.ray_tracing);
+        }
+        if enabled.synchronization2() {
+            feature_chain = feature_chain.push_next(&mut self.synchronization2);
+        }
+        feature_chain
+    }
+
     /// Clear all feature pointers to `NULL` to avoid circular references.
     pub fn clear_pointers(&mut self) {
         self.acceleration_structure.p_next = std::ptr::null_mut::<c_void>();
@@ -403,6 +515,8 @@ impl EnginePhysicalDeviceFeatures {
         self.descriptor_indexing.p_next = std::ptr::null_mut::<c_void>();
         self.dynamic_rendering.p_next = std::ptr::null_mut::<c_void>();
         self.pageable_device_local_memory.p_next = std::ptr::null_mut::<c_void>();
+        self.present_id.p_next = std::ptr::null_mut::<c_void>();
+        self.present_wait.p_next = std::ptr::null_mut::<c_void>();
         self.ray_query.p_next = std::ptr::null_mut::<c_void>();
         self.ray_tracing.p_next = std::ptr::null_mut::<c_void>();
         self.synchronization2.p_next = std::ptr::null_mut::<c_void>();
@@ -520,26 +634,14 @@ pub fn get_sorted_physical_devices(
     physical_devices
 }
 
-/// Available queue family types and their indices.
-/// Also, a map of queue family indices to the number of queues that may be and are currently allocated.
+/// The queue families of a physical device, and the indices of those which may present to the surface.
+/// Queue requests choose among these families by their capabilities.
 pub struct QueueFamilies {
-    pub graphics: Vec<u32>,
-    pub compute: Vec<u32>,
-    pub transfer: Vec<u32>,
     pub present: Vec<u32>,
     pub queue_families: Vec<ash::vk::QueueFamilyProperties>,
 }
 
-#[derive(strum::EnumCount)]
-#[repr(usize)]
-pub enum QueueType {
-    Graphics,
-    Compute,
-    Present,
-    Transfer,
-}
-
-/// Get the necessary queue family indices for a logical device capable of graphics, compute, and presentation.
+/// Get the queue families of the physical device, and which of them may present to the surface.
 pub fn get_queue_families(
     vulkan: &VulkanCore,
     physical_device: ash::vk::PhysicalDevice,
@@ -555,129 +657,173 @@ pub fn get_queue_families(
     #[cfg(debug_assertions)]
     println!("INFO: Queue families: {queue_families:?}\n");
 
-    // Find the queue families for each desired queue type.
-    // Use an array with indices to allow compile-time guarantees about the number of queue types.
-    let mut type_indices = [const { Vec::<u32>::new() }; QueueType::COUNT];
-    for (family_index, queue_family) in queue_families.iter().enumerate() {
-        // Get as a present queue family.
-        if let Some(khr) = vulkan.khr.as_ref() {
-            if unsafe {
-                khr.get_physical_device_surface_support(
-                    physical_device,
-                    family_index as u32,
-                    surface,
-                )
-            }
-            .expect("Unable to check if 'present' is supported")
-            {
-                type_indices[QueueType::Present as usize].push(family_index as u32);
-            }
-        }
+    // Find the queue families which support presentation to the surface.
+    let present = (0..queue_families.len() as u32)
+        .filter(|&family_index| {
+            vulkan.khr.as_ref().is_some_and(|khr| {
+                unsafe {
+                    khr.get_physical_device_surface_support(physical_device, family_index, surface)
+                }
+                .expect("Unable to check if 'present' is supported")
+            })
+        })
+        .collect();
 
-        // Get as a graphics queue family.
-        if queue_family
-            .queue_flags
-            .contains(ash::vk::QueueFlags::GRAPHICS)
-        {
-            type_indices[QueueType::Graphics as usize].push(family_index as u32);
-        }
+    QueueFamilies {
+        present,
+        queue_families,
+    }
+}
 
-        // Get as a compute queue family.
-        if queue_family
-            .queue_flags
-            .contains(ash::vk::QueueFlags::COMPUTE)
-        {
-            type_indices[QueueType::Compute as usize].push(family_index as u32);
-        }
+/// A request for queues from a queue family with the required capabilities, one queue for each priority.
+pub struct QueueRequest {
+    pub capabilities: ash::vk::QueueFlags,
+    pub prefer_sharing: ash::vk::QueueFlags, // Capabilities the queue family should also have, while avoiding families with any others.
+    pub present: bool, // Whether the queue family must support presentation to the surface.
+    pub priorities: Vec<f32>, // The priority of each queue, from `0.` to `1.`. Higher priority queues may be given more execution time.
+}
+impl QueueRequest {
+    /// Get the index of the queue family that supports the requested capabilities and prefers sharing the queue family with the preferred capabilities, and not sharing with the rest.
+    /// Returns `None` if no queue family supports the requested capabilities.
+    pub fn family_index(&self, queue_families: &QueueFamilies) -> Option<u32> {
+        queue_families
+            .queue_families
+            .iter()
+            .enumerate()
+            .filter_map(|(i, f)| {
+                // Graphics and compute queues always support transfers, even when they do not report it.
+                let mut supported = f.queue_flags;
+                if supported
+                    .intersects(ash::vk::QueueFlags::GRAPHICS | ash::vk::QueueFlags::COMPUTE)
+                {
+                    supported |= ash::vk::QueueFlags::TRANSFER;
+                }
+                if !supported.contains(self.capabilities)
+                    || (self.present && !queue_families.present.contains(&(i as u32)))
+                {
+                    return None;
+                }
 
-        // Get as a transfer queue family.
-        if queue_family
-            .queue_flags
-            .contains(ash::vk::QueueFlags::TRANSFER)
-        {
-            type_indices[QueueType::Transfer as usize].push(family_index as u32);
-        }
+                Some((
+                    i as u32,
+                    (f.queue_flags & self.prefer_sharing)
+                        .as_raw()
+                        .count_ones()
+                        .rotate_left(1) as i32
+                        - (f.queue_flags & !self.prefer_sharing).as_raw().count_ones() as i32,
+                ))
+            })
+            .max_by_key(|(_, score)| *score)
+            .map(|(index, _)| index)
     }
+}
 
-    let graphics = std::mem::take(&mut type_indices[QueueType::Graphics as usize]);
-    let compute = std::mem::take(&mut type_indices[QueueType::Compute as usize]);
-    let transfer = std::mem::take(&mut type_indices[QueueType::Transfer as usize]);
-    let present = std::mem::take(&mut type_indices[QueueType::Present as usize]);
+/// A handle to a queue request, for getting its queues from the `QueueSet` created with the device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueRequestId(usize);
 
-    QueueFamilies {
-        graphics,
-        compute,
-        transfer,
-        present,
-        queue_families,
+/// The queue requests for a new logical device. Queues are handed out in the order they were requested.
+/// A caller which should share the queues of an earlier request uses its `QueueRequestId` instead of pushing a new request.
+#[derive(Default)]
+pub struct QueueRequests {
+    requests: Vec<QueueRequest>,
+}
+impl QueueRequests {
+    /// Add a request for new queues, returning the handle to get them from the `QueueSet`.
+    pub fn push(&mut self, request: QueueRequest) -> QueueRequestId {
+        self.requests.push(request);
+        QueueRequestId(self.requests.len() - 1)
+    }
+
+    /// Get a request that was previously added.
+    pub fn request(&self, id: QueueRequestId) -> &QueueRequest {
+        &self.requests[id.0]
+    }
+}
+
+/// The queues created with a logical device for each queue request.
+pub struct QueueSet {
+    queues: Vec<SmallVec<[IndexedQueue; EXPECTED_MAX_QUEUES_PER_REQUEST]>>,
+}
+impl QueueSet {
+    /// Get the queue created for the priority at `index` of the given request.
+    /// Queues are distinct when the queue family has enough of them, and are otherwise shared with earlier requests.
+    pub fn queue(&self, request: QueueRequestId, index: usize) -> IndexedQueue {
+        self.queues[request.0][index]
     }
 }
 
-/// Create a Vulkan logical device capable of graphics, compute, and presentation queues.
-/// Returns the device and the queue family indices that were requested for use.
+/// Create a Vulkan logical device with the requested queues.
+/// Returns the device and the queues created for each request, in the same order as the requests.
 /// # Safety
 /// The behavior is undefined if the physical device does not support all the requested device extensions or features.
 pub fn new_device(
     vulkan: &VulkanCore,
     physical_device: ash::vk::PhysicalDevice,
-    surface: ash::vk::SurfaceKHR,
+    queue_families: &QueueFamilies,
+    queue_requests: &QueueRequests,
     device_extensions: &[*const i8],
     feature_chain: Option<&mut dyn ash::vk::ExtendsDeviceCreateInfo>,
-) -> (ash::Device, QueueFamilies) {
-    // Get the necessary queue family indices for the logical device.
-    let queue_families = get_queue_families(vulkan, physical_device, surface);
-
-    // Aggregate queue family indices and count the number of queues each family may need allocated.
-    let mut family_map = SmallVec::<[u32; EXPECTED_MAX_QUEUE_FAMILIES]>::from_elem(
-        0,
-        queue_families.queue_families.len(),
-    );
-    let all_family_types = [
-        &queue_families.graphics,
-        &queue_families.compute,
-        &queue_families.present,
-    ];
-    for family in all_family_types {
-        for index in family {
-            family_map[*index as usize] += 1;
-        }
-    }
-
-    // Allocate a vector of queue priorities capable of handling the largest queue count among all families.
-    let priorities = vec![
-        1.;
-        queue_families
-            .queue_families
-            .iter()
-            .fold(0, |acc, f| acc.max(f.queue_count as usize))
-    ];
+) -> (ash::Device, QueueSet) {
+    // The priorities of the queues to create from each family, and the number of queues handed out from each family.
+    let mut family_priorities =
+        SmallVec::<[(Vec<f32>, usize); EXPECTED_MAX_QUEUE_FAMILIES]>::from_elem(
+            (Vec::new(), 0),
+            queue_families.queue_families.len(),
+        );
 
     // Print a message if the queue family map has spilled over to the heap.
     #[cfg(debug_assertions)]
-    if family_map.spilled() {
+    if family_priorities.spilled() {
         println!(
             "INFO: Queue family map has spilled over to the heap. Family count {} greater than inline size {}",
             queue_families.queue_families.len(),
-            family_map.inline_size(),
+            family_priorities.inline_size(),
         );
     }
 
+    // Assign each requested queue an index in its family. Hand out distinct queues until the family has no more, then share them in order.
+    let request_queues = queue_requests
+        .requests
+        .iter()
+        .map(|request| {
+            let family_index = request
+                .family_index(queue_families)
+                .expect("Unable to find a queue family with the requested capabilities");
+            let queue_count = queue_families.queue_families[family_index as usize].queue_count;
+            let (priorities, handed_out) = &mut family_priorities[family_index as usize];
+
+            let indices = request
+                .priorities
+                .iter()
+                .map(|&priority| {
+                    let index = *handed_out % queue_count as usize;
+                    *handed_out += 1;
+                    if index == priorities.len() {
+                        priorities.push(priority);
+                    } else {
+                        #[cfg(debug_assertions)]
+                        println!("INFO: Queue family {family_index} has too few queues, sharing queue {index} with priority {}", priorities[index]);
+                    }
+                    index as u32
+                })
+                .collect::<SmallVec<[_; EXPECTED_MAX_QUEUES_PER_REQUEST]>>();
+            (family_index, indices)
+        })
+        .collect::<Vec<_>>();
+
     // Describe the queue families that will be used with the new logical device.
-    let queue_info = family_map
+    let queue_info = family_priorities
         .iter()
         .enumerate()
-        .filter_map(|(index, &count)| {
-            if count == 0 {
+        .filter_map(|(index, (priorities, _))| {
+            if priorities.is_empty() {
                 return None;
             }
 
-            // Limit to the number of available queues. Guaranteed to be non-zero.
-            let queue_count = count.min(queue_families.queue_families[index].queue_count);
-            let priorities = &priorities[..queue_count as usize];
-
             Some(ash::vk::DeviceQueueCreateInfo {
                 queue_family_index: index as u32,
-                queue_count,
+                queue_count: priorities.len() as u32,
                 p_queue_priorities: priorities.as_ptr(),
                 ..Default::default()
             })
@@ -705,10 +851,20 @@ pub fn new_device(
             .expect("Unable to create logical device")
     };
 
-    (device, queue_families)
+    let queues = request_queues
+        .into_iter()
+        .map(|(family_index, indices)| {
+            indices
+                .into_iter()
+                .map(|index| IndexedQueue::get(&device, family_index, index))
+                .collect()
+        })
+        .collect();
+    (device, QueueSet { queues })
 }
 
 /// A helper type for understanding the context of a queue in Vulkan.
+#[derive(Clone, Copy)]
 pub struct IndexedQueue {
     pub queue: ash::vk::Queue,
     pub family_index: u32,
@@ -726,6 +882,108 @@ impl IndexedQueue {
     }
 }
 
+/// Measure the GPU time of each frame in flight with a pair of timestamp queries around its commands.
+pub struct GpuFrameTimer {
+    query_pool: ash::vk::QueryPool,
+    timestamp_period: f32,
+    timestamp_mask: u64,
+    recorded: Vec<bool>,
+}
+impl GpuFrameTimer {
+    /// Create a timer for the given number of frames in flight, or `None` if the queue family does not support timestamps.
+    /// The timestamp period is the number of nanoseconds per timestamp tick, from the physical device limits.
+    pub fn new(
+        device: &ash::Device,
+        frames_in_flight: usize,
+        timestamp_period: f32,
+        timestamp_valid_bits: u32,
+    ) -> Option<Self> {
+        if timestamp_valid_bits == 0 {
+            return None;
+        }
+
+        let query_pool = unsafe {
+            device.create_query_pool(
+                &ash::vk::QueryPoolCreateInfo::default()
+                    .query_type(ash::vk::QueryType::TIMESTAMP)
+                    .query_count(2 * frames_in_flight as u32),
+                None,
+            )
+        }
+        .expect("Unable to create the timestamp query pool");
+
+        Some(Self {
+            query_pool,
+            timestamp_period,
+            timestamp_mask: u64::MAX >> (64 - timestamp_valid_bits.min(64)),
+            recorded: vec![false; frames_in_flight],
+        })
+    }
+
+    /// Record the start of the frame's commands.
+    /// # Safety
+    /// The command buffer must be in the recording state, and the frame's previous queries must not be in use by the GPU.
+    pub fn begin(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        frame: usize,
+    ) {
+        unsafe {
+            device.cmd_reset_query_pool(command_buffer, self.query_pool, 2 * frame as u32, 2);
+            device.cmd_write_timestamp2(
+                command_buffer,
+                ash::vk::PipelineStageFlags2::TOP_OF_PIPE,
+                self.query_pool,
+                2 * frame as u32,
+            );
+        }
+        self.recorded[frame] = true;
+    }
+
+    /// Record the end of the frame's commands.
+    /// # Safety
+    /// The command buffer must be in the recording state, and `begin` must have been recorded to it for the same frame.
+    pub fn end(&self, device: &ash::Device, command_buffer: ash::vk::CommandBuffer, frame: usize) {
+        unsafe {
+            device.cmd_write_timestamp2(
+                command_buffer,
+                ash::vk::PipelineStageFlags2::BOTTOM_OF_PIPE,
+                self.query_pool,
+                2 * frame as u32 + 1,
+            );
+        }
+    }
+
+    /// Get the GPU time of the last frame recorded for this frame in flight, in milliseconds.
+    /// Returns `None` if the frame has not been recorded yet, or the GPU has not finished it.
+    pub fn frame_time(&self, device: &ash::Device, frame: usize) -> Option<f32> {
+        if !self.recorded[frame] {
+            return None;
+        }
+
+        let mut timestamps = [0u64; 2];
+        unsafe {
+            device.get_query_pool_results(
+                self.query_pool,
+                2 * frame as u32,
+                &mut timestamps,
+                ash::vk::QueryResultFlags::TYPE_64,
+            )
+        }
+        .ok()?;
+        let ticks = timestamps[1].wrapping_sub(timestamps[0]) & self.timestamp_mask;
+        Some(ticks as f32 * self.timestamp_period / 1_000_000.)
+    }
+
+    /// Destroy the query pool.
+    /// # Safety
+    /// The queries must not be in use by the GPU.
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe { device.destroy_query_pool(self.query_pool, None) };
+    }
+}
+
 /// A helper for creating shader modules on a logical device.
 pub fn create_shader_module(device: &ash::Device, code: &[u32]) -> ash::vk::ShaderModule {
     unsafe {
@@ -767,6 +1025,21 @@ pub fn is_stencil_format(format: ash::vk::Format) -> bool {
     )
 }
 
+/// Check if the image format applies the sRGB transfer function when written, and its inverse when read.
+pub fn is_srgb_format(format: ash::vk::Format) -> bool {
+    use ash::vk::Format;
+    matches!(
+        format,
+        Format::R8_SRGB
+            | Format::R8G8_SRGB
+            | Format::R8G8B8_SRGB
+            | Format::B8G8R8_SRGB
+            | Format::R8G8B8A8_SRGB
+            | Format::B8G8R8A8_SRGB
+            | Format::A8B8G8R8_SRGB_PACK32
+    )
+}
+
 /// Helper for creating a new image allocated by a `gpu_allocator::vulkan::Allocator`.
 pub fn create_image(
     device: &ash::Device,
@@ -832,22 +1105,57 @@ pub fn create_image_view(
     }
 }
 
+/// The range of brightness a swapchain should be able to present.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DynamicRange {
+    /// Present in sRGB, where the brightest value is the display's SDR white.
+    #[default]
+    Standard,
+
+    /// Prefer a color space that can present highlights brighter than SDR white, if the surface supports one.
+    High,
+}
+
+/// The surface formats able to present HDR content, in order of preference.
+const HDR_SURFACE_FORMATS: [ash::vk::SurfaceFormatKHR; 3] = [
+    ash::vk::SurfaceFormatKHR {
+        format: ash::vk::Format::A2B10G10R10_UNORM_PACK32,
+        color_space: ash::vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+    },
+    ash::vk::SurfaceFormatKHR {
+        format: ash::vk::Format::A2R10G10B10_UNORM_PACK32,
+        color_space: ash::vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+    },
+    ash::vk::SurfaceFormatKHR {
+        format: ash::vk::Format::R16G16B16A16_SFLOAT,
+        color_space: ash::vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+    },
+];
+
 /// Preferences for the image and behavior used with a new swapchain.
 #[derive(Clone, Copy, Default)]
 pub struct SwapchainPreferences {
+    /// An explicit format and color space take precedence over the dynamic range.
     pub format: Option<ash::vk::Format>,
     pub color_space: Option<ash::vk::ColorSpaceKHR>,
+    pub dynamic_range: DynamicRange,
     pub present_mode: Option<ash::vk::PresentModeKHR>,
     pub color_samples: Option<ash::vk::SampleCountFlags>,
 
+    /// The format of the multisampled images, if different from the swapchain images they will be resolved to.
+    pub multisample_format: Option<ash::vk::Format>,
+
     /// The preferred extent to use if and only if the surface wants the caller to specify an extent to use.
     pub preferred_extent: Option<ash::vk::Extent2D>,
+
+    /// The number of frames the CPU may record ahead of the GPU, independent of the number of swapchain images. Clamped between one and `EXPECTED_MAX_FRAMES_IN_FLIGHT`.
+    /// Fewer frames reduce latency, and more frames improve throughput. Per-frame resources of the caller may be sized from the first swapchain, so this should not change between recreations.
+    pub frames_in_flight: Option<usize>,
 }
 
 /// Synchronization objects for a frame in flight.
 pub struct FrameInFlightSync {
     pub image_available: ash::vk::Semaphore,
-    pub image_rendered: ash::vk::Semaphore,
     pub present_complete: ash::vk::Fence,
 }
 
@@ -864,9 +1172,11 @@ pub struct Swapchain {
     handle: ash::vk::SwapchainKHR,
     image_views: Vec<ash::vk::ImageView>,
     images: Vec<ash::vk::Image>,
+    image_rendered: Vec<ash::vk::Semaphore>,
     format: ash::vk::Format,
     color_space: ash::vk::ColorSpaceKHR,
     present_mode: ash::vk::PresentModeKHR,
+    compatible_present_modes: SmallVec<[ash::vk::PresentModeKHR; EXPECTED_MAX_PRESENT_MODES]>,
     extent: ash::vk::Extent2D,
     frame_syncs: SmallVec<[FrameInFlightSync; EXPECTED_MAX_FRAMES_IN_FLIGHT]>,
     current_frame: usize,
@@ -924,6 +1234,23 @@ impl Swapchain {
             preferences.present_mode,
         );
 
+        // With `VK_EXT_swapchain_maintenance1`, the swapchain may switch between every present mode compatible with the chosen one without being recreated.
+        // The image count must then be valid for each of those present modes.
+        let (compatible_present_modes, image_count) = if enabled_swapchain_maintenance1 {
+            let (modes, min_image_count, max_image_count) = Self::query_compatible_present_modes(
+                vulkan,
+                physical_device,
+                surface,
+                present_mode,
+            );
+            (
+                modes,
+                image_count.clamp(min_image_count, max_image_count.max(min_image_count)),
+            )
+        } else {
+            (SmallVec::from_slice(&[present_mode]), image_count)
+        };
+
         // Determine the image format that is supported and compare it to what is preferred.
         let supported_formats = unsafe {
             khr.get_physical_device_surface_formats(physical_device, surface)
@@ -933,30 +1260,10 @@ impl Swapchain {
         #[cfg(debug_assertions)]
         println!("INFO: Supported surface formats: {supported_formats:?}\n");
 
-        let &ash::vk::SurfaceFormatKHR {
+        let ash::vk::SurfaceFormatKHR {
             format: image_format,
             color_space: image_color_space,
-        } = {
-            let lazy_is_color = |f| !is_depth_format(f) && !is_stencil_format(f);
-            let color_space = preferences
-                .color_space
-                .unwrap_or(ash::vk::ColorSpaceKHR::SRGB_NONLINEAR);
-            if let Some(format) = supported_formats.iter().find(|f| {
-                preferences
-                    .format
-                    .map_or_else(|| lazy_is_color(f.format), |fmt| f.format == fmt)
-                    && f.color_space == color_space
-            }) {
-                // Use the preferred format if it is supported.
-                format
-            } else {
-                // Otherwise, use the first supported format that is neither a depth nor a stencil format.
-                supported_formats
-                    .iter()
-                    .find(|f| lazy_is_color(f.format))
-                    .expect("Unable to find a suitable image format")
-            }
-        };
+        } = Self::choose_surface_format(&supported_formats, &preferences);
 
         // Prefer the post-multiplied alpha composite alpha mode if available to allow blending when supported.
         let composite_alpha = if surface_capabilities
@@ -1002,13 +1309,12 @@ impl Swapchain {
         #[cfg(debug_assertions)]
         println!("INFO: Swapchain extent: {extent:?}\n");
 
-        // Optionally provide additional present modes if the
+        // Optionally provide the additional present modes the swapchain may switch to when presenting, if the device supports it.
         let mut present_modes_ext = if enabled_swapchain_maintenance1 {
-            Some(ash::vk::SwapchainPresentModesCreateInfoEXT {
-                present_mode_count: 1,
-                p_present_modes: &present_mode,
-                ..Default::default()
-            })
+            Some(
+                ash::vk::SwapchainPresentModesCreateInfoEXT::default()
+                    .present_modes(&compatible_present_modes),
+            )
         } else {
             None
         };
@@ -1052,13 +1358,14 @@ impl Swapchain {
         };
 
         // Determine if the caller is trying to use multiple color samples, and if it is supported.
+        let multisample_format = preferences.multisample_format.unwrap_or(image_format);
         let multisample = if let Some(multisample_image_create) = query_multisample_support(
             vulkan,
             physical_device,
             preferences
                 .color_samples
                 .unwrap_or(ash::vk::SampleCountFlags::TYPE_1),
-            image_format,
+            multisample_format,
             extent,
             1,
             ash::vk::ImageUsageFlags::TRANSIENT_ATTACHMENT
@@ -1077,7 +1384,7 @@ impl Swapchain {
                 .collect();
             let image_views = multisample_images
                 .iter()
-                .map(|(i, _)| create_image_view(logical_device, *i, image_format, 1))
+                .map(|(i, _)| create_image_view(logical_device, *i, multisample_format, 1))
                 .collect();
 
             Some(MultiSampleAntiAliasing {
@@ -1096,19 +1403,26 @@ impl Swapchain {
             .collect();
 
         // Create synchronization objects. Semaphores synchronize between different operations on the GPU; fences synchronize operations between the CPU and GPU.
-        // We will have a frame in flight for each image in the swapchain, and at least two so that a new command can be recorded while another is read.
-        let frames_in_flight = image_count.max(2) as usize;
+        // The number of frames in flight is independent of the number of images, so the semaphore waited on by presentation belongs to each image.
+        // Otherwise, a frame could signal a semaphore which a previous presentation of a different image is still waiting on.
+        let image_rendered = swapchain_images
+            .iter()
+            .map(|_| unsafe {
+                logical_device
+                    .create_semaphore(&ash::vk::SemaphoreCreateInfo::default(), None)
+                    .expect("Unable to create render finished semaphore")
+            })
+            .collect();
+        let frames_in_flight = preferences
+            .frames_in_flight
+            .unwrap_or(DEFAULT_FRAMES_IN_FLIGHT)
+            .clamp(1, EXPECTED_MAX_FRAMES_IN_FLIGHT);
         let frame_syncs = std::iter::repeat_with(|| {
             let image_available = unsafe {
                 logical_device
                     .create_semaphore(&ash::vk::SemaphoreCreateInfo::default(), None)
                     .expect("Unable to create image available semaphore")
             };
-            let image_rendered = unsafe {
-                logical_device
-                    .create_semaphore(&ash::vk::SemaphoreCreateInfo::default(), None)
-                    .expect("Unable to create render finished semaphore")
-            };
             let present_complete = unsafe {
                 logical_device
                     .create_fence(
@@ -1122,7 +1436,6 @@ impl Swapchain {
             };
             FrameInFlightSync {
                 image_available,
-                image_rendered,
                 present_complete,
             }
         })
@@ -1130,16 +1443,18 @@ impl Swapchain {
         .collect();
 
         #[cfg(debug_assertions)]
-        println!("INFO: New Swapchain: Present mode: {image_count} * {present_mode:?}: Format {image_format:?} in {image_color_space:?}\n");
+        println!("INFO: New Swapchain: Present mode: {image_count} * {present_mode:?}: Frames in flight: {frames_in_flight}: Format {image_format:?} in {image_color_space:?}\n");
 
         Self {
             swapchain_device,
             handle: swapchain,
             image_views: swapchain_views,
             images: swapchain_images,
+            image_rendered,
             format: image_format,
             color_space: image_color_space,
             present_mode,
+            compatible_present_modes,
             extent,
             frame_syncs,
             current_frame: 0,
@@ -1162,9 +1477,11 @@ impl Swapchain {
             // Destroy the synchronization objects.
             for sync in self.frame_syncs {
                 logical_device.destroy_semaphore(sync.image_available, None);
-                logical_device.destroy_semaphore(sync.image_rendered, None);
                 logical_device.destroy_fence(sync.present_complete, None);
             }
+            for semaphore in self.image_rendered {
+                logical_device.destroy_semaphore(semaphore, None);
+            }
 
             // Destroy the image views and images.
             // NOTE: Do not directly destroy the images managed by the swapchain internally (i.e., the presentation images).
@@ -1186,6 +1503,111 @@ impl Swapchain {
         }
     }
 
+    /// Queue the swapchain and its associated resources for destruction once the frames in flight which presented to it have completed.
+    /// Used when the swapchain has been replaced without `VK_EXT_swapchain_maintenance1`, so there are no presentation fences to wait on.
+    pub fn retire(self, deletion_queue: &mut deletion_queue::DeletionQueue) {
+        // The image views and multisampled images are destroyed before the swapchain which owns the presentation images.
+        for image_view in self.image_views {
+            deletion_queue.push(deletion_queue::DeferredDestroy::ImageView(image_view));
+        }
+        if let Some(multisample) = self.multisample {
+            for image_view in multisample.image_views {
+                deletion_queue.push(deletion_queue::DeferredDestroy::ImageView(image_view));
+            }
+            for (image, allocation) in multisample.images {
+                deletion_queue.push(deletion_queue::DeferredDestroy::Image(image, allocation));
+            }
+        }
+
+        let (swapchain_device, handle, frame_syncs, image_rendered) = (
+            self.swapchain_device,
+            self.handle,
+            self.frame_syncs,
+            self.image_rendered,
+        );
+        deletion_queue.push(deletion_queue::DeferredDestroy::Other(Box::new(
+            move |logical_device, _| unsafe {
+                for sync in frame_syncs {
+                    logical_device.destroy_semaphore(sync.image_available, None);
+                    logical_device.destroy_fence(sync.present_complete, None);
+                }
+                for semaphore in image_rendered {
+                    logical_device.destroy_semaphore(semaphore, None);
+                }
+                swapchain_device.destroy_swapchain(handle, None);
+            },
+        )));
+    }
+
+    /// Helper to choose a supported surface format and color space.
+    /// An explicit preference for either is tried first, then a color space matching the preferred dynamic range.
+    /// Falls back to the first supported color format in the non-linear sRGB color space.
+    fn choose_surface_format(
+        supported_formats: &[ash::vk::SurfaceFormatKHR],
+        preferences: &SwapchainPreferences,
+    ) -> ash::vk::SurfaceFormatKHR {
+        let lazy_is_color = |f| !is_depth_format(f) && !is_stencil_format(f);
+
+        // Use the explicitly preferred format and color space if they are supported.
+        if preferences.format.is_some() || preferences.color_space.is_some() {
+            let color_space = preferences
+                .color_space
+                .unwrap_or(ash::vk::ColorSpaceKHR::SRGB_NONLINEAR);
+            if let Some(&format) = supported_formats.iter().find(|f| {
+                preferences
+                    .format
+                    .map_or_else(|| lazy_is_color(f.format), |fmt| f.format == fmt)
+                    && f.color_space == color_space
+            }) {
+                return format;
+            }
+        }
+
+        // Try each of the HDR formats in order of preference.
+        if preferences.dynamic_range == DynamicRange::High {
+            if let Some(&format) = HDR_SURFACE_FORMATS
+                .iter()
+                .find(|hdr| supported_formats.contains(hdr))
+            {
+                return format;
+            }
+            println!("WARN: No HDR surface format is supported, falling back to SDR");
+        }
+
+        // Otherwise, prefer an `_SRGB` format in the sRGB color space so the hardware encodes the output, regardless of the order the driver lists formats in.
+        // Then fall back to any color format in the sRGB color space, or any color format at all.
+        let is_srgb_color_space = |f: &&ash::vk::SurfaceFormatKHR| {
+            f.color_space == ash::vk::ColorSpaceKHR::SRGB_NONLINEAR
+        };
+        *supported_formats
+            .iter()
+            .filter(is_srgb_color_space)
+            .find(|f| is_srgb_format(f.format))
+            .or_else(|| {
+                supported_formats
+                    .iter()
+                    .filter(is_srgb_color_space)
+                    .find(|f| lazy_is_color(f.format))
+            })
+            .or_else(|| supported_formats.iter().find(|f| lazy_is_color(f.format)))
+            .expect("Unable to find a suitable image format")
+    }
+
+    /// Describe the HDR content presented to the swapchain, so the display can map it to its own capabilities.
+    /// This is only present with device extension `VK_EXT_hdr_metadata`.
+    pub fn set_hdr_metadata(
+        &self,
+        hdr_metadata_device: &ash::ext::hdr_metadata::Device,
+        metadata: &ash::vk::HdrMetadataEXT,
+    ) {
+        unsafe {
+            hdr_metadata_device.set_hdr_metadata(
+                std::slice::from_ref(&self.handle),
+                std::slice::from_ref(metadata),
+            );
+        }
+    }
+
     /// Helper to attempt to usee the preferred present mode, but falls back to the default of `FIFO` which is always supported.
     /// Also, tries to use the most reasonable and valid image count for whichever present mode is determined.
     /// # Panics
@@ -1248,26 +1670,17 @@ impl Swapchain {
             .unwrap_or((ash::vk::PresentModeKHR::FIFO, 3));
 
         if vulkan.enabled_instance_extension(ash::ext::surface_maintenance1::NAME) {
-            let mut present_mode_ext =
-                ash::vk::SurfacePresentModeEXT::default().present_mode(present_mode);
-            let surface_info = ash::vk::PhysicalDeviceSurfaceInfo2KHR::default()
-                .surface(surface)
-                .push_next(&mut present_mode_ext);
-            let mut surface_capabilities = ash::vk::SurfaceCapabilities2KHR::default();
-            unsafe {
-                ash::khr::get_surface_capabilities2::Instance::new(&vulkan.api, &vulkan.instance)
-                    .get_physical_device_surface_capabilities2(
-                        physical_device,
-                        &surface_info,
-                        &mut surface_capabilities,
-                    )
-            }
-            .expect("Unable to get extended surface capabilities(2)");
+            let surface_capabilities = Self::present_mode_capabilities(
+                vulkan,
+                physical_device,
+                surface,
+                present_mode,
+                None,
+            );
 
-            let present_max =
-                NonZeroU32::new(surface_capabilities.surface_capabilities.max_image_count);
+            let present_max = NonZeroU32::new(surface_capabilities.max_image_count);
             image_count = image_count.clamp(
-                surface_capabilities.surface_capabilities.min_image_count,
+                surface_capabilities.min_image_count,
                 present_max.map_or(u32::MAX, NonZeroU32::get),
             );
         } else {
@@ -1280,11 +1693,107 @@ impl Swapchain {
         (present_mode, image_count)
     }
 
+    /// Helper to get the surface capabilities specific to a present mode, and optionally the present modes compatible with it.
+    /// Requires instance extension `VK_EXT_surface_maintenance1`.
+    fn present_mode_capabilities(
+        vulkan: &VulkanCore,
+        physical_device: ash::vk::PhysicalDevice,
+        surface: ash::vk::SurfaceKHR,
+        present_mode: ash::vk::PresentModeKHR,
+        compatibility: Option<&mut ash::vk::SurfacePresentModeCompatibilityEXT>,
+    ) -> ash::vk::SurfaceCapabilitiesKHR {
+        let mut present_mode_ext =
+            ash::vk::SurfacePresentModeEXT::default().present_mode(present_mode);
+        let surface_info = ash::vk::PhysicalDeviceSurfaceInfo2KHR::default()
+            .surface(surface)
+            .push_next(&mut present_mode_ext);
+        let mut surface_capabilities = ash::vk::SurfaceCapabilities2KHR::default();
+        if let Some(compatibility) = compatibility {
+            surface_capabilities = surface_capabilities.push_next(compatibility);
+        }
+        unsafe {
+            ash::khr::get_surface_capabilities2::Instance::new(&vulkan.api, &vulkan.instance)
+                .get_physical_device_surface_capabilities2(
+                    physical_device,
+                    &surface_info,
+                    &mut surface_capabilities,
+                )
+        }
+        .expect("Unable to get extended surface capabilities(2)");
+
+        surface_capabilities.surface_capabilities
+    }
+
+    /// Helper to query the present modes a swapchain using `present_mode` can switch between without being recreated.
+    /// Also returns the range of image counts which is valid for all of the compatible present modes.
+    /// Requires instance extension `VK_EXT_surface_maintenance1`.
+    fn query_compatible_present_modes(
+        vulkan: &VulkanCore,
+        physical_device: ash::vk::PhysicalDevice,
+        surface: ash::vk::SurfaceKHR,
+        present_mode: ash::vk::PresentModeKHR,
+    ) -> (
+        SmallVec<[ash::vk::PresentModeKHR; EXPECTED_MAX_PRESENT_MODES]>,
+        u32,
+        u32,
+    ) {
+        // Query the number of compatible present modes, then fill them in.
+        let mut compatibility = ash::vk::SurfacePresentModeCompatibilityEXT::default();
+        let _ = Self::present_mode_capabilities(
+            vulkan,
+            physical_device,
+            surface,
+            present_mode,
+            Some(&mut compatibility),
+        );
+        let mut modes: SmallVec<[ash::vk::PresentModeKHR; EXPECTED_MAX_PRESENT_MODES]> =
+            SmallVec::from_elem(
+                ash::vk::PresentModeKHR::default(),
+                compatibility.present_mode_count as usize,
+            );
+        let mut compatibility =
+            ash::vk::SurfacePresentModeCompatibilityEXT::default().present_modes(&mut modes);
+        let capabilities = Self::present_mode_capabilities(
+            vulkan,
+            physical_device,
+            surface,
+            present_mode,
+            Some(&mut compatibility),
+        );
+        let compatible_count = compatibility.present_mode_count as usize;
+        modes.truncate(compatible_count);
+
+        // The chosen present mode is always compatible with itself, but be defensive against drivers omitting it.
+        if !modes.contains(&present_mode) {
+            modes.push(present_mode);
+        }
+
+        #[cfg(debug_assertions)]
+        println!("INFO: Present modes compatible with {present_mode:?}: {modes:?}\n");
+
+        // Find the tightest image count bounds shared by all compatible present modes.
+        let mut min_image_count = capabilities.min_image_count;
+        let mut max_image_count =
+            NonZeroU32::new(capabilities.max_image_count).map_or(u32::MAX, NonZeroU32::get);
+        for &mode in modes.iter().filter(|&&mode| mode != present_mode) {
+            let capabilities =
+                Self::present_mode_capabilities(vulkan, physical_device, surface, mode, None);
+            min_image_count = min_image_count.max(capabilities.min_image_count);
+            if let Some(max) = NonZeroU32::new(capabilities.max_image_count) {
+                max_image_count = max_image_count.min(max.get());
+            }
+        }
+
+        (modes, min_image_count, max_image_count)
+    }
+
     /// Recreate the swapchain using the existing one.
     /// This is useful when the window is resized, or the window is moved to a different monitor.
+    /// Returns the old swapchain if it may still be in use, which must be destroyed once every frame in flight submitted before the recreation has completed.
     /// # Notes
-    /// * This function will wait for the logical device to finish its operations on the swapchain before recreating it.
+    /// * With `VK_EXT_swapchain_maintenance1`, this function will wait for the old swapchain to finish presenting and destroy it.
     /// * The framebuffers will be destroyed and must be recreated by the caller.
+    #[must_use]
     pub fn recreate_swapchain(
         &mut self,
         vulkan: &VulkanCore,
@@ -1293,7 +1802,7 @@ impl Swapchain {
         surface: ash::vk::SurfaceKHR,
         memory_allocator: &mut gpu_allocator::vulkan::Allocator,
         preferences: SwapchainPreferences,
-    ) {
+    ) -> Option<Self> {
         let mut stack_var_swapchain = Self::new(
             vulkan,
             physical_device,
@@ -1309,33 +1818,32 @@ impl Swapchain {
         std::mem::swap(self, &mut stack_var_swapchain);
         let old_swapchain = stack_var_swapchain; // Variable rename for clarity.
 
+        // Without presentation fences, there is no way to know when the old swapchain is no longer in use.
+        // Let the caller defer its destruction until the frames which used it have completed, rather than waiting for the device to be idle.
+        if !self.enabled_swapchain_maintenance1 {
+            return Some(old_swapchain);
+        }
+
+        // Wait for the old swapchain to complete its presentation fences.
+        let presentation_fences: SmallVec<[ash::vk::Fence; EXPECTED_MAX_FRAMES_IN_FLIGHT]> =
+            old_swapchain
+                .frame_syncs
+                .iter()
+                .map(|s| s.present_complete)
+                .collect();
         unsafe {
-            if self.enabled_swapchain_maintenance1 {
-                // Wait for the old swapchain to complete its presentation fences.
-                let presentation_fences: SmallVec<[ash::vk::Fence; EXPECTED_MAX_FRAMES_IN_FLIGHT]> =
-                    old_swapchain
-                        .frame_syncs
-                        .iter()
-                        .map(|s| s.present_complete)
-                        .collect();
-                logical_device
-                    .wait_for_fences(
-                        presentation_fences.as_slice(),
-                        true,
-                        FIVE_SECONDS_IN_NANOSECONDS,
-                    )
-                    .expect("Unable to wait for the logical device to finish its operations");
-            } else {
-                // Wait for the logical device to finish its operations on the swapchain.
-                // This is not particularly optimal.
-                logical_device
-                    .device_wait_idle()
-                    .expect("Unable to wait for the logical device to finish its operations");
-            }
+            logical_device
+                .wait_for_fences(
+                    presentation_fences.as_slice(),
+                    true,
+                    FIVE_SECONDS_IN_NANOSECONDS,
+                )
+                .expect("Unable to wait for the logical device to finish its operations");
         }
 
         // Destroy the old swapchain and its associated resources.
         old_swapchain.destroy(logical_device, memory_allocator);
+        None
     }
 
     /// Acquire the next image in the swapchain. Maintain the index of the acquired image.
@@ -1361,43 +1869,54 @@ impl Swapchain {
     }
 
     /// Present the next image in the swapchain. Return whether the swapchain is suboptimal for the surface on success.
+    /// The present identifier is only used with device extension `VK_KHR_present_id`, and must increase with each presentation to this swapchain.
     pub fn present(
         &mut self,
         present_queue: ash::vk::Queue,
         use_present_fence: bool,
+        present_id: Option<u64>,
     ) -> ash::prelude::VkResult<bool> {
         let acquired_index = self
             .acquired_index
             .take()
             .expect("No image has been acquired by the swapchain before presenting");
 
+        let wait_semaphores = [self.image_rendered[acquired_index as usize]];
+        let swapchains = [self.handle];
+        let image_indices = [acquired_index]; // Needs to have the same number of entries as `swapchains`, i.e. 1.
+        let mut present_info = ash::vk::PresentInfoKHR::default()
+            .wait_semaphores(&wait_semaphores)
+            .swapchains(&swapchains)
+            .image_indices(&image_indices);
+
         // Optionally, use a present fence to signal completion of the presentation operation.
         // This is only present with device extension `VK_EXT_swapchain_maintenance1`.
-        let fence_info = if use_present_fence {
-            Some(ash::vk::SwapchainPresentFenceInfoEXT {
-                swapchain_count: 1,
-                p_fences: &self.frame_syncs[self.current_frame].present_complete,
-                ..Default::default()
-            })
-        } else {
-            None
-        };
+        let present_fences = [self.frame_syncs[self.current_frame].present_complete];
+        let mut fence_info =
+            ash::vk::SwapchainPresentFenceInfoEXT::default().fences(&present_fences);
+        if use_present_fence {
+            present_info = present_info.push_next(&mut fence_info);
+        }
+
+        // Specify the present mode for this presentation, which may differ from the one the swapchain was created with.
+        // This is also only present with device extension `VK_EXT_swapchain_maintenance1`.
+        let present_modes = [self.present_mode];
+        let mut present_mode_info =
+            ash::vk::SwapchainPresentModeInfoEXT::default().present_modes(&present_modes);
+        if self.enabled_swapchain_maintenance1 {
+            present_info = present_info.push_next(&mut present_mode_info);
+        }
+
+        // Optionally, identify this presentation so that its completion can be waited on.
+        let present_ids = [present_id.unwrap_or_default()];
+        let mut present_id_info = ash::vk::PresentIdKHR::default().present_ids(&present_ids);
+        if present_id.is_some() {
+            present_info = present_info.push_next(&mut present_id_info);
+        }
 
         let result = unsafe {
-            self.swapchain_device.queue_present(
-                present_queue,
-                &ash::vk::PresentInfoKHR {
-                    p_next: fence_info
-                        .as_ref()
-                        .map_or(std::ptr::null(), |f| std::ptr::from_ref(f).cast()),
-                    wait_semaphore_count: 1,
-                    p_wait_semaphores: &self.image_rendered(),
-                    swapchain_count: 1,
-                    p_swapchains: &self.handle,
-                    p_image_indices: &acquired_index, // Needs to have the same number of entries as `swapchain_count`, i.e. 1.
-                    ..Default::default()
-                },
-            )
+            self.swapchain_device
+                .queue_present(present_queue, &present_info)
         };
 
         // Advance the current frame index to the next after successfully submitting the presentation command.
@@ -1406,7 +1925,21 @@ impl Swapchain {
         result
     }
 
+    /// Switch the present mode used by subsequent presentations without recreating the swapchain.
+    /// Returns `false` if the present mode is not compatible with this swapchain, in which case the swapchain must be recreated to use it.
+    pub fn set_present_mode(&mut self, present_mode: ash::vk::PresentModeKHR) -> bool {
+        if !self.compatible_present_modes.contains(&present_mode) {
+            return false;
+        }
+        self.present_mode = present_mode;
+        true
+    }
+
     // Swapchain getters.
+    /// Whether the swapchain format applies the sRGB transfer function when written, so passes must write linear values.
+    pub fn encodes_srgb(&self) -> bool {
+        is_srgb_format(self.format)
+    }
     pub fn current_frame(&self) -> usize {
         self.current_frame
     }
@@ -1419,14 +1952,17 @@ impl Swapchain {
     pub fn frame_syncs(&self) -> &[FrameInFlightSync] {
         &self.frame_syncs
     }
+    pub fn handle(&self) -> ash::vk::SwapchainKHR {
+        self.handle
+    }
     pub fn image_available(&self) -> ash::vk::Semaphore {
         self.frame_syncs[self.current_frame].image_available
     }
-    pub fn image_format(&self) -> ash::vk::Format {
-        self.format
-    }
     pub fn image_rendered(&self) -> ash::vk::Semaphore {
-        self.frame_syncs[self.current_frame].image_rendered
+        let acquired_index = self
+            .acquired_index
+            .expect("No image has been acquired by the swapchain");
+        self.image_rendered[acquired_index as usize]
     }
     pub fn image_views(&self) -> &[ash::vk::ImageView] {
         &self.image_views
@@ -1446,6 +1982,12 @@ impl Swapchain {
     pub fn present_mode(&self) -> ash::vk::PresentModeKHR {
         self.present_mode
     }
+    pub fn surface_format(&self) -> ash::vk::SurfaceFormatKHR {
+        ash::vk::SurfaceFormatKHR {
+            format: self.format,
+            color_space: self.color_space,
+        }
+    }
 }
 
 /// Query the physical device for the supported sample count for color images.
@@ -1537,46 +2079,16 @@ pub fn query_multisample_support(
     }
 }
 
-/// A helper to get a queue family index that supports the required queue flags and prefers sharing
-/// the queue family with the specified flags, and not sharing with the rest.
-pub fn get_queue_family_index(
-    required_capabilities: ash::vk::QueueFlags,
-    prefer_sharing_capabilities: ash::vk::QueueFlags,
-    queue_families: &QueueFamilies,
-) -> u32 {
-    queue_families
-        .queue_families
-        .iter()
-        .enumerate()
-        .filter_map(|(i, f)| {
-            if f.queue_flags.contains(required_capabilities) {
-                Some((
-                    i as u32,
-                    (f.queue_flags & prefer_sharing_capabilities)
-                        .as_raw()
-                        .count_ones()
-                        .rotate_left(1) as i32
-                        - (f.queue_flags & !prefer_sharing_capabilities)
-                            .as_raw()
-                            .count_ones() as i32,
-                ))
-            } else {
-                None
-            }
-        })
-        .max_by_key(|(_, score)| *score)
-        .expect("Unable to find a queue family with the required flags")
-        .0
-}
-
 /// Create a new device-local buffer with the given data using a staging buffer.
+/// The data is copied with the transfer queue before this returns, and the buffer is owned by the queue family of `owner_queue`.
 pub fn new_device_buffer(
     device: &ash::Device,
     allocator: &mut gpu_allocator::vulkan::Allocator,
-    command_pool: ash::vk::CommandPool,
-    queue: ash::vk::Queue,
+    transfer_queue: &transfer::TransferQueue,
+    owner_queue: &IndexedQueue,
+    owner_command_pool: ash::vk::CommandPool,
     data: &[u8],
-) -> Result<(ash::vk::Buffer, ash::vk::Fence), ash::vk::Result> {
+) -> Result<(ash::vk::Buffer, gpu_allocator::vulkan::Allocation), ash::vk::Result> {
     // Create a staging buffer to copy the data to the device-local buffer.
     let staging_buffer = unsafe {
         device.create_buffer(
@@ -1651,27 +2163,31 @@ pub fn new_device_buffer(
         )?;
     }
 
-    // Copy the data from the staging buffer to the device-local buffer.
-    let command_buffer = unsafe {
-        device.allocate_command_buffers(&ash::vk::CommandBufferAllocateInfo {
-            command_pool,
-            level: ash::vk::CommandBufferLevel::PRIMARY,
-            command_buffer_count: 1,
-            ..Default::default()
-        })?
-    }[0];
-    unsafe {
-        device.cmd_copy_buffer(
-            command_buffer,
-            staging_buffer,
-            device_buffer,
-            &[ash::vk::BufferCopy {
-                src_offset: 0,
-                dst_offset: 0,
-                size: data.len() as u64,
-            }],
-        );
-    }
+    // Copy the data from the staging buffer to the device-local buffer, returning it to the owner queue family afterwards.
+    transfer_queue.submit(
+        device,
+        owner_queue,
+        owner_command_pool,
+        &[device_buffer],
+        |command_buffer| unsafe {
+            device.cmd_copy_buffer(
+                command_buffer,
+                staging_buffer,
+                device_buffer,
+                &[ash::vk::BufferCopy {
+                    src_offset: 0,
+                    dst_offset: 0,
+                    size: data.len() as u64,
+                }],
+            );
+        },
+    );
+
+    // The copy has completed, so the staging buffer can be destroyed immediately.
+    unsafe { device.destroy_buffer(staging_buffer, None) };
+    allocator
+        .free(staging_allocation)
+        .expect("Unable to free the staging buffer allocation");
 
-    todo!()
+    Ok((device_buffer, device_allocation))
 }
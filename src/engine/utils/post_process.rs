@@ -0,0 +1,513 @@
+use super::{
+    bloom_pass::{BloomPass, BloomSettings},
+    fxaa_pass::{self, FxaaPass},
+    smaa_pass::SmaaPass,
+    taa_pass::TaaPass,
+    tonemap_pass::{OutputEncoding, TonemapPass},
+    upscale_pass::{UpscalePass, UpscaleSettings},
+};
+
+/// The format demos render the scene to, and the chain processes it in, before it is tonemapped for the swapchain.
+/// Floating point values allow the scene to contain brightness beyond SDR white.
+pub const SCENE_FORMAT: ash::vk::Format = ash::vk::Format::R16G16B16A16_SFLOAT;
+
+/// The post-processing effects which can be added to a chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostProcessEffect {
+    Fxaa,
+    Smaa,
+    Taa,
+    Bloom,
+}
+
+/// The configuration of a post-processing chain, kept by its owner so that an equivalent chain can be built for a new swapchain.
+#[derive(Clone, Debug)]
+pub struct PostProcessSettings {
+    pub effects: Vec<PostProcessEffect>,
+    pub fxaa_constants: fxaa_pass::SpecializationConstants,
+    pub upscale_settings: UpscaleSettings,
+    pub split_screen: bool,
+    pub bloom_settings: BloomSettings,
+}
+
+/// The sub-pixel offset a demo applies when rendering a frame, for passes which accumulate frames over time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Jitter {
+    /// The offset in pixels.
+    pub offset: [f32; 2],
+
+    /// The fraction of the image width, from the left, which is rendered with the offset.
+    /// The rest is rendered without it, such as the unprocessed half of a split-screen comparison.
+    pub split_position: f32,
+}
+
+/// The sizes a pass works with in a frame.
+/// The images of a chain are allocated at the swapchain resolution, and the scene fills only the top-left render area of them when rendered below it.
+/// Texels outside the render area keep stale contents, which passes sampling past its edge may pick up along the border of the scene.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameExtent {
+    /// The size of the images the chain processes, which is the size of the swapchain.
+    pub image: ash::vk::Extent2D,
+
+    /// The size of the area of the images the scene is rendered to this frame.
+    pub render: ash::vk::Extent2D,
+}
+
+impl FrameExtent {
+    /// The reciprocal of the width and height of the images, for converting pixel coordinates to texture coordinates.
+    pub fn inverse_image_size(&self) -> [f32; 2] {
+        [1. / self.image.width as f32, 1. / self.image.height as f32]
+    }
+
+    /// The fraction of the image width, from the left, where a pass is applied: half of the render area for a split-screen comparison, or all of it.
+    pub fn split_position(&self, split_screen: bool) -> f32 {
+        if split_screen {
+            0.5 * self.render.width as f32 / self.image.width as f32
+        } else {
+            1.
+        }
+    }
+}
+
+/// A render pass which reads the output of the previous render and writes a processed image to its destination.
+pub trait PostProcessPass {
+    /// Record the commands necessary to process the input image and write the result to the destination image.
+    /// Passes before the upscale pass only process the render area, and the rest write the whole image.
+    /// # Safety
+    /// * The command buffer must be in the recording state.
+    /// * The image index must be a valid index into the input and destination images.
+    /// * The input image must currently have layout `SHADER_READ_ONLY_OPTIMAL`, and be visible to the fragment and compute shaders.
+    fn render_frame(
+        &self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        extent: FrameExtent,
+        image_index: usize,
+    );
+
+    /// Enable or disable a split-screen comparison, where only the left half of the screen is processed.
+    /// Passes which do not support a comparison will ignore this.
+    fn set_split_screen(&mut self, _split_screen: bool) {}
+
+    /// Change the intensity and threshold of bloom. Passes which do not apply bloom will ignore this.
+    fn set_bloom_settings(&mut self, _settings: BloomSettings) {}
+
+    /// The sub-pixel offset the demo should apply when rendering the next frame.
+    /// Passes which do not accumulate frames over time will return `None`.
+    fn jitter(&self) -> Option<Jitter> {
+        None
+    }
+
+    /// Discard any state accumulated from previous frames, such as after the demo changes.
+    fn invalidate_history(&mut self) {}
+
+    /// Clean up the resources used by this pass.
+    fn destroy(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    );
+}
+
+/// An image the chain renders to between passes, one for each swapchain image.
+struct IntermediateImage {
+    image: ash::vk::Image,
+    image_view: ash::vk::ImageView,
+    allocation: gpu_allocator::vulkan::Allocation,
+}
+
+/// An ordered list of post-processing passes, followed by a tonemap pass which writes to the swapchain image for presentation.
+/// Passes alternate between two sets of intermediate images, so that each pass samples the output of the one before it.
+/// When the scene is rendered below the swapchain resolution, the effects run on the render area and an upscale pass precedes the tonemap.
+/// Every image is allocated at the swapchain resolution, so the render scale can change without rebuilding the chain.
+pub struct PostProcessChain {
+    settings: PostProcessSettings,
+    passes: Vec<Box<dyn PostProcessPass>>,
+    extent: ash::vk::Extent2D,
+    surface_format: ash::vk::SurfaceFormatKHR,
+    intermediate_images: Vec<Vec<IntermediateImage>>,
+    upscaled_images: Vec<IntermediateImage>,
+}
+
+impl PostProcessChain {
+    /// Create the passes for the effects of the settings, in order, and the intermediate images between them.
+    /// An empty list of effects creates a chain which only tonemaps, and if necessary upscales, the scene for the swapchain.
+    /// Constant lookup textures are uploaded with the transfer queue before this returns, ready to be sampled by the graphics queue.
+    pub fn new(
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        transfer_queue: &super::transfer::TransferQueue,
+        graphics_queue: &super::IndexedQueue,
+        graphics_command_pool: ash::vk::CommandPool,
+        swapchain: &super::Swapchain,
+        settings: &PostProcessSettings,
+    ) -> Self {
+        let mut chain = Self {
+            settings: settings.clone(),
+            passes: Vec::with_capacity(settings.effects.len() + 2),
+            extent: swapchain.extent(),
+            surface_format: swapchain.surface_format(),
+            intermediate_images: Vec::new(),
+            upscaled_images: Vec::new(),
+        };
+        chain.create_intermediate_images(device, memory_allocator, swapchain.image_views().len());
+        chain.passes = chain.create_passes(
+            device,
+            memory_allocator,
+            transfer_queue,
+            graphics_queue,
+            graphics_command_pool,
+            swapchain.image_views(),
+        );
+        chain.set_split_screen(settings.split_screen);
+        chain.set_bloom_settings(settings.bloom_settings);
+        chain
+    }
+
+    /// Clean up the passes and intermediate images owned by this chain.
+    pub fn destroy(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    ) {
+        for mut pass in self.passes.drain(..) {
+            pass.destroy(device, memory_allocator);
+        }
+        self.destroy_intermediate_images(device, memory_allocator);
+    }
+
+    /// Record the commands for every pass in the chain, in order.
+    /// # Safety
+    /// * The command buffer must be in the recording state.
+    /// * The image index must specify an input image that currently has layout `COLOR_ATTACHMENT_OPTIMAL`.
+    pub fn render_frame(
+        &self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        image_index: usize,
+    ) {
+        let frame_extent = FrameExtent {
+            image: self.extent,
+            render: self.render_extent(),
+        };
+        for (index, pass) in self.passes.iter().enumerate() {
+            // Use a pipeline barrier to ensure that the pass is able to read its input in the correct layout.
+            let image_barrier = ash::vk::ImageMemoryBarrier2::default()
+                .src_stage_mask(ash::vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+                .src_access_mask(ash::vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+                .dst_stage_mask(
+                    ash::vk::PipelineStageFlags2::FRAGMENT_SHADER
+                        | ash::vk::PipelineStageFlags2::COMPUTE_SHADER,
+                )
+                .dst_access_mask(ash::vk::AccessFlags2::SHADER_READ)
+                .old_layout(ash::vk::ImageLayout::ATTACHMENT_OPTIMAL)
+                .new_layout(ash::vk::ImageLayout::READ_ONLY_OPTIMAL)
+                .image(self.input_images_of_pass(index)[image_index].image)
+                .subresource_range(ash::vk::ImageSubresourceRange {
+                    aspect_mask: ash::vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                });
+            unsafe {
+                device.cmd_pipeline_barrier2(
+                    command_buffer,
+                    &ash::vk::DependencyInfo::default().image_memory_barriers(&[image_barrier]),
+                );
+            }
+
+            pass.render_frame(device, command_buffer, frame_extent, image_index);
+        }
+    }
+
+    /// Enable or disable the split-screen comparison for every pass in the chain.
+    pub fn set_split_screen(&mut self, split_screen: bool) {
+        self.settings.split_screen = split_screen;
+        for pass in &mut self.passes {
+            pass.set_split_screen(split_screen);
+        }
+    }
+
+    /// Change the intensity and threshold of every bloom pass in the chain.
+    pub fn set_bloom_settings(&mut self, settings: BloomSettings) {
+        self.settings.bloom_settings = settings;
+        for pass in &mut self.passes {
+            pass.set_bloom_settings(settings);
+        }
+    }
+
+    /// Change the resolution the scene is rendered at, if the chain already has the passes the new settings need.
+    /// Returns `false` without changing anything if the chain must be rebuilt instead, such as when the upscale filter changes.
+    pub fn set_upscale_settings(&mut self, settings: UpscaleSettings) -> bool {
+        let current = &self.settings.upscale_settings;
+        if Self::needs_upscale(&settings) != Self::needs_upscale(current)
+            || settings.filter != current.filter
+        {
+            return false;
+        }
+        self.settings.upscale_settings = settings;
+        true
+    }
+
+    /// The sub-pixel offset the demo should apply when rendering the next frame.
+    pub fn jitter(&self) -> Jitter {
+        self.passes
+            .iter()
+            .find_map(|pass| pass.jitter())
+            .unwrap_or_default()
+    }
+
+    /// Discard the history of every pass which accumulates frames over time.
+    pub fn invalidate_history(&mut self) {
+        for pass in &mut self.passes {
+            pass.invalidate_history();
+        }
+    }
+
+    /// How the final pass encodes the scene for the swapchain color space.
+    pub fn output_encoding(&self) -> OutputEncoding {
+        OutputEncoding::from_surface_format(self.surface_format)
+    }
+
+    /// The size of the swapchain images the chain writes to.
+    pub fn extent(&self) -> ash::vk::Extent2D {
+        self.extent
+    }
+
+    /// The size of the area at the top-left of the input images which demos render the scene to, which may be smaller than the swapchain.
+    pub fn render_extent(&self) -> ash::vk::Extent2D {
+        self.settings.upscale_settings.render_extent(self.extent)
+    }
+
+    /// The layout a demo must leave its rendered image in, for the chain to consume it.
+    pub fn input_layout(&self) -> ash::vk::ImageLayout {
+        ash::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+    }
+
+    /// The scene image views a demo should render to, one for each swapchain image.
+    pub fn input_views(&self) -> Vec<ash::vk::ImageView> {
+        self.input_views_of_pass(0)
+    }
+
+    /// Whether the scene is rendered below the swapchain resolution, and must be upscaled.
+    fn is_upscaling(&self) -> bool {
+        Self::needs_upscale(&self.settings.upscale_settings)
+    }
+
+    /// Helper to check whether a chain with the given settings needs an upscale pass.
+    /// A render scale which changes while running keeps the pass even at full scale, so that changing the scale does not rebuild the chain.
+    fn needs_upscale(settings: &UpscaleSettings) -> bool {
+        settings.render_scale < 1. || settings.dynamic
+    }
+
+    /// Helper to get the number of passes in the chain: one for each effect, the upscale pass if necessary, and the tonemap pass.
+    fn pass_count(&self) -> usize {
+        self.settings.effects.len() + usize::from(self.is_upscaling()) + 1
+    }
+
+    /// Helper to get the images sampled by the pass at the given index.
+    /// The tonemap pass samples the upscaled images when upscaling, and every other pass samples one of the sets of intermediate images.
+    fn input_images_of_pass(&self, index: usize) -> &[IntermediateImage] {
+        if index > self.settings.effects.len() {
+            &self.upscaled_images
+        } else {
+            &self.intermediate_images[index % 2]
+        }
+    }
+
+    /// Helper to get the image views sampled by the pass at the given index.
+    fn input_views_of_pass(&self, index: usize) -> Vec<ash::vk::ImageView> {
+        self.input_images_of_pass(index)
+            .iter()
+            .map(|intermediate| intermediate.image_view)
+            .collect()
+    }
+
+    /// Helper to get the layout and image views written by the pass at the given index.
+    /// The last pass writes to the swapchain images, and every other pass writes to the images the next pass samples.
+    fn destination_of_pass(
+        &self,
+        index: usize,
+        swapchain_views: &[ash::vk::ImageView],
+    ) -> (ash::vk::ImageLayout, Vec<ash::vk::ImageView>) {
+        if index + 1 == self.pass_count() {
+            (
+                ash::vk::ImageLayout::PRESENT_SRC_KHR,
+                swapchain_views.to_vec(),
+            )
+        } else {
+            (
+                ash::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                self.input_views_of_pass(index + 1),
+            )
+        }
+    }
+
+    /// Helper to create a pass for each effect in the chain, followed by the upscale pass if necessary, and the tonemap pass.
+    fn create_passes(
+        &self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        transfer_queue: &super::transfer::TransferQueue,
+        graphics_queue: &super::IndexedQueue,
+        graphics_command_pool: ash::vk::CommandPool,
+        swapchain_views: &[ash::vk::ImageView],
+    ) -> Vec<Box<dyn PostProcessPass>> {
+        let extent = self.extent;
+        let mut passes: Vec<Box<dyn PostProcessPass>> = self
+            .settings
+            .effects
+            .iter()
+            .enumerate()
+            .map(|(index, effect)| {
+                let input_views = self.input_views_of_pass(index);
+                let (destination_layout, destination_views) =
+                    self.destination_of_pass(index, swapchain_views);
+                match effect {
+                    PostProcessEffect::Fxaa => Box::new(FxaaPass::new(
+                        device,
+                        extent,
+                        SCENE_FORMAT,
+                        &input_views,
+                        &destination_views,
+                        destination_layout,
+                        &self.settings.fxaa_constants,
+                    )) as Box<dyn PostProcessPass>,
+                    PostProcessEffect::Smaa => {
+                        let smaa = SmaaPass::new(
+                            device,
+                            memory_allocator,
+                            extent,
+                            SCENE_FORMAT,
+                            &input_views,
+                            &destination_views,
+                            destination_layout,
+                        );
+                        smaa.upload_lookup_textures(
+                            device,
+                            memory_allocator,
+                            transfer_queue,
+                            graphics_queue,
+                            graphics_command_pool,
+                        );
+                        Box::new(smaa)
+                    }
+                    PostProcessEffect::Taa => Box::new(TaaPass::new(
+                        device,
+                        memory_allocator,
+                        extent,
+                        SCENE_FORMAT,
+                        &input_views,
+                        &destination_views,
+                        destination_layout,
+                    )),
+                    PostProcessEffect::Bloom => Box::new(BloomPass::new(
+                        device,
+                        memory_allocator,
+                        extent,
+                        SCENE_FORMAT,
+                        &input_views,
+                        &destination_views,
+                        destination_layout,
+                    )),
+                }
+            })
+            .collect();
+
+        if self.is_upscaling() {
+            let index = passes.len();
+            let (destination_layout, destination_views) =
+                self.destination_of_pass(index, swapchain_views);
+            passes.push(Box::new(UpscalePass::new(
+                device,
+                memory_allocator,
+                self.extent,
+                self.settings.upscale_settings.filter,
+                &self.input_views_of_pass(index),
+                &destination_views,
+                destination_layout,
+            )));
+        }
+
+        let index = passes.len();
+        let (destination_layout, destination_views) =
+            self.destination_of_pass(index, swapchain_views);
+        passes.push(Box::new(TonemapPass::new(
+            device,
+            self.extent,
+            self.surface_format.format,
+            self.output_encoding(),
+            &self.input_views_of_pass(index),
+            &destination_views,
+            destination_layout,
+        )));
+        passes
+    }
+
+    /// Helper to create the sets of intermediate images, and the upscaled images if necessary, all at the swapchain resolution.
+    /// Each set has one image per swapchain image.
+    fn create_intermediate_images(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        image_count: usize,
+    ) {
+        let mut create_images = |extent: ash::vk::Extent2D, name| {
+            let image_info = ash::vk::ImageCreateInfo {
+                image_type: ash::vk::ImageType::TYPE_2D,
+                format: SCENE_FORMAT,
+                extent: ash::vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                },
+                mip_levels: 1,
+                array_layers: 1,
+                samples: ash::vk::SampleCountFlags::TYPE_1,
+                usage: ash::vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | ash::vk::ImageUsageFlags::SAMPLED,
+                ..Default::default()
+            };
+            (0..image_count)
+                .map(|_| {
+                    let (image, allocation) =
+                        super::create_image(device, memory_allocator, &image_info, name);
+                    let image_view = super::create_image_view(device, image, SCENE_FORMAT, 1);
+                    IntermediateImage {
+                        image,
+                        image_view,
+                        allocation,
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+
+        self.intermediate_images = (0..(self.settings.effects.len() + 1).min(2))
+            .map(|_| create_images(self.extent, "Post-Process Image"))
+            .collect();
+        if self.is_upscaling() {
+            self.upscaled_images = create_images(self.extent, "Post-Process Upscaled Image");
+        }
+    }
+
+    /// Helper to destroy the intermediate images.
+    fn destroy_intermediate_images(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    ) {
+        for intermediate in self
+            .intermediate_images
+            .drain(..)
+            .flatten()
+            .chain(self.upscaled_images.drain(..))
+        {
+            unsafe { device.destroy_image_view(intermediate.image_view, None) };
+            unsafe { device.destroy_image(intermediate.image, None) };
+            memory_allocator
+                .free(intermediate.allocation)
+                .expect("Failed to free post-process image allocation");
+        }
+    }
+}
@@ -0,0 +1,1172 @@
+use super::{
+    post_process::{FrameExtent, PostProcessPass},
+    shaders::ENTRY_POINT_MAIN,
+};
+
+/// Shader for the first SMAA pass, which detects edges in the input image.
+const SMAA_EDGES_FRAGMENT: &[u32] =
+    inline_spirv::include_spirv!("src/shaders/smaa_edges_frag.glsl", frag, glsl);
+
+/// Shader for the second SMAA pass, which calculates the blending weights across each edge.
+const SMAA_WEIGHTS_FRAGMENT: &[u32] =
+    inline_spirv::include_spirv!("src/shaders/smaa_weights_frag.glsl", frag, glsl);
+
+/// Shader for the final SMAA pass, which blends each pixel with its neighbors.
+const SMAA_BLEND_FRAGMENT: &[u32] =
+    inline_spirv::include_spirv!("src/shaders/smaa_blend_frag.glsl", frag, glsl);
+
+/// The format of the images storing the detected edges, one channel each for left and top edges.
+const EDGES_FORMAT: ash::vk::Format = ash::vk::Format::R8G8_UNORM;
+
+/// The format of the images storing the blending weights across the top and left edges of each pixel.
+const WEIGHTS_FORMAT: ash::vk::Format = ash::vk::Format::R8G8B8A8_UNORM;
+
+/// The size of each orthogonal tile of the area texture. Distances to the ends of an edge are stored by their square root, so this covers distances up to 225 pixels.
+const AREA_TEXTURE_MAX_DISTANCE: usize = 16;
+
+/// The size of each diagonal tile of the area texture, which stores the distances to the ends of an edge directly.
+const AREA_TEXTURE_MAX_DISTANCE_DIAGONAL: usize = 20;
+
+/// The subsample offsets of the orthogonal areas, one for each subtexture of the area texture.
+/// SMAA 1x only uses the first, while the others are for the temporal and multisampled modes of the reference implementation.
+const ORTHOGONAL_SUBSAMPLE_OFFSETS: [f64; 7] = [0., -0.25, 0.25, -0.125, 0.125, -0.375, 0.375];
+
+/// The subsample offsets of the diagonal areas, one for each subtexture of the area texture.
+const DIAGONAL_SUBSAMPLE_OFFSETS: [[f64; 2]; 5] = [
+    [0., 0.],
+    [0.25, -0.25],
+    [-0.25, 0.25],
+    [0.125, -0.125],
+    [-0.125, 0.125],
+];
+
+/// The width of the area texture, with the five-by-five grid of orthogonal tiles on the left and the four-by-four grid of diagonal tiles on the right.
+const AREA_TEXTURE_WIDTH: usize = 2 * 5 * AREA_TEXTURE_MAX_DISTANCE;
+
+/// The height of the area texture, with a subtexture for each subsample offset stacked vertically.
+const AREA_TEXTURE_HEIGHT: usize =
+    ORTHOGONAL_SUBSAMPLE_OFFSETS.len() * 5 * AREA_TEXTURE_MAX_DISTANCE;
+
+/// The edge length at which the U-shaped patterns stop being smoothed, and use their exact area.
+const SMOOTH_MAX_DISTANCE: f64 = 32.;
+
+/// The number of samples along each axis of a pixel when calculating the diagonal areas by brute force.
+const DIAGONAL_SAMPLES: usize = 30;
+
+/// The width of the search texture, with a half for searches in each direction, cropped from 66 texels.
+const SEARCH_TEXTURE_WIDTH: usize = 64;
+
+/// The height of the search texture, cropped from 33 texels to the bilinear fetches of the top edges which may continue an edge.
+const SEARCH_TEXTURE_HEIGHT: usize = 16;
+
+/// Define the push constants that are used in the fragment shaders of the SMAA algorithm.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PushConstants {
+    /// The width and height of the render surface, as `f32`s.
+    pub inverse_screen_size: [f32; 2],
+
+    /// The fraction of the screen width, from the left, where SMAA is applied. The rest shows the unprocessed input.
+    pub split_position: f32,
+}
+
+/// The crossing edges which may be found at an end of an orthogonal edge, from the perspective of a horizontal edge.
+/// The value of each is the bilinear fetch of the crossing edges, scaled by four, which is also its tile in the area texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Crossing {
+    None = 0,
+    Above = 1,
+    Below = 3,
+    Both = 4,
+}
+impl Crossing {
+    /// Every crossing, in the order of the area texture tiles.
+    const ALL: [Crossing; 4] = [
+        Crossing::None,
+        Crossing::Above,
+        Crossing::Below,
+        Crossing::Both,
+    ];
+
+    /// The vertical offset of the edge's silhouette at an end with this crossing edge, shifted by the subsample offset.
+    fn offset(self, subsample_offset: f64) -> f64 {
+        match self {
+            Crossing::Above => 0.5 + subsample_offset,
+            Crossing::Below => subsample_offset - 0.5,
+            Crossing::None | Crossing::Both => 0.,
+        }
+    }
+
+    /// The crossing on the other side of the edge, for ends with a crossing edge on both sides.
+    fn opposite(self) -> Self {
+        match self {
+            Crossing::Above => Crossing::Below,
+            Crossing::Below => Crossing::Above,
+            other => other,
+        }
+    }
+}
+
+/// Calculate the area of the pixel column starting at `x` between an edge and the line from `p1` to `p2`.
+/// The area below the edge is returned in the first component, and above it in the second,
+/// as these are the amounts the pixels on each side of the edge blend with the other side.
+fn area(p1: [f64; 2], p2: [f64; 2], x: f64) -> [f64; 2] {
+    let (x1, x2) = (x, x + 1.);
+    if !((x1 >= p1[0] && x1 < p2[0]) || (x2 > p1[0] && x2 <= p2[0])) {
+        return [0.; 2];
+    }
+
+    // Find the height of the line at both sides of the pixel.
+    let d = [p2[0] - p1[0], p2[1] - p1[1]];
+    let y1 = p1[1] + d[1] * (x1 - p1[0]) / d[0];
+    let y2 = p1[1] + d[1] * (x2 - p1[0]) / d[0];
+
+    // If the line crosses the edge within the pixel, the area is split into two triangles on opposite sides.
+    let triangles = if y1.signum() != y2.signum() && y1.abs() > 1e-4 && y2.abs() > 1e-4 {
+        let crossing = -p1[1] * d[0] / d[1] + p1[0];
+        let t = crossing.fract();
+        [y1 * t / 2., y2 * (1. - t) / 2.]
+    } else {
+        [(y1 + y2) / 2., 0.]
+    };
+
+    triangles.into_iter().fold([0.; 2], |[below, above], a| {
+        if a < 0. {
+            [below - a, above]
+        } else {
+            [below, above + a]
+        }
+    })
+}
+
+/// Blend the areas of a U-shaped pattern towards a smoother, rounder shape for short edges.
+/// Without this, long U-shaped edges would blur more than the silhouettes they likely represent.
+fn smooth_area(distance: f64, a1: [f64; 2], a2: [f64; 2]) -> [f64; 2] {
+    let p = (distance / SMOOTH_MAX_DISTANCE).clamp(0., 1.);
+    let smooth = |a: f64| (a * 2.).sqrt() * 0.5;
+    [0, 1].map(|i| {
+        let b1 = smooth(a1[i]);
+        let b2 = smooth(a2[i]);
+        b1 + (a1[i] - b1) * p + b2 + (a2[i] - b2) * p
+    })
+}
+
+/// Calculate the coverage of a pixel on an orthogonal edge, given the crossing edges at each end and the distances to them.
+fn orthogonal_area(
+    e1: Crossing,
+    e2: Crossing,
+    left: f64,
+    right: f64,
+    subsample_offset: f64,
+) -> [f64; 2] {
+    let d = left + right + 1.;
+    let middle = d / 2.;
+    let o1 = e1.offset(subsample_offset);
+    let o2 = e2.offset(subsample_offset);
+    match (e1, e2) {
+        // Crossing edges on opposite sides form a line across the whole edge.
+        // With a subsample offset, this is blended with the U-shape through the middle of the edge,
+        // so that neighboring pixels agree on which side of the edge the line crosses at.
+        (Crossing::Above, Crossing::Below) | (Crossing::Below, Crossing::Above) => {
+            let z = area([0., o1], [d, o2], left);
+            if subsample_offset == 0. {
+                z
+            } else {
+                let l1 = area([0., o1], [middle, 0.], left);
+                let l2 = area([middle, 0.], [d, o2], left);
+                [0, 1].map(|i| (z[i] + l1[i] + l2[i]) / 2.)
+            }
+        }
+
+        // A crossing edge on both sides of an end is treated as being opposite the other end.
+        (Crossing::Both, Crossing::Above | Crossing::Below) => {
+            area([0., e2.opposite().offset(subsample_offset)], [d, o2], left)
+        }
+        (Crossing::Above | Crossing::Below, Crossing::Both) => {
+            area([0., o1], [d, e1.opposite().offset(subsample_offset)], left)
+        }
+
+        // Crossing edges on the same side form a U-shape, with a line from each end to the middle.
+        (Crossing::Above, Crossing::Above) | (Crossing::Below, Crossing::Below) => smooth_area(
+            d,
+            area([0., o1], [middle, 0.], left),
+            area([middle, 0.], [d, o2], left),
+        ),
+
+        // A single crossing edge forms a line to the middle of the edge, covering only the pixels on its half.
+        (Crossing::Above | Crossing::Below, Crossing::None) if left <= right => {
+            area([0., o1], [middle, 0.], left)
+        }
+        (Crossing::None, Crossing::Above | Crossing::Below) if left >= right => {
+            area([middle, 0.], [d, o2], left)
+        }
+
+        // Otherwise, there is no silhouette to reconstruct.
+        _ => [0.; 2],
+    }
+}
+
+/// Estimate the area of the pixel with its top-left corner at `pixel` which is on the left of the line from `p1` to `p2`,
+/// by counting the samples in a grid across the pixel.
+fn diagonal_pixel_area(p1: [f64; 2], p2: [f64; 2], pixel: [f64; 2]) -> f64 {
+    let (a, b) = (p2[1] - p1[1], p1[0] - p2[0]);
+    let middle = [(p1[0] + p2[0]) / 2., (p1[1] + p2[1]) / 2.];
+    let step = 1. / (DIAGONAL_SAMPLES - 1) as f64;
+    let samples: [f64; DIAGONAL_SAMPLES] = std::array::from_fn(|i| i as f64 * step);
+
+    // Within each column of samples, the side of the line only changes once, so the samples inside can be found by a binary search.
+    // This is much faster than testing every sample, which matters as the area texture is also generated in unoptimized builds.
+    let inside: usize = samples
+        .iter()
+        .map(|x| {
+            let is_inside =
+                |y: &f64| a * (pixel[0] + x - middle[0]) + b * (pixel[1] + y - middle[1]) > 0.;
+            if b >= 0. {
+                DIAGONAL_SAMPLES - samples.partition_point(|y| !is_inside(y))
+            } else {
+                samples.partition_point(is_inside)
+            }
+        })
+        .sum();
+    inside as f64 / (DIAGONAL_SAMPLES * DIAGONAL_SAMPLES) as f64
+}
+
+/// Calculate the coverage of a pixel on a diagonal edge, given the crossing edges at each end and the distances to them.
+/// The crossing edges are the bilinear fetch of the edges beside each end, with one bit for each of the two edges.
+fn diagonal_area(
+    e1: usize,
+    e2: usize,
+    left: f64,
+    right: f64,
+    subsample_offset: [f64; 2],
+) -> [f64; 2] {
+    let d = left + right + 1.;
+
+    // The area of a line from `p1` to `p2`, relative to the ends of the diagonal edge, and shifted by the subsample offset
+    // at each end that has a crossing edge.
+    let line = |p1: [f64; 2], p2: [f64; 2]| {
+        let shift = |p: [f64; 2], e: usize| {
+            if e > 0 {
+                [p[0] + subsample_offset[0], p[1] + subsample_offset[1]]
+            } else {
+                p
+            }
+        };
+        let p1 = shift(p1, e1);
+        let p2 = shift([p2[0] + d, p2[1] + d], e2);
+        let a1 = diagonal_pixel_area(p1, p2, [1. + left, left]);
+        let a2 = diagonal_pixel_area(p1, p2, [1. + left, 1. + left]);
+        [1. - a1, a2]
+    };
+    let blend = |a1: [f64; 2], a2: [f64; 2]| [(a1[0] + a2[0]) / 2., (a1[1] + a2[1]) / 2.];
+
+    match (e1, e2) {
+        (0, 0) => blend(line([1., 1.], [1., 1.]), line([1., 0.], [1., 0.])),
+        (1, 0) => blend(line([1., 0.], [0., 0.]), line([1., 0.], [1., 0.])),
+        (0, 2) => blend(line([0., 0.], [1., 0.]), line([1., 0.], [1., 0.])),
+        (1, 2) => line([1., 0.], [1., 0.]),
+        (2, 0) => blend(line([1., 1.], [0., 0.]), line([1., 1.], [1., 0.])),
+        (3, 0) => blend(line([1., 1.], [0., 0.]), line([1., 0.], [1., 0.])),
+        (2, 2) => line([1., 1.], [1., 0.]),
+        (3, 2) => blend(line([1., 1.], [1., 0.]), line([1., 0.], [1., 0.])),
+        (0, 1) => blend(line([0., 0.], [1., 1.]), line([1., 0.], [1., 1.])),
+        (1, 1) => line([1., 0.], [1., 1.]),
+        (0, 3) => blend(line([0., 0.], [1., 1.]), line([1., 0.], [1., 0.])),
+        (1, 3) => blend(line([1., 0.], [1., 1.]), line([1., 0.], [1., 0.])),
+        (2, 1) => line([1., 1.], [1., 1.]),
+        (3, 1) => blend(line([1., 1.], [1., 1.]), line([1., 0.], [1., 1.])),
+        (2, 3) => blend(line([1., 1.], [1., 1.]), line([1., 1.], [1., 0.])),
+        (3, 3) => blend(line([1., 1.], [1., 1.]), line([1., 0.], [1., 0.])),
+        _ => unreachable!("Diagonal crossing edges are two bits"),
+    }
+}
+
+/// Generate the area texture, which maps the crossing edges at the ends of an edge, and the distances to them, to the coverage of a pixel.
+/// The left half holds the orthogonal tiles, indexed by the bilinear fetch of the crossing edges at each end, and within a tile by the square roots of the distances.
+/// The right half holds the diagonal tiles, indexed by the crossing edges at each end, and within a tile by the distances themselves.
+/// Each subsample offset has its own subtexture, stacked vertically.
+/// The texture is generated once and cached, since estimating the diagonal areas is slow.
+fn area_texture_data() -> &'static [u8] {
+    static DATA: std::sync::OnceLock<Vec<u8>> = std::sync::OnceLock::new();
+    DATA.get_or_init(|| {
+        let mut data = vec![0; 2 * AREA_TEXTURE_WIDTH * AREA_TEXTURE_HEIGHT];
+        let mut write = |texel_x: usize, texel_y: usize, area: [f64; 2]| {
+            let offset = 2 * (texel_y * AREA_TEXTURE_WIDTH + texel_x);
+            data[offset..offset + 2]
+                .copy_from_slice(&area.map(|a| (a.clamp(0., 1.) * 255.).round() as u8));
+        };
+        let subtexture_size = 5 * AREA_TEXTURE_MAX_DISTANCE;
+
+        for (i, &subsample_offset) in ORTHOGONAL_SUBSAMPLE_OFFSETS.iter().enumerate() {
+            for e1 in Crossing::ALL {
+                for e2 in Crossing::ALL {
+                    for y in 0..AREA_TEXTURE_MAX_DISTANCE {
+                        for x in 0..AREA_TEXTURE_MAX_DISTANCE {
+                            let area = orthogonal_area(
+                                e1,
+                                e2,
+                                (x * x) as f64,
+                                (y * y) as f64,
+                                subsample_offset,
+                            );
+                            write(
+                                e1 as usize * AREA_TEXTURE_MAX_DISTANCE + x,
+                                i * subtexture_size + e2 as usize * AREA_TEXTURE_MAX_DISTANCE + y,
+                                area,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        for (i, &subsample_offset) in DIAGONAL_SUBSAMPLE_OFFSETS.iter().enumerate() {
+            for e1 in 0..4 {
+                for e2 in 0..4 {
+                    for y in 0..AREA_TEXTURE_MAX_DISTANCE_DIAGONAL {
+                        for x in 0..AREA_TEXTURE_MAX_DISTANCE_DIAGONAL {
+                            let area = diagonal_area(e1, e2, x as f64, y as f64, subsample_offset);
+                            write(
+                                AREA_TEXTURE_WIDTH / 2
+                                    + e1 * AREA_TEXTURE_MAX_DISTANCE_DIAGONAL
+                                    + x,
+                                i * subtexture_size + e2 * AREA_TEXTURE_MAX_DISTANCE_DIAGONAL + y,
+                                area,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        data
+    })
+}
+
+/// Generate the search texture, which maps the bilinear fetch of two pixels of edges to how many of those pixels the edge continues for.
+/// This lets the edge searches step two pixels at a time, while still stopping at the exact end of an edge.
+fn search_texture_data() -> &'static [u8] {
+    static DATA: std::sync::OnceLock<Vec<u8>> = std::sync::OnceLock::new();
+    DATA.get_or_init(|| {
+        // The bilinear fetch of the edges of four pixels, scaled by 32 so that each combination has a unique integer value.
+        // The fetch is a quarter pixel towards the previous pixel and an eighth of a pixel up, ordered top-left, top-right, bottom-left, bottom-right.
+        let bilinear = |e: [bool; 4]| {
+            usize::from(e[0])
+                + 3 * usize::from(e[1])
+                + 7 * usize::from(e[2])
+                + 21 * usize::from(e[3])
+        };
+        let edges = |bits: usize| [0, 1, 2, 3].map(|i| bits & (1 << i) != 0);
+
+        let mut data = vec![0; SEARCH_TEXTURE_WIDTH * SEARCH_TEXTURE_HEIGHT];
+        for left_bits in 0..16 {
+            for top_bits in 0..16 {
+                let (left, top) = (edges(left_bits), edges(top_bits));
+
+                // Searching left, continue while there is an edge with no crossing edges.
+                let mut delta_left = 0;
+                if top[3] {
+                    delta_left += 1;
+                }
+                if delta_left == 1 && top[2] && !left[1] && !left[3] {
+                    delta_left += 1;
+                }
+
+                // Searching right, crossing edges are on the left of each pixel, so they also end the edge.
+                let mut delta_right = 0;
+                if top[3] && !left[1] && !left[3] {
+                    delta_right += 1;
+                }
+                if delta_right == 1 && top[2] && !left[0] && !left[2] {
+                    delta_right += 1;
+                }
+
+                // Only fetches with a top edge at the bottom can continue an edge, so the texture is cropped to the bottom rows
+                // of the full 66 by 33 texture and flipped vertically, along with the last two columns of the right half.
+                let row = 2 * SEARCH_TEXTURE_HEIGHT - bilinear(top);
+                if row >= SEARCH_TEXTURE_HEIGHT {
+                    continue;
+                }
+                let (x, half) = (bilinear(left), 2 * SEARCH_TEXTURE_HEIGHT + 1);
+                data[row * SEARCH_TEXTURE_WIDTH + x] = 127 * delta_left;
+                if x + half < SEARCH_TEXTURE_WIDTH {
+                    data[row * SEARCH_TEXTURE_WIDTH + x + half] = 127 * delta_right;
+                }
+            }
+        }
+        data
+    })
+}
+
+/// A graphics pipeline for one of the fullscreen passes of SMAA, and the render pass it draws in.
+struct Stage {
+    render_pass: ash::vk::RenderPass,
+    layout: ash::vk::PipelineLayout,
+    pipeline: ash::vk::Pipeline,
+    descriptor_set_layout: ash::vk::DescriptorSetLayout,
+    frag_shader: ash::vk::ShaderModule,
+}
+
+impl Stage {
+    /// Create a new render pass and graphics pipeline for one of the SMAA passes.
+    /// Each sampler creates a combined image sampler binding, in order, for the fragment shader.
+    fn new(
+        device: &ash::Device,
+        vert_shader: ash::vk::ShaderModule,
+        fragment_code: &[u32],
+        format: ash::vk::Format,
+        final_layout: ash::vk::ImageLayout,
+        samplers: &[ash::vk::Sampler],
+    ) -> Self {
+        let frag_shader = super::create_shader_module(device, fragment_code);
+        let render_pass = Self::create_render_pass(device, format, final_layout);
+
+        let shader_stages = [
+            ash::vk::PipelineShaderStageCreateInfo::default()
+                .stage(ash::vk::ShaderStageFlags::VERTEX)
+                .module(vert_shader)
+                .name(ENTRY_POINT_MAIN),
+            ash::vk::PipelineShaderStageCreateInfo::default()
+                .stage(ash::vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_shader)
+                .name(ENTRY_POINT_MAIN),
+        ];
+
+        // Define the push constants that will be used by the fragment shader.
+        let push_constants_range = ash::vk::PushConstantRange {
+            stage_flags: ash::vk::ShaderStageFlags::FRAGMENT,
+            offset: 0,
+            size: std::mem::size_of::<PushConstants>() as u32,
+        };
+
+        let viewport_state = ash::vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        let input_state = ash::vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(ash::vk::PrimitiveTopology::TRIANGLE_STRIP);
+        let rasterizer = ash::vk::PipelineRasterizationStateCreateInfo::default().line_width(1.);
+        let sampling = ash::vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(ash::vk::SampleCountFlags::TYPE_1);
+
+        // Every pass overwrites its whole output, so blending is disabled.
+        let color_blend_attachment = [ash::vk::PipelineColorBlendAttachmentState {
+            color_write_mask: ash::vk::ColorComponentFlags::RGBA,
+            ..Default::default()
+        }];
+        let color_blend_state = ash::vk::PipelineColorBlendStateCreateInfo::default()
+            .attachments(&color_blend_attachment);
+
+        // Create the descriptor set layout, with a binding for each sampled image.
+        let descriptor_set_layout = {
+            let immutable_samplers: Vec<_> = samplers.iter().map(std::slice::from_ref).collect();
+            let bindings: Vec<_> = immutable_samplers
+                .iter()
+                .enumerate()
+                .map(|(binding, sampler)| {
+                    ash::vk::DescriptorSetLayoutBinding::default()
+                        .binding(binding as u32)
+                        .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                        .stage_flags(ash::vk::ShaderStageFlags::FRAGMENT)
+                        .immutable_samplers(sampler)
+                })
+                .collect();
+            let descriptor_set_info =
+                ash::vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+            unsafe { device.create_descriptor_set_layout(&descriptor_set_info, None) }
+                .expect("Failed to create descriptor set layout for SMAA post-processing")
+        };
+
+        // Create the pipeline layout.
+        let layout = {
+            let descriptor_set_layout = [descriptor_set_layout];
+            let push_constants_range = [push_constants_range];
+            let layout_info = ash::vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&descriptor_set_layout)
+                .push_constant_ranges(&push_constants_range);
+
+            unsafe { device.create_pipeline_layout(&layout_info, None) }
+                .expect("Failed to create pipeline layout for SMAA post-processing")
+        };
+
+        // Use dynamic states for the viewport and scissor rectangles.
+        let dynamic_states = ash::vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&[
+            ash::vk::DynamicState::VIEWPORT,
+            ash::vk::DynamicState::SCISSOR,
+        ]);
+
+        let pipeline = {
+            let pipeline_info = ash::vk::GraphicsPipelineCreateInfo {
+                stage_count: shader_stages.len() as u32,
+                p_stages: shader_stages.as_ptr(),
+                p_vertex_input_state: &ash::vk::PipelineVertexInputStateCreateInfo::default(),
+                p_input_assembly_state: &input_state,
+                p_viewport_state: &viewport_state,
+                p_rasterization_state: &rasterizer,
+                p_multisample_state: &sampling,
+                p_color_blend_state: &color_blend_state,
+                p_dynamic_state: &dynamic_states,
+                layout,
+                render_pass,
+                subpass: 0,
+                base_pipeline_index: -1,
+                ..Default::default()
+            };
+
+            unsafe {
+                device.create_graphics_pipelines(
+                    ash::vk::PipelineCache::null(),
+                    &[pipeline_info],
+                    None,
+                )
+            }
+            .expect("Failed to create graphics pipeline for SMAA post-processing")[0]
+        };
+
+        Self {
+            render_pass,
+            layout,
+            pipeline,
+            descriptor_set_layout,
+            frag_shader,
+        }
+    }
+
+    /// Helper to create a render pass which overwrites a single color attachment.
+    fn create_render_pass(
+        device: &ash::Device,
+        format: ash::vk::Format,
+        final_layout: ash::vk::ImageLayout,
+    ) -> ash::vk::RenderPass {
+        let attachment = [ash::vk::AttachmentDescription {
+            format,
+            samples: ash::vk::SampleCountFlags::TYPE_1,
+            load_op: ash::vk::AttachmentLoadOp::DONT_CARE, // Each pixel is re-drawn so a clear is not necessary.
+            store_op: ash::vk::AttachmentStoreOp::STORE,
+            initial_layout: ash::vk::ImageLayout::UNDEFINED,
+            final_layout,
+            ..Default::default()
+        }];
+        let color_attachment_reference = [ash::vk::AttachmentReference {
+            attachment: 0,
+            layout: ash::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }];
+        let subpass_description = [ash::vk::SubpassDescription::default()
+            .pipeline_bind_point(ash::vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_attachment_reference)];
+
+        // Wait for any earlier reads of the attachment before writing, and make the writes visible to the passes which sample it.
+        let subpass_dependencies = [
+            ash::vk::SubpassDependency {
+                src_subpass: ash::vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | ash::vk::PipelineStageFlags::FRAGMENT_SHADER,
+                dst_stage_mask: ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: ash::vk::AccessFlags::NONE,
+                dst_access_mask: ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                ..Default::default()
+            },
+            ash::vk::SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: ash::vk::SUBPASS_EXTERNAL,
+                src_stage_mask: ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask: ash::vk::PipelineStageFlags::FRAGMENT_SHADER,
+                src_access_mask: ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask: ash::vk::AccessFlags::SHADER_READ,
+                ..Default::default()
+            },
+        ];
+
+        let render_pass_info = ash::vk::RenderPassCreateInfo::default()
+            .attachments(&attachment)
+            .subpasses(&subpass_description)
+            .dependencies(&subpass_dependencies);
+        unsafe { device.create_render_pass(&render_pass_info, None) }
+            .expect("Failed to create render pass for SMAA post-processing")
+    }
+
+    /// Record a fullscreen draw of this stage to the given framebuffer.
+    fn render(
+        &self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        extent: ash::vk::Extent2D,
+        framebuffer: ash::vk::Framebuffer,
+        descriptor_set: ash::vk::DescriptorSet,
+        push_constants: &PushConstants,
+    ) {
+        let render_area = ash::vk::Rect2D {
+            offset: ash::vk::Offset2D::default(),
+            extent,
+        };
+        unsafe {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &ash::vk::RenderPassBeginInfo::default()
+                    .render_pass(self.render_pass)
+                    .framebuffer(framebuffer)
+                    .render_area(render_area),
+                ash::vk::SubpassContents::INLINE,
+            );
+            device.cmd_bind_pipeline(
+                command_buffer,
+                ash::vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline,
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.layout,
+                ash::vk::ShaderStageFlags::FRAGMENT,
+                0,
+                super::data_byte_slice(push_constants),
+            );
+            device.cmd_set_viewport(
+                command_buffer,
+                0,
+                &[ash::vk::Viewport {
+                    x: 0.,
+                    y: 0.,
+                    width: extent.width as f32,
+                    height: extent.height as f32,
+                    min_depth: 0.,
+                    max_depth: 1.,
+                }],
+            );
+            device.cmd_set_scissor(command_buffer, 0, &[render_area]);
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                ash::vk::PipelineBindPoint::GRAPHICS,
+                self.layout,
+                0,
+                &[descriptor_set],
+                &[],
+            );
+
+            // Draw the full screen quad.
+            device.cmd_draw(command_buffer, 4, 1, 0, 0);
+            device.cmd_end_render_pass(command_buffer);
+        }
+    }
+
+    /// Clean up the resources used by this stage.
+    fn destroy(&self, device: &ash::Device) {
+        unsafe { device.destroy_pipeline(self.pipeline, None) };
+        unsafe { device.destroy_pipeline_layout(self.layout, None) };
+        unsafe { device.destroy_descriptor_set_layout(self.descriptor_set_layout, None) };
+        unsafe { device.destroy_render_pass(self.render_pass, None) };
+        unsafe { device.destroy_shader_module(self.frag_shader, None) };
+    }
+}
+
+/// An image written by one SMAA pass and sampled by the next, with the framebuffer used to render to it.
+struct InternalImage {
+    image: ash::vk::Image,
+    image_view: ash::vk::ImageView,
+    allocation: gpu_allocator::vulkan::Allocation,
+    framebuffer: ash::vk::Framebuffer,
+}
+
+/// A constant texture sampled by the blending weight pass.
+struct LookupTexture {
+    image: ash::vk::Image,
+    image_view: ash::vk::ImageView,
+    allocation: gpu_allocator::vulkan::Allocation,
+    extent: ash::vk::Extent3D,
+}
+
+/// An implementation of SMAA 1x (Enhanced Subpixel Morphological Anti-Aliasing) as a post-processing effect.
+/// Edges are detected by luma, then the shape of the silhouette around each edge is estimated from the
+/// distances to its ends, and each pixel is blended with its neighbors by the area the silhouette covers.
+pub struct SmaaPass {
+    edges: Stage,
+    weights: Stage,
+    blend: Stage,
+    fullscreen_vert_shader: ash::vk::ShaderModule,
+    linear_sampler: ash::vk::Sampler,
+    nearest_sampler: ash::vk::Sampler,
+    edges_images: Vec<InternalImage>,
+    weights_images: Vec<InternalImage>,
+    framebuffers: Vec<ash::vk::Framebuffer>,
+    area_texture: LookupTexture,
+    search_texture: LookupTexture,
+    descriptor_pool: ash::vk::DescriptorPool,
+    descriptor_sets: Vec<[ash::vk::DescriptorSet; 3]>,
+    split_screen: bool,
+}
+
+impl SmaaPass {
+    const OPTIMAL_INTERNAL_IMAGE_LAYOUT: ash::vk::ImageLayout =
+        ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+
+    /// Create a new SMAA render pass and associated resources.
+    /// The input images are sampled and the result is written to the destination images with the same index.
+    /// The lookup textures must be uploaded with `upload_lookup_textures` before the first call to `render_frame`.
+    pub fn new(
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        extent: ash::vk::Extent2D,
+        swapchain_format: ash::vk::Format,
+        input_views: &[ash::vk::ImageView],
+        destination_views: &[ash::vk::ImageView],
+        destination_layout: ash::vk::ImageLayout,
+    ) -> Self {
+        // Create the samplers. Linear filtering lets the searches and blending fetch several pixels at once.
+        // The search texture stores integers, which must use nearest filtering.
+        let create_sampler = |filter| {
+            let sampler_info = ash::vk::SamplerCreateInfo {
+                mag_filter: filter,
+                min_filter: filter,
+                mipmap_mode: ash::vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: ash::vk::LOD_CLAMP_NONE,
+                ..Default::default()
+            };
+            unsafe { device.create_sampler(&sampler_info, None) }
+                .expect("Failed to create sampler for SMAA post-processing")
+        };
+        let linear_sampler = create_sampler(ash::vk::Filter::LINEAR);
+        let nearest_sampler = create_sampler(ash::vk::Filter::NEAREST);
+
+        // Create the render passes and graphics pipelines for each stage.
+        let fullscreen_vert_shader =
+            super::create_shader_module(device, super::shaders::FULLSCREEN_VERTEX);
+        let edges = Stage::new(
+            device,
+            fullscreen_vert_shader,
+            SMAA_EDGES_FRAGMENT,
+            EDGES_FORMAT,
+            Self::OPTIMAL_INTERNAL_IMAGE_LAYOUT,
+            &[linear_sampler],
+        );
+        let weights = Stage::new(
+            device,
+            fullscreen_vert_shader,
+            SMAA_WEIGHTS_FRAGMENT,
+            WEIGHTS_FORMAT,
+            Self::OPTIMAL_INTERNAL_IMAGE_LAYOUT,
+            &[linear_sampler, linear_sampler, nearest_sampler],
+        );
+        let blend = Stage::new(
+            device,
+            fullscreen_vert_shader,
+            SMAA_BLEND_FRAGMENT,
+            swapchain_format,
+            destination_layout,
+            &[linear_sampler, linear_sampler],
+        );
+
+        // Create the lookup textures, whose contents are uploaded separately.
+        let area_texture = Self::create_lookup_texture(
+            device,
+            memory_allocator,
+            ash::vk::Format::R8G8_UNORM,
+            AREA_TEXTURE_WIDTH as u32,
+            AREA_TEXTURE_HEIGHT as u32,
+            "SMAA Area Texture",
+        );
+        let search_texture = Self::create_lookup_texture(
+            device,
+            memory_allocator,
+            ash::vk::Format::R8_UNORM,
+            SEARCH_TEXTURE_WIDTH as u32,
+            SEARCH_TEXTURE_HEIGHT as u32,
+            "SMAA Search Texture",
+        );
+
+        // Create a descriptor pool with three sets for each input image, one for each stage.
+        let descriptor_pool = {
+            let pool_sizes = [ash::vk::DescriptorPoolSize {
+                ty: ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 6 * input_views.len() as u32,
+            }];
+            let pool_info = ash::vk::DescriptorPoolCreateInfo::default()
+                .max_sets(3 * input_views.len() as u32)
+                .pool_sizes(&pool_sizes);
+            unsafe { device.create_descriptor_pool(&pool_info, None) }
+                .expect("Failed to create descriptor pool for SMAA post-processing")
+        };
+
+        let mut smaa = Self {
+            edges,
+            weights,
+            blend,
+            fullscreen_vert_shader,
+            linear_sampler,
+            nearest_sampler,
+            edges_images: Vec::new(),
+            weights_images: Vec::new(),
+            framebuffers: Vec::new(),
+            area_texture,
+            search_texture,
+            descriptor_pool,
+            descriptor_sets: Vec::new(),
+            split_screen: false,
+        };
+        smaa.create_framebuffers(
+            device,
+            memory_allocator,
+            extent,
+            input_views,
+            destination_views,
+        );
+        smaa
+    }
+
+    /// Upload the constant lookup textures with the transfer queue, blocking until they are ready to be sampled by the graphics queue.
+    /// The graphics queue family acquires the textures with a command buffer allocated from `graphics_command_pool` if it differs from the transfer queue family.
+    pub fn upload_lookup_textures(
+        &self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        transfer_queue: &super::transfer::TransferQueue,
+        graphics_queue: &super::IndexedQueue,
+        graphics_command_pool: ash::vk::CommandPool,
+    ) {
+        let area_data = area_texture_data();
+        let search_data = search_texture_data();
+        let (staging_buffer, staging_allocation) = {
+            let buffer = unsafe {
+                device.create_buffer(
+                    &ash::vk::BufferCreateInfo::default()
+                        .size((area_data.len() + search_data.len()) as u64)
+                        .usage(ash::vk::BufferUsageFlags::TRANSFER_SRC),
+                    None,
+                )
+            }
+            .expect("Unable to create the staging buffer for the SMAA lookup textures");
+            let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+            let mut allocation = memory_allocator
+                .allocate(&gpu_allocator::vulkan::AllocationCreateDesc {
+                    name: "SMAA staging buffer",
+                    requirements,
+                    location: gpu_allocator::MemoryLocation::CpuToGpu,
+                    linear: true,
+                    allocation_scheme: gpu_allocator::vulkan::AllocationScheme::DedicatedBuffer(
+                        buffer,
+                    ),
+                })
+                .expect("Unable to allocate the staging buffer for the SMAA lookup textures");
+            unsafe { device.bind_buffer_memory(buffer, allocation.memory(), allocation.offset()) }
+                .expect("Unable to bind the staging buffer memory for the SMAA lookup textures");
+
+            let mapped = allocation
+                .mapped_slice_mut()
+                .expect("SMAA staging buffer did not allocate a mapping");
+            mapped[..area_data.len()].copy_from_slice(area_data);
+            mapped[area_data.len()..area_data.len() + search_data.len()]
+                .copy_from_slice(search_data);
+            (buffer, allocation)
+        };
+        transfer_queue.upload_images(
+            device,
+            graphics_queue,
+            graphics_command_pool,
+            &[self.area_texture.image, self.search_texture.image],
+            Self::OPTIMAL_INTERNAL_IMAGE_LAYOUT,
+            |command_buffer| self.record_lookup_copies(device, command_buffer, staging_buffer),
+        );
+        unsafe { device.destroy_buffer(staging_buffer, None) };
+        memory_allocator
+            .free(staging_allocation)
+            .expect("Failed to free SMAA staging buffer allocation");
+    }
+
+    /// Helper to create one of the constant lookup textures, which must be uploaded before use.
+    fn create_lookup_texture(
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        format: ash::vk::Format,
+        width: u32,
+        height: u32,
+        name: &str,
+    ) -> LookupTexture {
+        let extent = ash::vk::Extent3D {
+            width,
+            height,
+            depth: 1,
+        };
+        let image_info = ash::vk::ImageCreateInfo {
+            image_type: ash::vk::ImageType::TYPE_2D,
+            format,
+            extent,
+            mip_levels: 1,
+            array_layers: 1,
+            samples: ash::vk::SampleCountFlags::TYPE_1,
+            usage: ash::vk::ImageUsageFlags::TRANSFER_DST | ash::vk::ImageUsageFlags::SAMPLED,
+            ..Default::default()
+        };
+        let (image, allocation) = super::create_image(device, memory_allocator, &image_info, name);
+        let image_view = super::create_image_view(device, image, format, 1);
+        LookupTexture {
+            image,
+            image_view,
+            allocation,
+            extent,
+        }
+    }
+
+    /// Helper to create the internal images, framebuffers, and descriptor sets, which all depend on the size of the images.
+    fn create_framebuffers(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        extent: ash::vk::Extent2D,
+        input_views: &[ash::vk::ImageView],
+        destination_views: &[ash::vk::ImageView],
+    ) {
+        let create_framebuffer = |render_pass, image_view: &ash::vk::ImageView| {
+            let framebuffer_info = ash::vk::FramebufferCreateInfo {
+                render_pass,
+                attachment_count: 1,
+                p_attachments: image_view,
+                width: extent.width,
+                height: extent.height,
+                layers: 1,
+                ..Default::default()
+            };
+            unsafe { device.create_framebuffer(&framebuffer_info, None) }
+                .expect("Failed to create framebuffer for SMAA post-processing")
+        };
+        let mut create_internal_images = |format, render_pass, name| {
+            let image_info = ash::vk::ImageCreateInfo {
+                image_type: ash::vk::ImageType::TYPE_2D,
+                format,
+                extent: ash::vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                },
+                mip_levels: 1,
+                array_layers: 1,
+                samples: ash::vk::SampleCountFlags::TYPE_1,
+                usage: ash::vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | ash::vk::ImageUsageFlags::SAMPLED,
+                ..Default::default()
+            };
+            (0..input_views.len())
+                .map(|_| {
+                    let (image, allocation) =
+                        super::create_image(device, memory_allocator, &image_info, name);
+                    let image_view = super::create_image_view(device, image, format, 1);
+                    InternalImage {
+                        image,
+                        image_view,
+                        allocation,
+                        framebuffer: create_framebuffer(render_pass, &image_view),
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        self.edges_images =
+            create_internal_images(EDGES_FORMAT, self.edges.render_pass, "SMAA Edges Image");
+        self.weights_images = create_internal_images(
+            WEIGHTS_FORMAT,
+            self.weights.render_pass,
+            "SMAA Weights Image",
+        );
+        self.framebuffers = destination_views
+            .iter()
+            .map(|destination_view| create_framebuffer(self.blend.render_pass, destination_view))
+            .collect();
+
+        // Allocate a descriptor set for each stage, and point them at the images they sample.
+        let set_layouts = [
+            self.edges.descriptor_set_layout,
+            self.weights.descriptor_set_layout,
+            self.blend.descriptor_set_layout,
+        ];
+        self.descriptor_sets = input_views
+            .iter()
+            .enumerate()
+            .map(|(index, &input_view)| {
+                let sets: [ash::vk::DescriptorSet; 3] = unsafe {
+                    device.allocate_descriptor_sets(
+                        &ash::vk::DescriptorSetAllocateInfo::default()
+                            .descriptor_pool(self.descriptor_pool)
+                            .set_layouts(&set_layouts),
+                    )
+                }
+                .expect("Failed to allocate descriptor sets for SMAA post-processing")
+                .try_into()
+                .expect("SMAA descriptor set allocation returned the wrong number of sets");
+
+                let image_info = |image_view| {
+                    [ash::vk::DescriptorImageInfo {
+                        sampler: ash::vk::Sampler::null(), // Immutable samplers are set in the layout.
+                        image_view,
+                        image_layout: Self::OPTIMAL_INTERNAL_IMAGE_LAYOUT,
+                    }]
+                };
+                let bindings = [
+                    (sets[0], 0, image_info(input_view)),
+                    (sets[1], 0, image_info(self.edges_images[index].image_view)),
+                    (sets[1], 1, image_info(self.area_texture.image_view)),
+                    (sets[1], 2, image_info(self.search_texture.image_view)),
+                    (sets[2], 0, image_info(input_view)),
+                    (
+                        sets[2],
+                        1,
+                        image_info(self.weights_images[index].image_view),
+                    ),
+                ];
+                let writes: Vec<_> = bindings
+                    .iter()
+                    .map(|(set, binding, image_info)| {
+                        ash::vk::WriteDescriptorSet::default()
+                            .dst_set(*set)
+                            .dst_binding(*binding)
+                            .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                            .image_info(image_info)
+                    })
+                    .collect();
+                unsafe { device.update_descriptor_sets(&writes, &[]) };
+                sets
+            })
+            .collect();
+    }
+
+    /// Helper to destroy the internal images, framebuffers, and descriptor sets.
+    fn destroy_framebuffers(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    ) {
+        unsafe {
+            device.reset_descriptor_pool(
+                self.descriptor_pool,
+                ash::vk::DescriptorPoolResetFlags::empty(),
+            )
+        }
+        .expect("Failed to free SMAA descriptor sets");
+        self.descriptor_sets.clear();
+
+        for framebuffer in self.framebuffers.drain(..) {
+            unsafe { device.destroy_framebuffer(framebuffer, None) };
+        }
+        for internal in self
+            .edges_images
+            .drain(..)
+            .chain(self.weights_images.drain(..))
+        {
+            unsafe { device.destroy_framebuffer(internal.framebuffer, None) };
+            unsafe { device.destroy_image_view(internal.image_view, None) };
+            unsafe { device.destroy_image(internal.image, None) };
+            memory_allocator
+                .free(internal.allocation)
+                .expect("Failed to free SMAA image allocation");
+        }
+    }
+
+    /// Record the commands to copy the lookup textures from the staging buffer, whose images are in `TRANSFER_DST_OPTIMAL` layout.
+    fn record_lookup_copies(
+        &self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        staging_buffer: ash::vk::Buffer,
+    ) {
+        // The area texture is at the start of the staging buffer, followed immediately by the search texture.
+        let buffer_offsets = [0, (2 * AREA_TEXTURE_WIDTH * AREA_TEXTURE_HEIGHT) as u64];
+        for (texture, buffer_offset) in [&self.area_texture, &self.search_texture]
+            .into_iter()
+            .zip(buffer_offsets)
+        {
+            unsafe {
+                device.cmd_copy_buffer_to_image(
+                    command_buffer,
+                    staging_buffer,
+                    texture.image,
+                    ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[ash::vk::BufferImageCopy {
+                        buffer_offset,
+                        image_subresource: ash::vk::ImageSubresourceLayers {
+                            aspect_mask: ash::vk::ImageAspectFlags::COLOR,
+                            mip_level: 0,
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        },
+                        image_extent: texture.extent,
+                        ..Default::default()
+                    }],
+                );
+            }
+        }
+    }
+}
+
+impl PostProcessPass for SmaaPass {
+    /// Record the commands necessary to perform SMAA post-processing on the input image and write the result to the output image.
+    fn render_frame(
+        &self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        frame_extent: FrameExtent,
+        image_index: usize,
+    ) {
+        let push_constants = PushConstants {
+            inverse_screen_size: frame_extent.inverse_image_size(),
+            split_position: frame_extent.split_position(self.split_screen),
+        };
+        let extent = frame_extent.render; // Only the render area of the images holds the scene.
+        let [edges_set, weights_set, blend_set] = self.descriptor_sets[image_index];
+
+        // Each render pass transitions its output to be sampled by the next.
+        self.edges.render(
+            device,
+            command_buffer,
+            extent,
+            self.edges_images[image_index].framebuffer,
+            edges_set,
+            &push_constants,
+        );
+        self.weights.render(
+            device,
+            command_buffer,
+            extent,
+            self.weights_images[image_index].framebuffer,
+            weights_set,
+            &push_constants,
+        );
+        self.blend.render(
+            device,
+            command_buffer,
+            extent,
+            self.framebuffers[image_index],
+            blend_set,
+            &push_constants,
+        );
+    }
+
+    /// Apply SMAA to only the left half of the screen, for comparison with the unprocessed right half.
+    fn set_split_screen(&mut self, split_screen: bool) {
+        self.split_screen = split_screen;
+    }
+
+    /// Clean up the resources used by this SMAA render pass instance.
+    fn destroy(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    ) {
+        self.destroy_framebuffers(device, memory_allocator);
+        unsafe { device.destroy_descriptor_pool(self.descriptor_pool, None) };
+
+        // Destroy the lookup textures.
+        for texture in [&mut self.area_texture, &mut self.search_texture] {
+            unsafe { device.destroy_image_view(texture.image_view, None) };
+            unsafe { device.destroy_image(texture.image, None) };
+            memory_allocator
+                .free(std::mem::take(&mut texture.allocation))
+                .expect("Failed to free SMAA lookup texture allocation");
+        }
+
+        // Destroy the pipelines, shader modules, and samplers.
+        for stage in [&self.edges, &self.weights, &self.blend] {
+            stage.destroy(device);
+        }
+        unsafe { device.destroy_shader_module(self.fullscreen_vert_shader, None) };
+        unsafe { device.destroy_sampler(self.linear_sampler, None) };
+        unsafe { device.destroy_sampler(self.nearest_sampler, None) };
+    }
+}
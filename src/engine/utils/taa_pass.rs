@@ -0,0 +1,636 @@
+use super::{
+    post_process::{FrameExtent, Jitter, PostProcessPass},
+    shaders::ENTRY_POINT_MAIN,
+};
+
+/// Shader for blending the current frame with the history of previous frames.
+const TAA_FRAGMENT: &[u32] = inline_spirv::include_spirv!("src/shaders/taa_frag.glsl", frag, glsl);
+
+/// The format of the history images. Extra precision avoids banding as small differences accumulate over many frames.
+const HISTORY_FORMAT: ash::vk::Format = ash::vk::Format::R16G16B16A16_SFLOAT;
+
+/// The fraction of the history kept each frame, once the history is valid.
+const HISTORY_WEIGHT: f32 = 0.9;
+
+/// The number of distinct jitter offsets before the sequence repeats.
+const JITTER_SEQUENCE_LENGTH: u64 = 8;
+
+/// Define the push constants that are used in the fragment shader of the TAA algorithm.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PushConstants {
+    /// The width and height of the render surface, as `f32`s.
+    pub inverse_screen_size: [f32; 2],
+
+    /// The fraction of the screen width, from the left, where TAA is applied. The rest shows the unprocessed input.
+    pub split_position: f32,
+
+    /// The fraction of the history kept this frame. Zero discards the history entirely.
+    pub history_weight: f32,
+
+    /// The size of the render area of the history relative to that of the current frame, which differ after the render scale changes.
+    pub history_scale: [f32; 2],
+}
+
+/// Get an element of the Halton low-discrepancy sequence with the given base, in the range `[0, 1)`.
+fn halton(mut index: u64, base: u64) -> f32 {
+    let mut fraction = 1.;
+    let mut result = 0.;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// Define the pipeline used for the TAA post-processing effect.
+struct Pipeline {
+    render_pass: ash::vk::RenderPass,
+    layout: ash::vk::PipelineLayout,
+    pipeline: ash::vk::Pipeline,
+    descriptor_set_layout: ash::vk::DescriptorSetLayout,
+    fullscreen_vert_shader: ash::vk::ShaderModule,
+    taa_frag_shader: ash::vk::ShaderModule,
+}
+
+impl Pipeline {
+    /// Create a new graphics pipeline for the TAA post-processing effect.
+    pub fn new(
+        device: &ash::Device,
+        render_pass: ash::vk::RenderPass,
+        sampler: ash::vk::Sampler,
+    ) -> Self {
+        // Create the shader modules for the vertex and fragment shaders.
+        let fullscreen_vert_shader =
+            super::create_shader_module(device, super::shaders::FULLSCREEN_VERTEX);
+        let taa_frag_shader = super::create_shader_module(device, TAA_FRAGMENT);
+
+        let shader_stages = [
+            ash::vk::PipelineShaderStageCreateInfo::default()
+                .stage(ash::vk::ShaderStageFlags::VERTEX)
+                .module(fullscreen_vert_shader)
+                .name(ENTRY_POINT_MAIN),
+            ash::vk::PipelineShaderStageCreateInfo::default()
+                .stage(ash::vk::ShaderStageFlags::FRAGMENT)
+                .module(taa_frag_shader)
+                .name(ENTRY_POINT_MAIN),
+        ];
+
+        // Define the push constants that will be used by the fragment shader.
+        let push_constants_range = ash::vk::PushConstantRange {
+            stage_flags: ash::vk::ShaderStageFlags::FRAGMENT,
+            offset: 0,
+            size: std::mem::size_of::<PushConstants>() as u32,
+        };
+
+        let viewport_state = ash::vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        let input_state = ash::vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(ash::vk::PrimitiveTopology::TRIANGLE_STRIP);
+        let rasterizer = ash::vk::PipelineRasterizationStateCreateInfo::default().line_width(1.);
+        let sampling = ash::vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(ash::vk::SampleCountFlags::TYPE_1);
+
+        // Overwrite both the destination and the history image, without blending.
+        let color_blend_attachment = [ash::vk::PipelineColorBlendAttachmentState {
+            color_write_mask: ash::vk::ColorComponentFlags::RGBA,
+            ..Default::default()
+        }; 2];
+        let color_blend_state = ash::vk::PipelineColorBlendStateCreateInfo::default()
+            .attachments(&color_blend_attachment);
+
+        // Create the descriptor set layout, sampling the current frame and the history.
+        let descriptor_set_layout = {
+            let sampler = [sampler];
+            let bindings = [0, 1].map(|binding| {
+                ash::vk::DescriptorSetLayoutBinding::default()
+                    .binding(binding)
+                    .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .stage_flags(ash::vk::ShaderStageFlags::FRAGMENT)
+                    .immutable_samplers(&sampler)
+            });
+            let descriptor_set_info =
+                ash::vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+            unsafe { device.create_descriptor_set_layout(&descriptor_set_info, None) }
+                .expect("Failed to create descriptor set layout for TAA post-processing")
+        };
+
+        // Create the pipeline layout.
+        let pipeline_layout = {
+            let descriptor_set_layout = [descriptor_set_layout];
+            let push_constants_range = [push_constants_range];
+            let layout_info = ash::vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&descriptor_set_layout)
+                .push_constant_ranges(&push_constants_range);
+
+            unsafe { device.create_pipeline_layout(&layout_info, None) }
+                .expect("Failed to create pipeline layout for TAA post-processing")
+        };
+
+        // Use dynamic states for the viewport and scissor rectangles.
+        let dynamic_states = ash::vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&[
+            ash::vk::DynamicState::VIEWPORT,
+            ash::vk::DynamicState::SCISSOR,
+        ]);
+
+        // Create the TAA pipeline.
+        let pipeline = {
+            let pipeline_info = ash::vk::GraphicsPipelineCreateInfo {
+                stage_count: shader_stages.len() as u32,
+                p_stages: shader_stages.as_ptr(),
+                p_vertex_input_state: &ash::vk::PipelineVertexInputStateCreateInfo::default(),
+                p_input_assembly_state: &input_state,
+                p_viewport_state: &viewport_state,
+                p_rasterization_state: &rasterizer,
+                p_multisample_state: &sampling,
+                p_color_blend_state: &color_blend_state,
+                p_dynamic_state: &dynamic_states,
+                layout: pipeline_layout,
+                render_pass,
+                subpass: 0,
+                base_pipeline_index: -1,
+                ..Default::default()
+            };
+
+            unsafe {
+                device.create_graphics_pipelines(
+                    ash::vk::PipelineCache::null(),
+                    &[pipeline_info],
+                    None,
+                )
+            }
+            .expect("Failed to create graphics pipeline for TAA post-processing")[0]
+        };
+
+        Self {
+            render_pass,
+            layout: pipeline_layout,
+            pipeline,
+            descriptor_set_layout,
+            fullscreen_vert_shader,
+            taa_frag_shader,
+        }
+    }
+}
+
+/// An image holding the accumulated result of previous frames.
+struct HistoryImage {
+    image: ash::vk::Image,
+    image_view: ash::vk::ImageView,
+    allocation: gpu_allocator::vulkan::Allocation,
+}
+
+/// An implementation of a TAA render pass.
+/// The demo jitters each frame by a sub-pixel offset, and this pass blends the frames together over time.
+/// Two history images persist across frames, with each frame reading one and writing the other.
+pub struct TaaPass {
+    pipeline: Pipeline,
+    sampler: ash::vk::Sampler,
+    history_images: Vec<HistoryImage>,
+    framebuffers: Vec<[ash::vk::Framebuffer; 2]>,
+    descriptor_pool: ash::vk::DescriptorPool,
+    descriptor_sets: Vec<[ash::vk::DescriptorSet; 2]>,
+    frame: std::cell::Cell<u64>,
+    history_valid: std::cell::Cell<bool>,
+    history_extent: std::cell::Cell<ash::vk::Extent2D>,
+    split_screen: bool,
+}
+
+impl TaaPass {
+    const OPTIMAL_INTERNAL_IMAGE_LAYOUT: ash::vk::ImageLayout =
+        ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+
+    /// Create a new TAA render pass and associated resources.
+    /// The input images are sampled and the result is written to the destination images with the same index.
+    pub fn new(
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        extent: ash::vk::Extent2D,
+        swapchain_format: ash::vk::Format,
+        input_views: &[ash::vk::ImageView],
+        destination_views: &[ash::vk::ImageView],
+        destination_layout: ash::vk::ImageLayout,
+    ) -> Self {
+        // Create the sampler. Pixels are sampled at their centers, so no filtering is needed.
+        let sampler = {
+            let sampler_info = ash::vk::SamplerCreateInfo {
+                mag_filter: ash::vk::Filter::NEAREST,
+                min_filter: ash::vk::Filter::NEAREST,
+                mipmap_mode: ash::vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: ash::vk::LOD_CLAMP_NONE,
+                ..Default::default()
+            };
+            unsafe { device.create_sampler(&sampler_info, None) }
+                .expect("Failed to create sampler for TAA post-processing")
+        };
+
+        // Create the TAA render pass and graphics pipeline.
+        let render_pass = Self::create_render_pass(device, swapchain_format, destination_layout);
+        let pipeline = Pipeline::new(device, render_pass, sampler);
+
+        // Create a descriptor pool with a set for each input image and history image to read.
+        let descriptor_pool = {
+            let pool_sizes = [ash::vk::DescriptorPoolSize {
+                ty: ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 4 * input_views.len() as u32,
+            }];
+            let pool_info = ash::vk::DescriptorPoolCreateInfo::default()
+                .max_sets(2 * input_views.len() as u32)
+                .pool_sizes(&pool_sizes);
+            unsafe { device.create_descriptor_pool(&pool_info, None) }
+                .expect("Failed to create descriptor pool for TAA post-processing")
+        };
+
+        let mut taa = Self {
+            pipeline,
+            sampler,
+            history_images: Vec::new(),
+            framebuffers: Vec::new(),
+            descriptor_pool,
+            descriptor_sets: Vec::new(),
+            frame: std::cell::Cell::new(0),
+            history_valid: std::cell::Cell::new(false),
+            history_extent: std::cell::Cell::new(ash::vk::Extent2D::default()),
+            split_screen: false,
+        };
+        taa.create_framebuffers(
+            device,
+            memory_allocator,
+            extent,
+            input_views,
+            destination_views,
+        );
+        taa
+    }
+
+    /// Helper to create a new render pass for the TAA post-processing effect.
+    /// The pass writes the destination image and the history image for the next frame.
+    fn create_render_pass(
+        device: &ash::Device,
+        swapchain_format: ash::vk::Format,
+        destination_layout: ash::vk::ImageLayout,
+    ) -> ash::vk::RenderPass {
+        let attachments = [
+            ash::vk::AttachmentDescription {
+                format: swapchain_format,
+                samples: ash::vk::SampleCountFlags::TYPE_1,
+                load_op: ash::vk::AttachmentLoadOp::DONT_CARE, // Each bit of the surface will be re-drawn so a clear is not necessary.
+                store_op: ash::vk::AttachmentStoreOp::STORE,
+                initial_layout: ash::vk::ImageLayout::UNDEFINED,
+                final_layout: destination_layout,
+                ..Default::default()
+            },
+            ash::vk::AttachmentDescription {
+                format: HISTORY_FORMAT,
+                samples: ash::vk::SampleCountFlags::TYPE_1,
+                load_op: ash::vk::AttachmentLoadOp::DONT_CARE,
+                store_op: ash::vk::AttachmentStoreOp::STORE,
+                initial_layout: ash::vk::ImageLayout::UNDEFINED,
+                final_layout: Self::OPTIMAL_INTERNAL_IMAGE_LAYOUT,
+                ..Default::default()
+            },
+        ];
+        let color_attachment_references = [0, 1].map(|attachment| ash::vk::AttachmentReference {
+            attachment,
+            layout: ash::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        });
+
+        // Define the single subpass that will be used in the render pass.
+        let subpass_description = [ash::vk::SubpassDescription::default()
+            .pipeline_bind_point(ash::vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_attachment_references)];
+
+        // Wait for the previous frame to finish reading the history before overwriting it,
+        // and make the new history visible to the next frame.
+        let subpass_dependencies = [
+            ash::vk::SubpassDependency {
+                src_subpass: ash::vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | ash::vk::PipelineStageFlags::FRAGMENT_SHADER,
+                dst_stage_mask: ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: ash::vk::AccessFlags::NONE,
+                dst_access_mask: ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                ..Default::default()
+            },
+            ash::vk::SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: ash::vk::SUBPASS_EXTERNAL,
+                src_stage_mask: ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask: ash::vk::PipelineStageFlags::FRAGMENT_SHADER,
+                src_access_mask: ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask: ash::vk::AccessFlags::SHADER_READ,
+                ..Default::default()
+            },
+        ];
+
+        // Create the render pass.
+        let render_pass_info = ash::vk::RenderPassCreateInfo::default()
+            .attachments(&attachments)
+            .subpasses(&subpass_description)
+            .dependencies(&subpass_dependencies);
+        unsafe { device.create_render_pass(&render_pass_info, None) }
+            .expect("Failed to create render pass for TAA post-processing")
+    }
+
+    /// Helper to create the history images, framebuffers, and descriptor sets, which all depend on the size of the images.
+    /// The new history images are invalid until the first frame is rendered to them.
+    fn create_framebuffers(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        extent: ash::vk::Extent2D,
+        input_views: &[ash::vk::ImageView],
+        destination_views: &[ash::vk::ImageView],
+    ) {
+        let image_info = ash::vk::ImageCreateInfo {
+            image_type: ash::vk::ImageType::TYPE_2D,
+            format: HISTORY_FORMAT,
+            extent: ash::vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            samples: ash::vk::SampleCountFlags::TYPE_1,
+            usage: ash::vk::ImageUsageFlags::COLOR_ATTACHMENT | ash::vk::ImageUsageFlags::SAMPLED,
+            ..Default::default()
+        };
+        self.history_images = (0..2)
+            .map(|_| {
+                let (image, allocation) =
+                    super::create_image(device, memory_allocator, &image_info, "TAA History Image");
+                let image_view = super::create_image_view(device, image, HISTORY_FORMAT, 1);
+                HistoryImage {
+                    image,
+                    image_view,
+                    allocation,
+                }
+            })
+            .collect();
+        self.history_valid.set(false);
+
+        // Create a framebuffer for each destination image, writing to either history image.
+        self.framebuffers = destination_views
+            .iter()
+            .map(|&destination_view| {
+                [0, 1].map(|history_index| {
+                    let attachments = [
+                        destination_view,
+                        self.history_images[history_index].image_view,
+                    ];
+                    let framebuffer_info = ash::vk::FramebufferCreateInfo::default()
+                        .render_pass(self.pipeline.render_pass)
+                        .attachments(&attachments)
+                        .width(extent.width)
+                        .height(extent.height)
+                        .layers(1);
+                    unsafe { device.create_framebuffer(&framebuffer_info, None) }
+                        .expect("Failed to create framebuffer for TAA post-processing")
+                })
+            })
+            .collect();
+
+        // Create a descriptor set for each input image, reading from either history image.
+        let set_layouts = [self.pipeline.descriptor_set_layout; 2];
+        self.descriptor_sets = input_views
+            .iter()
+            .map(|&input_view| {
+                let sets: [ash::vk::DescriptorSet; 2] = unsafe {
+                    device.allocate_descriptor_sets(
+                        &ash::vk::DescriptorSetAllocateInfo::default()
+                            .descriptor_pool(self.descriptor_pool)
+                            .set_layouts(&set_layouts),
+                    )
+                }
+                .expect("Failed to allocate descriptor sets for TAA post-processing")
+                .try_into()
+                .expect("TAA descriptor set allocation returned the wrong number of sets");
+
+                for (set, history) in sets.iter().zip(&self.history_images) {
+                    let image_infos = [input_view, history.image_view].map(|image_view| {
+                        [ash::vk::DescriptorImageInfo {
+                            sampler: self.sampler,
+                            image_view,
+                            image_layout: Self::OPTIMAL_INTERNAL_IMAGE_LAYOUT,
+                        }]
+                    });
+                    let writes = [0, 1].map(|binding| {
+                        ash::vk::WriteDescriptorSet::default()
+                            .dst_set(*set)
+                            .dst_binding(binding)
+                            .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                            .image_info(&image_infos[binding as usize])
+                    });
+                    unsafe { device.update_descriptor_sets(&writes, &[]) };
+                }
+                sets
+            })
+            .collect();
+    }
+
+    /// Helper to destroy the history images, framebuffers, and descriptor sets.
+    fn destroy_framebuffers(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    ) {
+        unsafe {
+            device.reset_descriptor_pool(
+                self.descriptor_pool,
+                ash::vk::DescriptorPoolResetFlags::empty(),
+            )
+        }
+        .expect("Failed to free TAA descriptor sets");
+        self.descriptor_sets.clear();
+
+        for framebuffer in self.framebuffers.drain(..).flatten() {
+            unsafe { device.destroy_framebuffer(framebuffer, None) };
+        }
+        for history in self.history_images.drain(..) {
+            unsafe { device.destroy_image_view(history.image_view, None) };
+            unsafe { device.destroy_image(history.image, None) };
+            memory_allocator
+                .free(history.allocation)
+                .expect("Failed to free TAA history image allocation");
+        }
+    }
+}
+
+impl PostProcessPass for TaaPass {
+    /// Record the commands necessary to blend the input image with the history, writing the result to the output image and the next history.
+    fn render_frame(
+        &self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        frame_extent: FrameExtent,
+        image_index: usize,
+    ) {
+        // Only the render area of the images holds the scene.
+        let extent = frame_extent.render;
+        let frame = self.frame.get();
+        let write_index = (frame % 2) as usize;
+        let read_index = 1 - write_index;
+
+        // An invalid history has undefined contents, which the shader ignores, but it must still be in a layout it can be sampled from.
+        let history_weight = if self.history_valid.replace(true) {
+            HISTORY_WEIGHT
+        } else {
+            let image_barrier = ash::vk::ImageMemoryBarrier2::default()
+                .src_stage_mask(ash::vk::PipelineStageFlags2::NONE)
+                .src_access_mask(ash::vk::AccessFlags2::NONE)
+                .dst_stage_mask(ash::vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                .dst_access_mask(ash::vk::AccessFlags2::SHADER_READ)
+                .old_layout(ash::vk::ImageLayout::UNDEFINED)
+                .new_layout(Self::OPTIMAL_INTERNAL_IMAGE_LAYOUT)
+                .image(self.history_images[read_index].image)
+                .subresource_range(ash::vk::ImageSubresourceRange {
+                    aspect_mask: ash::vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                });
+            unsafe {
+                device.cmd_pipeline_barrier2(
+                    command_buffer,
+                    &ash::vk::DependencyInfo::default().image_memory_barriers(&[image_barrier]),
+                );
+            }
+            0.
+        };
+
+        // The history holds the render area of the previous frame, so it is stretched to the current one when the render scale has changed.
+        let history_extent = self.history_extent.replace(extent);
+        let history_scale = [
+            history_extent.width as f32 / extent.width as f32,
+            history_extent.height as f32 / extent.height as f32,
+        ];
+
+        // Begin the render pass for the current frame.
+        let render_area = ash::vk::Rect2D {
+            offset: ash::vk::Offset2D::default(),
+            extent,
+        };
+        unsafe {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &ash::vk::RenderPassBeginInfo::default()
+                    .render_pass(self.pipeline.render_pass)
+                    .framebuffer(self.framebuffers[image_index][write_index])
+                    .render_area(render_area),
+                ash::vk::SubpassContents::INLINE,
+            );
+        }
+
+        // Set the shader push constants.
+        let push_constants = PushConstants {
+            inverse_screen_size: frame_extent.inverse_image_size(),
+            split_position: frame_extent.split_position(self.split_screen),
+            history_weight,
+            history_scale,
+        };
+        unsafe {
+            device.cmd_push_constants(
+                command_buffer,
+                self.pipeline.layout,
+                ash::vk::ShaderStageFlags::FRAGMENT,
+                0,
+                super::data_byte_slice(&push_constants),
+            );
+        }
+
+        // Set the viewport and scissor in the command buffer because we specified they would be set dynamically in the pipeline.
+        unsafe {
+            device.cmd_set_viewport(
+                command_buffer,
+                0,
+                &[ash::vk::Viewport {
+                    x: 0.,
+                    y: 0.,
+                    width: extent.width as f32,
+                    height: extent.height as f32,
+                    min_depth: 0.,
+                    max_depth: 1.,
+                }],
+            );
+            device.cmd_set_scissor(command_buffer, 0, &[render_area]);
+        }
+
+        // Bind the graphics pipeline and the descriptor set reading the previous history.
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                ash::vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline.pipeline,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                ash::vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline.layout,
+                0,
+                &[self.descriptor_sets[image_index][read_index]],
+                &[],
+            );
+        }
+
+        // Draw the full screen quad.
+        unsafe { device.cmd_draw(command_buffer, 4, 1, 0, 0) };
+
+        // End the render pass.
+        unsafe { device.cmd_end_render_pass(command_buffer) };
+
+        // Advance to the next jitter offset and swap the history images.
+        self.frame.set(frame.wrapping_add(1));
+    }
+
+    /// Get the sub-pixel offset of the next frame, from a Halton sequence which evenly covers the pixel over several frames.
+    /// In a split-screen comparison only the processed half is offset, so the unprocessed half does not shake.
+    fn jitter(&self) -> Option<Jitter> {
+        let index = self.frame.get() % JITTER_SEQUENCE_LENGTH + 1; // The first element of the sequence is always zero.
+        Some(Jitter {
+            offset: [halton(index, 2) - 0.5, halton(index, 3) - 0.5],
+            split_position: if self.split_screen { 0.5 } else { 1. },
+        })
+    }
+
+    /// Discard the accumulated history, so the next frame is shown without blending with previous ones.
+    fn invalidate_history(&mut self) {
+        self.history_valid.set(false);
+    }
+
+    /// Apply TAA to only the left half of the screen, for comparison with the unprocessed right half.
+    fn set_split_screen(&mut self, split_screen: bool) {
+        self.split_screen = split_screen;
+    }
+
+    /// Clean up the resources used by this TAA render pass instance.
+    fn destroy(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    ) {
+        self.destroy_framebuffers(device, memory_allocator);
+        unsafe { device.destroy_descriptor_pool(self.descriptor_pool, None) };
+
+        // Destroy the pipeline.
+        unsafe { device.destroy_pipeline(self.pipeline.pipeline, None) };
+        unsafe { device.destroy_pipeline_layout(self.pipeline.layout, None) };
+        unsafe { device.destroy_descriptor_set_layout(self.pipeline.descriptor_set_layout, None) };
+        unsafe { device.destroy_render_pass(self.pipeline.render_pass, None) };
+
+        // Destroy the shader modules.
+        unsafe { device.destroy_shader_module(self.pipeline.fullscreen_vert_shader, None) };
+        unsafe { device.destroy_shader_module(self.pipeline.taa_frag_shader, None) };
+
+        // Destroy the sampler.
+        unsafe { device.destroy_sampler(self.sampler, None) };
+    }
+}
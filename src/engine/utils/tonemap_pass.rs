@@ -0,0 +1,505 @@
+use super::{
+    post_process::{FrameExtent, PostProcessPass},
+    shaders::ENTRY_POINT_MAIN,
+};
+
+/// Shader for tonemapping the scene and encoding it for the swapchain color space.
+const TONEMAP_FRAGMENT: &[u32] =
+    inline_spirv::include_spirv!("src/shaders/tonemap_frag.glsl", frag, glsl);
+
+/// The brightness of SDR white when presenting in HDR, in nits. This is the reference white of ITU-R BT.2408.
+pub const PAPER_WHITE_NITS: f32 = 203.;
+
+/// The peak brightness assumed of HDR displays, in nits. Brighter highlights are compressed towards this.
+pub const HDR_PEAK_NITS: f32 = 1000.;
+
+/// The encodings applied to the scene for each supported swapchain color space.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputEncoding {
+    /// Linear values for SDR displays, where the `_SRGB` swapchain format applies the sRGB curve when written.
+    Linear = 0,
+
+    /// Non-linear sRGB for SDR displays, applied by the shader because the swapchain format is `_UNORM`.
+    Srgb = 1,
+
+    /// The SMPTE ST 2084 perceptual quantizer with BT.2020 primaries, as used by HDR10.
+    Pq = 2,
+
+    /// Linear values with BT.709 primaries, where one is 80 nits and brighter values extend past one.
+    ScRgb = 3,
+}
+impl OutputEncoding {
+    /// Get the encoding required to present with the given format and color space.
+    pub fn from_surface_format(surface_format: ash::vk::SurfaceFormatKHR) -> Self {
+        match surface_format.color_space {
+            ash::vk::ColorSpaceKHR::HDR10_ST2084_EXT => OutputEncoding::Pq,
+            ash::vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT => OutputEncoding::ScRgb,
+            _ if super::is_srgb_format(surface_format.format) => OutputEncoding::Linear,
+            _ => OutputEncoding::Srgb,
+        }
+    }
+
+    /// Whether the encoding can show brightness beyond SDR white.
+    pub fn is_hdr(self) -> bool {
+        matches!(self, OutputEncoding::Pq | OutputEncoding::ScRgb)
+    }
+
+    /// Describe the content presented with this encoding, for `VK_EXT_hdr_metadata`.
+    pub fn hdr_metadata(self) -> ash::vk::HdrMetadataEXT<'static> {
+        let xy = |x, y| ash::vk::XYColorEXT { x, y };
+        let (red, green, blue) = if self == OutputEncoding::Pq {
+            (xy(0.708, 0.292), xy(0.170, 0.797), xy(0.131, 0.046))
+        } else {
+            (xy(0.64, 0.33), xy(0.30, 0.60), xy(0.15, 0.06))
+        };
+        ash::vk::HdrMetadataEXT::default()
+            .display_primary_red(red)
+            .display_primary_green(green)
+            .display_primary_blue(blue)
+            .white_point(xy(0.3127, 0.3290))
+            .max_luminance(HDR_PEAK_NITS)
+            .min_luminance(0.001)
+            .max_content_light_level(HDR_PEAK_NITS)
+            .max_frame_average_light_level(PAPER_WHITE_NITS)
+    }
+}
+
+/// Define the push constants that are used in the fragment shader of the tonemap pass.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PushConstants {
+    /// How to encode the tonemapped scene for the swapchain.
+    pub encoding: OutputEncoding,
+
+    /// The brightness of SDR white, in nits.
+    pub paper_white: f32,
+
+    /// The brightest the display can show, in nits.
+    pub peak_luminance: f32,
+}
+
+/// Define the pipeline used for the tonemap pass.
+struct Pipeline {
+    render_pass: ash::vk::RenderPass,
+    layout: ash::vk::PipelineLayout,
+    pipeline: ash::vk::Pipeline,
+    descriptor_set_layout: ash::vk::DescriptorSetLayout,
+    fullscreen_vert_shader: ash::vk::ShaderModule,
+    tonemap_frag_shader: ash::vk::ShaderModule,
+}
+
+impl Pipeline {
+    /// Create a new graphics pipeline for the tonemap pass.
+    pub fn new(
+        device: &ash::Device,
+        render_pass: ash::vk::RenderPass,
+        sampler: ash::vk::Sampler,
+    ) -> Self {
+        // Create the shader modules for the vertex and fragment shaders.
+        let fullscreen_vert_shader =
+            super::create_shader_module(device, super::shaders::FULLSCREEN_VERTEX);
+        let tonemap_frag_shader = super::create_shader_module(device, TONEMAP_FRAGMENT);
+
+        let shader_stages = [
+            ash::vk::PipelineShaderStageCreateInfo::default()
+                .stage(ash::vk::ShaderStageFlags::VERTEX)
+                .module(fullscreen_vert_shader)
+                .name(ENTRY_POINT_MAIN),
+            ash::vk::PipelineShaderStageCreateInfo::default()
+                .stage(ash::vk::ShaderStageFlags::FRAGMENT)
+                .module(tonemap_frag_shader)
+                .name(ENTRY_POINT_MAIN),
+        ];
+
+        // Define the push constants that will be used by the fragment shader.
+        let push_constants_range = ash::vk::PushConstantRange {
+            stage_flags: ash::vk::ShaderStageFlags::FRAGMENT,
+            offset: 0,
+            size: std::mem::size_of::<PushConstants>() as u32,
+        };
+
+        let viewport_state = ash::vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        let input_state = ash::vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(ash::vk::PrimitiveTopology::TRIANGLE_STRIP);
+        let rasterizer = ash::vk::PipelineRasterizationStateCreateInfo::default().line_width(1.);
+        let sampling = ash::vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(ash::vk::SampleCountFlags::TYPE_1);
+
+        // Overwrite the destination without blending.
+        let color_blend_attachment = [ash::vk::PipelineColorBlendAttachmentState {
+            color_write_mask: ash::vk::ColorComponentFlags::RGBA,
+            ..Default::default()
+        }];
+        let color_blend_state = ash::vk::PipelineColorBlendStateCreateInfo::default()
+            .attachments(&color_blend_attachment);
+
+        // Create the descriptor set layout.
+        let descriptor_set_layout = {
+            let sampler = [sampler];
+            let bindings = [ash::vk::DescriptorSetLayoutBinding::default()
+                .binding(0)
+                .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .stage_flags(ash::vk::ShaderStageFlags::FRAGMENT)
+                .immutable_samplers(&sampler)];
+            let descriptor_set_info =
+                ash::vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+            unsafe { device.create_descriptor_set_layout(&descriptor_set_info, None) }
+                .expect("Failed to create descriptor set layout for the tonemap pass")
+        };
+
+        // Create the pipeline layout.
+        let pipeline_layout = {
+            let descriptor_set_layout = [descriptor_set_layout];
+            let push_constants_range = [push_constants_range];
+            let layout_info = ash::vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&descriptor_set_layout)
+                .push_constant_ranges(&push_constants_range);
+
+            unsafe { device.create_pipeline_layout(&layout_info, None) }
+                .expect("Failed to create pipeline layout for the tonemap pass")
+        };
+
+        // Use dynamic states for the viewport and scissor rectangles.
+        let dynamic_states = ash::vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&[
+            ash::vk::DynamicState::VIEWPORT,
+            ash::vk::DynamicState::SCISSOR,
+        ]);
+
+        // Create the tonemap pipeline.
+        let pipeline = {
+            let pipeline_info = ash::vk::GraphicsPipelineCreateInfo {
+                stage_count: shader_stages.len() as u32,
+                p_stages: shader_stages.as_ptr(),
+                p_vertex_input_state: &ash::vk::PipelineVertexInputStateCreateInfo::default(),
+                p_input_assembly_state: &input_state,
+                p_viewport_state: &viewport_state,
+                p_rasterization_state: &rasterizer,
+                p_multisample_state: &sampling,
+                p_color_blend_state: &color_blend_state,
+                p_dynamic_state: &dynamic_states,
+                layout: pipeline_layout,
+                render_pass,
+                subpass: 0,
+                base_pipeline_index: -1,
+                ..Default::default()
+            };
+
+            unsafe {
+                device.create_graphics_pipelines(
+                    ash::vk::PipelineCache::null(),
+                    &[pipeline_info],
+                    None,
+                )
+            }
+            .expect("Failed to create graphics pipeline for the tonemap pass")[0]
+        };
+
+        Self {
+            render_pass,
+            layout: pipeline_layout,
+            pipeline,
+            descriptor_set_layout,
+            fullscreen_vert_shader,
+            tonemap_frag_shader,
+        }
+    }
+}
+
+/// The final pass of the post-processing chain, tonemapping the linear scene and encoding it for the swapchain color space.
+pub struct TonemapPass {
+    pipeline: Pipeline,
+    sampler: ash::vk::Sampler,
+    framebuffers: Vec<ash::vk::Framebuffer>,
+    descriptor_pool: ash::vk::DescriptorPool,
+    descriptor_sets: Vec<ash::vk::DescriptorSet>,
+    encoding: OutputEncoding,
+}
+
+impl TonemapPass {
+    const OPTIMAL_INTERNAL_IMAGE_LAYOUT: ash::vk::ImageLayout =
+        ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+
+    /// Create a new tonemap pass and associated resources.
+    /// The input images are sampled and the result is written to the destination images with the same index.
+    pub fn new(
+        device: &ash::Device,
+        extent: ash::vk::Extent2D,
+        swapchain_format: ash::vk::Format,
+        encoding: OutputEncoding,
+        input_views: &[ash::vk::ImageView],
+        destination_views: &[ash::vk::ImageView],
+        destination_layout: ash::vk::ImageLayout,
+    ) -> Self {
+        // Create the sampler. Each pixel is fetched directly, so no filtering is needed.
+        let sampler = {
+            let sampler_info = ash::vk::SamplerCreateInfo {
+                mag_filter: ash::vk::Filter::NEAREST,
+                min_filter: ash::vk::Filter::NEAREST,
+                mipmap_mode: ash::vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: ash::vk::LOD_CLAMP_NONE,
+                ..Default::default()
+            };
+            unsafe { device.create_sampler(&sampler_info, None) }
+                .expect("Failed to create sampler for the tonemap pass")
+        };
+
+        // Create the render pass and graphics pipeline.
+        let render_pass = Self::create_render_pass(device, swapchain_format, destination_layout);
+        let pipeline = Pipeline::new(device, render_pass, sampler);
+
+        // Create a descriptor pool with a set for each input image.
+        let descriptor_pool = {
+            let pool_sizes = [ash::vk::DescriptorPoolSize {
+                ty: ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: input_views.len() as u32,
+            }];
+            let pool_info = ash::vk::DescriptorPoolCreateInfo::default()
+                .max_sets(input_views.len() as u32)
+                .pool_sizes(&pool_sizes);
+            unsafe { device.create_descriptor_pool(&pool_info, None) }
+                .expect("Failed to create descriptor pool for the tonemap pass")
+        };
+
+        let mut tonemap = Self {
+            pipeline,
+            sampler,
+            framebuffers: Vec::new(),
+            descriptor_pool,
+            descriptor_sets: Vec::new(),
+            encoding,
+        };
+        tonemap.create_framebuffers(device, extent, input_views, destination_views);
+        tonemap
+    }
+
+    /// Helper to create a new render pass for the tonemap pass.
+    fn create_render_pass(
+        device: &ash::Device,
+        swapchain_format: ash::vk::Format,
+        destination_layout: ash::vk::ImageLayout,
+    ) -> ash::vk::RenderPass {
+        let attachment = [ash::vk::AttachmentDescription {
+            format: swapchain_format,
+            samples: ash::vk::SampleCountFlags::TYPE_1,
+            load_op: ash::vk::AttachmentLoadOp::DONT_CARE, // Each bit of the surface will be re-drawn so a clear is not necessary.
+            store_op: ash::vk::AttachmentStoreOp::STORE,
+            initial_layout: ash::vk::ImageLayout::UNDEFINED,
+            final_layout: destination_layout,
+            ..Default::default()
+        }];
+        let color_attachment_reference = [ash::vk::AttachmentReference {
+            attachment: 0,
+            layout: ash::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }];
+
+        // Define the single subpass that will be used in the render pass.
+        let subpass_description = [ash::vk::SubpassDescription::default()
+            .pipeline_bind_point(ash::vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_attachment_reference)];
+
+        // Define the subpass dependencies.
+        let subpass_dependencies = [ash::vk::SubpassDependency {
+            src_subpass: ash::vk::SUBPASS_EXTERNAL,
+            dst_subpass: 0,
+            src_stage_mask: ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            dst_stage_mask: ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            src_access_mask: ash::vk::AccessFlags::NONE,
+            dst_access_mask: ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            ..Default::default()
+        }];
+
+        // Create the render pass.
+        let render_pass_info = ash::vk::RenderPassCreateInfo::default()
+            .attachments(&attachment)
+            .subpasses(&subpass_description)
+            .dependencies(&subpass_dependencies);
+        unsafe { device.create_render_pass(&render_pass_info, None) }
+            .expect("Failed to create render pass for the tonemap pass")
+    }
+
+    /// Helper to create the framebuffers and descriptor sets, which depend on the input and destination images.
+    fn create_framebuffers(
+        &mut self,
+        device: &ash::Device,
+        extent: ash::vk::Extent2D,
+        input_views: &[ash::vk::ImageView],
+        destination_views: &[ash::vk::ImageView],
+    ) {
+        self.framebuffers = destination_views
+            .iter()
+            .map(|destination_view| {
+                let framebuffer_info = ash::vk::FramebufferCreateInfo::default()
+                    .render_pass(self.pipeline.render_pass)
+                    .attachments(std::slice::from_ref(destination_view))
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1);
+                unsafe { device.create_framebuffer(&framebuffer_info, None) }
+                    .expect("Failed to create framebuffer for the tonemap pass")
+            })
+            .collect();
+
+        let set_layouts = [self.pipeline.descriptor_set_layout];
+        self.descriptor_sets = input_views
+            .iter()
+            .map(|&image_view| {
+                let &set = unsafe {
+                    device.allocate_descriptor_sets(
+                        &ash::vk::DescriptorSetAllocateInfo::default()
+                            .descriptor_pool(self.descriptor_pool)
+                            .set_layouts(&set_layouts),
+                    )
+                }
+                .expect("Failed to allocate descriptor set for the tonemap pass")
+                .first()
+                .expect("Tonemap descriptor set allocation returned an empty list");
+
+                // Update the descriptor set with the scene image that will be sampled.
+                let image_info = [ash::vk::DescriptorImageInfo {
+                    sampler: self.sampler,
+                    image_view,
+                    image_layout: Self::OPTIMAL_INTERNAL_IMAGE_LAYOUT,
+                }];
+                let write = ash::vk::WriteDescriptorSet::default()
+                    .dst_set(set)
+                    .dst_binding(0)
+                    .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(&image_info);
+                unsafe { device.update_descriptor_sets(&[write], &[]) };
+                set
+            })
+            .collect();
+    }
+
+    /// Helper to destroy the framebuffers and descriptor sets.
+    fn destroy_framebuffers(&mut self, device: &ash::Device) {
+        unsafe {
+            device.reset_descriptor_pool(
+                self.descriptor_pool,
+                ash::vk::DescriptorPoolResetFlags::empty(),
+            )
+        }
+        .expect("Failed to free tonemap descriptor sets");
+        self.descriptor_sets.clear();
+
+        for framebuffer in self.framebuffers.drain(..) {
+            unsafe { device.destroy_framebuffer(framebuffer, None) };
+        }
+    }
+}
+
+impl PostProcessPass for TonemapPass {
+    /// Record the commands necessary to tonemap and encode the input image, writing the result to the output image.
+    fn render_frame(
+        &self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        extent: FrameExtent,
+        image_index: usize,
+    ) {
+        // The tonemap follows any upscaling, so it writes the whole image.
+        let extent = extent.image;
+
+        // Begin the render pass for the current frame.
+        let render_area = ash::vk::Rect2D {
+            offset: ash::vk::Offset2D::default(),
+            extent,
+        };
+        unsafe {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &ash::vk::RenderPassBeginInfo::default()
+                    .render_pass(self.pipeline.render_pass)
+                    .framebuffer(self.framebuffers[image_index])
+                    .render_area(render_area),
+                ash::vk::SubpassContents::INLINE,
+            );
+        }
+
+        // Set the shader push constants.
+        let push_constants = PushConstants {
+            encoding: self.encoding,
+            paper_white: PAPER_WHITE_NITS,
+            peak_luminance: HDR_PEAK_NITS,
+        };
+        unsafe {
+            device.cmd_push_constants(
+                command_buffer,
+                self.pipeline.layout,
+                ash::vk::ShaderStageFlags::FRAGMENT,
+                0,
+                super::data_byte_slice(&push_constants),
+            );
+        }
+
+        // Set the viewport and scissor in the command buffer because we specified they would be set dynamically in the pipeline.
+        unsafe {
+            device.cmd_set_viewport(
+                command_buffer,
+                0,
+                &[ash::vk::Viewport {
+                    x: 0.,
+                    y: 0.,
+                    width: extent.width as f32,
+                    height: extent.height as f32,
+                    min_depth: 0.,
+                    max_depth: 1.,
+                }],
+            );
+            device.cmd_set_scissor(command_buffer, 0, &[render_area]);
+        }
+
+        // Bind the graphics pipeline and the descriptor set for the scene image.
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                ash::vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline.pipeline,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                ash::vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline.layout,
+                0,
+                &[self.descriptor_sets[image_index]],
+                &[],
+            );
+        }
+
+        // Draw the full screen quad.
+        unsafe { device.cmd_draw(command_buffer, 4, 1, 0, 0) };
+
+        // End the render pass.
+        unsafe { device.cmd_end_render_pass(command_buffer) };
+    }
+
+    /// Clean up the resources used by this tonemap pass.
+    fn destroy(
+        &mut self,
+        device: &ash::Device,
+        _memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    ) {
+        self.destroy_framebuffers(device);
+        unsafe { device.destroy_descriptor_pool(self.descriptor_pool, None) };
+
+        // Destroy the pipeline.
+        unsafe { device.destroy_pipeline(self.pipeline.pipeline, None) };
+        unsafe { device.destroy_pipeline_layout(self.pipeline.layout, None) };
+        unsafe { device.destroy_descriptor_set_layout(self.pipeline.descriptor_set_layout, None) };
+        unsafe { device.destroy_render_pass(self.pipeline.render_pass, None) };
+
+        // Destroy the shader modules.
+        unsafe { device.destroy_shader_module(self.pipeline.fullscreen_vert_shader, None) };
+        unsafe { device.destroy_shader_module(self.pipeline.tonemap_frag_shader, None) };
+
+        // Destroy the sampler.
+        unsafe { device.destroy_sampler(self.sampler, None) };
+    }
+}
@@ -0,0 +1,482 @@
+use super::{IndexedQueue, FIVE_SECONDS_IN_NANOSECONDS};
+
+/// The access of every copy recorded in a transfer.
+const COPY_ACCESS: ash::vk::AccessFlags2 = ash::vk::AccessFlags2::from_raw(
+    ash::vk::AccessFlags2::TRANSFER_READ.as_raw() | ash::vk::AccessFlags2::TRANSFER_WRITE.as_raw(),
+);
+
+/// Submit one-off copies between device buffers and the host, such as staging uploads and readbacks.
+/// Uses a dedicated transfer queue when the device exposes one, so copies do not occupy the graphics or compute queues.
+/// Buffers owned by a different queue family are released to the transfer queue family before the copies and returned after.
+pub struct TransferQueue {
+    queue: IndexedQueue,
+    command_pool: ash::vk::CommandPool,
+    command_buffer: ash::vk::CommandBuffer,
+    fence: ash::vk::Fence,
+    owner_released: ash::vk::Semaphore,
+    transfer_released: ash::vk::Semaphore,
+}
+
+impl TransferQueue {
+    /// Create the command pool and synchronization primitives for submitting copies to the queue.
+    pub fn new(device: &ash::Device, queue: IndexedQueue) -> Self {
+        let command_pool = unsafe {
+            device.create_command_pool(
+                &ash::vk::CommandPoolCreateInfo {
+                    flags: ash::vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER
+                        | ash::vk::CommandPoolCreateFlags::TRANSIENT,
+                    queue_family_index: queue.family_index,
+                    ..Default::default()
+                },
+                None,
+            )
+        }
+        .expect("Unable to create the transfer command pool");
+        let command_buffer = unsafe {
+            device.allocate_command_buffers(&ash::vk::CommandBufferAllocateInfo {
+                command_pool,
+                level: ash::vk::CommandBufferLevel::PRIMARY,
+                command_buffer_count: 1,
+                ..Default::default()
+            })
+        }
+        .expect("Unable to allocate the transfer command buffer")[0];
+
+        let (fence, owner_released, transfer_released) = unsafe {
+            (
+                device
+                    .create_fence(&ash::vk::FenceCreateInfo::default(), None)
+                    .expect("Unable to create the transfer fence"),
+                device
+                    .create_semaphore(&ash::vk::SemaphoreCreateInfo::default(), None)
+                    .expect("Unable to create the transfer semaphore"),
+                device
+                    .create_semaphore(&ash::vk::SemaphoreCreateInfo::default(), None)
+                    .expect("Unable to create the transfer semaphore"),
+            )
+        };
+
+        Self {
+            queue,
+            command_pool,
+            command_buffer,
+            fence,
+            owner_released,
+            transfer_released,
+        }
+    }
+
+    /// Destroy the command pool and synchronization primitives.
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            device.destroy_semaphore(self.transfer_released, None);
+            device.destroy_semaphore(self.owner_released, None);
+            device.destroy_fence(self.fence, None);
+            device.destroy_command_pool(self.command_pool, None);
+        }
+    }
+
+    /// Record copies with the transfer command buffer, submit them, and wait for them to complete.
+    /// The `buffers` are the device buffers the copies access, which are owned by the queue family of `owner_queue`.
+    /// If that family differs from the transfer queue family, command buffers are allocated from `owner_command_pool` to release the buffers to the transfer queue and acquire them again afterwards.
+    /// Writes to the buffers before the call are visible to the copies, and the copies are visible to the host and to later commands on the owner queue.
+    pub fn submit(
+        &self,
+        device: &ash::Device,
+        owner_queue: &IndexedQueue,
+        owner_command_pool: ash::vk::CommandPool,
+        buffers: &[ash::vk::Buffer],
+        record: impl FnOnce(ash::vk::CommandBuffer),
+    ) {
+        let (owner_family, transfer_family) = (owner_queue.family_index, self.queue.family_index);
+        let transfer_ownership = owner_family != transfer_family;
+        let ownership_barriers = |src_queue_family_index,
+                                  dst_queue_family_index,
+                                  src_stage_mask,
+                                  src_access_mask,
+                                  dst_stage_mask,
+                                  dst_access_mask| {
+            buffers
+                .iter()
+                .map(|&buffer| {
+                    ash::vk::BufferMemoryBarrier2::default()
+                        .src_stage_mask(src_stage_mask)
+                        .src_access_mask(src_access_mask)
+                        .dst_stage_mask(dst_stage_mask)
+                        .dst_access_mask(dst_access_mask)
+                        .src_queue_family_index(src_queue_family_index)
+                        .dst_queue_family_index(dst_queue_family_index)
+                        .buffer(buffer)
+                        .size(ash::vk::WHOLE_SIZE)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Release the buffers from the owner queue family after any of its writes, and acquire them on the transfer queue family before the copies.
+        let release_barriers = ownership_barriers(
+            owner_family,
+            transfer_family,
+            ash::vk::PipelineStageFlags2::ALL_COMMANDS,
+            ash::vk::AccessFlags2::MEMORY_WRITE,
+            ash::vk::PipelineStageFlags2::NONE,
+            ash::vk::AccessFlags2::NONE,
+        );
+        let acquire_barriers = ownership_barriers(
+            owner_family,
+            transfer_family,
+            ash::vk::PipelineStageFlags2::NONE,
+            ash::vk::AccessFlags2::NONE,
+            ash::vk::PipelineStageFlags2::COPY,
+            COPY_ACCESS,
+        );
+
+        // Return the buffers to the owner queue family after the copies, and before any of its later commands.
+        let return_release_barriers = ownership_barriers(
+            transfer_family,
+            owner_family,
+            ash::vk::PipelineStageFlags2::COPY,
+            COPY_ACCESS,
+            ash::vk::PipelineStageFlags2::NONE,
+            ash::vk::AccessFlags2::NONE,
+        );
+        let return_acquire_barriers = ownership_barriers(
+            transfer_family,
+            owner_family,
+            ash::vk::PipelineStageFlags2::NONE,
+            ash::vk::AccessFlags2::NONE,
+            ash::vk::PipelineStageFlags2::ALL_COMMANDS,
+            ash::vk::AccessFlags2::MEMORY_READ | ash::vk::AccessFlags2::MEMORY_WRITE,
+        );
+
+        unsafe {
+            device
+                .begin_command_buffer(
+                    self.command_buffer,
+                    &ash::vk::CommandBufferBeginInfo::default()
+                        .flags(ash::vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+                )
+                .expect("Failed to begin recording the transfer command buffer");
+            if transfer_ownership {
+                device.cmd_pipeline_barrier2(
+                    self.command_buffer,
+                    &ash::vk::DependencyInfo::default().buffer_memory_barriers(&acquire_barriers),
+                );
+            } else {
+                let barrier = ash::vk::MemoryBarrier2::default()
+                    .src_stage_mask(ash::vk::PipelineStageFlags2::ALL_COMMANDS)
+                    .src_access_mask(ash::vk::AccessFlags2::MEMORY_WRITE)
+                    .dst_stage_mask(ash::vk::PipelineStageFlags2::COPY)
+                    .dst_access_mask(COPY_ACCESS);
+                device.cmd_pipeline_barrier2(
+                    self.command_buffer,
+                    &ash::vk::DependencyInfo::default().memory_barriers(&[barrier]),
+                );
+            }
+        }
+
+        record(self.command_buffer);
+
+        unsafe {
+            // Make the copies visible to the host, and to later commands on the same queue.
+            let barrier = ash::vk::MemoryBarrier2::default()
+                .src_stage_mask(ash::vk::PipelineStageFlags2::COPY)
+                .src_access_mask(ash::vk::AccessFlags2::TRANSFER_WRITE)
+                .dst_stage_mask(
+                    ash::vk::PipelineStageFlags2::ALL_COMMANDS | ash::vk::PipelineStageFlags2::HOST,
+                )
+                .dst_access_mask(
+                    ash::vk::AccessFlags2::MEMORY_READ
+                        | ash::vk::AccessFlags2::MEMORY_WRITE
+                        | ash::vk::AccessFlags2::HOST_READ,
+                );
+            let mut dependency_info =
+                ash::vk::DependencyInfo::default().memory_barriers(std::slice::from_ref(&barrier));
+            if transfer_ownership {
+                dependency_info = dependency_info.buffer_memory_barriers(&return_release_barriers);
+            }
+            device.cmd_pipeline_barrier2(self.command_buffer, &dependency_info);
+            device
+                .end_command_buffer(self.command_buffer)
+                .expect("Failed to end recording the transfer command buffer");
+        }
+
+        if !transfer_ownership {
+            unsafe {
+                device
+                    .queue_submit(
+                        self.queue.queue,
+                        &[ash::vk::SubmitInfo::default().command_buffers(&[self.command_buffer])],
+                        self.fence,
+                    )
+                    .expect("Failed to submit the transfer command buffer");
+            }
+            self.wait_and_reset(device);
+            return;
+        }
+
+        // Record the release and acquire of the buffers on the owner queue, around the copies.
+        let owner_command_buffers = unsafe {
+            device.allocate_command_buffers(&ash::vk::CommandBufferAllocateInfo {
+                command_pool: owner_command_pool,
+                level: ash::vk::CommandBufferLevel::PRIMARY,
+                command_buffer_count: 2,
+                ..Default::default()
+            })
+        }
+        .expect("Unable to allocate the queue ownership transfer command buffers");
+        for (&command_buffer, barriers) in owner_command_buffers
+            .iter()
+            .zip([&release_barriers, &return_acquire_barriers])
+        {
+            unsafe {
+                device
+                    .begin_command_buffer(
+                        command_buffer,
+                        &ash::vk::CommandBufferBeginInfo::default()
+                            .flags(ash::vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+                    )
+                    .expect("Failed to begin recording a queue ownership transfer command buffer");
+                device.cmd_pipeline_barrier2(
+                    command_buffer,
+                    &ash::vk::DependencyInfo::default().buffer_memory_barriers(barriers),
+                );
+                device
+                    .end_command_buffer(command_buffer)
+                    .expect("Failed to end recording a queue ownership transfer command buffer");
+            }
+        }
+
+        // Order the release, the copies, and the acquire with semaphores, since they are submitted to different queues.
+        // The copies complete before the acquire begins, so the fence of the acquire signals once both are done.
+        unsafe {
+            device
+                .queue_submit(
+                    owner_queue.queue,
+                    &[ash::vk::SubmitInfo::default()
+                        .command_buffers(&owner_command_buffers[..1])
+                        .signal_semaphores(&[self.owner_released])],
+                    ash::vk::Fence::null(),
+                )
+                .expect("Failed to submit the release of the buffers to the transfer queue");
+            device
+                .queue_submit(
+                    self.queue.queue,
+                    &[ash::vk::SubmitInfo::default()
+                        .wait_semaphores(&[self.owner_released])
+                        .wait_dst_stage_mask(&[ash::vk::PipelineStageFlags::TRANSFER])
+                        .command_buffers(&[self.command_buffer])
+                        .signal_semaphores(&[self.transfer_released])],
+                    ash::vk::Fence::null(),
+                )
+                .expect("Failed to submit the transfer command buffer");
+            device
+                .queue_submit(
+                    owner_queue.queue,
+                    &[ash::vk::SubmitInfo::default()
+                        .wait_semaphores(&[self.transfer_released])
+                        .wait_dst_stage_mask(&[ash::vk::PipelineStageFlags::ALL_COMMANDS])
+                        .command_buffers(&owner_command_buffers[1..])],
+                    self.fence,
+                )
+                .expect("Failed to submit the return of the buffers to the owner queue");
+        }
+        self.wait_and_reset(device);
+
+        unsafe { device.free_command_buffers(owner_command_pool, &owner_command_buffers) };
+    }
+
+    /// Record copies into new images with the transfer command buffer, submit them, and wait for them to complete.
+    /// The images are in `TRANSFER_DST_OPTIMAL` layout while `record` copies to them, and are left in `final_layout` for use by later commands on `owner_queue`.
+    /// Their previous contents are discarded, so if the queue families differ the images are only released to the owner queue family after the copies, and acquired with a command buffer allocated from `owner_command_pool`.
+    pub fn upload_images(
+        &self,
+        device: &ash::Device,
+        owner_queue: &IndexedQueue,
+        owner_command_pool: ash::vk::CommandPool,
+        images: &[ash::vk::Image],
+        final_layout: ash::vk::ImageLayout,
+        record: impl FnOnce(ash::vk::CommandBuffer),
+    ) {
+        let (owner_family, transfer_family) = (owner_queue.family_index, self.queue.family_index);
+        let transfer_ownership = owner_family != transfer_family;
+        let image_barriers = |(src_queue_family_index, dst_queue_family_index),
+                              src_stage_mask,
+                              src_access_mask,
+                              dst_stage_mask,
+                              dst_access_mask,
+                              old_layout,
+                              new_layout| {
+            images
+                .iter()
+                .map(|&image| {
+                    ash::vk::ImageMemoryBarrier2::default()
+                        .src_stage_mask(src_stage_mask)
+                        .src_access_mask(src_access_mask)
+                        .dst_stage_mask(dst_stage_mask)
+                        .dst_access_mask(dst_access_mask)
+                        .old_layout(old_layout)
+                        .new_layout(new_layout)
+                        .src_queue_family_index(src_queue_family_index)
+                        .dst_queue_family_index(dst_queue_family_index)
+                        .image(image)
+                        .subresource_range(ash::vk::ImageSubresourceRange {
+                            aspect_mask: ash::vk::ImageAspectFlags::COLOR,
+                            base_mip_level: 0,
+                            level_count: ash::vk::REMAINING_MIP_LEVELS,
+                            base_array_layer: 0,
+                            layer_count: ash::vk::REMAINING_ARRAY_LAYERS,
+                        })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Discard the previous contents of the images, which need no ownership transfer to be written by the copies.
+        let copy_barriers = image_barriers(
+            (ash::vk::QUEUE_FAMILY_IGNORED, ash::vk::QUEUE_FAMILY_IGNORED),
+            ash::vk::PipelineStageFlags2::NONE,
+            ash::vk::AccessFlags2::NONE,
+            ash::vk::PipelineStageFlags2::COPY,
+            ash::vk::AccessFlags2::TRANSFER_WRITE,
+            ash::vk::ImageLayout::UNDEFINED,
+            ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        );
+
+        // Release the images to the owner queue family after the copies, or make them visible to later commands on the same family.
+        let (release_families, release_stage_mask, release_access_mask) = if transfer_ownership {
+            (
+                (transfer_family, owner_family),
+                ash::vk::PipelineStageFlags2::NONE,
+                ash::vk::AccessFlags2::NONE,
+            )
+        } else {
+            (
+                (ash::vk::QUEUE_FAMILY_IGNORED, ash::vk::QUEUE_FAMILY_IGNORED),
+                ash::vk::PipelineStageFlags2::ALL_COMMANDS,
+                ash::vk::AccessFlags2::MEMORY_READ,
+            )
+        };
+        let release_barriers = image_barriers(
+            release_families,
+            ash::vk::PipelineStageFlags2::COPY,
+            ash::vk::AccessFlags2::TRANSFER_WRITE,
+            release_stage_mask,
+            release_access_mask,
+            ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            final_layout,
+        );
+        let acquire_barriers = image_barriers(
+            (transfer_family, owner_family),
+            ash::vk::PipelineStageFlags2::NONE,
+            ash::vk::AccessFlags2::NONE,
+            ash::vk::PipelineStageFlags2::ALL_COMMANDS,
+            ash::vk::AccessFlags2::MEMORY_READ,
+            ash::vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            final_layout,
+        );
+
+        unsafe {
+            device
+                .begin_command_buffer(
+                    self.command_buffer,
+                    &ash::vk::CommandBufferBeginInfo::default()
+                        .flags(ash::vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+                )
+                .expect("Failed to begin recording the transfer command buffer");
+            device.cmd_pipeline_barrier2(
+                self.command_buffer,
+                &ash::vk::DependencyInfo::default().image_memory_barriers(&copy_barriers),
+            );
+        }
+
+        record(self.command_buffer);
+
+        unsafe {
+            device.cmd_pipeline_barrier2(
+                self.command_buffer,
+                &ash::vk::DependencyInfo::default().image_memory_barriers(&release_barriers),
+            );
+            device
+                .end_command_buffer(self.command_buffer)
+                .expect("Failed to end recording the transfer command buffer");
+        }
+
+        if !transfer_ownership {
+            unsafe {
+                device
+                    .queue_submit(
+                        self.queue.queue,
+                        &[ash::vk::SubmitInfo::default().command_buffers(&[self.command_buffer])],
+                        self.fence,
+                    )
+                    .expect("Failed to submit the transfer command buffer");
+            }
+            self.wait_and_reset(device);
+            return;
+        }
+
+        // Record the acquire of the images on the owner queue, after the copies.
+        let owner_command_buffer = unsafe {
+            device.allocate_command_buffers(&ash::vk::CommandBufferAllocateInfo {
+                command_pool: owner_command_pool,
+                level: ash::vk::CommandBufferLevel::PRIMARY,
+                command_buffer_count: 1,
+                ..Default::default()
+            })
+        }
+        .expect("Unable to allocate the queue ownership transfer command buffer")[0];
+        unsafe {
+            device
+                .begin_command_buffer(
+                    owner_command_buffer,
+                    &ash::vk::CommandBufferBeginInfo::default()
+                        .flags(ash::vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+                )
+                .expect("Failed to begin recording a queue ownership transfer command buffer");
+            device.cmd_pipeline_barrier2(
+                owner_command_buffer,
+                &ash::vk::DependencyInfo::default().image_memory_barriers(&acquire_barriers),
+            );
+            device
+                .end_command_buffer(owner_command_buffer)
+                .expect("Failed to end recording a queue ownership transfer command buffer");
+        }
+
+        // The acquire waits on the copies with a semaphore, so the fence of the acquire signals once both are done.
+        unsafe {
+            device
+                .queue_submit(
+                    self.queue.queue,
+                    &[ash::vk::SubmitInfo::default()
+                        .command_buffers(&[self.command_buffer])
+                        .signal_semaphores(&[self.transfer_released])],
+                    ash::vk::Fence::null(),
+                )
+                .expect("Failed to submit the transfer command buffer");
+            device
+                .queue_submit(
+                    owner_queue.queue,
+                    &[ash::vk::SubmitInfo::default()
+                        .wait_semaphores(&[self.transfer_released])
+                        .wait_dst_stage_mask(&[ash::vk::PipelineStageFlags::ALL_COMMANDS])
+                        .command_buffers(&[owner_command_buffer])],
+                    self.fence,
+                )
+                .expect("Failed to submit the acquire of the images by the owner queue");
+        }
+        self.wait_and_reset(device);
+
+        unsafe { device.free_command_buffers(owner_command_pool, &[owner_command_buffer]) };
+    }
+
+    /// Wait for the submitted transfer to complete, then reset the fence for the next one.
+    fn wait_and_reset(&self, device: &ash::Device) {
+        unsafe {
+            device
+                .wait_for_fences(&[self.fence], true, FIVE_SECONDS_IN_NANOSECONDS)
+                .expect("Unable to wait for the transfer to complete");
+            device
+                .reset_fences(&[self.fence])
+                .expect("Unable to reset the transfer fence");
+        }
+    }
+}
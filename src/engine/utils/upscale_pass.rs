@@ -0,0 +1,734 @@
+use super::{
+    post_process::{FrameExtent, PostProcessPass, SCENE_FORMAT},
+    shaders::ENTRY_POINT_MAIN,
+};
+
+/// Shader for resampling the scene from the render resolution to the output resolution.
+const UPSCALE_FRAGMENT: &[u32] =
+    inline_spirv::include_spirv!("src/shaders/upscale_frag.glsl", frag, glsl);
+
+/// Shader for sharpening the edge-adaptive upscale, restoring detail lost to the lower render resolution.
+const RCAS_FRAGMENT: &[u32] =
+    inline_spirv::include_spirv!("src/shaders/rcas_frag.glsl", frag, glsl);
+
+/// The strength of sharpening after an edge-adaptive upscale, in stops below the maximum. Zero is the sharpest.
+const RCAS_SHARPNESS_STOPS: f32 = 0.2;
+
+/// The smallest fraction of the output resolution the scene may be rendered at.
+pub const MIN_RENDER_SCALE: f32 = 0.25;
+
+/// The filters available for upscaling the scene to the output resolution.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UpscaleFilter {
+    /// Blend the four nearest pixels. The fastest, but the softest.
+    Bilinear = 0,
+
+    /// A two-lobed Lanczos kernel over the sixteen nearest pixels, keeping more detail than bilinear.
+    Lanczos = 1,
+
+    /// An edge-adaptive upscale followed by contrast-adaptive sharpening, in the style of FSR 1 (EASU and RCAS).
+    #[default]
+    Fsr = 2,
+}
+
+/// The resolution the scene is rendered at, and how it is upscaled to the resolution of the swapchain.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UpscaleSettings {
+    /// The fraction of the swapchain width and height the scene is rendered at. One disables upscaling.
+    pub render_scale: f32,
+
+    /// The filter used to upscale the scene when the render scale is below one.
+    pub filter: UpscaleFilter,
+
+    /// Whether the render scale changes while running, such as with dynamic resolution.
+    /// The scene is then always upscaled, even at full scale, so that changing the scale does not rebuild the post-processing chain.
+    pub dynamic: bool,
+}
+impl Default for UpscaleSettings {
+    fn default() -> Self {
+        Self {
+            render_scale: 1.,
+            filter: UpscaleFilter::default(),
+            dynamic: false,
+        }
+    }
+}
+impl UpscaleSettings {
+    /// The size the scene is rendered at for the given output size, never smaller than one pixel.
+    pub fn render_extent(&self, extent: ash::vk::Extent2D) -> ash::vk::Extent2D {
+        let scale = |size: u32| ((size as f32 * self.render_scale).round() as u32).clamp(1, size);
+        ash::vk::Extent2D {
+            width: scale(extent.width),
+            height: scale(extent.height),
+        }
+    }
+}
+
+/// Adjust the render scale to keep the GPU time of each frame near a target.
+/// The scale is lowered quickly when frames take too long, and raised slowly when there is time to spare, to avoid oscillating.
+pub struct DynamicResolution {
+    target_frame_time: f32,
+    average_frame_time: Option<f32>,
+    frames_since_change: u32,
+}
+
+impl DynamicResolution {
+    /// The number of frames to measure after a change before the scale is changed again.
+    const SETTLE_FRAMES: u32 = 30;
+
+    /// How much the render scale changes at a time.
+    const SCALE_STEP: f32 = 0.05;
+
+    /// The weight of the newest frame time in the moving average.
+    const SMOOTHING: f32 = 0.1;
+
+    /// Create a controller aiming for the given GPU time per frame, in milliseconds.
+    pub fn new(target_frame_time: f32) -> Self {
+        Self {
+            target_frame_time,
+            average_frame_time: None,
+            frames_since_change: 0,
+        }
+    }
+
+    /// Record the GPU time of the latest frame, in milliseconds, and return a new render scale if it should change.
+    pub fn update(&mut self, frame_time: f32, render_scale: f32) -> Option<f32> {
+        let average = self.average_frame_time.map_or(frame_time, |average| {
+            average + Self::SMOOTHING * (frame_time - average)
+        });
+        self.average_frame_time = Some(average);
+        self.frames_since_change += 1;
+        if self.frames_since_change < Self::SETTLE_FRAMES {
+            return None;
+        }
+
+        // The GPU time scales roughly with the number of pixels, so step twice as far when far over budget.
+        let new_scale = if average > 1.5 * self.target_frame_time {
+            render_scale - 2. * Self::SCALE_STEP
+        } else if average > 1.05 * self.target_frame_time {
+            render_scale - Self::SCALE_STEP
+        } else if average < 0.8 * self.target_frame_time {
+            render_scale + Self::SCALE_STEP
+        } else {
+            return None;
+        }
+        .clamp(MIN_RENDER_SCALE, 1.);
+
+        if (new_scale - render_scale).abs() < f32::EPSILON {
+            return None;
+        }
+        self.frames_since_change = 0;
+        self.average_frame_time = None; // Frames at the old scale no longer represent the cost of rendering.
+        Some(new_scale)
+    }
+}
+
+/// Define the push constants that are used in the fragment shader of the upscale stage.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct UpscalePushConstants {
+    /// The reciprocal of the width and height of the output image.
+    inverse_output_size: [f32; 2],
+
+    /// The width and height of the area of the input image the scene was rendered to.
+    input_size: [f32; 2],
+
+    /// The filter used to resample the scene.
+    filter: UpscaleFilter,
+}
+
+/// Define the push constants that are used in the fragment shader of the sharpening stage.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct SharpenPushConstants {
+    /// The strength of sharpening, where one is the maximum.
+    sharpness: f32,
+}
+
+/// A graphics pipeline for one of the fullscreen stages of the upscale pass, and the render pass it draws in.
+struct Stage {
+    render_pass: ash::vk::RenderPass,
+    layout: ash::vk::PipelineLayout,
+    pipeline: ash::vk::Pipeline,
+    descriptor_set_layout: ash::vk::DescriptorSetLayout,
+    frag_shader: ash::vk::ShaderModule,
+}
+
+impl Stage {
+    /// Create a new render pass and graphics pipeline for one of the upscale stages, which samples a single image.
+    fn new(
+        device: &ash::Device,
+        vert_shader: ash::vk::ShaderModule,
+        fragment_code: &[u32],
+        push_constants_size: usize,
+        final_layout: ash::vk::ImageLayout,
+        sampler: ash::vk::Sampler,
+    ) -> Self {
+        let frag_shader = super::create_shader_module(device, fragment_code);
+        let render_pass = Self::create_render_pass(device, final_layout);
+
+        let shader_stages = [
+            ash::vk::PipelineShaderStageCreateInfo::default()
+                .stage(ash::vk::ShaderStageFlags::VERTEX)
+                .module(vert_shader)
+                .name(ENTRY_POINT_MAIN),
+            ash::vk::PipelineShaderStageCreateInfo::default()
+                .stage(ash::vk::ShaderStageFlags::FRAGMENT)
+                .module(frag_shader)
+                .name(ENTRY_POINT_MAIN),
+        ];
+
+        // Define the push constants that will be used by the fragment shader.
+        let push_constants_range = ash::vk::PushConstantRange {
+            stage_flags: ash::vk::ShaderStageFlags::FRAGMENT,
+            offset: 0,
+            size: push_constants_size as u32,
+        };
+
+        let viewport_state = ash::vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        let input_state = ash::vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(ash::vk::PrimitiveTopology::TRIANGLE_STRIP);
+        let rasterizer = ash::vk::PipelineRasterizationStateCreateInfo::default().line_width(1.);
+        let sampling = ash::vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(ash::vk::SampleCountFlags::TYPE_1);
+
+        // Every stage overwrites its whole output, so blending is disabled.
+        let color_blend_attachment = [ash::vk::PipelineColorBlendAttachmentState {
+            color_write_mask: ash::vk::ColorComponentFlags::RGBA,
+            ..Default::default()
+        }];
+        let color_blend_state = ash::vk::PipelineColorBlendStateCreateInfo::default()
+            .attachments(&color_blend_attachment);
+
+        // Create the descriptor set layout.
+        let descriptor_set_layout = {
+            let sampler = [sampler];
+            let bindings = [ash::vk::DescriptorSetLayoutBinding::default()
+                .binding(0)
+                .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .stage_flags(ash::vk::ShaderStageFlags::FRAGMENT)
+                .immutable_samplers(&sampler)];
+            let descriptor_set_info =
+                ash::vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+
+            unsafe { device.create_descriptor_set_layout(&descriptor_set_info, None) }
+                .expect("Failed to create descriptor set layout for the upscale pass")
+        };
+
+        // Create the pipeline layout.
+        let layout = {
+            let descriptor_set_layout = [descriptor_set_layout];
+            let push_constants_range = [push_constants_range];
+            let layout_info = ash::vk::PipelineLayoutCreateInfo::default()
+                .set_layouts(&descriptor_set_layout)
+                .push_constant_ranges(&push_constants_range);
+
+            unsafe { device.create_pipeline_layout(&layout_info, None) }
+                .expect("Failed to create pipeline layout for the upscale pass")
+        };
+
+        // Use dynamic states for the viewport and scissor rectangles.
+        let dynamic_states = ash::vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&[
+            ash::vk::DynamicState::VIEWPORT,
+            ash::vk::DynamicState::SCISSOR,
+        ]);
+
+        let pipeline = {
+            let pipeline_info = ash::vk::GraphicsPipelineCreateInfo {
+                stage_count: shader_stages.len() as u32,
+                p_stages: shader_stages.as_ptr(),
+                p_vertex_input_state: &ash::vk::PipelineVertexInputStateCreateInfo::default(),
+                p_input_assembly_state: &input_state,
+                p_viewport_state: &viewport_state,
+                p_rasterization_state: &rasterizer,
+                p_multisample_state: &sampling,
+                p_color_blend_state: &color_blend_state,
+                p_dynamic_state: &dynamic_states,
+                layout,
+                render_pass,
+                subpass: 0,
+                base_pipeline_index: -1,
+                ..Default::default()
+            };
+
+            unsafe {
+                device.create_graphics_pipelines(
+                    ash::vk::PipelineCache::null(),
+                    &[pipeline_info],
+                    None,
+                )
+            }
+            .expect("Failed to create graphics pipeline for the upscale pass")[0]
+        };
+
+        Self {
+            render_pass,
+            layout,
+            pipeline,
+            descriptor_set_layout,
+            frag_shader,
+        }
+    }
+
+    /// Helper to create a render pass which overwrites a single color attachment in the scene format.
+    fn create_render_pass(
+        device: &ash::Device,
+        final_layout: ash::vk::ImageLayout,
+    ) -> ash::vk::RenderPass {
+        let attachment = [ash::vk::AttachmentDescription {
+            format: SCENE_FORMAT,
+            samples: ash::vk::SampleCountFlags::TYPE_1,
+            load_op: ash::vk::AttachmentLoadOp::DONT_CARE, // Each pixel is re-drawn so a clear is not necessary.
+            store_op: ash::vk::AttachmentStoreOp::STORE,
+            initial_layout: ash::vk::ImageLayout::UNDEFINED,
+            final_layout,
+            ..Default::default()
+        }];
+        let color_attachment_reference = [ash::vk::AttachmentReference {
+            attachment: 0,
+            layout: ash::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        }];
+        let subpass_description = [ash::vk::SubpassDescription::default()
+            .pipeline_bind_point(ash::vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_attachment_reference)];
+
+        // Wait for any earlier reads of the attachment before writing, and make the writes visible to the stages which sample it.
+        let subpass_dependencies = [
+            ash::vk::SubpassDependency {
+                src_subpass: ash::vk::SUBPASS_EXTERNAL,
+                dst_subpass: 0,
+                src_stage_mask: ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                    | ash::vk::PipelineStageFlags::FRAGMENT_SHADER,
+                dst_stage_mask: ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access_mask: ash::vk::AccessFlags::NONE,
+                dst_access_mask: ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                ..Default::default()
+            },
+            ash::vk::SubpassDependency {
+                src_subpass: 0,
+                dst_subpass: ash::vk::SUBPASS_EXTERNAL,
+                src_stage_mask: ash::vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_stage_mask: ash::vk::PipelineStageFlags::FRAGMENT_SHADER,
+                src_access_mask: ash::vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_access_mask: ash::vk::AccessFlags::SHADER_READ,
+                ..Default::default()
+            },
+        ];
+
+        let render_pass_info = ash::vk::RenderPassCreateInfo::default()
+            .attachments(&attachment)
+            .subpasses(&subpass_description)
+            .dependencies(&subpass_dependencies);
+        unsafe { device.create_render_pass(&render_pass_info, None) }
+            .expect("Failed to create render pass for the upscale pass")
+    }
+
+    /// Record a fullscreen draw of this stage to the given framebuffer.
+    fn render(
+        &self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        extent: ash::vk::Extent2D,
+        framebuffer: ash::vk::Framebuffer,
+        descriptor_set: ash::vk::DescriptorSet,
+        push_constants: &[u8],
+    ) {
+        let render_area = ash::vk::Rect2D {
+            offset: ash::vk::Offset2D::default(),
+            extent,
+        };
+        unsafe {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &ash::vk::RenderPassBeginInfo::default()
+                    .render_pass(self.render_pass)
+                    .framebuffer(framebuffer)
+                    .render_area(render_area),
+                ash::vk::SubpassContents::INLINE,
+            );
+            device.cmd_bind_pipeline(
+                command_buffer,
+                ash::vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline,
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.layout,
+                ash::vk::ShaderStageFlags::FRAGMENT,
+                0,
+                push_constants,
+            );
+            device.cmd_set_viewport(
+                command_buffer,
+                0,
+                &[ash::vk::Viewport {
+                    x: 0.,
+                    y: 0.,
+                    width: extent.width as f32,
+                    height: extent.height as f32,
+                    min_depth: 0.,
+                    max_depth: 1.,
+                }],
+            );
+            device.cmd_set_scissor(command_buffer, 0, &[render_area]);
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                ash::vk::PipelineBindPoint::GRAPHICS,
+                self.layout,
+                0,
+                &[descriptor_set],
+                &[],
+            );
+
+            // Draw the full screen quad.
+            device.cmd_draw(command_buffer, 4, 1, 0, 0);
+            device.cmd_end_render_pass(command_buffer);
+        }
+    }
+
+    /// Clean up the resources used by this stage.
+    fn destroy(&self, device: &ash::Device) {
+        unsafe { device.destroy_pipeline(self.pipeline, None) };
+        unsafe { device.destroy_pipeline_layout(self.layout, None) };
+        unsafe { device.destroy_descriptor_set_layout(self.descriptor_set_layout, None) };
+        unsafe { device.destroy_render_pass(self.render_pass, None) };
+        unsafe { device.destroy_shader_module(self.frag_shader, None) };
+    }
+}
+
+/// An image written by the upscale stage and sampled by the sharpening stage, with the framebuffer used to render to it.
+struct InternalImage {
+    image: ash::vk::Image,
+    image_view: ash::vk::ImageView,
+    allocation: gpu_allocator::vulkan::Allocation,
+    framebuffer: ash::vk::Framebuffer,
+}
+
+/// Resample the scene from the render resolution to the output resolution, so that demos may render fewer pixels than are presented.
+/// The scene is read from the render area at the top-left of the input images, and the destination images must match the extent given to the pass.
+pub struct UpscalePass {
+    upscale: Stage,
+    sharpen: Option<Stage>,
+    fullscreen_vert_shader: ash::vk::ShaderModule,
+    sampler: ash::vk::Sampler,
+    filter: UpscaleFilter,
+    upscaled_images: Vec<InternalImage>,
+    framebuffers: Vec<ash::vk::Framebuffer>,
+    descriptor_pool: ash::vk::DescriptorPool,
+    descriptor_sets: Vec<[ash::vk::DescriptorSet; 2]>,
+}
+
+impl UpscalePass {
+    const OPTIMAL_INTERNAL_IMAGE_LAYOUT: ash::vk::ImageLayout =
+        ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+
+    /// Create a new upscale pass and associated resources.
+    /// The input images are sampled and the result is written to the destination images with the same index.
+    pub fn new(
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        extent: ash::vk::Extent2D,
+        filter: UpscaleFilter,
+        input_views: &[ash::vk::ImageView],
+        destination_views: &[ash::vk::ImageView],
+        destination_layout: ash::vk::ImageLayout,
+    ) -> Self {
+        // Create the sampler. Bilinear upscaling relies on linear filtering, and the other filters fetch pixels directly.
+        let sampler = {
+            let sampler_info = ash::vk::SamplerCreateInfo {
+                mag_filter: ash::vk::Filter::LINEAR,
+                min_filter: ash::vk::Filter::LINEAR,
+                mipmap_mode: ash::vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: ash::vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_lod: ash::vk::LOD_CLAMP_NONE,
+                ..Default::default()
+            };
+            unsafe { device.create_sampler(&sampler_info, None) }
+                .expect("Failed to create sampler for the upscale pass")
+        };
+
+        // Create the render passes and graphics pipelines. Only the edge-adaptive filter is followed by sharpening.
+        let fullscreen_vert_shader =
+            super::create_shader_module(device, super::shaders::FULLSCREEN_VERTEX);
+        let sharpened = filter == UpscaleFilter::Fsr;
+        let upscale = Stage::new(
+            device,
+            fullscreen_vert_shader,
+            UPSCALE_FRAGMENT,
+            std::mem::size_of::<UpscalePushConstants>(),
+            if sharpened {
+                Self::OPTIMAL_INTERNAL_IMAGE_LAYOUT
+            } else {
+                destination_layout
+            },
+            sampler,
+        );
+        let sharpen = sharpened.then(|| {
+            Stage::new(
+                device,
+                fullscreen_vert_shader,
+                RCAS_FRAGMENT,
+                std::mem::size_of::<SharpenPushConstants>(),
+                destination_layout,
+                sampler,
+            )
+        });
+
+        // Create a descriptor pool with two sets for each input image, one for each stage.
+        let descriptor_pool = {
+            let pool_sizes = [ash::vk::DescriptorPoolSize {
+                ty: ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 2 * input_views.len() as u32,
+            }];
+            let pool_info = ash::vk::DescriptorPoolCreateInfo::default()
+                .max_sets(2 * input_views.len() as u32)
+                .pool_sizes(&pool_sizes);
+            unsafe { device.create_descriptor_pool(&pool_info, None) }
+                .expect("Failed to create descriptor pool for the upscale pass")
+        };
+
+        let mut upscale_pass = Self {
+            upscale,
+            sharpen,
+            fullscreen_vert_shader,
+            sampler,
+            filter,
+            upscaled_images: Vec::new(),
+            framebuffers: Vec::new(),
+            descriptor_pool,
+            descriptor_sets: Vec::new(),
+        };
+        upscale_pass.create_framebuffers(
+            device,
+            memory_allocator,
+            extent,
+            input_views,
+            destination_views,
+        );
+        upscale_pass
+    }
+
+    /// Helper to create the internal images, framebuffers, and descriptor sets, which all depend on the size of the images.
+    fn create_framebuffers(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+        extent: ash::vk::Extent2D,
+        input_views: &[ash::vk::ImageView],
+        destination_views: &[ash::vk::ImageView],
+    ) {
+        let create_framebuffer = |render_pass, image_view: &ash::vk::ImageView| {
+            let framebuffer_info = ash::vk::FramebufferCreateInfo {
+                render_pass,
+                attachment_count: 1,
+                p_attachments: image_view,
+                width: extent.width,
+                height: extent.height,
+                layers: 1,
+                ..Default::default()
+            };
+            unsafe { device.create_framebuffer(&framebuffer_info, None) }
+                .expect("Failed to create framebuffer for the upscale pass")
+        };
+
+        // The upscale stage renders to the destination directly, unless it is sharpened afterwards.
+        let final_render_pass = if let Some(sharpen) = &self.sharpen {
+            let image_info = ash::vk::ImageCreateInfo {
+                image_type: ash::vk::ImageType::TYPE_2D,
+                format: SCENE_FORMAT,
+                extent: ash::vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                },
+                mip_levels: 1,
+                array_layers: 1,
+                samples: ash::vk::SampleCountFlags::TYPE_1,
+                usage: ash::vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | ash::vk::ImageUsageFlags::SAMPLED,
+                ..Default::default()
+            };
+            self.upscaled_images = (0..input_views.len())
+                .map(|_| {
+                    let (image, allocation) = super::create_image(
+                        device,
+                        memory_allocator,
+                        &image_info,
+                        "Upscaled Image",
+                    );
+                    let image_view = super::create_image_view(device, image, SCENE_FORMAT, 1);
+                    InternalImage {
+                        image,
+                        image_view,
+                        allocation,
+                        framebuffer: create_framebuffer(self.upscale.render_pass, &image_view),
+                    }
+                })
+                .collect();
+            sharpen.render_pass
+        } else {
+            self.upscale.render_pass
+        };
+        self.framebuffers = destination_views
+            .iter()
+            .map(|destination_view| create_framebuffer(final_render_pass, destination_view))
+            .collect();
+
+        // Allocate a descriptor set for each stage, and point them at the images they sample.
+        let set_layouts = [
+            self.upscale.descriptor_set_layout,
+            self.sharpen
+                .as_ref()
+                .map_or(self.upscale.descriptor_set_layout, |sharpen| {
+                    sharpen.descriptor_set_layout
+                }),
+        ];
+        self.descriptor_sets = input_views
+            .iter()
+            .enumerate()
+            .map(|(index, &input_view)| {
+                let sets: [ash::vk::DescriptorSet; 2] = unsafe {
+                    device.allocate_descriptor_sets(
+                        &ash::vk::DescriptorSetAllocateInfo::default()
+                            .descriptor_pool(self.descriptor_pool)
+                            .set_layouts(&set_layouts),
+                    )
+                }
+                .expect("Failed to allocate descriptor sets for the upscale pass")
+                .try_into()
+                .expect("Upscale descriptor set allocation returned the wrong number of sets");
+
+                // The second set is unused without sharpening, so it samples the input to remain valid.
+                let sharpen_input = self
+                    .upscaled_images
+                    .get(index)
+                    .map_or(input_view, |upscaled| upscaled.image_view);
+                let image_info = |image_view| {
+                    [ash::vk::DescriptorImageInfo {
+                        sampler: ash::vk::Sampler::null(), // Immutable samplers are set in the layout.
+                        image_view,
+                        image_layout: Self::OPTIMAL_INTERNAL_IMAGE_LAYOUT,
+                    }]
+                };
+                let bindings = [
+                    (sets[0], image_info(input_view)),
+                    (sets[1], image_info(sharpen_input)),
+                ];
+                let writes: Vec<_> = bindings
+                    .iter()
+                    .map(|(set, image_info)| {
+                        ash::vk::WriteDescriptorSet::default()
+                            .dst_set(*set)
+                            .dst_binding(0)
+                            .descriptor_type(ash::vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                            .image_info(image_info)
+                    })
+                    .collect();
+                unsafe { device.update_descriptor_sets(&writes, &[]) };
+                sets
+            })
+            .collect();
+    }
+
+    /// Helper to destroy the internal images, framebuffers, and descriptor sets.
+    fn destroy_framebuffers(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    ) {
+        unsafe {
+            device.reset_descriptor_pool(
+                self.descriptor_pool,
+                ash::vk::DescriptorPoolResetFlags::empty(),
+            )
+        }
+        .expect("Failed to free upscale descriptor sets");
+        self.descriptor_sets.clear();
+
+        for framebuffer in self.framebuffers.drain(..) {
+            unsafe { device.destroy_framebuffer(framebuffer, None) };
+        }
+        for internal in self.upscaled_images.drain(..) {
+            unsafe { device.destroy_framebuffer(internal.framebuffer, None) };
+            unsafe { device.destroy_image_view(internal.image_view, None) };
+            unsafe { device.destroy_image(internal.image, None) };
+            memory_allocator
+                .free(internal.allocation)
+                .expect("Failed to free upscale image allocation");
+        }
+    }
+}
+
+impl PostProcessPass for UpscalePass {
+    /// Record the commands necessary to upscale the input image, and optionally sharpen it, writing the result to the output image.
+    fn render_frame(
+        &self,
+        device: &ash::Device,
+        command_buffer: ash::vk::CommandBuffer,
+        extent: FrameExtent,
+        image_index: usize,
+    ) {
+        let upscale_push_constants = UpscalePushConstants {
+            inverse_output_size: extent.inverse_image_size(),
+            input_size: [extent.render.width as f32, extent.render.height as f32],
+            filter: self.filter,
+        };
+        let extent = extent.image;
+        let [upscale_set, sharpen_set] = self.descriptor_sets[image_index];
+
+        // The upscale render pass transitions its output to be sampled by the sharpening stage, if any.
+        let Some(sharpen) = &self.sharpen else {
+            self.upscale.render(
+                device,
+                command_buffer,
+                extent,
+                self.framebuffers[image_index],
+                upscale_set,
+                super::data_byte_slice(&upscale_push_constants),
+            );
+            return;
+        };
+        self.upscale.render(
+            device,
+            command_buffer,
+            extent,
+            self.upscaled_images[image_index].framebuffer,
+            upscale_set,
+            super::data_byte_slice(&upscale_push_constants),
+        );
+        let sharpen_push_constants = SharpenPushConstants {
+            sharpness: (-RCAS_SHARPNESS_STOPS).exp2(),
+        };
+        sharpen.render(
+            device,
+            command_buffer,
+            extent,
+            self.framebuffers[image_index],
+            sharpen_set,
+            super::data_byte_slice(&sharpen_push_constants),
+        );
+    }
+
+    /// Clean up the resources used by this upscale pass.
+    fn destroy(
+        &mut self,
+        device: &ash::Device,
+        memory_allocator: &mut gpu_allocator::vulkan::Allocator,
+    ) {
+        self.destroy_framebuffers(device, memory_allocator);
+        unsafe { device.destroy_descriptor_pool(self.descriptor_pool, None) };
+
+        // Destroy the pipelines, shader modules, and sampler.
+        self.upscale.destroy(device);
+        if let Some(sharpen) = &self.sharpen {
+            sharpen.destroy(device);
+        }
+        unsafe { device.destroy_shader_module(self.fullscreen_vert_shader, None) };
+        unsafe { device.destroy_sampler(self.sampler, None) };
+    }
+}
@@ -0,0 +1,343 @@
+//! Recording and replay of the inputs which drive the application, so that a session can be reproduced exactly.
+//! The log is a line-based text file. After a header with the version and the initial extent of the main window's swapchain,
+//! each line begins with the index of the frame it applies to, followed by the entry type and its values.
+//! The simulation grid and the cursor scaling follow the main window, so its extent is recorded whenever it changes.
+//! Floating-point values are written with their shortest round-trip representation so they parse back to identical bits.
+
+/// The first line of every input log, identifying the format and its version.
+const INPUT_LOG_HEADER: &str = "pompeii-input-log 2";
+
+/// The discrete actions a user can trigger, independent of the key bound to them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::EnumString, strum::IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
+pub enum AppAction {
+    ExitFullscreen,
+    ToggleFullscreen,
+    ToggleDemoOption,
+    SwitchDemo,
+    CycleAdvectionScheme,
+    SaveSnapshot,
+    LoadSnapshot,
+    ToggleFxaa,
+    TogglePostProcessSplitScreen,
+    ToggleBloom,
+    IncreaseBloomIntensity,
+    DecreaseBloomIntensity,
+    IncreaseBloomThreshold,
+    DecreaseBloomThreshold,
+    CyclePresentMode,
+    OpenWindow,
+}
+
+/// An input applied to the application state between two frames.
+#[derive(Clone, Copy, Debug)]
+pub enum InputEvent {
+    /// The cursor moved to a new position. The smoothed velocity is stored because it depends on wall-clock time.
+    CursorMoved {
+        position: [f64; 2],
+        velocity: [f32; 2],
+    },
+
+    /// The left mouse button was pressed or released.
+    MouseButton { pressed: bool },
+
+    /// A keyboard action was triggered.
+    Action(AppAction),
+}
+
+/// The inputs, timing, and main window extent used to compute a single frame.
+#[derive(Debug, Default)]
+pub struct ReplayFrame {
+    pub inputs: Vec<InputEvent>,
+    pub time: f32,
+    pub delta_time: f32,
+    pub extent: [u32; 2],
+}
+
+/// Writes input events and frame timings to a log as they occur.
+pub struct InputRecorder<W: std::io::Write = std::io::BufWriter<std::fs::File>> {
+    writer: W,
+    extent: [u32; 2],
+}
+impl InputRecorder {
+    /// Create a new input log at the given path, replacing any existing file.
+    /// The extent is the size of the main window's swapchain when recording begins.
+    pub fn create(path: &std::path::Path, extent: [u32; 2]) -> std::io::Result<Self> {
+        Self::new(
+            std::io::BufWriter::new(std::fs::File::create(path)?),
+            extent,
+        )
+    }
+}
+impl<W: std::io::Write> InputRecorder<W> {
+    /// Begin an input log by writing its header to the given writer.
+    fn new(mut writer: W, extent: [u32; 2]) -> std::io::Result<Self> {
+        writeln!(writer, "{INPUT_LOG_HEADER}")?;
+        writeln!(writer, "extent {} {}", extent[0], extent[1])?;
+        Ok(Self { writer, extent })
+    }
+
+    /// Record an input event which will be applied to the given frame.
+    pub fn record_input(&mut self, frame: u64, event: &InputEvent) -> std::io::Result<()> {
+        match event {
+            InputEvent::CursorMoved { position, velocity } => writeln!(
+                self.writer,
+                "{frame} cursor {} {} {} {}",
+                position[0], position[1], velocity[0], velocity[1]
+            ),
+            InputEvent::MouseButton { pressed } => writeln!(
+                self.writer,
+                "{frame} button {}",
+                if *pressed { "pressed" } else { "released" }
+            ),
+            InputEvent::Action(action) => {
+                writeln!(self.writer, "{frame} action {}", <&str>::from(action))
+            }
+        }
+    }
+
+    /// Record the timing and main window extent used to compute the given frame. All inputs recorded since the previous frame are applied before it.
+    pub fn record_frame(
+        &mut self,
+        frame: u64,
+        time: f32,
+        delta_time: f32,
+        extent: [u32; 2],
+    ) -> std::io::Result<()> {
+        if extent != self.extent {
+            writeln!(self.writer, "{frame} resize {} {}", extent[0], extent[1])?;
+            self.extent = extent;
+        }
+        writeln!(self.writer, "{frame} frame {time} {delta_time}")?;
+
+        // Flush every frame so that the log is complete even if the application crashes.
+        self.writer.flush()
+    }
+}
+
+/// Provides the recorded inputs and timings of each frame from an input log.
+pub struct InputReplay {
+    frames: std::collections::VecDeque<ReplayFrame>,
+    extent: [u32; 2],
+}
+impl InputReplay {
+    /// Read and parse an entire input log.
+    pub fn open(path: &std::path::Path) -> std::io::Result<Self> {
+        Self::parse(std::io::BufReader::new(std::fs::File::open(path)?))
+    }
+
+    /// Parse an entire input log from the given reader.
+    fn parse(reader: impl std::io::BufRead) -> std::io::Result<Self> {
+        let mut lines = reader.lines().enumerate();
+
+        let invalid = |line_number: usize, message: &str| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Input log line {}: {message}", line_number + 1),
+            )
+        };
+
+        match lines.next() {
+            Some((_, Ok(header))) if header == INPUT_LOG_HEADER => {}
+            Some((_, Err(e))) => return Err(e),
+            _ => return Err(invalid(0, "Missing or unsupported input log header")),
+        }
+        let parse_extent = |line_number: usize, width: &str, height: &str| match (
+            width.parse::<u32>(),
+            height.parse::<u32>(),
+        ) {
+            (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok([width, height]),
+            _ => Err(invalid(line_number, "Invalid extent")),
+        };
+        let extent = match lines.next() {
+            Some((line_number, Ok(line))) => {
+                match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    &["extent", width, height] => parse_extent(line_number, width, height)?,
+                    _ => return Err(invalid(line_number, "Missing initial extent")),
+                }
+            }
+            Some((_, Err(e))) => return Err(e),
+            None => return Err(invalid(1, "Missing initial extent")),
+        };
+
+        let mut frames = std::collections::VecDeque::new();
+        let mut current = ReplayFrame::default();
+        let mut current_extent = extent;
+        for (line_number, line) in lines {
+            let line = line?;
+            let mut tokens = line.split_whitespace();
+            let Some(frame) = tokens.next() else {
+                continue; // Ignore empty lines.
+            };
+            let frame: u64 = frame
+                .parse()
+                .map_err(|_| invalid(line_number, "Invalid frame index"))?;
+            if frame != frames.len() as u64 {
+                return Err(invalid(line_number, "Frame index is out of sequence"));
+            }
+
+            let kind = tokens.next().unwrap_or_default();
+            let values: Vec<&str> = tokens.collect();
+            let parse_f32 = |s: &str| {
+                s.parse::<f32>()
+                    .map_err(|_| invalid(line_number, "Invalid number"))
+            };
+            let parse_f64 = |s: &str| {
+                s.parse::<f64>()
+                    .map_err(|_| invalid(line_number, "Invalid number"))
+            };
+
+            match (kind, values.as_slice()) {
+                ("frame", &[time, delta_time]) => {
+                    current.time = parse_f32(time)?;
+                    current.delta_time = parse_f32(delta_time)?;
+                    current.extent = current_extent;
+                    frames.push_back(std::mem::take(&mut current));
+                }
+                ("resize", &[width, height]) => {
+                    current_extent = parse_extent(line_number, width, height)?;
+                }
+                ("cursor", &[x, y, velocity_x, velocity_y]) => {
+                    current.inputs.push(InputEvent::CursorMoved {
+                        position: [parse_f64(x)?, parse_f64(y)?],
+                        velocity: [parse_f32(velocity_x)?, parse_f32(velocity_y)?],
+                    });
+                }
+                ("button", &[state]) => {
+                    let pressed = match state {
+                        "pressed" => true,
+                        "released" => false,
+                        _ => return Err(invalid(line_number, "Invalid button state")),
+                    };
+                    current.inputs.push(InputEvent::MouseButton { pressed });
+                }
+                ("action", &[action]) => {
+                    let action = action
+                        .parse()
+                        .map_err(|_| invalid(line_number, "Unknown action"))?;
+                    current.inputs.push(InputEvent::Action(action));
+                }
+                _ => return Err(invalid(line_number, "Unrecognized entry")),
+            }
+        }
+
+        Ok(Self { frames, extent })
+    }
+
+    /// The extent of the main window's swapchain when recording began.
+    pub fn extent(&self) -> [u32; 2] {
+        self.extent
+    }
+
+    /// The extent of the main window's swapchain which the next frame was recorded with, if any frames remain.
+    pub fn next_extent(&self) -> Option<[u32; 2]> {
+        self.frames.front().map(|frame| frame.extent)
+    }
+
+    /// Take the inputs and timing for the next frame, if any remain.
+    pub fn next_frame(&mut self) -> Option<ReplayFrame> {
+        self.frames.pop_front()
+    }
+
+    /// Whether every recorded frame has been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse an input log from its text.
+    fn parse(log: &str) -> std::io::Result<InputReplay> {
+        InputReplay::parse(log.as_bytes())
+    }
+
+    #[test]
+    fn recorded_frames_replay_identically() {
+        // Values without a short decimal representation check that floats round-trip to identical bits.
+        let cursor = InputEvent::CursorMoved {
+            position: [0.1 + 0.2, -1. / 3.],
+            velocity: [f32::MIN_POSITIVE, 1. / 3.],
+        };
+        let mut recorder = InputRecorder::new(Vec::new(), [800, 600]).unwrap();
+        recorder.record_frame(0, 0., 1. / 60., [800, 600]).unwrap();
+        recorder.record_input(1, &cursor).unwrap();
+        recorder
+            .record_input(1, &InputEvent::MouseButton { pressed: true })
+            .unwrap();
+        recorder
+            .record_input(1, &InputEvent::Action(AppAction::ToggleBloom))
+            .unwrap();
+        recorder
+            .record_frame(1, 0.1 + 0.2, 1. / 3., [1024, 768])
+            .unwrap();
+        recorder.record_frame(2, 0.5, 0.2, [1024, 768]).unwrap();
+
+        let mut replay = InputReplay::parse(recorder.writer.as_slice()).unwrap();
+        assert_eq!(replay.extent(), [800, 600]);
+
+        let first = replay.next_frame().unwrap();
+        assert!(first.inputs.is_empty());
+        assert_eq!(first.time.to_bits(), 0f32.to_bits());
+        assert_eq!(first.delta_time.to_bits(), (1f32 / 60.).to_bits());
+        assert_eq!(first.extent, [800, 600]);
+
+        let second = replay.next_frame().unwrap();
+        assert_eq!(second.time.to_bits(), (0.1f32 + 0.2).to_bits());
+        assert_eq!(second.delta_time.to_bits(), (1f32 / 3.).to_bits());
+        assert_eq!(second.extent, [1024, 768]);
+        match second.inputs.as_slice() {
+            [InputEvent::CursorMoved { position, velocity }, InputEvent::MouseButton { pressed: true }, InputEvent::Action(AppAction::ToggleBloom)] =>
+            {
+                let InputEvent::CursorMoved {
+                    position: expected_position,
+                    velocity: expected_velocity,
+                } = cursor
+                else {
+                    unreachable!()
+                };
+                assert_eq!(
+                    position.map(f64::to_bits),
+                    expected_position.map(f64::to_bits)
+                );
+                assert_eq!(
+                    velocity.map(f32::to_bits),
+                    expected_velocity.map(f32::to_bits)
+                );
+            }
+            inputs => panic!("Unexpected replayed inputs {inputs:?}"),
+        }
+
+        assert_eq!(replay.next_extent(), Some([1024, 768]));
+        assert_eq!(replay.next_frame().unwrap().extent, [1024, 768]);
+        assert!(replay.is_finished());
+        assert_eq!(replay.next_extent(), None);
+    }
+
+    #[test]
+    fn rejects_unknown_header() {
+        let error = parse("pompeii-input-log 1\n0 frame 0 0\n").err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(parse("").is_err());
+    }
+
+    #[test]
+    fn rejects_missing_extent() {
+        let log = format!("{INPUT_LOG_HEADER}\n0 frame 0 0\n");
+        assert!(parse(&log).is_err());
+        let log = format!("{INPUT_LOG_HEADER}\nextent 0 600\n");
+        assert!(parse(&log).is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_order_frames() {
+        let log = format!("{INPUT_LOG_HEADER}\nextent 800 600\n0 frame 0 0\n2 frame 1 1\n");
+        let error = parse(&log).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        let log = format!("{INPUT_LOG_HEADER}\nextent 800 600\n0 frame 0 0\n0 button pressed\n");
+        assert!(parse(&log).is_err());
+    }
+}
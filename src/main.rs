@@ -1,4 +1,4 @@
-use std::ffi::CStr;
+use std::{collections::HashMap, ffi::CStr};
 
 use smallvec::SmallVec;
 use winit::{
@@ -12,11 +12,15 @@ const WINDOW_TITLE: &str = "Pompeii";
 /// The default size of the main window.
 const DEFAULT_WINDOWS_SIZE: winit::dpi::LogicalSize<f32> = winit::dpi::LogicalSize::new(800., 600.);
 
+/// How long to wait for the main window to be resized to the size a replayed frame was recorded at.
+const REPLAY_RESIZE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
 /// The number of ticks to sample before printing a message.
 const TICK_SAMPLING_LENGTH: u64 = 6_000;
 
 mod cli;
 mod engine;
+mod input_log;
 use engine::utils;
 
 fn main() {
@@ -42,7 +46,7 @@ fn main() {
 fn create_window(
     event_loop: &winit::event_loop::ActiveEventLoop,
     title: &str,
-    size: winit::dpi::LogicalSize<f32>,
+    size: impl Into<winit::dpi::Size>,
 ) -> Result<winit::window::Window, winit::error::OsError> {
     let window_attributes: winit::window::WindowAttributes =
         winit::window::Window::default_attributes()
@@ -51,12 +55,43 @@ fn create_window(
     event_loop.create_window(window_attributes)
 }
 
+/// Create a Vulkan surface for the window.
+fn create_surface(
+    vulkan: &utils::VulkanCore,
+    window: &winit::window::Window,
+) -> ash::vk::SurfaceKHR {
+    unsafe {
+        ash_window::create_surface(
+            &vulkan.api,
+            &vulkan.instance,
+            window
+                .display_handle()
+                .expect("Failed to get a display handle")
+                .into(),
+            window
+                .window_handle()
+                .expect("Failed to get a window handle")
+                .into(),
+            None,
+        )
+        .expect("Unable to create Vulkan surface")
+    }
+}
+
 /// App-specific events that can be created and handled.
 enum PompeiiEvent {}
 
+/// A window of the application and the render target which presents to it.
+struct PompeiiWindow {
+    window: winit::window::Window,
+    monitor: Option<winit::monitor::MonitorHandle>, // The monitor the window was last on, to detect moves between monitors.
+    target: engine::TargetId,
+}
+
 /// The graphics-specific state. Needs to be initialized after the event loop has begun.
 struct PompeiiGraphics {
-    window: winit::window::Window,
+    windows: HashMap<winit::window::WindowId, PompeiiWindow>,
+    main_window: winit::window::WindowId, // Closing the main window exits the application.
     renderer: engine::Renderer,
 }
 
@@ -71,6 +106,13 @@ struct PompeiiApp {
     last_mouse_position: Option<(winit::dpi::PhysicalPosition<f64>, std::time::Instant)>,
     mouse_click: Option<[f32; 2]>,
     mouse_velocity: [f32; 2],
+    simulation_accumulator: f32,
+    input_recorder: Option<input_log::InputRecorder>,
+    input_replay: Option<input_log::InputReplay>,
+    replay_resize_started: Option<std::time::Instant>, // When the main window was asked to match the size of the replayed frame.
+    focused_window: Option<winit::window::WindowId>,
+    open_window_requested: bool,
+    last_push_constants: Option<engine::DemoPushConstants>, // The push constants of the last frame of the main window, which the other windows also draw.
 }
 
 impl PompeiiApp {
@@ -113,6 +155,14 @@ impl PompeiiApp {
             }
         };
 
+        // Optionally, open the input log to replay from. A recording is created once the main window's size is known.
+        let input_replay = args.replay.as_ref().map(|path| {
+            input_log::InputReplay::open(path).unwrap_or_else(|e| {
+                eprintln!("Error reading input log {path:?}: {e}");
+                std::process::exit(-1);
+            })
+        });
+
         // Create a Vulkan instance for our application initialized with the `Empty` state.
         PompeiiApp {
             args,
@@ -124,6 +174,13 @@ impl PompeiiApp {
             last_mouse_position: None,
             mouse_click: None,
             mouse_velocity: [0., 0.],
+            simulation_accumulator: 0.,
+            input_recorder: None,
+            input_replay,
+            replay_resize_started: None,
+            focused_window: None,
+            open_window_requested: false,
+            last_push_constants: None,
         }
     }
 
@@ -131,39 +188,90 @@ impl PompeiiApp {
     /// # Panics
     /// Panics if the `graphics` field is not initialized.
     fn update_gamestate(&mut self) -> engine::DemoPushConstants {
+        // Get updated state for drawing, either from the wall clock or from the replayed input log.
+        let (time, delta_time) = if let Some(replay) = &mut self.input_replay {
+            let frame = replay
+                .next_frame()
+                .expect("Frames should not be computed after the input log is finished");
+            for event in frame.inputs {
+                self.apply_input(event);
+            }
+            (frame.time, frame.delta_time)
+        } else {
+            let now = std::time::Instant::now();
+            let time = now.duration_since(self.start_time).as_secs_f32();
+            let delta_time = self.last_frame_time.map_or(time, |last_frame| {
+                now.duration_since(last_frame).as_secs_f32()
+            });
+            self.last_frame_time = Some(now);
+            (time, delta_time)
+        };
+
         let Some(PompeiiGraphics { renderer, .. }) = &mut self.graphics else {
             panic!("Graphics state not initialized");
         };
 
-        // Get updated state for drawing.
-        let now = std::time::Instant::now();
-        let time = now.duration_since(self.start_time).as_secs_f32();
-        let delta_time = self.last_frame_time.map_or(time, |last_frame| {
-            now.duration_since(last_frame).as_secs_f32()
-        });
-        self.last_frame_time = Some(now);
+        // The input state is read below, so this frame's latency is measured from now.
+        renderer.input_sampled(engine::TargetId::PRIMARY);
+
+        // The cursor is scaled from the window coordinates of the main window to the grid of the simulation.
+        let window_extent = renderer
+            .swapchain_extent(engine::TargetId::PRIMARY)
+            .expect("The game state is only updated while the renderer has a swapchain");
+
+        if let Some(recorder) = &mut self.input_recorder {
+            if let Err(e) = recorder.record_frame(
+                self.tick_count,
+                time,
+                delta_time,
+                [window_extent.width, window_extent.height],
+            ) {
+                eprintln!("Error recording input, recording has stopped: {e}");
+                self.input_recorder = None;
+            }
+        }
 
         // Update the game state and get the per-frame data in the form of push constants.
         let push_constants = match &mut renderer.active_demo {
             engine::DemoPipeline::Triangle(_) => {
                 engine::DemoPushConstants::Triangle(engine::example_triangle::PushConstants {
                     time,
+                    jitter: [0.; 2], // Set by the renderer when a post-processing pass requests it.
                 })
             }
 
             engine::DemoPipeline::Fluid(fluid) => {
+                // Advance the simulation in fixed steps to consume the time which has passed, up to a limit per frame.
+                let step_time = self.args.simulation_rate.recip();
+                self.simulation_accumulator += delta_time;
+                let steps =
+                    ((self.simulation_accumulator / step_time) as u32).min(self.args.max_substeps);
+                self.simulation_accumulator -= steps as f32 * step_time;
+                if steps == self.args.max_substeps {
+                    // Drop the time that couldn't be simulated, rather than trying to catch up over the following frames.
+                    self.simulation_accumulator = self.simulation_accumulator.min(step_time);
+                }
+                fluid.set_simulation_steps(steps);
+
+                let grid_extent = fluid.extent();
+                let to_grid_scale = |[x, y]: [f32; 2]| {
+                    [
+                        x * grid_extent.width as f32 / window_extent.width as f32,
+                        y * grid_extent.height as f32 / window_extent.height as f32,
+                    ]
+                };
                 let dye_cycle = 12. * time;
                 let push_constants = fluid.new_push_constants(
-                    renderer.swapchain.extent(),
-                    self.last_mouse_position.map_or([-1024.; 2], |m| m.0.into()),
-                    self.mouse_velocity,
+                    grid_extent,
+                    to_grid_scale(self.last_mouse_position.map_or([-1024.; 2], |m| m.0.into())),
+                    to_grid_scale(self.mouse_velocity),
                     [
                         ((dye_cycle - 0.7).sin() + 0.5).max(0.) * (2. / 3.),
                         ((-dye_cycle - 0.3).sin() + 0.2).max(0.) * (5. / 6.),
                         (dye_cycle - 0.1).cos().max(0.),
                         f32::from(self.mouse_click.is_none()),
                     ],
-                    delta_time,
+                    step_time,
                 );
                 engine::DemoPushConstants::Fluid(push_constants)
             }
@@ -180,7 +288,7 @@ impl PompeiiApp {
         push_constants
     }
 
-    /// Redraw the window surface if we have initialized the relevant components.
+    /// Redraw the main window surface if we have initialized the relevant components.
     fn redraw(&mut self, push_constants: engine::DemoPushConstants) {
         let Some(PompeiiGraphics { renderer, .. }) = &mut self.graphics else {
             return;
@@ -197,82 +305,365 @@ impl PompeiiApp {
         self.tick_count += 1;
 
         // Attempt to render the frame, or bail if there is a recoverable error.
-        renderer.render_frame(&self.vulkan, &push_constants);
+        renderer.render_frame(&self.vulkan, engine::TargetId::PRIMARY, &push_constants);
+        self.last_push_constants = Some(push_constants);
     }
 
-    /// Handle keyboard input events.
-    fn handle_keyboard_input(&mut self, key_event: winit::event::KeyEvent) {
-        let Some(PompeiiGraphics { window, .. }) = &mut self.graphics else {
+    /// Whether the window is the main window of the application, whose input drives the demos.
+    fn is_main_window(&self, window_id: winit::window::WindowId) -> bool {
+        self.graphics
+            .as_ref()
+            .is_some_and(|graphics| graphics.main_window == window_id)
+    }
+
+    /// Get the swapchain preferences requested from the command line for a new window.
+    fn swapchain_preferences(&self, window: &winit::window::Window) -> utils::SwapchainPreferences {
+        let current_extent = window.inner_size();
+        #[cfg(debug_assertions)]
+        println!("INFO: Window size at creation: {current_extent:?}");
+
+        // NOTE: Some platforms require us to specify the preferred extent for the swapchain before
+        // a current one will be established.
+        let preferred_extent = Some(ash::vk::Extent2D {
+            width: current_extent.width,
+            height: current_extent.height,
+        });
+
+        let mut swapchain_preferences = utils::SwapchainPreferences {
+            present_mode: Some(self.args.present_mode.into()),
+            preferred_extent,
+            color_samples: Some(self.multisampling().into()),
+            frames_in_flight: Some(self.args.frames_in_flight as usize),
+            ..Default::default()
+        };
+
+        if self.args.hdr
+            && self
+                .vulkan
+                .enabled_instance_extension(ash::ext::swapchain_colorspace::NAME)
+        {
+            // Let the swapchain choose the best supported HDR format and color space.
+            swapchain_preferences.dynamic_range = utils::DynamicRange::High;
+        }
+        swapchain_preferences
+    }
+
+    /// Open another window which draws the active demo with its own swapchain and post-processing.
+    fn open_window(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        if self.graphics.is_none() {
+            return;
+        }
+
+        let window = match create_window(event_loop, WINDOW_TITLE, DEFAULT_WINDOWS_SIZE) {
+            Ok(window) => window,
+            Err(e) => {
+                eprintln!("Error creating window: {e}");
+                return;
+            }
+        };
+        let surface = create_surface(&self.vulkan, &window);
+        let swapchain_preferences = self.swapchain_preferences(&window);
+
+        let Some(PompeiiGraphics {
+            windows, renderer, ..
+        }) = &mut self.graphics
+        else {
             return;
         };
+        let target = renderer.add_target(&self.vulkan, surface, swapchain_preferences);
+        println!("Opened window {:?}", window.id());
+        windows.insert(
+            window.id(),
+            PompeiiWindow {
+                monitor: window.current_monitor(),
+                window,
+                target,
+            },
+        );
+    }
+
+    /// Get the fluid simulation specialization constants requested from the command line.
+    fn fluid_specialization_constants(&self) -> engine::example_fluid::SpecializationConstants {
+        engine::example_fluid::SpecializationConstants {
+            advection_scheme: self.args.advection.into(),
+            smoke: self.args.smoke.into(),
+            ..Default::default()
+        }
+    }
 
-        // Handle keyboard input events.
+    /// Get the FXAA specialization constants requested from the command line.
+    fn fxaa_specialization_constants(&self) -> utils::fxaa_pass::SpecializationConstants {
+        utils::fxaa_pass::SpecializationConstants {
+            debug_edges: self.args.fxaa_debug.into(),
+            ..utils::fxaa_pass::FxaaQuality::from(self.args.fxaa_quality).into()
+        }
+    }
+
+    /// Get the ordered post-processing effects requested from the command line.
+    fn post_process_effects(&self) -> Vec<engine::utils::post_process::PostProcessEffect> {
+        let mut effects = self.args.post_process.clone();
+        let anti_aliasing = match self.args.aa {
+            Some(aa) => aa.post_process_effect(),
+            None => self.args.fxaa.then_some(cli::PostProcessEffect::Fxaa),
+        };
+        if let Some(effect) = anti_aliasing {
+            if !effects.contains(&effect) {
+                effects.push(effect);
+            }
+        }
+        effects.into_iter().map(Into::into).collect()
+    }
+
+    /// Get the multisampling mode requested from the command line.
+    fn multisampling(&self) -> cli::MultiSamplingMode {
+        self.args
+            .aa
+            .map_or(self.args.msaa, cli::AntiAliasing::multisampling)
+    }
+
+    /// Handle keyboard input events by mapping keys to application actions.
+    fn handle_keyboard_input(&mut self, key_event: winit::event::KeyEvent) {
         let winit::event::KeyEvent {
             state,
             logical_key: key,
             ..
         } = key_event;
-        if state == winit::event::ElementState::Pressed {
-            match key.as_ref() {
-                // Handle the escape key to exit fullscreen mode.
-                winit::keyboard::Key::Named(winit::keyboard::NamedKey::Escape) => {
-                    if window.fullscreen().is_some() {
-                        // Exit fullscreen mode.
-                        window.set_fullscreen(None);
-                    }
+        if state != winit::event::ElementState::Pressed {
+            return;
+        }
+
+        let action = match key.as_ref() {
+            // Handle the escape key to exit fullscreen mode.
+            winit::keyboard::Key::Named(winit::keyboard::NamedKey::Escape) => {
+                input_log::AppAction::ExitFullscreen
+            }
+
+            // Handle the `F` key and `F11` to toggle fullscreen mode.
+            winit::keyboard::Key::Character("f")
+            | winit::keyboard::Key::Named(winit::keyboard::NamedKey::F11) => {
+                input_log::AppAction::ToggleFullscreen
+            }
+
+            // Handle the `SPACE` key to toggle the user toggle.
+            winit::keyboard::Key::Named(winit::keyboard::NamedKey::Space) => {
+                input_log::AppAction::ToggleDemoOption
+            }
+
+            // Handle the `TAB` key to switch between demos.
+            winit::keyboard::Key::Named(winit::keyboard::NamedKey::Tab) => {
+                input_log::AppAction::SwitchDemo
+            }
+
+            // Handle the `F5` key to save a snapshot of the fluid simulation.
+            winit::keyboard::Key::Named(winit::keyboard::NamedKey::F5) => {
+                input_log::AppAction::SaveSnapshot
+            }
+
+            // Handle the `F9` key to load a snapshot of the fluid simulation.
+            winit::keyboard::Key::Named(winit::keyboard::NamedKey::F9) => {
+                input_log::AppAction::LoadSnapshot
+            }
+
+            // Handle the `M` key to cycle the advection scheme of the fluid simulation.
+            winit::keyboard::Key::Character("m") => input_log::AppAction::CycleAdvectionScheme,
+
+            // Handle the `A` key to toggle FXAA.
+            winit::keyboard::Key::Character("a") => input_log::AppAction::ToggleFxaa,
+
+            // Handle the `C` key to toggle the split-screen comparison of post-processing.
+            winit::keyboard::Key::Character("c") => {
+                input_log::AppAction::TogglePostProcessSplitScreen
+            }
+
+            // Handle the `B` key to toggle bloom.
+            winit::keyboard::Key::Character("b") => input_log::AppAction::ToggleBloom,
+
+            // Handle the `[` and `]` keys to adjust the intensity of bloom.
+            winit::keyboard::Key::Character("[") => input_log::AppAction::DecreaseBloomIntensity,
+            winit::keyboard::Key::Character("]") => input_log::AppAction::IncreaseBloomIntensity,
+
+            // Handle the `,` and `.` keys to adjust the threshold of bloom.
+            winit::keyboard::Key::Character(",") => input_log::AppAction::DecreaseBloomThreshold,
+            winit::keyboard::Key::Character(".") => input_log::AppAction::IncreaseBloomThreshold,
+
+            // Handle the `P` key to cycle the present mode.
+            winit::keyboard::Key::Character("p") => input_log::AppAction::CyclePresentMode,
+
+            // Handle the `N` key to open another window.
+            winit::keyboard::Key::Character("n") => input_log::AppAction::OpenWindow,
+
+            _ => return,
+        };
+
+        self.handle_input(input_log::InputEvent::Action(action));
+    }
+
+    /// Record and apply a live input event. Live input is ignored while replaying an input log.
+    fn handle_input(&mut self, event: input_log::InputEvent) {
+        if self.input_replay.is_some() {
+            return;
+        }
+
+        if let Some(recorder) = &mut self.input_recorder {
+            if let Err(e) = recorder.record_input(self.tick_count, &event) {
+                eprintln!("Error recording input, recording has stopped: {e}");
+                self.input_recorder = None;
+            }
+        }
+        self.apply_input(event);
+    }
+
+    /// Apply an input event to the application state.
+    fn apply_input(&mut self, event: input_log::InputEvent) {
+        match event {
+            input_log::InputEvent::CursorMoved { position, velocity } => {
+                self.last_mouse_position = Some((position.into(), std::time::Instant::now()));
+                self.mouse_velocity = velocity;
+            }
+            input_log::InputEvent::MouseButton { pressed } => {
+                if pressed {
+                    self.mouse_click =
+                        Some(self.last_mouse_position.map_or([-1024.; 2], |m| m.0.into()));
+                } else {
+                    self.mouse_click = None;
                 }
+            }
+            input_log::InputEvent::Action(action) => self.apply_action(action),
+        }
+    }
 
-                // Handle the `F` key and `F11` to toggle fullscreen mode.
-                winit::keyboard::Key::Character("f")
-                | winit::keyboard::Key::Named(winit::keyboard::NamedKey::F11) => {
-                    if window.fullscreen().is_some() {
-                        // Exit fullscreen mode.
-                        window.set_fullscreen(None);
-                    } else {
-                        // Enter fullscreen mode in borderless mode, defaulting to the active monitor.
-                        window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
-                    }
+    /// Perform a user-triggered action.
+    fn apply_action(&mut self, action: input_log::AppAction) {
+        let fluid_specialization_constants = self.fluid_specialization_constants();
+        let Some(PompeiiGraphics {
+            windows,
+            main_window,
+            renderer,
+        }) = &mut self.graphics
+        else {
+            return;
+        };
+
+        // Actions on a single window apply to the focused window, or the main window if none is focused.
+        let PompeiiWindow { window, target, .. } = self
+            .focused_window
+            .and_then(|window_id| windows.get(&window_id))
+            .unwrap_or(&windows[main_window]);
+        let target = *target;
+
+        match action {
+            input_log::AppAction::ExitFullscreen => {
+                if window.fullscreen().is_some() {
+                    // Exit fullscreen mode.
+                    window.set_fullscreen(None);
                 }
+            }
 
-                // Handle the `SPACE` key to toggle the user toggle.
-                winit::keyboard::Key::Named(winit::keyboard::NamedKey::Space) => {
-                    let Some(PompeiiGraphics { renderer, .. }) = &mut self.graphics else {
-                        return;
-                    };
+            input_log::AppAction::ToggleFullscreen => {
+                if window.fullscreen().is_some() {
+                    // Exit fullscreen mode.
+                    window.set_fullscreen(None);
+                } else {
+                    // Enter fullscreen mode in borderless mode, defaulting to the active monitor.
+                    window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+                }
+            }
 
-                    match &mut renderer.active_demo {
-                        engine::DemoPipeline::Triangle(t) => {
-                            let toggle = (t.specialization_constants().toggle + 1) % 2;
+            input_log::AppAction::ToggleDemoOption => match &mut renderer.active_demo {
+                engine::DemoPipeline::Triangle(t) => {
+                    let toggle = (t.specialization_constants().toggle + 1) % 2;
 
-                            // Update the specialization constants for the renderer.
-                            renderer.update_specialization_constants(
-                                engine::DemoSpecializationConstants::Triangle(
-                                    engine::example_triangle::SpecializationConstants { toggle },
-                                ),
-                            );
-                        }
-                        engine::DemoPipeline::Fluid(f) => f.next_display_texture(),
+                    // Update the specialization constants for the renderer.
+                    renderer.update_specialization_constants(
+                        engine::DemoSpecializationConstants::Triangle(
+                            engine::example_triangle::SpecializationConstants { toggle },
+                        ),
+                    );
+                }
+                engine::DemoPipeline::Fluid(_) => renderer.next_fluid_display_texture(target),
+            },
+
+            input_log::AppAction::SwitchDemo => {
+                let new_demo = match renderer.active_demo {
+                    engine::DemoPipeline::Triangle(_) => {
+                        engine::NewDemo::Fluid(fluid_specialization_constants)
                     }
+                    engine::DemoPipeline::Fluid(_) => engine::NewDemo::Triangle(
+                        engine::example_triangle::SpecializationConstants::default(),
+                    ),
+                };
+
+                println!("Switching to new demo: {new_demo:?}");
+                renderer.switch_demo(new_demo);
+                self.last_push_constants = None; // The push constants belong to the previous demo.
+            }
+
+            input_log::AppAction::SaveSnapshot => {
+                match renderer.save_fluid_snapshot(&self.args.snapshot_file) {
+                    Ok(()) => println!("Saved fluid snapshot to {:?}", self.args.snapshot_file),
+                    Err(e) => eprintln!("Error saving fluid snapshot: {e}"),
                 }
+            }
 
-                winit::keyboard::Key::Named(winit::keyboard::NamedKey::Tab) => {
-                    // Update the specialization constants for the renderer.
-                    let Some(PompeiiGraphics { renderer, .. }) = &mut self.graphics else {
-                        return;
-                    };
+            input_log::AppAction::LoadSnapshot => {
+                match renderer.load_fluid_snapshot(&self.args.snapshot_file) {
+                    Ok(()) => println!("Loaded fluid snapshot from {:?}", self.args.snapshot_file),
+                    Err(e) => eprintln!("Error loading fluid snapshot: {e}"),
+                }
+            }
 
-                    let new_demo = match renderer.active_demo {
-                        engine::DemoPipeline::Triangle(_) => engine::NewDemo::Fluid,
-                        engine::DemoPipeline::Fluid(_) => engine::NewDemo::Triangle(
-                            engine::example_triangle::SpecializationConstants::default(),
-                        ),
-                    };
+            input_log::AppAction::CycleAdvectionScheme => {
+                let engine::DemoPipeline::Fluid(f) = &renderer.active_demo else {
+                    return;
+                };
+
+                let mut specialization_constants = f.specialization_constants();
+                specialization_constants.advection_scheme =
+                    specialization_constants.advection_scheme.next();
+                println!(
+                    "Switched to the next advection scheme: {:?}",
+                    specialization_constants.advection_scheme
+                );
+
+                renderer.update_specialization_constants(
+                    engine::DemoSpecializationConstants::Fluid(specialization_constants),
+                );
+            }
 
-                    println!("Switching to new demo: {new_demo:?}");
-                    renderer.switch_demo(new_demo);
+            input_log::AppAction::ToggleFxaa => renderer.toggle_fxaa(),
+
+            input_log::AppAction::TogglePostProcessSplitScreen => {
+                renderer.toggle_post_process_split_screen();
+            }
+
+            input_log::AppAction::ToggleBloom => renderer.toggle_bloom(),
+
+            input_log::AppAction::IncreaseBloomIntensity
+            | input_log::AppAction::DecreaseBloomIntensity
+            | input_log::AppAction::IncreaseBloomThreshold
+            | input_log::AppAction::DecreaseBloomThreshold => {
+                // Scale the settings by a constant factor, so each step is equally noticeable.
+                let mut settings = renderer.bloom_settings();
+                match action {
+                    input_log::AppAction::IncreaseBloomIntensity => settings.intensity *= 1.25,
+                    input_log::AppAction::DecreaseBloomIntensity => settings.intensity /= 1.25,
+                    input_log::AppAction::IncreaseBloomThreshold => settings.threshold *= 1.25,
+                    _ => settings.threshold /= 1.25,
                 }
-                _ => (),
+                println!(
+                    "Bloom intensity: {}, threshold: {}",
+                    settings.intensity, settings.threshold
+                );
+                renderer.set_bloom_settings(settings);
             }
+
+            input_log::AppAction::CyclePresentMode => {
+                let present_mode = renderer.cycle_present_mode(&self.vulkan, target);
+                println!("Present mode: {present_mode:?}");
+            }
+
+            // Windows can only be created with the event loop, so the window is opened after the current event.
+            input_log::AppAction::OpenWindow => self.open_window_requested = true,
         }
     }
 }
@@ -282,96 +673,163 @@ impl winit::application::ApplicationHandler<PompeiiEvent> for PompeiiApp {
     /// Create a new windowing system if the application is initialized.
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         // Some platforms may have circumstances in which the application is resumed after a pause.
-        if self.graphics.is_some() {
+        if let Some(PompeiiGraphics {
+            windows, renderer, ..
+        }) = &mut self.graphics
+        {
+            // The surfaces were destroyed when the application was suspended, so create new ones for each window.
+            for PompeiiWindow { window, target, .. } in windows.values() {
+                if renderer.is_suspended(*target) {
+                    renderer.resume(&self.vulkan, *target, create_surface(&self.vulkan, window));
+                    window.request_redraw();
+                }
+            }
             return println!("Application resumed");
         }
 
         println!("Application starting...");
 
-        // Create a new main window for our application.
-        let window = create_window(event_loop, WINDOW_TITLE, DEFAULT_WINDOWS_SIZE)
-            .expect("Unable to create window");
+        // Create a new main window for our application, with the size it had when a replayed input log was recorded.
+        let window = match &self.input_replay {
+            Some(replay) => {
+                let [width, height] = replay.extent();
+                create_window(
+                    event_loop,
+                    WINDOW_TITLE,
+                    winit::dpi::PhysicalSize::new(width, height),
+                )
+            }
+            None => create_window(event_loop, WINDOW_TITLE, DEFAULT_WINDOWS_SIZE),
+        }
+        .expect("Unable to create window");
 
         // Get a handle to a Vulkan surface for use with the window.
-        let surface = unsafe {
-            ash_window::create_surface(
-                &self.vulkan.api,
-                &self.vulkan.instance,
-                window
-                    .display_handle()
-                    .expect("Failed to get a display handle")
-                    .into(),
-                window
-                    .window_handle()
-                    .expect("Failed to get a window handle")
-                    .into(),
-                None,
-            )
-            .expect("Unable to create Vulkan surface")
-        };
+        let surface = create_surface(&self.vulkan, &window);
 
-        let current_extent = window.inner_size();
-        #[cfg(debug_assertions)]
-        println!("INFO: Window size at creation: {current_extent:?}");
+        // Create a renderer specific to this application's needs, with the main window as its primary render target.
+        let mut renderer = engine::Renderer::new(
+            &self.vulkan,
+            surface,
+            self.swapchain_preferences(&window),
+            engine::DemoSpecializationConstants::Fluid(self.fluid_specialization_constants()),
+            utils::post_process::PostProcessSettings {
+                effects: self.post_process_effects(),
+                fxaa_constants: self.fxaa_specialization_constants(),
+                upscale_settings: utils::upscale_pass::UpscaleSettings {
+                    render_scale: self.args.render_scale,
+                    filter: self.args.upscaler.into(),
+                    dynamic: self.args.target_frame_time.is_some(),
+                },
+                split_screen: false,
+                bloom_settings: utils::bloom_pass::BloomSettings::default(),
+            },
+            self.args.async_compute,
+        );
+        renderer.set_dynamic_resolution(self.args.target_frame_time);
+        renderer.set_fps_cap(self.args.fps_cap);
+        renderer.set_low_latency(self.args.low_latency);
 
-        // Create a renderer specific to this application's needs.
-        // NOTE: Some platforms require us to specify the preferred extent for the swapchain before
-        // a current one will be established.
-        let preferred_extent = Some(ash::vk::Extent2D {
-            width: current_extent.width,
-            height: current_extent.height,
+        renderer.set_bloom_settings(utils::bloom_pass::BloomSettings {
+            intensity: self.args.bloom_intensity,
+            threshold: self.args.bloom_threshold,
         });
 
-        let mut swapchain_preferences = utils::SwapchainPreferences {
-            present_mode: Some(self.args.present_mode.into()),
-            preferred_extent,
-            color_samples: Some(self.args.msaa.into()),
-            ..Default::default()
-        };
-
-        if self.args.hdr
-            && self
-                .vulkan
-                .enabled_instance_extension(ash::ext::swapchain_colorspace::NAME)
-        {
-            // TODO: Let swapchain know what kind of format/colorspace we want (HDR/SDR) and let
-            // them choose the specifics.
-            swapchain_preferences.format = Some(ash::vk::Format::A2B10G10R10_UNORM_PACK32);
-            swapchain_preferences.color_space = Some(ash::vk::ColorSpaceKHR::HDR10_ST2084_EXT);
+        // Optionally, begin recording the input log now that the main window's swapchain extent is known.
+        if let Some(path) = &self.args.record {
+            let extent = renderer
+                .swapchain_extent(engine::TargetId::PRIMARY)
+                .expect("The renderer is created with a swapchain for the main window");
+            match input_log::InputRecorder::create(path, [extent.width, extent.height]) {
+                Ok(recorder) => self.input_recorder = Some(recorder),
+                Err(e) => {
+                    eprintln!("Error creating input log {path:?}: {e}");
+                    std::process::exit(-1);
+                }
+            }
         }
 
-        let renderer = engine::Renderer::new(
-            &self.vulkan,
-            surface,
-            swapchain_preferences,
-            engine::DemoSpecializationConstants::Fluid,
-            self.args.fxaa,
-        );
+        // Optionally, restore the fluid simulation state from a previous session.
+        if self.args.load_snapshot {
+            match renderer.load_fluid_snapshot(&self.args.snapshot_file) {
+                Ok(()) => println!("Loaded fluid snapshot from {:?}", self.args.snapshot_file),
+                Err(e) => eprintln!("Error loading fluid snapshot: {e}"),
+            }
+        }
 
         // Complete the state transition to windowed mode.
-        self.graphics = Some(PompeiiGraphics { window, renderer });
+        let main_window = window.id();
+        self.graphics = Some(PompeiiGraphics {
+            windows: HashMap::from([(
+                main_window,
+                PompeiiWindow {
+                    monitor: window.current_monitor(),
+                    window,
+                    target: engine::TargetId::PRIMARY,
+                },
+            )]),
+            main_window,
+            renderer,
+        });
 
         self.start_time = std::time::Instant::now();
         println!("Application started at {:?}", self.start_time);
     }
 
+    /// Destroy the surfaces and swapchains while the application is suspended, as the windows may no longer be drawn to.
+    fn suspended(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+        if let Some(PompeiiGraphics {
+            windows, renderer, ..
+        }) = &mut self.graphics
+        {
+            for PompeiiWindow { target, .. } in windows.values() {
+                renderer.suspend(&self.vulkan, *target);
+            }
+            println!("Application suspended");
+        }
+    }
+
     /// Handle OS events to the windowing system.
     fn window_event(
         &mut self,
         event_loop: &winit::event_loop::ActiveEventLoop,
-        _window_id: winit::window::WindowId,
+        window_id: winit::window::WindowId,
         event: winit::event::WindowEvent,
     ) {
         match event {
             // Handle application close requests.
             winit::event::WindowEvent::CloseRequested => {
+                // Closing a window other than the main window only removes its render target.
+                if let Some(PompeiiGraphics {
+                    windows,
+                    main_window,
+                    renderer,
+                }) = &mut self.graphics
+                {
+                    if window_id != *main_window {
+                        if let Some(PompeiiWindow { target, .. }) = windows.remove(&window_id) {
+                            renderer.remove_target(&self.vulkan, target);
+                            println!("Closed window {window_id:?}");
+                        }
+                        return;
+                    }
+                }
+
                 println!("Window close requested");
                 event_loop.exit();
             }
 
+            // Track the focused window, which keyboard actions on a single window apply to.
+            winit::event::WindowEvent::Focused(true) => self.focused_window = Some(window_id),
+
             // Handle window resizing events.
             winit::event::WindowEvent::Resized(winit::dpi::PhysicalSize { width, height }) => {
-                let Some(PompeiiGraphics { renderer, .. }) = &mut self.graphics else {
+                let Some(PompeiiGraphics {
+                    windows, renderer, ..
+                }) = &mut self.graphics
+                else {
+                    return;
+                };
+                let Some(PompeiiWindow { target, .. }) = windows.get(&window_id) else {
                     return;
                 };
 
@@ -380,27 +838,78 @@ impl winit::application::ApplicationHandler<PompeiiEvent> for PompeiiApp {
                     return;
                 }
 
-                renderer.swapchain_resize_required(Some(ash::vk::Extent2D { width, height }));
+                renderer
+                    .swapchain_resize_required(*target, Some(ash::vk::Extent2D { width, height }));
+            }
+
+            // Choose the surface format again when the window moves to a monitor which may have different capabilities, such as HDR support.
+            winit::event::WindowEvent::Moved(_)
+            | winit::event::WindowEvent::ScaleFactorChanged { .. } => {
+                let Some(PompeiiGraphics {
+                    windows, renderer, ..
+                }) = &mut self.graphics
+                else {
+                    return;
+                };
+                let Some(PompeiiWindow {
+                    window,
+                    monitor,
+                    target,
+                }) = windows.get_mut(&window_id)
+                else {
+                    return;
+                };
+
+                let current_monitor = window.current_monitor();
+                if current_monitor != *monitor {
+                    #[cfg(debug_assertions)]
+                    println!(
+                        "INFO: Window moved to monitor {:?}",
+                        current_monitor
+                            .as_ref()
+                            .and_then(winit::monitor::MonitorHandle::name)
+                    );
+                    *monitor = current_monitor;
+                    renderer.swapchain_resize_required(*target, None);
+                }
             }
 
             // Redraw the window surface when requested.
             winit::event::WindowEvent::RedrawRequested => {
                 let Some(PompeiiGraphics {
-                    window, renderer, ..
+                    windows,
+                    main_window,
+                    renderer,
                 }) = &mut self.graphics
                 else {
                     return;
                 };
+                let Some(PompeiiWindow { window, target, .. }) = windows.get(&window_id) else {
+                    return;
+                };
+                let target = *target;
+                let is_main_window = window_id == *main_window;
+
+                // Skip rendering while suspended, until the application is resumed with a new surface.
+                let Some(extent) = renderer.swapchain_extent(target) else {
+                    return;
+                };
 
                 // Request a redraw of the window surface whenever possible.
                 window.request_redraw();
 
+                // Replace a lost surface with a new one for the same window.
+                if renderer.surface_lost(target) {
+                    println!("WARN: Recreating the lost surface");
+                    renderer.resume(&self.vulkan, target, create_surface(&self.vulkan, window));
+                    return;
+                }
+
                 // Process any pending swapchain recreation requests.
-                renderer.handle_swapchain_resize(&self.vulkan);
+                renderer.handle_swapchain_resize(&self.vulkan, target);
 
                 // Check that the current window size won't affect rendering.
                 {
-                    let extent = renderer.swapchain.extent();
                     let window_size = window.inner_size();
                     if window_size.width == 0 || window_size.height == 0 {
                         // Skip all operations if the window contains no pixels.
@@ -419,9 +928,60 @@ impl winit::application::ApplicationHandler<PompeiiEvent> for PompeiiApp {
                             "ERROR: Swapchain is out of date at window-size check, needs to be recreated."
                         );
 
-                        renderer.swapchain_resize_required(Some(window_size));
+                        renderer.swapchain_resize_required(target, Some(window_size));
+                        return;
+                    }
+                }
+
+                // The other windows draw the state of the last frame of the main window, without advancing the game state.
+                if !is_main_window {
+                    if let Some(push_constants) = &self.last_push_constants {
+                        renderer.render_frame(&self.vulkan, target, push_constants);
+                    }
+                    return;
+                }
+
+                // In low-latency mode, wait for the next frame before sampling input, and render it on the following redraw.
+                // The event loop then delivers the input which arrived during the wait, so the frame is recorded with the freshest input.
+                if self.args.low_latency && !renderer.frame_began(target) {
+                    renderer.wait_for_next_frame(target);
+                    return;
+                }
+
+                // Exit once every frame of the input log has been replayed.
+                if self
+                    .input_replay
+                    .as_ref()
+                    .is_some_and(input_log::InputReplay::is_finished)
+                {
+                    println!("Input replay finished after {} frames", self.tick_count);
+                    event_loop.exit();
+                    return;
+                }
+
+                // The simulation grid and cursor scaling follow the main window, so each replayed frame must be computed at its recorded size.
+                // Ask the window to match, and refuse to continue if it can't, rather than silently computing a different simulation.
+                if let Some([width, height]) = self
+                    .input_replay
+                    .as_ref()
+                    .and_then(input_log::InputReplay::next_extent)
+                {
+                    if extent != (ash::vk::Extent2D { width, height }) {
+                        let started = *self.replay_resize_started.get_or_insert_with(|| {
+                            let _ = window
+                                .request_inner_size(winit::dpi::PhysicalSize::new(width, height));
+                            std::time::Instant::now()
+                        });
+                        if started.elapsed() > REPLAY_RESIZE_TIMEOUT {
+                            eprintln!(
+                                "Error replaying input log: frame {} was recorded at {width}x{height}, but the window could not be resized from {}x{}",
+                                self.tick_count, extent.width, extent.height
+                            );
+                            event_loop.exit();
+                        }
                         return;
                     }
+                    self.replay_resize_started = None;
                 }
 
                 // Update the game state and get the push constants for the next frame.
@@ -436,9 +996,12 @@ impl winit::application::ApplicationHandler<PompeiiEvent> for PompeiiApp {
                 self.handle_keyboard_input(event);
             }
 
-            winit::event::WindowEvent::CursorMoved { position, .. } => {
+            // Only the mouse input of the main window drives the demos, since it is scaled to the main window.
+            winit::event::WindowEvent::CursorMoved { position, .. }
+                if self.is_main_window(window_id) =>
+            {
                 let now = std::time::Instant::now();
-                self.mouse_velocity = match self.last_mouse_position {
+                let velocity = match self.last_mouse_position {
                     Some((last_position, last_time)) => {
                         let delta_time = now.duration_since(last_time).as_secs_f64();
                         let delta = (
@@ -453,24 +1016,29 @@ impl winit::application::ApplicationHandler<PompeiiEvent> for PompeiiApp {
                     None => [0.; 2],
                 };
 
-                self.last_mouse_position = Some((position, now));
+                self.handle_input(input_log::InputEvent::CursorMoved {
+                    position: position.into(),
+                    velocity,
+                });
             }
             winit::event::WindowEvent::MouseInput {
                 button: winit::event::MouseButton::Left,
                 state,
                 ..
-            } => {
-                if matches!(state, winit::event::ElementState::Pressed) {
-                    self.mouse_click =
-                        Some(self.last_mouse_position.map_or([-1024.; 2], |m| m.0.into()));
-                } else {
-                    self.mouse_click = None;
-                }
+            } if self.is_main_window(window_id) => {
+                self.handle_input(input_log::InputEvent::MouseButton {
+                    pressed: matches!(state, winit::event::ElementState::Pressed),
+                });
             }
 
             // Ignore other events.
             _ => (),
         }
+
+        // Open the windows requested while handling the event, now that the event loop is available.
+        if std::mem::take(&mut self.open_window_requested) {
+            self.open_window(event_loop);
+        }
     }
 }
 
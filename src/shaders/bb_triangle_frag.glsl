@@ -1,12 +1,20 @@
 #version 460
+#extension GL_EXT_scalar_block_layout : require
 
 layout(location = 0) in vec4 inColor;
 layout(location = 0) out vec4 outColor;
 
-layout(push_constant) uniform PushConstants {
+layout(scalar, push_constant) uniform PushConstants {
   float time;
+  vec2 jitter;
 } pushConstants;
 
+// The colors are chosen in sRGB, but the scene is rendered in linear values.
+vec3 srgbToLinear(vec3 color) {
+  return mix(color / 12.92, pow((color + 0.055) / 1.055, vec3(2.4)), greaterThan(color, vec3(0.04045)));
+}
+
 void main() {
-  outColor = fract(inColor + 0.5*pushConstants.time*vec4(1.0, 0.6, 0.3, 0.0));
+  const vec4 color = fract(inColor + 0.5*pushConstants.time*vec4(1.0, 0.6, 0.3, 0.0));
+  outColor = vec4(srgbToLinear(color.rgb), color.a);
 }
\ No newline at end of file
@@ -1,9 +1,15 @@
 #version 460
+#extension GL_EXT_scalar_block_layout : require
 
 layout(location = 0) out vec4 outColor;
 
 layout(constant_id = 0) const bool toggle = false;
 
+layout(scalar, push_constant) uniform PushConstants {
+  float time;
+  vec2 jitter; // Sub-pixel offset in normalized device coordinates.
+} pushConstants;
+
 vec2 positions[3] = vec2[](vec2(0.0, -0.5), vec2(-0.5, 0.5), vec2(0.5, 0.5));
 
 vec3 colors[3] = vec3[](vec3(1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0), vec3(0.0, 0.0, 1.0));
@@ -14,5 +20,6 @@ void main() {
   } else {
     gl_Position = vec4(positions[gl_VertexIndex], 0.0, 1.0);
   }
+  gl_Position.xy += pushConstants.jitter;
   outColor = vec4(colors[gl_VertexIndex], 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,28 @@
+// Add the blurred bright light from the bloom mip chain back to the scene, before it is tonemapped.
+#version 460
+
+layout(push_constant) uniform PushConstants {
+  vec2 inverse_screen_size;
+  float split_position; // The fraction of the screen width, from the left, where bloom is applied.
+  float intensity; // The strength of the bloom, already divided by the number of accumulated levels.
+} push_constants;
+
+layout(set = 0, binding = 0) uniform sampler2D scene_texture;
+layout(set = 0, binding = 1) uniform sampler2D bloom_texture;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+  const vec4 scene = texelFetch(scene_texture, ivec2(gl_FragCoord.xy), 0);
+
+  // Pass the raw input through to the right of the split, for comparison. Draw a thin divider along the split.
+  const float split_x = push_constants.split_position / push_constants.inverse_screen_size.x;
+  if(gl_FragCoord.x >= split_x) {
+    out_color = gl_FragCoord.x < split_x + 1.0 ? vec4(1) : scene;
+    return;
+  }
+
+  const vec2 tex_coord = gl_FragCoord.xy * push_constants.inverse_screen_size;
+  const vec3 bloom = textureLod(bloom_texture, tex_coord, 0.0).rgb;
+  out_color = vec4(scene.rgb + push_constants.intensity * bloom, scene.a);
+}
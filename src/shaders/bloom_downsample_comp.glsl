@@ -0,0 +1,64 @@
+// Downsample one level of the bloom mip chain into the next, smaller level. See "Next Generation Post Processing in Call of Duty: Advanced Warfare" by Jorge Jimenez.
+// The first level also applies a bright-pass filter, so only light above the threshold blooms.
+#version 460
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(push_constant) uniform PushConstants {
+  float threshold; // The brightness above which light contributes to bloom.
+  float soft_knee; // The fraction of the threshold over which the bright-pass smoothly fades in.
+  uint prefilter; // Non-zero when downsampling the scene itself, applying the bright-pass filter.
+  float filter_radius; // Unused by the downsample.
+  vec2 source_max_coord; // The largest texture coordinates sampled, which keep the bright-pass inside the rendered area of the scene.
+} push_constants;
+
+layout(set = 0, binding = 0) uniform sampler2D source_texture;
+layout(set = 0, binding = 1, rgba16f) uniform writeonly image2D destination_image;
+
+// Keep only the light above the threshold, with a quadratic curve around it to avoid a hard cutoff.
+vec3 bright_pass(vec3 color) {
+  const float brightness = max(color.r, max(color.g, color.b));
+  const float knee = push_constants.threshold * push_constants.soft_knee + 1e-4;
+  float soft = clamp(brightness - push_constants.threshold + knee, 0.0, 2.0 * knee);
+  soft = soft * soft / (4.0 * knee);
+  return color * (max(soft, brightness - push_constants.threshold) / max(brightness, 1e-4));
+}
+
+vec3 sample_source(vec2 tex_coord) {
+  return max(textureLod(source_texture, min(tex_coord, push_constants.source_max_coord), 0.0).rgb, 0.0);
+}
+
+void main() {
+  const ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+  const ivec2 size = imageSize(destination_image);
+  if(any(greaterThanEqual(pixel, size))) {
+    return;
+  }
+
+  // Take 13 bilinear samples spanning a 6x6 texel area of the source, weighted as five overlapping boxes.
+  const vec2 texel = 1.0 / vec2(textureSize(source_texture, 0));
+  const vec2 center = (vec2(pixel) + 0.5) / vec2(size);
+  const vec3 outer_nw = sample_source(center + texel * vec2(-2, -2));
+  const vec3 outer_n = sample_source(center + texel * vec2(0, -2));
+  const vec3 outer_ne = sample_source(center + texel * vec2(2, -2));
+  const vec3 outer_w = sample_source(center + texel * vec2(-2, 0));
+  const vec3 middle = sample_source(center);
+  const vec3 outer_e = sample_source(center + texel * vec2(2, 0));
+  const vec3 outer_sw = sample_source(center + texel * vec2(-2, 2));
+  const vec3 outer_s = sample_source(center + texel * vec2(0, 2));
+  const vec3 outer_se = sample_source(center + texel * vec2(2, 2));
+  const vec3 inner_nw = sample_source(center + texel * vec2(-1, -1));
+  const vec3 inner_ne = sample_source(center + texel * vec2(1, -1));
+  const vec3 inner_sw = sample_source(center + texel * vec2(-1, 1));
+  const vec3 inner_se = sample_source(center + texel * vec2(1, 1));
+
+  vec3 color = middle * 0.125;
+  color += (outer_nw + outer_ne + outer_sw + outer_se) * 0.03125;
+  color += (outer_n + outer_w + outer_e + outer_s) * 0.0625;
+  color += (inner_nw + inner_ne + inner_sw + inner_se) * 0.125;
+
+  if(push_constants.prefilter != 0) {
+    color = bright_pass(color);
+  }
+  imageStore(destination_image, pixel, vec4(color, 1));
+}
@@ -0,0 +1,39 @@
+// Upsample one level of the bloom mip chain with a tent filter and add it to the next, larger level.
+// Accumulating every level this way blurs the bright light with a wide, smooth falloff.
+#version 460
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(push_constant) uniform PushConstants {
+  float threshold; // Unused by the upsample.
+  float soft_knee; // Unused by the upsample.
+  uint prefilter; // Unused by the upsample.
+  float filter_radius; // The spacing of the tent filter taps, in texels of the source.
+  vec2 source_max_coord; // Unused by the upsample.
+} push_constants;
+
+layout(set = 0, binding = 0) uniform sampler2D source_texture;
+layout(set = 0, binding = 1, rgba16f) uniform image2D destination_image;
+
+void main() {
+  const ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+  const ivec2 size = imageSize(destination_image);
+  if(any(greaterThanEqual(pixel, size))) {
+    return;
+  }
+
+  // Take nine bilinear samples of the smaller level with a 3x3 tent filter.
+  const vec2 offset = push_constants.filter_radius / vec2(textureSize(source_texture, 0));
+  const vec2 center = (vec2(pixel) + 0.5) / vec2(size);
+  vec3 color = textureLod(source_texture, center, 0.0).rgb * 4.0;
+  color += textureLod(source_texture, center + offset * vec2(-1, 0), 0.0).rgb * 2.0;
+  color += textureLod(source_texture, center + offset * vec2(1, 0), 0.0).rgb * 2.0;
+  color += textureLod(source_texture, center + offset * vec2(0, -1), 0.0).rgb * 2.0;
+  color += textureLod(source_texture, center + offset * vec2(0, 1), 0.0).rgb * 2.0;
+  color += textureLod(source_texture, center + offset * vec2(-1, -1), 0.0).rgb;
+  color += textureLod(source_texture, center + offset * vec2(1, -1), 0.0).rgb;
+  color += textureLod(source_texture, center + offset * vec2(-1, 1), 0.0).rgb;
+  color += textureLod(source_texture, center + offset * vec2(1, 1), 0.0).rgb;
+
+  imageStore(destination_image, pixel, imageLoad(destination_image, pixel) + vec4(color / 16.0, 0));
+}
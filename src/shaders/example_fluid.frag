@@ -12,15 +12,22 @@ layout(scalar, buffer_reference, buffer_reference_align = 16) readonly buffer Dy
 layout(scalar, buffer_reference, buffer_reference_align = 16) readonly buffer PressureTexture {
   float pressure[];
 };
+layout(scalar, buffer_reference, buffer_reference_align = 16) readonly buffer SmokeTexture {
+  vec2 s[]; // Temperature and density.
+};
 
 layout(scalar, push_constant) uniform PushConstants {
   // GPU buffer references.
   VelocityTexture velocity;
   DyeTexture dye;
   PressureTexture pressure;
+  SmokeTexture smoke;
 
   ivec2 screen_size;
   uint display_texture;
+  vec2 jitter; // Sub-pixel offset of the sample position, for temporal anti-aliasing.
+  ivec2 render_size; // Size of the image being drawn to, which the simulation is scaled to fill.
+  uint jittered_width; // Pixels left of this position are offset by the jitter, and the rest are not.
 } push_constants;
 
 layout(location = 0) out vec4 out_color;
@@ -31,6 +38,21 @@ int texture_index(ivec2 uv) {
   return uv.y * push_constants.screen_size.x + uv.x;
 }
 
+// Bilinearly interpolate the dye at a position in pixels, so that sub-pixel offsets affect the result.
+vec4 sample_dye(vec2 position) {
+  const vec2 texel = position - 0.5;
+  const ivec2 base = ivec2(floor(texel));
+  const vec2 f = texel - vec2(base);
+  const vec4 top = mix(push_constants.dye.colors[texture_index(base)], push_constants.dye.colors[texture_index(base + ivec2(1, 0))], f.x);
+  const vec4 bottom = mix(push_constants.dye.colors[texture_index(base + ivec2(0, 1))], push_constants.dye.colors[texture_index(base + ivec2(1, 1))], f.x);
+  return mix(top, bottom, f.y);
+}
+
+// The visualizations are designed in sRGB, but the scene is rendered in linear values.
+vec3 srgb_to_linear(vec3 color) {
+  return mix(color / 12.92, pow((color + 0.055) / 1.055, vec3(2.4)), greaterThan(color, vec3(0.04045)));
+}
+
 vec3 color_wheel(float t) {
   return vec3(
     max(sin(t - 0.625) + 0.5, 0) * (2.0 / 3.0),
@@ -38,18 +60,35 @@ vec3 color_wheel(float t) {
     max(cos(t - 0.1) - 0.3, 0)) * (1.0 / 0.7);
 }
 
+// Approximate the normalized color of a blackbody radiator at the given temperature in Kelvin.
+// Uses Tanner Helland's curve fit, which is reasonable between 1000K and 40000K.
+vec3 blackbody_color(float kelvin) {
+  const float t = kelvin / 100.0;
+  vec3 color;
+  color.r = t <= 66.0 ? 1.0 : 1.292936186 * pow(t - 60.0, -0.1332047592);
+  color.g = t <= 66.0 ? 0.390081579 * log(t) - 0.631841444 : 1.129890861 * pow(t - 60.0, -0.0755148492);
+  color.b = t >= 66.0 ? 1.0 : (t <= 19.0 ? 0.0 : 0.543206789 * log(t - 10.0) - 1.196254089);
+  return clamp(color, 0.0, 1.0);
+}
+
 void main() {
+  // Map the fragment to the simulation grid, which may be a different size than the image being drawn to.
+  const vec2 grid_scale = vec2(push_constants.screen_size) / vec2(push_constants.render_size);
+
   // Oddly, some platforms may give a gl_FragCoord that is out of screen bounds.
-  const ivec2 pixel_coord = ivec2(gl_FragCoord.xy);
+  const ivec2 pixel_coord = ivec2(gl_FragCoord.xy * grid_scale);
   if(pixel_coord.x >= push_constants.screen_size.x || pixel_coord.y >= push_constants.screen_size.y) {
     out_color = vec4(1, 0.2, 1, 1);
     return;
   }
   const int pixel_index = pixel_coord.y * push_constants.screen_size.x + pixel_coord.x;
+  const vec2 jitter = gl_FragCoord.x < push_constants.jittered_width ? push_constants.jitter : vec2(0);
+  const vec2 frag_coord = (gl_FragCoord.xy + jitter) * grid_scale;
 
   if(push_constants.display_texture == 0) {
-    // Dye color.
-    out_color = pow(push_constants.dye.colors[pixel_index], vec4(0.55));
+    // Dye color. The dye is already a linear quantity of light.
+    out_color = max(sample_dye(frag_coord), 0);
+    return;
   } else if(push_constants.display_texture == 1) {
     // Velocity magnitudes.
     out_color = vec4(abs(push_constants.velocity.v[pixel_index]) / 1200.0, 0, 1);
@@ -57,12 +96,16 @@ void main() {
     // Pressure.
     float p = clamp(push_constants.pressure.pressure[pixel_index] / 220.0, -1, 1);
     out_color = vec4(vec3(pow(max(p, 0), 0.75)) + vec3(0, 0, pow(-min(p, 0), 0.75)), 1);
+  } else if(push_constants.display_texture == 7) {
+    // Temperature, mapped from ambient to 6500K through a blackbody ramp. Cooler regions also grow dimmer.
+    const float temperature = clamp(push_constants.smoke.s[pixel_index].x, 0, 1);
+    out_color = vec4(temperature * blackbody_color(mix(1000.0, 6500.0, temperature)), 1);
   } else {
     // Velocity direction.
     const float SIZE = 32;
     const vec2 SHIFT = vec2(0.5);
     const vec2 pixel_velocity = push_constants.velocity.v[pixel_index];
-    const vec2 ij = gl_FragCoord.xy / SIZE - SHIFT;
+    const vec2 ij = frag_coord / SIZE - SHIFT;
     const vec2 ij_center = round(ij);
 
     // Normalize the velocity at the center of the grid-cell this pixel belongs to.
@@ -100,4 +143,5 @@ void main() {
       out_color = vec4(s * color_wheel(atan(pixel_velocity.y, pixel_velocity.x)), 1);
     }
   }
+  out_color.rgb = srgb_to_linear(clamp(out_color.rgb, 0.0, 1.0));
 }
@@ -2,18 +2,22 @@
 
 layout(push_constant) uniform PushConstants {
   vec2 inverse_screen_size;
+  float split_position; // The fraction of the screen width, from the left, where FXAA is applied.
 } push_constants;
 
 layout(set = 0, binding = 0) uniform sampler2D input_texture;
 
 layout(location = 0) out vec4 out_color;
 
-// Constants used for the FXAA algorithm.
-const float EDGE_THRESHOLD_MIN = 1.0 / 20.0;
-const float EDGE_THRESHOLD_MAX = 1.0 / 12.0;
-const float PIXEL_BLEND_LIMIT  = 0.8;
-const float MIN_PIXEL_ALIASING = 1.0 / 8.0;
-const float NUM_LOOP_FOR_EDGE_DETECTION = 2;
+// Parameters of the FXAA algorithm, set by the application. The defaults match the medium quality preset.
+layout(constant_id = 0) const float EDGE_THRESHOLD_MIN = 1.0 / 20.0;
+layout(constant_id = 1) const float EDGE_THRESHOLD_MAX = 1.0 / 12.0;
+layout(constant_id = 2) const float PIXEL_BLEND_LIMIT = 0.8;
+layout(constant_id = 3) const float MIN_PIXEL_ALIASING = 1.0 / 8.0;
+layout(constant_id = 4) const int NUM_LOOP_FOR_EDGE_DETECTION = 2;
+
+// Whether to visualize which pixels FXAA modified instead of the anti-aliased image.
+layout(constant_id = 5) const bool DEBUG_EDGES = false;
 
 // Constants to help indexing neighboring cells by name.
 const int CENTER = 0;
@@ -32,9 +36,10 @@ const vec2 BLOCK_OFFSETS[] = {
   vec2(1, -1), vec2(1, 1), vec2(-1, -1), vec2(-1, 1)
 };
 
-// Calculate the luminance of a color using a normalized linear combination of the color channels.
+// Calculate the perceived luminance of a linear color using a normalized linear combination of the color channels.
+// The square root approximates the sRGB curve, so that contrast is judged as the eye would see it.
 float measure_luminance(vec3 color) {
-  return dot(color, vec3(0.299, 0.587, 0.114));
+  return sqrt(max(dot(color, vec3(0.299, 0.587, 0.114)), 0.0));
 }
 
 // Traverses an edge in the image bi-directionally in search of the endpoints.
@@ -223,6 +228,28 @@ vec4 apply_fxaa(vec2 screen_coord) {
   return vec4(mix(color_edge_anti_aliasing_pixel, average_color, pixel_blend), 1);
 }
 
+// Show unmodified pixels in dim grayscale and the pixels FXAA modified in red, brighter for larger changes.
+vec4 debug_edge_color(vec3 original, vec3 processed) {
+  if(all(equal(original, processed))) {
+    return vec4(vec3(0.3 * measure_luminance(original)), 1);
+  }
+  const float change = clamp(4.0 * length(processed - original), 0, 1);
+  return vec4(mix(vec3(0.35, 0, 0), vec3(1, 0.25, 0.25), change), 1);
+}
+
 void main() {
+  // Pass the raw input through to the right of the split, for comparison. Draw a thin divider along the split.
+  const float split_x = push_constants.split_position / push_constants.inverse_screen_size.x;
+  if(gl_FragCoord.x >= split_x) {
+    out_color = gl_FragCoord.x < split_x + 1.0
+      ? vec4(1)
+      : vec4(texture(input_texture, gl_FragCoord.xy * push_constants.inverse_screen_size).rgb, 1);
+    return;
+  }
+
   out_color = apply_fxaa(gl_FragCoord.xy);
+  if(DEBUG_EDGES) {
+    const vec3 original = texture(input_texture, gl_FragCoord.xy * push_constants.inverse_screen_size).rgb;
+    out_color = debug_edge_color(original, out_color.rgb);
+  }
 }
@@ -0,0 +1,46 @@
+// Sharpen the upscaled scene with robust contrast-adaptive sharpening, following FidelityFX Super Resolution 1 RCAS.
+// Each pixel is sharpened as much as possible without its neighbors pushing it past their local minimum or maximum.
+#version 460
+
+layout(push_constant) uniform PushConstants {
+  float sharpness; // The strength of sharpening, where one is the maximum.
+} push_constants;
+
+layout(set = 0, binding = 0) uniform sampler2D input_texture;
+
+layout(location = 0) out vec4 out_color;
+
+// The most negative weight given to the neighbors, which limits sharpening to avoid artifacts.
+const float LOBE_LIMIT = 0.25 - 1.0 / 16.0;
+
+// Fetch a texel, clamping to the edges of the image.
+// The scene is in linear HDR, so colors are compressed into the unit range where the limits of RCAS apply.
+vec3 fetch(ivec2 texel) {
+  const vec3 color = max(texelFetch(input_texture, clamp(texel, ivec2(0), textureSize(input_texture, 0) - 1), 0).rgb, 0.0);
+  return color / (1.0 + max(color.r, max(color.g, color.b)));
+}
+
+void main() {
+  // Sample the pixel and its four direct neighbors.
+  //    b
+  //  d e f
+  //    h
+  const ivec2 texel = ivec2(gl_FragCoord.xy);
+  const vec3 b = fetch(texel + ivec2(0, -1));
+  const vec3 d = fetch(texel + ivec2(-1, 0));
+  const vec3 e = fetch(texel);
+  const vec3 f = fetch(texel + ivec2(1, 0));
+  const vec3 h = fetch(texel + ivec2(0, 1));
+
+  // Find the largest negative weight for the neighbors which keeps the result within their range, for each channel.
+  const vec3 neighbor_min = min(min(b, d), min(f, h));
+  const vec3 neighbor_max = max(max(b, d), max(f, h));
+  const vec3 hit_min = min(neighbor_min, e) / (4.0 * neighbor_max + 1e-5);
+  const vec3 hit_max = (1.0 - max(neighbor_max, e)) / (4.0 * min(neighbor_min, e) - 4.0);
+  const vec3 lobe_rgb = max(-hit_min, hit_max);
+  const float lobe = max(-LOBE_LIMIT, min(max(lobe_rgb.r, max(lobe_rgb.g, lobe_rgb.b)), 0.0)) * push_constants.sharpness;
+
+  // Apply the weights, and restore the linear HDR range of the scene.
+  const vec3 sharpened = clamp((lobe * (b + d + f + h) + e) / (4.0 * lobe + 1.0), 0.0, 0.999);
+  out_color = vec4(sharpened / (1.0 - max(sharpened.r, max(sharpened.g, sharpened.b))), 1.0);
+}
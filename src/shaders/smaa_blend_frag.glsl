@@ -0,0 +1,46 @@
+// Final pass of SMAA 1x, blending each pixel with its neighbors using the weights of the edges around it.
+#version 460
+
+layout(push_constant) uniform PushConstants {
+  vec2 inverse_screen_size;
+  float split_position; // The fraction of the screen width, from the left, where SMAA is applied.
+} push_constants;
+
+layout(set = 0, binding = 0) uniform sampler2D color_texture;
+layout(set = 0, binding = 1) uniform sampler2D weights_texture;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+  const vec2 texel = push_constants.inverse_screen_size;
+  const vec2 tex_coord = gl_FragCoord.xy * texel;
+
+  // Pass the raw input through to the right of the split, for comparison. Draw a thin divider along the split.
+  const float split_x = push_constants.split_position / texel.x;
+  if(gl_FragCoord.x >= split_x) {
+    out_color = gl_FragCoord.x < split_x + 1.0 ? vec4(1) : vec4(textureLod(color_texture, tex_coord, 0).rgb, 1);
+    return;
+  }
+
+  // Gather the weights of the four edges around this pixel. The right and bottom edges are stored by those neighbors.
+  vec4 a;
+  a.x = textureLod(weights_texture, tex_coord + vec2(texel.x, 0), 0).a; // Right.
+  a.y = textureLod(weights_texture, tex_coord + vec2(0, texel.y), 0).g; // Bottom.
+  a.wz = textureLod(weights_texture, tex_coord, 0).rb; // Top and left.
+
+  if(dot(a, vec4(1)) < 1e-5) {
+    out_color = vec4(textureLod(color_texture, tex_coord, 0).rgb, 1);
+    return;
+  }
+
+  // Blend along the direction with the strongest weight, using bilinear filtering to mix with the neighbors.
+  const bool horizontal = max(a.x, a.z) > max(a.y, a.w);
+  const vec4 blending_offset = horizontal ? vec4(a.x, 0, -a.z, 0) : vec4(0, a.y, 0, -a.w);
+  vec2 blending_weight = horizontal ? a.xz : a.yw;
+  blending_weight /= dot(blending_weight, vec2(1));
+
+  const vec4 blending_coord = tex_coord.xyxy + blending_offset * texel.xyxy;
+  const vec3 color = blending_weight.x * textureLod(color_texture, blending_coord.xy, 0).rgb
+    + blending_weight.y * textureLod(color_texture, blending_coord.zw, 0).rgb;
+  out_color = vec4(color, 1);
+}
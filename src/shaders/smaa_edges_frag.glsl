@@ -0,0 +1,54 @@
+// First pass of SMAA 1x, detecting edges by the local contrast in luma. See https://www.iryoku.com/smaa/.
+#version 460
+
+layout(push_constant) uniform PushConstants {
+  vec2 inverse_screen_size;
+  float split_position;
+} push_constants;
+
+layout(set = 0, binding = 0) uniform sampler2D color_texture;
+
+// The red channel marks an edge on the left of the pixel, and green an edge on the top.
+layout(location = 0) out vec2 out_edges;
+
+// The minimum difference in luma between neighbors to be considered an edge.
+const float THRESHOLD = 0.1;
+
+// Edges are ignored if a neighboring edge has this many times more contrast, as the eye perceives only the stronger one.
+const float LOCAL_CONTRAST_ADAPTATION_FACTOR = 2.0;
+
+// The scene is linear, so approximate the sRGB curve with a square root to find edges as the eye would see them.
+float luma(vec2 tex_coord) {
+  return sqrt(max(dot(texture(color_texture, tex_coord).rgb, vec3(0.2126, 0.7152, 0.0722)), 0.0));
+}
+
+void main() {
+  const vec2 texel = push_constants.inverse_screen_size;
+  const vec2 tex_coord = gl_FragCoord.xy * texel;
+
+  // Compare against the left and top neighbors.
+  const float center = luma(tex_coord);
+  const float left = luma(tex_coord - vec2(texel.x, 0));
+  const float top = luma(tex_coord - vec2(0, texel.y));
+  const vec2 delta_left_top = abs(center - vec2(left, top));
+  vec2 edges = step(THRESHOLD, delta_left_top);
+  if(dot(edges, vec2(1)) == 0.0) {
+    out_edges = vec2(0);
+    return;
+  }
+
+  // Find the maximum contrast with the other neighbors, and of the neighbors themselves.
+  const float right = luma(tex_coord + vec2(texel.x, 0));
+  const float bottom = luma(tex_coord + vec2(0, texel.y));
+  vec2 max_delta = max(delta_left_top, abs(center - vec2(right, bottom)));
+
+  const float left_left = luma(tex_coord - vec2(2.0 * texel.x, 0));
+  const float top_top = luma(tex_coord - vec2(0, 2.0 * texel.y));
+  max_delta = max(max_delta, abs(vec2(left, top) - vec2(left_left, top_top)));
+
+  // Apply the local contrast adaptation.
+  const float final_delta = max(max_delta.x, max_delta.y);
+  edges *= step(final_delta, LOCAL_CONTRAST_ADAPTATION_FACTOR * delta_left_top);
+
+  out_edges = edges;
+}
@@ -0,0 +1,274 @@
+// Second pass of SMAA 1x, calculating how much each pixel should blend with its neighbors across the detected edges.
+// The distances to the ends of each edge, and the crossing edges found there, select the pixel coverage from a precomputed area texture.
+// Diagonal edges take priority over orthogonal ones, and the orthogonal weights are reduced at corners to keep them sharp.
+#version 460
+
+layout(push_constant) uniform PushConstants {
+  vec2 inverse_screen_size;
+  float split_position;
+} push_constants;
+
+layout(set = 0, binding = 0) uniform sampler2D edges_texture;
+layout(set = 0, binding = 1) uniform sampler2D area_texture;
+layout(set = 0, binding = 2) uniform sampler2D search_texture;
+
+// The red and green channels hold the blending weights across the top edge, and blue and alpha across the left edge.
+layout(location = 0) out vec4 out_weights;
+
+// The maximum number of steps when searching for the ends of an edge. Each step covers two pixels.
+const int MAX_SEARCH_STEPS = 8;
+
+// The maximum number of pixels to search along a diagonal edge.
+const int MAX_SEARCH_STEPS_DIAGONAL = 8;
+
+// How much to keep of the blending at corners, as a fraction. Lower values keep corners sharper.
+const float CORNER_ROUNDING = 0.25;
+
+// The size of each orthogonal tile in the area texture, indexed by the square root of the distances to the ends of the edge.
+const float AREA_TEXTURE_MAX_DISTANCE = 16.0;
+
+// The size of each diagonal tile in the area texture, indexed by the distances to the ends of the edge.
+const float AREA_TEXTURE_MAX_DISTANCE_DIAGONAL = 20.0;
+
+// Use the search texture to find how many more pixels the edge continues for within a bilinear fetch of edges.
+// The left and up searches use the left half of the texture with an offset of zero, and the right and down searches the right half with an offset of one half.
+// The texture is cropped from 66 by 33 texels to the bilinear fetches which may continue an edge, and flipped vertically.
+float search_length(vec2 e, float offset) {
+  const vec2 packed_size = vec2(64, 16);
+  const vec2 scale = (vec2(33, -33) + vec2(-1, 1)) / packed_size;
+  const vec2 bias = (vec2(66.0 * offset, 33) + vec2(0.5, -0.5)) / packed_size;
+  return textureLod(search_texture, scale * e + bias, 0).r;
+}
+
+// Search for the left end of a horizontal edge, two pixels at a time by exploiting bilinear filtering.
+float search_x_left(vec2 tex_coord, float end) {
+  vec2 e = vec2(0, 1);
+  while(tex_coord.x > end && e.g > 0.8281 && e.r == 0.0) {
+    e = textureLod(edges_texture, tex_coord, 0).rg;
+    tex_coord.x -= 2.0 * push_constants.inverse_screen_size.x;
+  }
+  const float offset = 3.25 - (255.0 / 127.0) * search_length(e, 0.0);
+  return tex_coord.x + offset * push_constants.inverse_screen_size.x;
+}
+
+// Search for the right end of a horizontal edge.
+float search_x_right(vec2 tex_coord, float end) {
+  vec2 e = vec2(0, 1);
+  while(tex_coord.x < end && e.g > 0.8281 && e.r == 0.0) {
+    e = textureLod(edges_texture, tex_coord, 0).rg;
+    tex_coord.x += 2.0 * push_constants.inverse_screen_size.x;
+  }
+  const float offset = 3.25 - (255.0 / 127.0) * search_length(e, 0.5);
+  return tex_coord.x - offset * push_constants.inverse_screen_size.x;
+}
+
+// Search for the top end of a vertical edge.
+float search_y_up(vec2 tex_coord, float end) {
+  vec2 e = vec2(1, 0);
+  while(tex_coord.y > end && e.r > 0.8281 && e.g == 0.0) {
+    e = textureLod(edges_texture, tex_coord, 0).rg;
+    tex_coord.y -= 2.0 * push_constants.inverse_screen_size.y;
+  }
+  const float offset = 3.25 - (255.0 / 127.0) * search_length(e.gr, 0.0);
+  return tex_coord.y + offset * push_constants.inverse_screen_size.y;
+}
+
+// Search for the bottom end of a vertical edge.
+float search_y_down(vec2 tex_coord, float end) {
+  vec2 e = vec2(1, 0);
+  while(tex_coord.y < end && e.r > 0.8281 && e.g == 0.0) {
+    e = textureLod(edges_texture, tex_coord, 0).rg;
+    tex_coord.y += 2.0 * push_constants.inverse_screen_size.y;
+  }
+  const float offset = 3.25 - (255.0 / 127.0) * search_length(e.gr, 0.5);
+  return tex_coord.y - offset * push_constants.inverse_screen_size.y;
+}
+
+// Look up the coverage of this pixel, given the square root of the distances to each end and the crossing edges found there.
+// SMAA 1x only uses the first subtexture of the area texture, without a subsample offset.
+vec2 area(vec2 sqrt_distance, float e1, float e2) {
+  const vec2 texel = AREA_TEXTURE_MAX_DISTANCE * round(4.0 * vec2(e1, e2)) + sqrt_distance;
+  return textureLod(area_texture, (texel + 0.5) / vec2(textureSize(area_texture, 0)), 0).rg;
+}
+
+// Look up the coverage of this pixel on a diagonal edge, given the distances to each end and the crossing edges found there.
+// The diagonal tiles are in the right half of the area texture.
+vec2 area_diagonal(vec2 distance, vec2 e) {
+  const vec2 texel = AREA_TEXTURE_MAX_DISTANCE_DIAGONAL * e + distance;
+  return textureLod(area_texture, (texel + 0.5) / vec2(textureSize(area_texture, 0)) + vec2(0.5, 0), 0).rg;
+}
+
+// Recover which of the two edges were set in a bilinear fetch of edges, which is offset a quarter pixel horizontally.
+// Only the red channel is mixed with its neighbor, as the vertical position is exact.
+vec2 decode_diagonal_bilinear_access(vec2 e) {
+  e.r = e.r * abs(5.0 * e.r - 5.0 * 0.75);
+  return round(e);
+}
+
+vec4 decode_diagonal_bilinear_access(vec4 e) {
+  e.rb = e.rb * abs(5.0 * e.rb - 5.0 * 0.75);
+  return round(e);
+}
+
+// Search along a diagonal edge going from bottom-left to top-right, one pixel at a time.
+// Returns the distance to the end of the edge, and whether the search ended while still on an edge.
+vec2 search_diagonal_1(vec2 tex_coord, vec2 direction, out vec2 e) {
+  vec4 coord = vec4(tex_coord, -1, 1);
+  const vec3 t = vec3(push_constants.inverse_screen_size, 1);
+  while(coord.z < float(MAX_SEARCH_STEPS_DIAGONAL - 1) && coord.w > 0.9) {
+    coord.xyz += t * vec3(direction, 1);
+    e = textureLod(edges_texture, coord.xy, 0).rg;
+    coord.w = dot(e, vec2(0.5));
+  }
+  return coord.zw;
+}
+
+// Search along a diagonal edge going from top-left to bottom-right.
+// The fetches are offset a quarter pixel to read both edges with a single bilinear fetch.
+vec2 search_diagonal_2(vec2 tex_coord, vec2 direction, out vec2 e) {
+  vec4 coord = vec4(tex_coord, -1, 1);
+  coord.x += 0.25 * push_constants.inverse_screen_size.x;
+  const vec3 t = vec3(push_constants.inverse_screen_size, 1);
+  while(coord.z < float(MAX_SEARCH_STEPS_DIAGONAL - 1) && coord.w > 0.9) {
+    coord.xyz += t * vec3(direction, 1);
+    e = textureLod(edges_texture, coord.xy, 0).rg;
+    e = decode_diagonal_bilinear_access(e);
+    coord.w = dot(e, vec2(0.5));
+  }
+  return coord.zw;
+}
+
+// Calculate the blending weights across the top edge of this pixel for a diagonal edge passing through it, in both directions.
+vec2 diagonal_weights(vec2 tex_coord, vec2 e) {
+  const vec2 texel = push_constants.inverse_screen_size;
+  vec2 weights = vec2(0);
+  vec4 d;
+  vec2 end;
+
+  // Search the diagonal going from bottom-left to top-right.
+  if(e.r > 0.0) {
+    d.xz = search_diagonal_1(tex_coord, vec2(-1, 1), end);
+    d.x += float(end.y > 0.9);
+  } else {
+    d.xz = vec2(0);
+  }
+  d.yw = search_diagonal_1(tex_coord, vec2(1, -1), end);
+
+  // Only diagonals longer than three pixels are blended.
+  if(d.x + d.y > 2.0) {
+    // Fetch the crossing edges at each end. Ends where the search ran out of steps have no known crossing edges.
+    const vec4 coords = tex_coord.xyxy + texel.xyxy * vec4(-d.x + 0.25, d.x, d.y, -d.y - 0.25);
+    vec4 c;
+    c.xy = textureLodOffset(edges_texture, coords.xy, 0, ivec2(-1, 0)).rg;
+    c.zw = textureLodOffset(edges_texture, coords.zw, 0, ivec2(1, 0)).rg;
+    c.yxwz = decode_diagonal_bilinear_access(c.xyzw);
+
+    const vec2 cc = mix(2.0 * c.xz + c.yw, vec2(0), greaterThanEqual(d.zw, vec2(0.9)));
+    weights += area_diagonal(d.xy, cc);
+  }
+
+  // Search the diagonal going from top-left to bottom-right.
+  d.xz = search_diagonal_2(tex_coord, vec2(-1, -1), end);
+  if(textureLodOffset(edges_texture, tex_coord, 0, ivec2(1, 0)).r > 0.0) {
+    d.yw = search_diagonal_2(tex_coord, vec2(1, 1), end);
+    d.y += float(end.y > 0.9);
+  } else {
+    d.yw = vec2(0);
+  }
+
+  if(d.x + d.y > 2.0) {
+    const vec4 coords = tex_coord.xyxy + texel.xyxy * vec4(-d.xx, d.yy);
+    vec4 c;
+    c.x = textureLodOffset(edges_texture, coords.xy, 0, ivec2(-1, 0)).g;
+    c.y = textureLodOffset(edges_texture, coords.xy, 0, ivec2(0, -1)).r;
+    c.zw = textureLodOffset(edges_texture, coords.zw, 0, ivec2(1, 0)).gr;
+
+    const vec2 cc = mix(2.0 * c.xz + c.yw, vec2(0), greaterThanEqual(d.zw, vec2(0.9)));
+    weights += area_diagonal(d.xy, cc).gr;
+  }
+
+  return weights;
+}
+
+// Reduce the blending of a horizontal edge which ends at a corner, so that the corner stays sharp.
+// Pixels in the middle of an edge, equally far from both ends, are reduced by half as much.
+void detect_horizontal_corner_pattern(inout vec2 weights, vec4 coords, vec2 d) {
+  const vec2 left_right = step(d.xy, d.yx);
+  const vec2 rounding = (1.0 - CORNER_ROUNDING) * left_right / (left_right.x + left_right.y);
+
+  vec2 factor = vec2(1);
+  factor.x -= rounding.x * textureLodOffset(edges_texture, coords.xy, 0, ivec2(0, 1)).r;
+  factor.x -= rounding.y * textureLodOffset(edges_texture, coords.zw, 0, ivec2(1, 1)).r;
+  factor.y -= rounding.x * textureLodOffset(edges_texture, coords.xy, 0, ivec2(0, -2)).r;
+  factor.y -= rounding.y * textureLodOffset(edges_texture, coords.zw, 0, ivec2(1, -2)).r;
+  weights *= clamp(factor, 0.0, 1.0);
+}
+
+// Reduce the blending of a vertical edge which ends at a corner.
+void detect_vertical_corner_pattern(inout vec2 weights, vec4 coords, vec2 d) {
+  const vec2 left_right = step(d.xy, d.yx);
+  const vec2 rounding = (1.0 - CORNER_ROUNDING) * left_right / (left_right.x + left_right.y);
+
+  vec2 factor = vec2(1);
+  factor.x -= rounding.x * textureLodOffset(edges_texture, coords.xy, 0, ivec2(1, 0)).g;
+  factor.x -= rounding.y * textureLodOffset(edges_texture, coords.zw, 0, ivec2(1, 1)).g;
+  factor.y -= rounding.x * textureLodOffset(edges_texture, coords.xy, 0, ivec2(-2, 0)).g;
+  factor.y -= rounding.y * textureLodOffset(edges_texture, coords.zw, 0, ivec2(-2, 1)).g;
+  weights *= clamp(factor, 0.0, 1.0);
+}
+
+void main() {
+  const vec2 texel = push_constants.inverse_screen_size;
+  const vec2 tex_coord = gl_FragCoord.xy * texel;
+  const vec2 pixel_coord = gl_FragCoord.xy;
+
+  // Offset the searches to fetch two edges at a time, and to tell apart which side of the edge crossing edges are on.
+  const vec4 offset_horizontal = tex_coord.xyxy + texel.xyxy * vec4(-0.25, -0.125, 1.25, -0.125);
+  const vec4 offset_vertical = tex_coord.xyxy + texel.xyxy * vec4(-0.125, -0.25, -0.125, 1.25);
+  const vec4 search_end = texel.xxyy * vec4(-2, 2, -2, 2) * float(MAX_SEARCH_STEPS)
+    + vec4(offset_horizontal.xz, offset_vertical.yw);
+
+  vec4 weights = vec4(0);
+  vec2 e = texture(edges_texture, tex_coord).rg;
+
+  // Edge at the top of the pixel.
+  if(e.g > 0.0) {
+    // Diagonal edges take priority, and skip the vertical processing, as the diagonal weights cover both.
+    weights.rg = diagonal_weights(tex_coord, e);
+    if(weights.r == -weights.g) {
+      vec3 coords;
+      coords.x = search_x_left(offset_horizontal.xy, search_end.x);
+      coords.y = offset_vertical.y;
+      coords.z = search_x_right(offset_horizontal.zw, search_end.y);
+      const vec2 distance = abs(round(coords.xz / texel.x - pixel_coord.xx));
+
+      // Fetch the crossing edges at each end. Sampling a quarter pixel up tells which side of the edge they are on.
+      const float e1 = textureLod(edges_texture, coords.xy, 0).r;
+      const float e2 = textureLodOffset(edges_texture, coords.zy, 0, ivec2(1, 0)).r;
+      weights.rg = area(sqrt(distance), e1, e2);
+
+      coords.y = tex_coord.y;
+      detect_horizontal_corner_pattern(weights.rg, coords.xyzy, distance);
+    } else {
+      e.r = 0.0;
+    }
+  }
+
+  // Edge at the left of the pixel.
+  if(e.r > 0.0) {
+    vec3 coords;
+    coords.x = offset_horizontal.x;
+    coords.y = search_y_up(offset_vertical.xy, search_end.z);
+    coords.z = search_y_down(offset_vertical.zw, search_end.w);
+    const vec2 distance = abs(round(coords.yz / texel.y - pixel_coord.yy));
+
+    const float e1 = textureLod(edges_texture, coords.xy, 0).g;
+    const float e2 = textureLodOffset(edges_texture, coords.xz, 0, ivec2(0, 1)).g;
+    weights.ba = area(sqrt(distance), e1, e2);
+
+    coords.x = tex_coord.x;
+    detect_vertical_corner_pattern(weights.ba, coords.xyxz, distance);
+  }
+
+  out_weights = weights;
+}
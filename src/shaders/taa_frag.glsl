@@ -0,0 +1,49 @@
+// Temporal anti-aliasing, accumulating the jittered frames rendered by the demo into a history image.
+// Without motion vectors, the history is clamped to the neighborhood of the current frame to limit ghosting.
+#version 460
+
+layout(push_constant) uniform PushConstants {
+  vec2 inverse_screen_size;
+  float split_position; // The fraction of the screen width, from the left, where TAA is applied.
+  float history_weight; // How much of the history is kept each frame. Zero when the history is invalid.
+  vec2 history_scale; // The size of the rendered area of the history relative to that of the current frame.
+} push_constants;
+
+layout(set = 0, binding = 0) uniform sampler2D current_texture;
+layout(set = 0, binding = 1) uniform sampler2D history_texture;
+
+layout(location = 0) out vec4 out_color;
+layout(location = 1) out vec4 out_history;
+
+void main() {
+  const vec2 tex_coord = gl_FragCoord.xy * push_constants.inverse_screen_size;
+  const vec3 current = textureLod(current_texture, tex_coord, 0).rgb;
+
+  vec3 color = current;
+  if(push_constants.history_weight > 0.0) {
+    // Find the range of colors in the 3x3 neighborhood of the current frame.
+    vec3 neighborhood_min = current;
+    vec3 neighborhood_max = current;
+    for(int y = -1; y <= 1; y++) {
+      for(int x = -1; x <= 1; x++) {
+        const vec3 neighbor = textureLod(current_texture, tex_coord + vec2(x, y) * push_constants.inverse_screen_size, 0).rgb;
+        neighborhood_min = min(neighborhood_min, neighbor);
+        neighborhood_max = max(neighborhood_max, neighbor);
+      }
+    }
+
+    // Reject history that could not have come from this neighborhood, such as when the scene has changed.
+    const vec2 history_coord = tex_coord * push_constants.history_scale;
+    const vec3 history = clamp(textureLod(history_texture, history_coord, 0).rgb, neighborhood_min, neighborhood_max);
+    color = mix(current, history, push_constants.history_weight);
+  }
+  out_history = vec4(color, 1);
+
+  // Pass the raw input through to the right of the split, for comparison. Draw a thin divider along the split.
+  const float split_x = push_constants.split_position / push_constants.inverse_screen_size.x;
+  if(gl_FragCoord.x >= split_x) {
+    out_color = gl_FragCoord.x < split_x + 1.0 ? vec4(1) : vec4(current, 1);
+  } else {
+    out_color = vec4(color, 1);
+  }
+}
@@ -0,0 +1,73 @@
+// Final pass of every frame, tonemapping the scene to the brightness range of the display and encoding it for the swapchain color space.
+#version 460
+
+layout(push_constant) uniform PushConstants {
+  uint encoding; // 0: Linear, 1: sRGB, 2: HDR10 PQ, 3: extended sRGB linear (scRGB).
+  float paper_white; // The brightness of SDR white, in nits.
+  float peak_luminance; // The brightest the display can show, in nits.
+} push_constants;
+
+layout(set = 0, binding = 0) uniform sampler2D scene_texture;
+
+layout(location = 0) out vec4 out_color;
+
+const uint ENCODING_LINEAR = 0;
+const uint ENCODING_SRGB = 1;
+const uint ENCODING_PQ = 2;
+const uint ENCODING_SCRGB = 3;
+
+// The brightness scRGB defines for a value of one, in nits.
+const float SCRGB_WHITE = 80.0;
+
+// Convert linear BT.709 colors to the BT.2020 primaries used by HDR10. Column-major.
+const mat3 BT709_TO_BT2020 = mat3(
+  0.6274, 0.0691, 0.0164,
+  0.3293, 0.9195, 0.0880,
+  0.0433, 0.0114, 0.8956);
+
+vec3 linear_to_srgb(vec3 color) {
+  return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, greaterThan(color, vec3(0.0031308)));
+}
+
+// Encode absolute brightness with the SMPTE ST 2084 perceptual quantizer.
+vec3 pq_encode(vec3 nits) {
+  const float m1 = 0.1593017578125;
+  const float m2 = 78.84375;
+  const float c1 = 0.8359375;
+  const float c2 = 18.8515625;
+  const float c3 = 18.6875;
+  const vec3 y = pow(clamp(nits / 10000.0, 0.0, 1.0), vec3(m1));
+  return pow((c1 + c2 * y) / (1.0 + c3 * y), vec3(m2));
+}
+
+// Smoothly compress highlights above a knee towards the peak, leaving darker colors unchanged.
+// Scaling by the brightest channel preserves the hue of saturated highlights.
+vec3 tonemap(vec3 color, float peak) {
+  const float knee = 0.8 * peak;
+  const float brightest = max(color.r, max(color.g, color.b));
+  if(brightest <= knee) {
+    return color;
+  }
+  const float range = peak - knee;
+  const float compressed = knee + range * (1.0 - exp((knee - brightest) / range));
+  return color * (compressed / brightest);
+}
+
+void main() {
+  // Demos render linear colors, where one is SDR white.
+  const vec3 scene = max(texelFetch(scene_texture, ivec2(gl_FragCoord.xy), 0).rgb, 0.0);
+  const float relative_peak = push_constants.peak_luminance / push_constants.paper_white;
+
+  vec3 color;
+  if(push_constants.encoding == ENCODING_PQ) {
+    color = pq_encode(tonemap(BT709_TO_BT2020 * scene, relative_peak) * push_constants.paper_white);
+  } else if(push_constants.encoding == ENCODING_SCRGB) {
+    color = tonemap(scene, relative_peak) * (push_constants.paper_white / SCRGB_WHITE);
+  } else if(push_constants.encoding == ENCODING_SRGB) {
+    color = linear_to_srgb(tonemap(scene, 1.0));
+  } else {
+    // The swapchain format applies the sRGB curve itself when written.
+    color = tonemap(scene, 1.0);
+  }
+  out_color = vec4(color, 1);
+}
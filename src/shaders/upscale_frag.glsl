@@ -0,0 +1,158 @@
+// Upscale the scene from the internal render resolution to the resolution of the swapchain.
+// The edge-adaptive filter follows the approach of FidelityFX Super Resolution 1 EASU: a Lanczos-like kernel which is stretched along edges.
+#version 460
+
+layout(push_constant) uniform PushConstants {
+  vec2 inverse_output_size; // The reciprocal of the size of the destination image, in pixels.
+  vec2 input_size; // The size of the area at the top-left of the scene image which the scene was rendered to, in pixels.
+  uint filter_mode; // 0: Bilinear, 1: Lanczos, 2: Edge-adaptive (EASU).
+} push_constants;
+
+layout(set = 0, binding = 0) uniform sampler2D scene_texture;
+
+layout(location = 0) out vec4 out_color;
+
+const uint FILTER_BILINEAR = 0;
+const uint FILTER_LANCZOS = 1;
+const uint FILTER_EASU = 2;
+
+const float PI = 3.14159265;
+
+// Fetch a texel of the scene, clamping to the edges of the rendered area.
+vec3 fetch(ivec2 texel) {
+  return texelFetch(scene_texture, clamp(texel, ivec2(0), ivec2(push_constants.input_size) - 1), 0).rgb;
+}
+
+// Approximate the perceived brightness, only for detecting edges.
+float luma(vec3 color) {
+  return dot(color, vec3(0.5, 1.0, 0.5));
+}
+
+// The two-lobed Lanczos kernel.
+float lanczos2(float x) {
+  if(x == 0.0) {
+    return 1.0;
+  }
+  if(abs(x) >= 2.0) {
+    return 0.0;
+  }
+  const float px = PI * x;
+  return 2.0 * sin(px) * sin(px * 0.5) / (px * px);
+}
+
+// Sample the 4x4 texels surrounding the pixel with a two-lobed Lanczos kernel.
+// The result is clamped to the nearest 2x2 texels to remove ringing around sharp edges.
+vec3 sample_lanczos(vec2 position, ivec2 origin) {
+  const vec2 fraction = position - vec2(origin);
+  vec3 sum = vec3(0.0);
+  float weight_sum = 0.0;
+  vec3 nearest_min = vec3(1e30);
+  vec3 nearest_max = vec3(-1e30);
+  for(int y = -1; y <= 2; y++) {
+    const float weight_y = lanczos2(float(y) - fraction.y);
+    for(int x = -1; x <= 2; x++) {
+      const vec3 color = fetch(origin + ivec2(x, y));
+      const float weight = lanczos2(float(x) - fraction.x) * weight_y;
+      sum += color * weight;
+      weight_sum += weight;
+      if(x >= 0 && x <= 1 && y >= 0 && y <= 1) {
+        nearest_min = min(nearest_min, color);
+        nearest_max = max(nearest_max, color);
+      }
+    }
+  }
+  return clamp(sum / weight_sum, nearest_min, nearest_max);
+}
+
+// Accumulate the edge direction and length from one of the four texels nearest the pixel.
+// The neighbors are the luma of the texels to the left, right, above, and below the center texel.
+void accumulate_edge(inout vec2 direction, inout float edge_length, float weight, float center, float left, float right, float up, float down) {
+  const float direction_x = right - left;
+  const float length_x = clamp(abs(direction_x) / max(max(abs(right - center), abs(center - left)), 1e-5), 0.0, 1.0);
+  const float direction_y = down - up;
+  const float length_y = clamp(abs(direction_y) / max(max(abs(down - center), abs(center - up)), 1e-5), 0.0, 1.0);
+  direction += vec2(direction_x, direction_y) * weight;
+  edge_length += (length_x * length_x + length_y * length_y) * weight;
+}
+
+// Sample the 12 texels surrounding the pixel with a kernel which is stretched along the local edge and sharper across it.
+vec3 sample_easu(vec2 position, ivec2 origin) {
+  const vec2 fraction = position - vec2(origin);
+
+  // Gather the luma of the 4x4 texels, where the 2x2 nearest the pixel start at `origin`.
+  float l[4][4];
+  for(int y = 0; y < 4; y++) {
+    for(int x = 0; x < 4; x++) {
+      l[y][x] = luma(fetch(origin + ivec2(x - 1, y - 1)));
+    }
+  }
+
+  // Estimate the edge direction and length with a bilinear blend of the four nearest texels.
+  vec2 direction = vec2(0.0);
+  float edge_length = 0.0;
+  accumulate_edge(direction, edge_length, (1.0 - fraction.x) * (1.0 - fraction.y), l[1][1], l[1][0], l[1][2], l[0][1], l[2][1]);
+  accumulate_edge(direction, edge_length, fraction.x * (1.0 - fraction.y), l[1][2], l[1][1], l[1][3], l[0][2], l[2][2]);
+  accumulate_edge(direction, edge_length, (1.0 - fraction.x) * fraction.y, l[2][1], l[2][0], l[2][2], l[1][1], l[3][1]);
+  accumulate_edge(direction, edge_length, fraction.x * fraction.y, l[2][2], l[2][1], l[2][3], l[1][2], l[3][2]);
+
+  const float direction_squared = dot(direction, direction);
+  direction = direction_squared < 1.0 / 32768.0 ? vec2(1.0, 0.0) : direction * inversesqrt(direction_squared);
+  edge_length *= 0.5;
+  edge_length *= edge_length;
+
+  // Stretch the kernel along the edge, more so for diagonals, and narrow it across the edge.
+  const float stretch = 1.0 / max(abs(direction.x), abs(direction.y));
+  const vec2 axis_scale = vec2(1.0 + (stretch - 1.0) * edge_length, 1.0 - 0.5 * edge_length);
+  // Shape the negative lobe, from a soft window in flat regions to a sharper Lanczos-like kernel on strong edges.
+  const float lobe = 0.5 - 0.29 * edge_length;
+  const float clip = 1.0 / lobe;
+
+  vec3 sum = vec3(0.0);
+  float weight_sum = 0.0;
+  vec3 nearest_min = vec3(1e30);
+  vec3 nearest_max = vec3(-1e30);
+  for(int y = -1; y <= 2; y++) {
+    for(int x = -1; x <= 2; x++) {
+      // Skip the corners of the 4x4 grid, which are too far from the pixel to contribute.
+      if((x == -1 || x == 2) && (y == -1 || y == 2)) {
+        continue;
+      }
+      const vec3 color = fetch(origin + ivec2(x, y));
+      const vec2 offset = vec2(x, y) - fraction;
+      const vec2 rotated = vec2(dot(offset, direction), dot(offset, vec2(-direction.y, direction.x))) * axis_scale;
+      const float distance_squared = min(dot(rotated, rotated), clip);
+
+      // Approximate the windowed kernel with polynomials: (25/16 * (2/5 * x^2 - 1)^2 - (25/16 - 1)) * (lobe * x^2 - 1)^2.
+      const float window = 0.4 * distance_squared - 1.0;
+      const float base = lobe * distance_squared - 1.0;
+      const float weight = (1.5625 * window * window - 0.5625) * (base * base);
+      sum += color * weight;
+      weight_sum += weight;
+      if(x >= 0 && x <= 1 && y >= 0 && y <= 1) {
+        nearest_min = min(nearest_min, color);
+        nearest_max = max(nearest_max, color);
+      }
+    }
+  }
+  return clamp(sum / weight_sum, nearest_min, nearest_max);
+}
+
+void main() {
+  // The position of this pixel in the texel space of the scene, and the top-left of the 2x2 texels nearest to it.
+  const vec2 tex_coord = gl_FragCoord.xy * push_constants.inverse_output_size;
+  const vec2 position = tex_coord * push_constants.input_size - 0.5;
+  const ivec2 origin = ivec2(floor(position));
+
+  vec3 color;
+  if(push_constants.filter_mode == FILTER_EASU) {
+    color = sample_easu(position, origin);
+  } else if(push_constants.filter_mode == FILTER_LANCZOS) {
+    color = sample_lanczos(position, origin);
+  } else {
+    // Keep the bilinear footprint inside the rendered area, as the texels beyond it are stale.
+    const vec2 texel_size = 1.0 / vec2(textureSize(scene_texture, 0));
+    const vec2 scene_coord = clamp(position + 0.5, vec2(0.5), push_constants.input_size - 0.5);
+    color = texture(scene_texture, scene_coord * texel_size).rgb;
+  }
+  out_color = vec4(color, 1.0);
+}